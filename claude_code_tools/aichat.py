@@ -78,12 +78,13 @@ def main(ctx, claude_home, codex_home):
     ctx.obj['codex_home'] = codex_home
 
     # Auto-index sessions on every aichat command (incremental, fast if up-to-date)
-    # Skip for build-index/clear-index to avoid double-indexing or state conflicts
+    # Skip for build-index/clear-index to avoid double-indexing or state conflicts,
+    # and for --index-path, which may be another machine's index
     # In JSON mode (-j/--json), suppress all output for clean parsing
     import sys
-    skip_auto_index_cmds = ['build-index', 'clear-index', 'index-stats']
+    skip_auto_index_cmds = ['build-index', 'clear-index', 'index-stats', '--index-path']
     should_skip = any(cmd in sys.argv for cmd in skip_auto_index_cmds)
-    json_mode = any(arg in sys.argv for arg in ['-j', '--json'])
+    json_mode = any(arg in sys.argv for arg in ['-j', '--json', '--json-full', '--count', '--stats', '--export-md'])
     if not should_skip:
         try:
             from claude_code_tools.search_index import auto_index
@@ -268,7 +269,8 @@ def _find_and_run_session_ui(
         pass
 
     # Use the Tantivy index for fast session lookup
-    index_path = Path.home() / ".cctools" / "search-index"
+    from claude_code_tools.search_index import default_index_path
+    index_path = default_index_path()
     if not index_path.exists():
         print(
             "Session index not found. Run 'aichat search' first to build the index.",
@@ -1429,8 +1431,8 @@ def resume_session(ctx, claude_home, codex_home):
 @click.option(
     "--index", "-i",
     type=click.Path(),
-    default="~/.cctools/search-index",
-    help="Index directory (default: ~/.cctools/search-index/)",
+    help="Index directory (default: ~/.cctools/search-index/, or under "
+         "$XDG_DATA_HOME/cctools when set)",
 )
 @click.option("--dry-run", "-n", is_flag=True, help="Show what would be deleted")
 def clear_index(index, dry_run):
@@ -1441,7 +1443,8 @@ def clear_index(index, dry_run):
     import shutil
     from pathlib import Path
 
-    index_path = Path(index).expanduser()
+    from claude_code_tools.search_index import default_index_path
+    index_path = Path(index).expanduser() if index else default_index_path()
 
     if not index_path.exists():
         print(f"Index directory does not exist: {index_path}")
@@ -1752,8 +1755,8 @@ def _scan_session_files(
 @click.option(
     "--index", "-i",
     type=click.Path(),
-    default="~/.cctools/search-index",
-    help="Index directory",
+    help="Index directory (default: ~/.cctools/search-index/, or under "
+         "$XDG_DATA_HOME/cctools when set)",
 )
 @click.option("--cwd", "-c", help="Filter to specific cwd path")
 @click.option(
@@ -1783,9 +1786,10 @@ def index_stats(index, cwd, claude_home, codex_home):
         print("Error: tantivy not installed")
         return
 
+    from claude_code_tools.search_index import default_index_path
     from claude_code_tools.session_utils import get_claude_home, get_codex_home
 
-    index_path = Path(index).expanduser()
+    index_path = Path(index).expanduser() if index else default_index_path()
     claude_home_path = get_claude_home(cli_arg=claude_home)
     codex_home_path = get_codex_home(cli_arg=codex_home)
 
@@ -1938,34 +1942,108 @@ def index_stats(index, cwd, claude_home, codex_home):
               help='Search across all projects (not just current)')
 @click.option('--dir', 'filter_dir',
               help='Filter to directory[:branch] (overrides -g)')
+@click.option('--cwd', 'launch_cwd', type=click.Path(exists=True, file_okay=False),
+              help='Scope the default (non-global) search as if run from this directory')
 @click.option('--branch', 'filter_branch',
               help='Filter to specific git branch (only effective when not global)')
-@click.option('-n', '--num-results', type=int, default=None,
-              help='Limit number of results displayed')
+@click.option('-n', '--num-results', '--limit', 'num_results', type=int, default=None,
+              help='Keep the top N results, after ranking and sorting')
 @click.option('--no-original', is_flag=True, help='Exclude original sessions')
 @click.option('--sub-agent', is_flag=True, help='Include sub-agent sessions (additive)')
 @click.option('--no-trimmed', is_flag=True, help='Exclude trimmed sessions')
 @click.option('--no-rollover', is_flag=True, help='Exclude rollover sessions')
+@click.option('--include-tool-sessions', 'include_tool', is_flag=True,
+              help='Include sessions generated by these tools (session query, '
+                   'lmsh), detected by their prompt text (excluded by default)')
+@click.option('--has-attachments', is_flag=True,
+              help='Only sessions containing image or document attachments')
 @click.option('--min-lines', type=int, default=None,
               help='Only show sessions with at least N lines')
+@click.option('--min-messages', type=int, metavar='N',
+              help='Only show sessions with at least N conversation turns')
 @click.option('--after', metavar='DATE',
               help='Sessions modified after date (YYYYMMDD, MM/DD/YY)')
 @click.option('--before', metavar='DATE',
               help='Sessions modified before date (YYYYMMDD, MM/DD/YY)')
-@click.option('--agent', type=click.Choice(['claude', 'codex', 'all']),
+@click.option('--since', metavar='AGE',
+              help='Sessions modified within the last AGE: 7d, 2w, 3mo, 1y '
+                   '(not with --after)')
+@click.option('--agent', type=click.Choice(['claude', 'codex', 'all', 'both']),
               default='all', help='Filter by agent type')
+@click.option('--project', 'filter_project', metavar='NAME',
+              help='Sessions whose project name contains NAME (case-insensitive)')
+@click.option('--lang', 'filter_lang', metavar='LANG',
+              help='Sessions involving a code language (e.g. rust, python). '
+                   'Detected best-effort from code fences and keywords')
 @click.option('--json', 'json_output', is_flag=True,
               help='Output as JSONL for AI agents. Fields per line: session_id, '
                    'agent, project, branch, cwd, lines, created, modified, '
                    'first_msg, last_msg, file_path, derivation_type, '
                    'is_sidechain, snippet')
+@click.option('--json-full', 'json_full', is_flag=True,
+              help='Like --json, plus each session\'s conversation as "turns": '
+                   '[{role, text, tool_calls: [{name, input, result}]}]')
+@click.option('--fields', 'json_fields', metavar='A,B,...',
+              help='With --json, only output these keys (or "all" to add '
+                   'claude_home, modified_ts, message_count, ...)')
+@click.option('--count', 'count_only', is_flag=True,
+              help='Print only the number of matching sessions (same filters '
+                   'as --json)')
+@click.option('--stats', 'stats_only', is_flag=True,
+              help='Print session counts per agent, project, Claude home and '
+                   'derivation type (same filters as --json; add --json for JSON)')
+@click.option('--index-path', 'index_path', metavar='DIR',
+              help='Search this index instead of the default one (not '
+                   'auto-updated)')
+@click.option('--export-md', 'export_session', metavar='SESSION_ID',
+              help='Print the transcript of this session (ID or unique prefix) '
+                   'as Markdown and exit')
+@click.option('--export-format', 'export_format', type=click.Choice(['md', 'text']),
+              help='With --export-md, the transcript format (default: md)')
+@click.option('--output-file', 'export_file', metavar='PATH',
+              help='With --export-md, write the transcript here instead of stdout')
+@click.option('--all', 'json_all', is_flag=True,
+              help='With --json and no query, output every match '
+                   '(otherwise capped at 1000)')
 @click.option('--by-time', 'by_time', is_flag=True,
               help='Sort results by last-modified time (default: sort by relevance)')
+@click.option('--sort', 'sort_spec', metavar='KEY[:asc|:desc]',
+              help='Sort by relevance, modified, created, lines, messages or '
+                   'project (overrides --by-time)')
+@click.option('--group-by', 'group_by', type=click.Choice(['project', 'agent', 'home']),
+              help='List sessions in groups under project, agent or Claude home headers')
+@click.option('--preview-lines', 'preview_lines', type=int,
+              help='Lines of the last message shown in the preview (the match '
+                   'gets twice as many; default: from the pane height)')
+@click.option('--purge', 'purge', is_flag=True,
+              help='Also offer permanent deletion in the actions menu (default: '
+                   'only move sessions to ~/.cctools/trash)')
+@click.option('--snippet-chars', 'snippet_chars', type=click.IntRange(min=20),
+              help='Length of match snippets in the list and preview (default: 200)')
+@click.option('--theme', 'theme_name', type=click.Choice(['auto', 'dark', 'light']),
+              help='Color theme (default: auto, from COLORFGBG; NO_COLOR '
+                   'turns colors off)')
+@click.option('--show', 'show_column', type=click.Choice(['lines', 'messages']),
+              help='What the list size column counts: transcript lines '
+                   '(default) or messages')
+@click.option('--no-restore', 'no_restore', is_flag=True,
+              help='Start with default filters instead of the ones from the '
+                   'last session')
+@click.option('--phrase', 'phrase_mode', is_flag=True,
+              help='Treat the whole query as one exact phrase (no term OR-ing)')
+@click.option('--regex', 'regex_mode', is_flag=True,
+              help='Match the query as a regular expression against session '
+                   'content (bypasses keyword search). Case-insensitive unless '
+                   'the pattern starts with (?-i)')
+@click.option('--fuzzy', 'fuzzy_mode', is_flag=True,
+              help='When no session matches the keywords, fuzzy-match the '
+                   'query against project and branch names')
 @click.argument('query', required=False)
 def search(
-    claude_home_arg, codex_home_arg, global_search, filter_dir, filter_branch,
-    num_results, no_original, sub_agent, no_trimmed, no_rollover, min_lines,
-    after, before, agent, json_output, by_time, query
+    claude_home_arg, codex_home_arg, global_search, filter_dir, launch_cwd, filter_branch,
+    num_results, no_original, sub_agent, no_trimmed, no_rollover, include_tool,
+    has_attachments, min_lines, min_messages, after, before, since, agent, filter_project, filter_lang, json_output,
+    json_full, json_fields, count_only, stats_only, export_session, export_format, export_file, index_path, json_all, by_time, sort_spec, group_by, preview_lines, snippet_chars, purge, theme_name, show_column, no_restore, phrase_mode, regex_mode, fuzzy_mode, query
 ):
     """Launch interactive TUI for full-text session search.
 
@@ -1978,8 +2056,13 @@ def search(
         aichat search "langroid agent"     # Pre-fill search query
         aichat search -g --after 11/20/25  # Global, recent sessions
         aichat search --dir ~/Git/myproj   # Filter to specific directory
+        aichat search --cwd ~/Git/myproj   # Default scope as if run from there
         aichat search --json "MCP"         # JSON output (sorted by relevance)
         aichat search --json --by-time     # JSON output sorted by time
+        aichat search --json --sort lines  # Longest sessions first
+        aichat search --count -g "MCP"     # Number of matching sessions
+        aichat search --stats -g           # Sessions per agent, project, ...
+        echo "MCP" | aichat search --json -  # Query read from stdin
 
     \b
     Notes:
@@ -2025,12 +2108,58 @@ def search(
     claude_home = get_claude_home(cli_arg=claude_home_arg)
     codex_home = get_codex_home(cli_arg=codex_home_arg)
 
+    # UI preferences from config.json ($XDG_CONFIG_HOME/cctools or ~/.cctools)
+    from claude_code_tools.config import (
+        compact_list, confirm_exit, enter_action, permalink_template,
+        preview_context, restore_filters, theme, track_views,
+    )
+    enter_action_pref = enter_action()
+    permalink_pref = permalink_template()
+    track_views_pref = track_views()
+    confirm_exit_pref = confirm_exit()
+    preview_context_pref = preview_context()
+    compact_list_pref = compact_list()
+    restore_pref = restore_filters() and not no_restore
+    theme_pref = theme_name or theme()
+
+    # `aichat search cctools://<agent>/<session_id>` opens that session
+    permalink = None
+    if query and query.startswith("cctools://"):
+        permalink, query = query, None
+
     # Build CLI args for Rust binary
     rust_args = [str(rust_binary)]
 
     # Home directories
     rust_args.extend(["--claude-home", str(claude_home)])
     rust_args.extend(["--codex-home", str(codex_home)])
+    if launch_cwd:
+        rust_args.extend(["--cwd", str(Path(launch_cwd).resolve())])
+
+    # UI preferences
+    if enter_action_pref != "modal":
+        rust_args.extend(["--enter-action", enter_action_pref])
+    rust_args.extend(["--permalink-template", permalink_pref])
+    if not track_views_pref:
+        rust_args.append("--no-view-tracking")
+    if not confirm_exit_pref:
+        rust_args.append("--no-confirm-exit")
+    if purge:
+        rust_args.append("--purge")
+    if preview_context_pref:
+        rust_args.append("--preview-context")
+    if compact_list_pref:
+        rust_args.append("--compact")
+    if preview_lines:
+        rust_args.extend(["--preview-lines", str(preview_lines)])
+    if snippet_chars:
+        rust_args.extend(["--snippet-chars", str(snippet_chars)])
+    if theme_pref != "auto":
+        rust_args.extend(["--theme", theme_pref])
+    if show_column:
+        rust_args.extend(["--show", show_column])
+    if not restore_pref:
+        rust_args.append("--no-restore")
 
     # Filter options
     if filter_dir:
@@ -2044,13 +2173,13 @@ def search(
             parts = filter_dir.rsplit(':', 1)
             dir_part = parts[0]
             branch_part = parts[1] if len(parts) > 1 else None
-            resolved_dir = str(Path(dir_part).resolve())
+            resolved_dir = str((Path(launch_cwd or '.') / dir_part).resolve())
             if branch_part:
                 rust_args.extend(["--dir", f"{resolved_dir}:{branch_part}"])
             else:
                 rust_args.extend(["--dir", resolved_dir])
         else:
-            rust_args.extend(["--dir", str(Path(filter_dir).resolve())])
+            rust_args.extend(["--dir", str((Path(launch_cwd or '.') / filter_dir).resolve())])
     elif global_search:
         rust_args.append("--global")
     if filter_branch:
@@ -2065,30 +2194,72 @@ def search(
         rust_args.append("--no-trimmed")
     if no_rollover:
         rust_args.append("--no-rollover")
+    if include_tool:
+        rust_args.append("--include-tool-sessions")
+    if has_attachments:
+        rust_args.append("--has-attachments")
     if min_lines:
         rust_args.extend(["--min-lines", str(min_lines)])
+    if min_messages:
+        rust_args.extend(["--min-messages", str(min_messages)])
     if after:
         rust_args.extend(["--after", after])
     if before:
         rust_args.extend(["--before", before])
-    if agent and agent != "all":
+    if since:
+        rust_args.extend(["--since", since])
+    if agent and agent not in ("all", "both"):
         rust_args.extend(["--agent", agent])
+    if filter_project:
+        rust_args.extend(["--project", filter_project])
+    if filter_lang:
+        rust_args.extend(["--lang", filter_lang])
     if query:
         rust_args.extend(["--query", query])
     if by_time:
         rust_args.append("--by-time")
-
-    # JSON output mode - run Rust with --json, output to stdout, exit
-    if json_output:
-        rust_args.append("--json")
+    if sort_spec:
+        rust_args.extend(["--sort", sort_spec])
+    if index_path:
+        rust_args.extend(["--index-path", index_path])
+    if group_by:
+        rust_args.extend(["--group-by", group_by])
+    if phrase_mode:
+        rust_args.append("--phrase")
+    if regex_mode:
+        rust_args.append("--regex")
+    if fuzzy_mode:
+        rust_args.append("--fuzzy")
+    if permalink:
+        rust_args.append(permalink)
+
+    # JSON/count/stats/export output mode - run Rust with --json, --count, --stats or
+    # --export-md, output to stdout, exit
+    if json_output or json_full or count_only or stats_only or export_session:
+        if export_session:
+            rust_args.extend(["--export-md", export_session])
+            if export_format:
+                rust_args.extend(["--export-format", export_format])
+            if export_file:
+                rust_args.extend(["--output-file", export_file])
+        elif stats_only:
+            rust_args.append("--stats")
+        if not export_session and (json_output or json_full or not stats_only):
+            rust_args.append("--count" if count_only else "--json")
+        if json_full:
+            rust_args.append("--json-full")
+        if json_all:
+            rust_args.append("--all")
+        if json_fields:
+            rust_args.extend(["--fields", json_fields])
         try:
             result = subprocess.run(rust_args, capture_output=True, text=True)
-            # Output JSON to stdout (errors to stderr)
+            # Output JSON to stdout (errors and warnings to stderr)
             # Use end='' to avoid double newline (Rust already adds one)
             if result.stdout:
                 print(result.stdout, end='')
-            if result.returncode != 0 and result.stderr:
-                print(result.stderr, file=sys.stderr)
+            if result.stderr:
+                print(result.stderr, file=sys.stderr, end='')
             sys.exit(result.returncode)
         except Exception as e:
             print(f"Error running search: {e}", file=sys.stderr)
@@ -2124,17 +2295,27 @@ def search(
         """Check if session is from different directory and get user confirmation.
 
         Returns:
-            (proceed, original_dir) tuple:
+            (proceed, original_dir, session_dir) tuple:
             - proceed: True if user wants to proceed, False if cancelled
             - original_dir: The directory before any change (to restore on cancel),
                            or None if no directory change was made
+            - session_dir: The directory to resume in: the session's cwd, or home
+                           when that no longer exists
         """
         session_dir = sess.get("cwd") or "."
         current_dir = os.getcwd()
 
+        # The session's directory may have been moved or deleted since;
+        # resume from the home directory rather than failing the chdir
+        if not os.path.isdir(session_dir):
+            home_dir = str(Path.home())
+            print(f"Session directory no longer exists: {session_dir}")
+            print(f"Resuming from {home_dir} instead")
+            session_dir = home_dir
+
         # If same directory, proceed (no restore needed)
         if os.path.realpath(session_dir) == os.path.realpath(current_dir):
-            return (True, None)
+            return (True, None, session_dir)
 
         # Show confirmation dialog
         choice = run_dir_confirm_ui(current_dir, session_dir)
@@ -2147,13 +2328,13 @@ def search(
             except Exception as e:
                 print(f"Error changing directory: {e}")
                 original_dir = None  # No restore needed if change failed
-            return (True, original_dir)
+            return (True, original_dir, session_dir)
         elif choice == "no":
             # Proceed without changing directory (no restore needed)
-            return (True, None)
+            return (True, None, session_dir)
         else:
             # 'cancel' or None - user wants to go back
-            return (False, None)
+            return (False, None, session_dir)
 
     # Main loop: Rust TUI → Node menu → back to Rust TUI
     while True:
@@ -2202,6 +2383,11 @@ def search(
             print(f"Output was: {content[:200]}")
             return
 
+        # Multi-select: a JSON array of the marked sessions, printed for scripts
+        if isinstance(result, list):
+            print(json_lib.dumps(result, indent=2))
+            return
+
         # New format: {"session": {...}, "action": "...", "filter_state": {...}}
         # Legacy format: just the session object
         if "session" in result and "action" in result:
@@ -2219,6 +2405,35 @@ def search(
             rust_args = [str(rust_binary)]
             rust_args.extend(["--claude-home", str(claude_home)])
             rust_args.extend(["--codex-home", str(codex_home)])
+            if launch_cwd:
+                rust_args.extend(["--cwd", str(Path(launch_cwd).resolve())])
+            if enter_action_pref != "modal":
+                rust_args.extend(["--enter-action", enter_action_pref])
+            rust_args.extend(["--permalink-template", permalink_pref])
+            if not track_views_pref:
+                rust_args.append("--no-view-tracking")
+            if not confirm_exit_pref:
+                rust_args.append("--no-confirm-exit")
+            if purge:
+                rust_args.append("--purge")
+            if preview_context_pref:
+                rust_args.append("--preview-context")
+            if compact_list_pref:
+                rust_args.append("--compact")
+            if preview_lines:
+                rust_args.extend(["--preview-lines", str(preview_lines)])
+            if snippet_chars:
+                rust_args.extend(["--snippet-chars", str(snippet_chars)])
+            if group_by:
+                rust_args.extend(["--group-by", group_by])
+            if index_path:
+                rust_args.extend(["--index-path", index_path])
+            if theme_pref != "auto":
+                rust_args.extend(["--theme", theme_pref])
+            if show_column:
+                rust_args.extend(["--show", show_column])
+            if not restore_pref:
+                rust_args.append("--no-restore")
 
             # Scope: --dir overrides --global
             if filter_state.get("filter_dir"):
@@ -2241,20 +2456,36 @@ def search(
                 rust_args.append("--no-trimmed")
             if not filter_state.get("include_continued", True):
                 rust_args.append("--no-rollover")
+            if filter_state.get("include_tool"):
+                rust_args.append("--include-tool-sessions")
+            if filter_state.get("filter_has_attachments"):
+                rust_args.append("--has-attachments")
 
             # Other filters
             if filter_state.get("filter_min_lines"):
                 rust_args.extend(["--min-lines", str(filter_state["filter_min_lines"])])
+            if filter_state.get("filter_min_messages"):
+                rust_args.extend(["--min-messages", str(filter_state["filter_min_messages"])])
             if filter_state.get("filter_after_date"):
                 rust_args.extend(["--after", filter_state["filter_after_date"]])
             if filter_state.get("filter_before_date"):
                 rust_args.extend(["--before", filter_state["filter_before_date"]])
             if filter_state.get("filter_agent"):
                 rust_args.extend(["--agent", filter_state["filter_agent"]])
+            if filter_state.get("filter_project"):
+                rust_args.extend(["--project", filter_state["filter_project"]])
+            if filter_state.get("filter_lang"):
+                rust_args.extend(["--lang", filter_state["filter_lang"]])
             if filter_state.get("query"):
                 rust_args.extend(["--query", filter_state["query"]])
-            if filter_state.get("sort_by_time"):
-                rust_args.append("--by-time")
+            if filter_state.get("sort"):
+                rust_args.extend(["--sort", filter_state["sort"]])
+            if filter_state.get("phrase_mode"):
+                rust_args.append("--phrase")
+            if filter_state.get("regex_mode"):
+                rust_args.append("--regex")
+            if filter_state.get("fuzzy_mode"):
+                rust_args.append("--fuzzy")
 
             # Restore scroll/selection state
             if filter_state.get("selected") is not None:
@@ -2363,9 +2594,10 @@ def search(
                 # Continue loop to return to Rust TUI
             elif action == "suppress_resume":
                 # Trim + resume: check directory first, then show trim form
-                proceed, original_dir = check_directory_and_confirm(session)
+                proceed, original_dir, session_dir = check_directory_and_confirm(session)
                 if not proceed:
                     continue  # User cancelled - pop back to Rust search
+                session["cwd"] = session_dir  # The home fallback reaches action_handler
                 original_dir_for_interrupt = original_dir
                 run_node_menu_ui(
                     sessions=[session],
@@ -2383,9 +2615,10 @@ def search(
                     os.chdir(original_dir)
             elif action == "smart_trim_resume":
                 # Smart trim: check directory first, then show options form
-                proceed, original_dir = check_directory_and_confirm(session)
+                proceed, original_dir, session_dir = check_directory_and_confirm(session)
                 if not proceed:
                     continue  # User cancelled - pop back to Rust search
+                session["cwd"] = session_dir  # The home fallback reaches action_handler
                 original_dir_for_interrupt = original_dir
                 run_node_menu_ui(
                     sessions=[session],
@@ -2403,9 +2636,10 @@ def search(
                     os.chdir(original_dir)
             elif action == "continue":
                 # Continue with context: check directory first, then show options form
-                proceed, original_dir = check_directory_and_confirm(session)
+                proceed, original_dir, session_dir = check_directory_and_confirm(session)
                 if not proceed:
                     continue  # User cancelled - pop back to Rust search
+                session["cwd"] = session_dir  # The home fallback reaches action_handler
                 original_dir_for_interrupt = original_dir
                 run_node_menu_ui(
                     sessions=[session],
@@ -2423,9 +2657,10 @@ def search(
                     os.chdir(original_dir)
             elif action in ("resume", "clone"):
                 # Resume/clone: check directory first, then execute
-                proceed, original_dir = check_directory_and_confirm(session)
+                proceed, original_dir, session_dir = check_directory_and_confirm(session)
                 if not proceed:
                     continue  # User cancelled - pop back to Rust search
+                session["cwd"] = session_dir  # The home fallback reaches action_handler
                 original_dir_for_interrupt = original_dir
                 # Note: if successful, action_handler calls os.execvp and never returns
                 action_handler(session, action, {})
@@ -2437,8 +2672,8 @@ def search(
                 action_handler(session, action, {})
                 # Remove deleted session from search index
                 try:
-                    from claude_code_tools.search_index import SessionIndex
-                    idx = SessionIndex(Path("~/.cctools/search-index").expanduser())
+                    from claude_code_tools.search_index import SessionIndex, default_index_path
+                    idx = SessionIndex(default_index_path())
                     idx.prune_deleted()
                 except Exception:
                     pass  # Index errors shouldn't block the UI
@@ -1,10 +1,12 @@
 """
 Centralized configuration for claude-code-tools.
 
-Defaults are defined here. Users can override by creating ~/.cctools/config.json
+Defaults are defined here. Users can override by creating config.json in
+$XDG_CONFIG_HOME/cctools (when XDG_CONFIG_HOME is set) or ~/.cctools.
 """
 
 import json
+import os
 from pathlib import Path
 from typing import Any, Optional
 
@@ -17,14 +19,46 @@ DEFAULTS = {
     # Model for Codex interactive session after rollover (full capability)
     # Empty string means use codex's default model
     "codex_default_model": "",
+    # What Enter does in the aichat search TUI:
+    # "modal" (show actions menu), "view" (open full view), "select" (hand off)
+    "enter_action": "modal",
+    # Format of session references copied by the search TUI's permalink action
+    "permalink_template": "cctools://{agent}/{session_id}",
+    # Snapshot sessions opened in the search TUI's full view to flag later changes
+    "track_views": True,
+    # Ask before Esc exits the search TUI while filters are active
+    "confirm_exit": True,
+    # Load a few messages from the middle (or around the match) into the search preview
+    "preview_context": False,
+    # Drop the blank line between search results to fit more rows
+    "compact_list": False,
+    # Start the search TUI with the filters it had when it last quit
+    "restore_filters": True,
+    # Search TUI colors: "dark", "light", or "auto" (from COLORFGBG); NO_COLOR disables them
+    "theme": "auto",
 }
 
 _config_cache: Optional[dict[str, Any]] = None
 
 
+def cctools_dir(xdg_var: str) -> Path:
+    """$<xdg_var>/cctools when that variable is an absolute path, else ~/.cctools.
+
+    An existing ~/.cctools stays in use until the XDG directory exists, so setting
+    the variable later doesn't strand earlier files (matches aichat-search).
+    """
+    legacy = Path.home() / ".cctools"
+    xdg = os.environ.get(xdg_var, "")
+    if os.path.isabs(xdg):
+        xdg_dir = Path(xdg) / "cctools"
+        if xdg_dir.exists() or not legacy.exists():
+            return xdg_dir
+    return legacy
+
+
 def _load_user_config() -> dict[str, Any]:
-    """Load user config from ~/.cctools/config.json if it exists."""
-    config_path = Path.home() / ".cctools" / "config.json"
+    """Load user config from config.json (see cctools_dir) if it exists."""
+    config_path = cctools_dir("XDG_CONFIG_HOME") / "config.json"
     if config_path.exists():
         try:
             with open(config_path, "r") as f:
@@ -91,3 +125,43 @@ def codex_default_model() -> str:
     Returns empty string to use codex's default model.
     """
     return get("codex_default_model", DEFAULTS["codex_default_model"])
+
+
+def enter_action() -> str:
+    """Get what Enter does in the aichat search TUI (modal, view, or select)."""
+    return get("enter_action", DEFAULTS["enter_action"])
+
+
+def permalink_template() -> str:
+    """Get the format for session permalinks ({agent}, {session_id} placeholders)."""
+    return get("permalink_template", DEFAULTS["permalink_template"])
+
+
+def track_views() -> bool:
+    """Whether the search TUI records viewed sessions to show later changes."""
+    return bool(get("track_views", DEFAULTS["track_views"]))
+
+
+def confirm_exit() -> bool:
+    """Whether the search TUI confirms before Esc exits with active filters."""
+    return bool(get("confirm_exit", DEFAULTS["confirm_exit"]))
+
+
+def preview_context() -> bool:
+    """Whether the search TUI preview loads messages from inside the session."""
+    return bool(get("preview_context", DEFAULTS["preview_context"]))
+
+
+def compact_list() -> bool:
+    """Whether the search TUI omits the blank line between results."""
+    return bool(get("compact_list", DEFAULTS["compact_list"]))
+
+
+def restore_filters() -> bool:
+    """Whether the search TUI restores its filters from ui-state.json in the data dir."""
+    return bool(get("restore_filters", DEFAULTS["restore_filters"]))
+
+
+def theme() -> str:
+    """Get the search TUI color theme (dark, light, or auto)."""
+    return get("theme", DEFAULTS["theme"])
@@ -981,6 +981,12 @@ def resume_session(
         # Execute codex resume
         try:
             os.execvp("codex", ["codex", "resume", session_id])
+        except FileNotFoundError:
+            print(
+                "Error: 'codex' command not found. Make sure Codex CLI is installed.",
+                file=sys.stderr,
+            )
+            sys.exit(1)
         except OSError as e:
             print(f"Error launching codex: {e}")
             sys.exit(1)
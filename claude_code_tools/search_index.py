@@ -2,6 +2,7 @@
 
 import json
 import math
+import re
 import shutil
 import sys
 from dataclasses import dataclass
@@ -10,10 +11,23 @@ from importlib.metadata import version as get_pkg_version
 from pathlib import Path
 from typing import Any, Optional
 
+from claude_code_tools.config import cctools_dir
 from claude_code_tools.export_session import _is_meta_user_message
 from claude_code_tools.session_utils import is_valid_session
 
 
+def default_index_path() -> Path:
+    """Index directory: $XDG_DATA_HOME/cctools/search-index when XDG_DATA_HOME is
+    an absolute path, else ~/.cctools/search-index (see config.cctools_dir)."""
+    return cctools_dir("XDG_DATA_HOME") / "search-index"
+
+
+# Bump when the schema or what gets indexed into a field changes, so existing
+# indexes are rebuilt even without a package release (and bump INDEX_SCHEMA_VERSION
+# in rust-search-ui to match)
+INDEX_SCHEMA_VERSION = 5
+
+
 def _get_package_version() -> str:
     """Get installed package version for automatic index rebuilding."""
     try:
@@ -21,6 +35,11 @@ def _get_package_version() -> str:
     except Exception:
         return "unknown"
 
+
+def _get_index_version() -> str:
+    """Version stamped into the index's VERSION file: package and schema version."""
+    return f"{_get_package_version()}+index{INDEX_SCHEMA_VERSION}"
+
 # Lazy imports to allow module to load even if deps not installed
 try:
     import yaml
@@ -37,6 +56,20 @@ except ImportError:
     TANTIVY_AVAILABLE = False
 
 
+def _add_list_meta(doc) -> None:
+    """Copy a document's fields, all but content, into its list_meta column.
+
+    aichat-search reads that one fast column to list sessions at startup rather
+    than decompressing every stored document, content and all.
+    """
+    meta = {
+        name: values[0]
+        for name, values in doc.to_dict().items()
+        if name != "content" and values
+    }
+    doc.add_text("list_meta", json.dumps(meta, ensure_ascii=False))
+
+
 def _require_deps():
     """Raise helpful error if required dependencies are not installed."""
     missing = []
@@ -75,6 +108,148 @@ def _iso_to_epoch_ms(iso_str: str) -> int:
         return 0
 
 
+# Code fence info strings -> canonical language name. Mirrors normalize_code_lang
+# in rust-search-ui, which maps --lang values the same way.
+CODE_LANG_ALIASES = {
+    alias: lang
+    for lang, aliases in [
+        ("rust", ["rust", "rs"]),
+        ("python", ["python", "py", "python3", "py3", "ipython"]),
+        ("javascript", ["javascript", "js", "jsx", "mjs", "node"]),
+        ("typescript", ["typescript", "ts", "tsx"]),
+        ("shell", ["bash", "sh", "shell", "zsh", "fish", "console", "shell-session"]),
+        ("go", ["go", "golang"]),
+        ("c", ["c", "h"]),
+        ("cpp", ["cpp", "c++", "cc", "cxx", "hpp"]),
+        ("csharp", ["csharp", "cs", "c#"]),
+        ("java", ["java"]),
+        ("kotlin", ["kotlin", "kt"]),
+        ("swift", ["swift"]),
+        ("ruby", ["ruby", "rb"]),
+        ("php", ["php"]),
+        ("lua", ["lua"]),
+        ("haskell", ["haskell", "hs"]),
+        ("elixir", ["elixir", "ex", "exs"]),
+        ("scala", ["scala"]),
+        ("sql", ["sql"]),
+        ("html", ["html"]),
+        ("css", ["css", "scss"]),
+        ("json", ["json", "jsonl"]),
+        ("yaml", ["yaml", "yml"]),
+        ("toml", ["toml"]),
+        ("dockerfile", ["dockerfile", "docker"]),
+    ]
+    for alias in aliases
+}
+
+# Distinctive markers used when a session has no tagged code fences. A language
+# is detected only when at least CODE_LANG_MIN_MARKERS of its markers appear.
+CODE_LANG_MARKERS = [
+    ("rust", ["fn main(", "let mut ", "impl ", "pub fn ", "Cargo.toml", "println!("]),
+    ("python", ["def ", "import ", "__init__", "self.", "print(", ".py"]),
+    ("javascript", ["const ", "=> {", "function ", "require(", "console.log", "package.json"]),
+    ("typescript", ["interface ", ": string", "tsconfig", ".tsx", "export type "]),
+    ("go", ["package main", "func ", ":= ", "go.mod", "fmt."]),
+]
+CODE_LANG_MIN_MARKERS = 3
+
+
+def detect_code_languages(content: str) -> list[str]:
+    """Best-effort code languages a session involved, most frequent first.
+
+    Counts tagged code fences (```rust); when no fence is tagged, falls back to
+    keyword markers. Stored in the code_langs field for the TUI's --lang filter,
+    so it doesn't have to scan every transcript at startup.
+    """
+    counts: dict[str, int] = {}
+    for line in content.splitlines():
+        stripped = line.lstrip()
+        if not stripped.startswith("```"):
+            continue
+        # Info strings may carry extras: "rust,ignore", "python title=x", "ts:src/a.ts"
+        tag = re.split(r"[\s,:{]", stripped[3:], maxsplit=1)[0]
+        lang = CODE_LANG_ALIASES.get(tag.lower())
+        if lang:
+            counts[lang] = counts.get(lang, 0) + 1
+
+    if not counts:
+        for lang, markers in CODE_LANG_MARKERS:
+            hits = sum(1 for marker in markers if marker in content)
+            if hits >= CODE_LANG_MIN_MARKERS:
+                counts[lang] = hits
+
+    return [lang for lang, _ in sorted(counts.items(), key=lambda kv: (-kv[1], kv[0]))]
+
+
+# Message content block types counted as attachments: Claude pasted images and
+# PDFs/documents ("image", "document"), Codex pasted images ("input_image").
+ATTACHMENT_BLOCK_TYPES = ("image", "document", "input_image")
+
+# Tool inputs (commands, paths, edited code) and tool results are indexed so
+# code-oriented searches find them, cut to these lengths to keep the index small.
+TOOL_INPUT_MAX_CHARS = 2000
+TOOL_RESULT_MAX_CHARS = 500
+
+
+def _tool_input_text(tool_input: Any) -> str:
+    """Searchable text of a tool input: its string values, one per line.
+
+    Nested lists and dicts (e.g. MultiEdit edits, Codex command arrays) are
+    walked; each value is cut to TOOL_INPUT_MAX_CHARS.
+    """
+    if isinstance(tool_input, str):
+        return tool_input[:TOOL_INPUT_MAX_CHARS] + "\n" if tool_input else ""
+    if isinstance(tool_input, dict):
+        return "".join(_tool_input_text(v) for v in tool_input.values())
+    if isinstance(tool_input, list):
+        if tool_input and all(isinstance(v, str) for v in tool_input):
+            # Argument vectors read better (and match better) as one line
+            return _tool_input_text(" ".join(tool_input))
+        return "".join(_tool_input_text(v) for v in tool_input)
+    return ""
+
+
+def _tool_result_text(result: Any) -> str:
+    """Searchable text of a tool result (a string or a list of text blocks)."""
+    if isinstance(result, list):
+        result = "\n".join(
+            item.get("text", "")
+            for item in result
+            if isinstance(item, dict) and item.get("type") == "text"
+        )
+    if not isinstance(result, str) or not result:
+        return ""
+    return result[:TOOL_RESULT_MAX_CHARS] + "\n"
+
+
+def _codex_tool_text(payload: dict) -> str:
+    """Searchable text of a Codex tool call or tool output response_item.
+
+    Calls carry JSON-encoded `arguments` (or raw `input` for custom tools);
+    outputs are text, or JSON with an `output` field.
+    """
+    payload_type = payload.get("type")
+    if payload_type in ("function_call", "custom_tool_call"):
+        args = payload.get("arguments", payload.get("input", ""))
+        if isinstance(args, str):
+            try:
+                args = json.loads(args)
+            except json.JSONDecodeError:
+                pass
+        return f"[Tool: {payload.get('name', '')}]\n" + _tool_input_text(args)
+    if payload_type in ("function_call_output", "custom_tool_call_output"):
+        output = payload.get("output", "")
+        if isinstance(output, str):
+            try:
+                parsed = json.loads(output)
+                if isinstance(parsed, dict):
+                    output = parsed.get("output", output)
+            except json.JSONDecodeError:
+                pass
+        return _tool_result_text(output if isinstance(output, str) else str(output))
+    return ""
+
+
 @dataclass
 class SearchResult:
     """A search result with metadata and snippet."""
@@ -170,6 +345,11 @@ class SessionIndex:
         # Fast field for sorting by modification time (epoch milliseconds)
         self.schema_builder.add_unsigned_field("modified_ts", stored=True, fast=True)
         self.schema_builder.add_integer_field("lines", stored=True)
+        # Conversation turns: genuine user messages + assistant responses
+        # (consecutive assistant entries count as one response)
+        self.schema_builder.add_integer_field("message_count", stored=True)
+        # Non-text content blocks (see ATTACHMENT_BLOCK_TYPES)
+        self.schema_builder.add_integer_field("attachment_count", stored=True)
         # export_path needs "raw" tokenizer for exact match deletion
         self.schema_builder.add_text_field("export_path", stored=True, tokenizer_name="raw")
 
@@ -193,15 +373,22 @@ class SessionIndex:
 
         # Searchable content field
         self.schema_builder.add_text_field("content", stored=True)
+        # Comma-separated languages from detect_code_languages(content)
+        self.schema_builder.add_text_field("code_langs", stored=True)
+        # Everything but content as one JSON value (see _add_list_meta); "raw" keeps
+        # it whole in the fast column
+        self.schema_builder.add_text_field(
+            "list_meta", fast=True, tokenizer_name="raw", index_option="basic"
+        )
 
         self.schema = self.schema_builder.build()
 
         # Create or open index
         self.index_path.mkdir(parents=True, exist_ok=True)
 
-        # Check index version - rebuild if package version changed
+        # Check index version - rebuild if package or schema version changed
         version_file = self.index_path / "VERSION"
-        current_version = _get_package_version()
+        current_version = _get_index_version()
         needs_rebuild = False
 
         if version_file.exists():
@@ -210,7 +397,7 @@ class SessionIndex:
                 if stored_version != current_version:
                     needs_rebuild = True
                     print(
-                        f"Package version changed ({stored_version} -> {current_version}), "
+                        f"Index version changed ({stored_version} -> {current_version}), "
                         "rebuilding index..."
                     )
             except IOError:
@@ -222,14 +409,7 @@ class SessionIndex:
             print("Upgrading index to versioned format, rebuilding...")
 
         if needs_rebuild:
-            # Clear index directory contents (but keep the directory itself)
-            for item in self.index_path.iterdir():
-                if item.is_file():
-                    item.unlink()
-                elif item.is_dir():
-                    shutil.rmtree(item)
-            # Reset state tracker
-            self.state = IndexState(self.index_path / "index_state.json")
+            self._clear_index_dir()
 
         # Create or open index
         self.index = tantivy.Index(self.schema, path=str(self.index_path))
@@ -237,6 +417,15 @@ class SessionIndex:
         # Write current version
         version_file.write_text(current_version)
 
+    def _clear_index_dir(self) -> None:
+        """Remove index contents (keeping the directory) and reset state."""
+        for item in self.index_path.iterdir():
+            if item.is_file():
+                item.unlink()
+            elif item.is_dir():
+                shutil.rmtree(item)
+        self.state = IndexState(self.index_path / "index_state.json")
+
     def _parse_export_file(self, export_path: Path) -> Optional[dict[str, Any]]:
         """
         Parse an exported session file with YAML front matter.
@@ -341,6 +530,10 @@ class SessionIndex:
             doc.add_text("custom_title", metadata.get("customTitle", "") or "")
 
             doc.add_text("content", parsed["content"])
+            doc.add_text(
+                "code_langs", ",".join(detect_code_languages(parsed["content"]))
+            )
+            _add_list_meta(doc)
 
             writer.add_document(doc)
             self.state.mark_indexed(export_path)
@@ -429,6 +622,10 @@ class SessionIndex:
             doc.add_text("custom_title", metadata.get("customTitle", "") or "")
 
             doc.add_text("content", parsed["content"])
+            doc.add_text(
+                "code_langs", ",".join(detect_code_languages(parsed["content"]))
+            )
+            _add_list_meta(doc)
 
             writer.add_document(doc)
             self.state.mark_indexed(export_path)
@@ -484,7 +681,7 @@ class SessionIndex:
 
     def _extract_session_content(
         self, jsonl_path: Path, agent: str
-    ) -> tuple[str, int, str]:
+    ) -> tuple[str, int, int, int, str]:
         """
         Extract searchable content from a session file.
 
@@ -495,10 +692,14 @@ class SessionIndex:
             agent: Agent type ('claude' or 'codex')
 
         Returns:
-            Tuple of (content_string, user_message_count, custom_title)
+            Tuple of (content_string, user_message_count, message_count,
+            attachment_count, custom_title)
         """
         messages = []
         user_count = 0  # Count only user messages for the "lines" metric
+        turn_count = 0  # Genuine user messages + assistant responses
+        attachment_count = 0  # Image/document blocks in message content
+        last_turn_role: Optional[str] = None
         custom_title = ""  # Session name from /rename command
 
         try:
@@ -549,6 +750,8 @@ class SessionIndex:
                                 and not _is_meta_user_message(data, text_content)
                             ):
                                 user_count += 1
+                                turn_count += 1
+                                last_turn_role = "user"
 
                         if not content:
                             continue
@@ -561,21 +764,17 @@ class SessionIndex:
                                 if isinstance(block, str):
                                     text += block + "\n"
                                 elif isinstance(block, dict):
-                                    if block.get("type") == "text":
+                                    if block.get("type") in ATTACHMENT_BLOCK_TYPES:
+                                        attachment_count += 1
+                                    elif block.get("type") == "text":
                                         text += block.get("text", "") + "\n"
                                     elif block.get("type") == "tool_use":
                                         tool_name = block.get("name", "")
                                         text += f"[Tool: {tool_name}]\n"
                                         # Index tool input content
-                                        tool_input = block.get("input", {})
-                                        if isinstance(tool_input, dict):
-                                            for value in tool_input.values():
-                                                if isinstance(value, str) and value:
-                                                    text += f"{value}\n"
+                                        text += _tool_input_text(block.get("input", {}))
                                     elif block.get("type") == "tool_result":
-                                        result = block.get("content", "")
-                                        if isinstance(result, str):
-                                            text += result[:500] + "\n"
+                                        text += _tool_result_text(block.get("content", ""))
 
                     elif agent == "codex":
                         # Codex format: type is "response_item" with payload
@@ -584,6 +783,11 @@ class SessionIndex:
 
                         payload = data.get("payload", {})
                         if payload.get("type") != "message":
+                            # Tool calls and their output are searchable but
+                            # are not conversation turns
+                            tool_text = _codex_tool_text(payload)
+                            if tool_text.strip():
+                                messages.append(f"[tool] {tool_text.strip()}")
                             continue
 
                         role = payload.get("role")
@@ -603,6 +807,8 @@ class SessionIndex:
                         if role == "user":
                             if not _is_meta_user_message({}, codex_text):
                                 user_count += 1
+                                turn_count += 1
+                                last_turn_role = "user"
 
                         for block in content:
                             if not isinstance(block, dict):
@@ -611,14 +817,23 @@ class SessionIndex:
                             # input_text (user) and output_text (assistant)
                             if block_type in ("input_text", "output_text"):
                                 text += block.get("text", "") + "\n"
+                            elif block_type in ATTACHMENT_BLOCK_TYPES:
+                                attachment_count += 1
 
                     if role and text.strip():
                         messages.append(f"[{role}] {text.strip()}")
+                        # Tool results don't break up an assistant response
+                        if role == "assistant" and last_turn_role != "assistant":
+                            turn_count += 1
+                            last_turn_role = "assistant"
 
         except (OSError, IOError):
             pass
 
-        return "\n\n".join(messages), user_count, custom_title
+        return (
+            "\n\n".join(messages), user_count, turn_count, attachment_count,
+            custom_title,
+        )
 
     def _parse_jsonl_session(self, jsonl_path: Path) -> Optional[dict[str, Any]]:
         """
@@ -643,9 +858,9 @@ class SessionIndex:
             metadata = extract_session_metadata(jsonl_path, agent)
 
             # Extract content for full-text search (also extracts custom_title)
-            content, msg_count, custom_title = self._extract_session_content(
-                jsonl_path, agent
-            )
+            (
+                content, msg_count, turn_count, attachment_count, custom_title
+            ) = self._extract_session_content(jsonl_path, agent)
 
             # Add custom_title to metadata (extracted during content scan)
             if custom_title:
@@ -685,6 +900,8 @@ class SessionIndex:
                 "last_msg": last_msg,
                 "first_user_msg": first_user_msg,
                 "lines": msg_count,
+                "message_count": turn_count,
+                "attachment_count": attachment_count,
                 "file_path": str(jsonl_path),
             }
         except Exception as e:
@@ -803,6 +1020,10 @@ class SessionIndex:
                 doc.add_text("modified", modified_str)
                 doc.add_unsigned("modified_ts", _iso_to_epoch_ms(modified_str))
                 doc.add_integer("lines", parsed.get("lines", 0))
+                doc.add_integer("message_count", parsed.get("message_count", 0))
+                doc.add_integer(
+                    "attachment_count", parsed.get("attachment_count", 0)
+                )
                 doc.add_text("export_path", parsed["file_path"])  # Store JSONL path
 
                 # First and last message fields
@@ -835,6 +1056,10 @@ class SessionIndex:
                     doc.add_text("claude_home", claude_home_str)
 
                 doc.add_text("content", parsed["content"])
+                doc.add_text(
+                    "code_langs", ",".join(detect_code_languages(parsed["content"]))
+                )
+                _add_list_meta(doc)
 
                 writer.add_document(doc)
                 self.state.mark_indexed(jsonl_path)
@@ -1236,14 +1461,14 @@ def get_latest_session_from_index(
         cwd: Filter to sessions from this working directory
         branch: Filter to sessions on this git branch
         agent: Filter to specific agent ("claude" or "codex")
-        index_path: Path to index (default: ~/.cctools/search-index)
+        index_path: Path to index (default: default_index_path())
 
     Returns:
         Dict with session info including 'session_id' and 'export_path',
         or None if no matching session found
     """
     if index_path is None:
-        index_path = Path.home() / ".cctools" / "search-index"
+        index_path = default_index_path()
 
     if not index_path.exists():
         return None
@@ -1271,7 +1496,7 @@ def auto_index(
     - Returns quickly if nothing changed
 
     Args:
-        index_path: Path to Tantivy index (default: ~/.cctools/search-index)
+        index_path: Path to Tantivy index (default: default_index_path())
         claude_home: Claude home directory (default: ~/.claude)
         codex_home: Codex home directory (default: ~/.codex)
         verbose: If True, print progress messages
@@ -1284,7 +1509,7 @@ def auto_index(
 
     # Default paths
     if index_path is None:
-        index_path = Path.home() / ".cctools" / "search-index"
+        index_path = default_index_path()
     if claude_home is None:
         claude_home = Path.home() / ".claude"
     if codex_home is None:
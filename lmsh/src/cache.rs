@@ -0,0 +1,106 @@
+// Commands the user accepted for a request, so asking the same thing again skips the
+// agent. Keyed by the normalized request and kept in ~/.cctools/lmsh-cache.jsonl (in the
+// history file's format), so accepted translations carry across sessions too.
+use crate::history;
+use std::path::{Path, PathBuf};
+
+/// Most requests kept; the least recently used one goes first
+const CAPACITY: usize = 200;
+
+pub fn path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").filter(|h| !h.is_empty())?;
+    Some(PathBuf::from(home).join(".cctools").join("lmsh-cache.jsonl"))
+}
+
+pub struct TranslationCache {
+    entries: Vec<(String, String)>, // (normalized request, command), most recently used last
+    file: Option<PathBuf>,
+}
+
+impl TranslationCache {
+    /// A cache seeded from `file` (later lines win), which accepted commands are added to.
+    /// None keeps it in memory only.
+    pub fn open(file: Option<&Path>) -> Self {
+        let mut cache = TranslationCache { entries: Vec::new(), file: file.map(Path::to_path_buf) };
+        for (request, command) in file.map(history::load).unwrap_or_default() {
+            cache.insert(&request, &command);
+        }
+        cache
+    }
+
+    pub fn get(&mut self, request: &str) -> Option<String> {
+        let key = normalize(request);
+        let pos = self.entries.iter().position(|(k, _)| *k == key)?;
+        let entry = self.entries.remove(pos);
+        let command = entry.1.clone();
+        self.entries.push(entry);
+        Some(command)
+    }
+
+    /// Remember `command` (as run, after any edits) for `request`
+    pub fn accept(&mut self, request: &str, command: &str) {
+        if self.get(request).as_deref() == Some(command) {
+            return;
+        }
+        self.insert(request, command);
+        if let Some(file) = &self.file {
+            history::append(file, request, command);
+        }
+    }
+
+    fn insert(&mut self, request: &str, command: &str) {
+        let key = normalize(request);
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() >= CAPACITY {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, command.to_string()));
+    }
+}
+
+/// Case, spacing and trailing punctuation don't make a different request
+fn normalize(request: &str) -> String {
+    let words = request.split_whitespace().collect::<Vec<_>>().join(" ");
+    words.trim_end_matches(['.', '?', '!']).trim_end().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_normalizes_and_evicts_least_recent() {
+        let mut cache = TranslationCache::open(None);
+        cache.accept("list files", "ls");
+        cache.accept("List  files?", "ls -la");
+        assert_eq!(cache.get("  list FILES "), Some("ls -la".to_string()));
+        assert_eq!(cache.get("list big files"), None);
+
+        for i in 0..CAPACITY {
+            if i == 100 {
+                cache.get("list files"); // used again, so it outlives the older entries
+            }
+            cache.insert(&format!("request {i}"), "true");
+        }
+        assert_eq!(cache.entries.len(), CAPACITY);
+        assert_eq!(cache.get("list files"), Some("ls -la".to_string()));
+        assert_eq!(cache.get("request 0"), None);
+    }
+
+    #[test]
+    fn test_accepted_commands_persist() {
+        let dir = std::env::temp_dir().join(format!("lmsh-cache-{}", std::process::id()));
+        let file = dir.join("lmsh-cache.jsonl");
+        let mut cache = TranslationCache::open(Some(&file));
+        cache.accept("show disk usage", "df -h");
+        cache.accept("show disk usage", "df -h"); // unchanged, so not written again
+        cache.accept("count lines", "wc -l *.rs");
+        let lines = std::fs::read_to_string(&file).unwrap().lines().count();
+        let mut reopened = TranslationCache::open(Some(&file));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(lines, 2);
+        assert_eq!(reopened.get("Show disk usage."), Some("df -h".to_string()));
+        assert_eq!(reopened.get("count lines"), Some("wc -l *.rs".to_string()));
+    }
+}
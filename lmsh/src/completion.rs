@@ -0,0 +1,127 @@
+// Tab completion of file paths at the lmsh prompts. Relative paths resolve against
+// the persistent shell's working directory, which a `cd` may have moved away from lmsh's own.
+use rustyline::completion::{escape, extract_word, unescape, Completer, Pair, Quote};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ESCAPE_CHAR: Option<char> = Some('\\');
+
+/// Characters that end a path word, as in readline's default word breaks
+fn is_break_char(c: char) -> bool {
+    matches!(
+        c,
+        ' ' | '\t' | '\n' | '"' | '\\' | '\'' | '`' | '@' | '$' | '>' | '<' | '=' | ';' | '|' | '&' | '{' | '(' | '\0'
+    )
+}
+
+pub struct PathHelper {
+    cwd: PathBuf,
+}
+
+impl PathHelper {
+    pub fn new(cwd: &Path) -> Self {
+        PathHelper { cwd: cwd.to_path_buf() }
+    }
+
+    pub fn set_cwd(&mut self, cwd: &Path) {
+        self.cwd = cwd.to_path_buf();
+    }
+
+    /// (start of the word under the cursor, candidates to replace it with)
+    fn complete_path(&self, line: &str, pos: usize) -> (usize, Vec<Pair>) {
+        let (start, word) = extract_word(line, pos, ESCAPE_CHAR, is_break_char);
+        let word = unescape(word, ESCAPE_CHAR);
+        let (dir_part, file_part) = match word.rfind('/') {
+            Some(idx) => word.split_at(idx + 1),
+            None => ("", word.as_ref()),
+        };
+        let Ok(entries) = fs::read_dir(self.resolve(dir_part)) else {
+            return (start, Vec::new());
+        };
+
+        let mut candidates: Vec<Pair> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                // Dotfiles only when the word asks for them, like the shell
+                if !name.starts_with(file_part) || (name.starts_with('.') && !file_part.starts_with('.')) {
+                    return None;
+                }
+                let mut path = format!("{dir_part}{name}");
+                if entry.path().is_dir() {
+                    path.push('/');
+                }
+                Some(Pair {
+                    display: name,
+                    replacement: escape(path, ESCAPE_CHAR, is_break_char, Quote::None),
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.display.cmp(&b.display));
+        (start, candidates)
+    }
+
+    /// Directory a typed `dir/` part refers to: `~` is home, relative is under the shell's cwd
+    fn resolve(&self, dir_part: &str) -> PathBuf {
+        if let Some(rest) = dir_part.strip_prefix('~').filter(|r| r.is_empty() || r.starts_with('/')) {
+            if let Some(home) = std::env::var_os("HOME").filter(|h| !h.is_empty()) {
+                return PathBuf::from(home).join(rest.trim_start_matches('/'));
+            }
+        }
+        self.cwd.join(dir_part)
+    }
+}
+
+impl Completer for PathHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self.complete_path(line, pos))
+    }
+}
+
+impl Hinter for PathHelper {
+    type Hint = String;
+}
+
+impl Highlighter for PathHelper {}
+
+impl Validator for PathHelper {}
+
+impl Helper for PathHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_path_in_shell_cwd() {
+        let dir = std::env::temp_dir().join(format!("lmsh-complete-{}", std::process::id()));
+        fs::create_dir_all(dir.join("alps")).unwrap();
+        fs::write(dir.join("alpha.txt"), "").unwrap();
+        fs::write(dir.join("my notes.md"), "").unwrap();
+        fs::write(dir.join(".alias"), "").unwrap();
+        let helper = PathHelper::new(&dir);
+
+        let replacements = |line: &str| {
+            let (start, pairs) = helper.complete_path(line, line.len());
+            (start, pairs.into_iter().map(|p| p.replacement).collect::<Vec<_>>())
+        };
+        let results = (
+            replacements("cat al"),
+            replacements("cat my\\ n"),
+            replacements("ls ./.a"),
+            replacements(&format!("ls {}/al", dir.display())),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results.0, (4, vec!["alpha.txt".to_string(), "alps/".to_string()]));
+        assert_eq!(results.1, (4, vec!["my\\ notes.md".to_string()]));
+        assert_eq!(results.2, (3, vec!["./.alias".to_string()]));
+        assert_eq!(results.3 .1, vec![format!("{}/alpha.txt", dir.display()), format!("{}/alps/", dir.display())]);
+    }
+}
@@ -0,0 +1,194 @@
+// Natural-language -> command pairs kept in ~/.cctools/lmsh-history.jsonl, one
+// {"input": ..., "command": ...} object per line, so translations carry across sessions.
+// Only commands that were run are recorded, and the file is readable by its owner alone.
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Most pairs loaded at startup; the prompt itself only uses the last 10
+const MAX_LOADED: usize = 1000;
+
+pub fn path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").filter(|h| !h.is_empty())?;
+    Some(PathBuf::from(home).join(".cctools").join("lmsh-history.jsonl"))
+}
+
+/// Pairs from the history file, oldest first. A missing file is an empty history;
+/// lines that don't parse are skipped.
+pub fn load(path: &Path) -> Vec<(String, String)> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut pairs: Vec<(String, String)> = content.lines().filter_map(parse_line).collect();
+    let excess = pairs.len().saturating_sub(MAX_LOADED);
+    pairs.drain(..excess);
+    pairs
+}
+
+/// Best effort: a history that can't be written never gets in the way of the shell
+pub fn append(path: &Path, input: &str, command: &str) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    if let Ok(mut file) = options.open(path) {
+        let _ = writeln!(
+            file,
+            "{{\"input\": {}, \"command\": {}}}",
+            json_string(input),
+            json_string(command)
+        );
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Parse one history line: a flat object of string values with "input" and "command"
+fn parse_line(line: &str) -> Option<(String, String)> {
+    let mut chars = line.trim().chars().peekable();
+    let (mut input, mut command) = (None, None);
+    if chars.next()? != '{' {
+        return None;
+    }
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.peek() == Some(&'}') {
+            break;
+        }
+        let key = parse_string(&mut chars)?;
+        skip_whitespace(&mut chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        skip_whitespace(&mut chars);
+        let value = parse_string(&mut chars)?;
+        match key.as_str() {
+            "input" => input = Some(value),
+            "command" => command = Some(value),
+            _ => {}
+        }
+        skip_whitespace(&mut chars);
+        match chars.next()? {
+            ',' => continue,
+            '}' => break,
+            _ => return None,
+        }
+    }
+    Some((input?, command?))
+}
+
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    if chars.next()? != '"' {
+        return None;
+    }
+    let mut out = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(out),
+            '\\' => match chars.next()? {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'b' => out.push('\u{8}'),
+                'f' => out.push('\u{c}'),
+                'u' => {
+                    let mut code = parse_hex4(chars)?;
+                    // Characters outside the BMP come as a surrogate pair
+                    if (0xd800..0xdc00).contains(&code) {
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low = parse_hex4(chars)?;
+                        code = 0x10000 + ((code - 0xd800) << 10) + (low.checked_sub(0xdc00)? & 0x3ff);
+                    }
+                    out.push(char::from_u32(code)?);
+                }
+                c => out.push(c),
+            },
+            c => out.push(c),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<u32> {
+    let hex: String = (0..4).filter_map(|_| chars.next()).collect();
+    if hex.len() != 4 {
+        return None;
+    }
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_round_trip() {
+        let dir = std::env::temp_dir().join(format!("lmsh-history-{}", std::process::id()));
+        let file = dir.join("lmsh-history.jsonl");
+        append(&file, "list big files", "du -ah . | sort -rh | head");
+        append(&file, "say \"hi\"\tnow", "echo 'hi' \\\n  && date");
+        fs::write(&file, fs::read_to_string(&file).unwrap() + "not json\n").unwrap();
+        let pairs = load(&file);
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            fs::metadata(&file).unwrap().permissions().mode() & 0o777
+        };
+        fs::remove_dir_all(&dir).unwrap();
+
+        #[cfg(unix)]
+        assert_eq!(mode, 0o600);
+
+        assert_eq!(
+            pairs,
+            vec![
+                ("list big files".to_string(), "du -ah . | sort -rh | head".to_string()),
+                ("say \"hi\"\tnow".to_string(), "echo 'hi' \\\n  && date".to_string()),
+            ]
+        );
+        assert_eq!(load(&dir.join("missing.jsonl")), Vec::new());
+    }
+
+    #[test]
+    fn test_parse_line_escapes() {
+        assert_eq!(
+            parse_line(r#"{"command": "ls é 😀", "input": "x", "extra": "y"}"#),
+            Some(("x".to_string(), "ls é 😀".to_string()))
+        );
+        assert_eq!(
+            parse_line(r#"{"input": "caf\u00e9 \ud83d\ude00", "command": "a\nb"}"#),
+            Some(("café 😀".to_string(), "a\nb".to_string()))
+        );
+        assert_eq!(parse_line(r#"{"input": "x"}"#), None);
+        assert_eq!(parse_line(r#"{"input": "x", "command": 3}"#), None);
+    }
+}
@@ -1,6 +1,15 @@
 use std::env;
-use std::process::{Command, Stdio};
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+mod cache;
+mod completion;
+mod history;
 mod shell;
+use cache::TranslationCache;
+use completion::PathHelper;
 use shell::Shell;
 
 #[derive(Clone, Copy, PartialEq)]
@@ -9,8 +18,18 @@ enum Agent {
     Codex,
 }
 
-fn print_version() {
-    println!("lmsh {}", env!("CARGO_PKG_VERSION"));
+/// Claude model used when neither --model nor LMSH_MODEL is given
+const DEFAULT_MODEL: &str = "haiku";
+
+/// Seconds the agent gets before it is killed, unless --timeout or LMSH_TIMEOUT says otherwise
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+fn parse_timeout(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().filter(|&s| s > 0).map(Duration::from_secs)
+}
+
+fn print_version(model: &str) {
+    println!("lmsh {} (claude model: {})", env!("CARGO_PKG_VERSION"), model);
 }
 
 fn main() {
@@ -20,6 +39,12 @@ fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
 
     let mut agent = Agent::Claude; // default
+    let mut model_flag: Option<String> = None;
+    let mut timeout_flag: Option<Duration> = None;
+    let mut show_version = false;
+    let mut show_help = false;
+    let mut no_history = false;
+    let mut no_cache = false;
     let mut remaining_args = Vec::new();
     let mut i = 0;
 
@@ -27,14 +52,43 @@ fn main() {
     while i < args.len() {
         match args[i].as_str() {
             "--version" | "-V" => {
-                print_version();
-                return;
+                show_version = true;
+                i += 1;
             }
             "-h" | "--help" => {
-                println!(
-                    "Usage: lmsh [OPTIONS] [NATURAL_LANGUAGE_COMMAND]\n\n  [NATURAL_LANGUAGE_COMMAND]  Translate and execute, then enter interactive mode\n  --agent <claude|codex>     Agent to use (default: claude)\n  -V, --version              Print version and exit\n  -h, --help                 Show this help\n"
-                );
-                return;
+                show_help = true;
+                i += 1;
+            }
+            "--no-history" => {
+                no_history = true;
+                i += 1;
+            }
+            "--no-cache" => {
+                no_cache = true;
+                i += 1;
+            }
+            "--model" => {
+                if i + 1 < args.len() {
+                    model_flag = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("--model requires an argument");
+                    std::process::exit(1);
+                }
+            }
+            "--timeout" => {
+                match args.get(i + 1).map(|v| parse_timeout(v)) {
+                    Some(Some(timeout)) => timeout_flag = Some(timeout),
+                    Some(None) => {
+                        eprintln!("--timeout expects a positive number of seconds, got '{}'", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                    None => {
+                        eprintln!("--timeout requires an argument");
+                        std::process::exit(1);
+                    }
+                }
+                i += 2;
             }
             "--agent" => {
                 if i + 1 < args.len() {
@@ -59,6 +113,27 @@ fn main() {
         }
     }
 
+    // Model resolution: --model, then LMSH_MODEL, then the default
+    let model = model_flag
+        .or_else(|| env::var("LMSH_MODEL").ok())
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    // Same order for the agent timeout; an unparseable LMSH_TIMEOUT falls back to the default
+    let timeout = timeout_flag
+        .or_else(|| env::var("LMSH_TIMEOUT").ok().as_deref().and_then(parse_timeout))
+        .unwrap_or(Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+
+    if show_version {
+        print_version(&model);
+        return;
+    }
+    if show_help {
+        println!(
+            "Usage: lmsh [OPTIONS] [NATURAL_LANGUAGE_COMMAND]\n\n  [NATURAL_LANGUAGE_COMMAND]  Translate and execute, then enter interactive mode\n  --agent <claude|codex>     Agent to use (default: claude)\n  --model <name>             Claude model (default: $LMSH_MODEL or {DEFAULT_MODEL}; now: {model})\n  --timeout <secs>           Kill the agent after this long (default: $LMSH_TIMEOUT or {DEFAULT_TIMEOUT_SECS})\n  --no-history               Don't load or save ~/.cctools/lmsh-history.jsonl\n  --no-cache                 Always ask the agent, even for a request it has answered before\n  -V, --version              Print version and exit\n  -h, --help                 Show this help\n\nAt the prompt:\n  !<command>                 Run <command> as typed, without asking the agent\n  :retry                     Ask again for the last request, for a different command\n\nExit status: that of the last command run (0 if none ran), or 1 for a bad option or a shell that fails to start\n"
+        );
+        return;
+    }
+
     // If we have remaining args, treat them as natural language
     let initial_nl_command = if !remaining_args.is_empty() {
         Some(remaining_args.join(" "))
@@ -71,8 +146,8 @@ fn main() {
     use rustyline::{error::ReadlineError, Editor};
 
     // Keep config defaults to minimize initialization work.
-    let mut rl = Editor::<(), rustyline::history::DefaultHistory>::new().unwrap_or_else(|_| {
-        Editor::<(), rustyline::history::DefaultHistory>::new().expect("editor")
+    let mut rl = Editor::<PathHelper, rustyline::history::DefaultHistory>::new().unwrap_or_else(|_| {
+        Editor::<PathHelper, rustyline::history::DefaultHistory>::new().expect("editor")
     });
 
     // Start a persistent interactive shell in a PTY (aliases/functions/colors, one-time rc load)
@@ -83,34 +158,60 @@ fn main() {
             std::process::exit(1);
         }
     };
+    // Tab completes paths relative to the shell's directory
+    rl.set_helper(Some(PathHelper::new(pshell.cwd())));
 
-    let prompt = "lmsh> ";
-    let mut history: Vec<(String, String)> = Vec::new(); // (user_input, generated_command)
+    // (user_input, generated_command), seeded from earlier sessions unless --no-history
+    let history_file = if no_history { None } else { history::path() };
+    let mut history: Vec<(String, String)> = history_file.as_deref().map(history::load).unwrap_or_default();
+    for (input, command) in &history {
+        // Up-arrow recalls past requests and the commands they became
+        let _ = rl.add_history_entry(input.as_str());
+        let _ = rl.add_history_entry(command.as_str());
+    }
     
+    // Exit code of the last command run, which becomes lmsh's own exit status
+    let mut last_status = 0;
+    // Natural-language request last sent to the agent and the command it got, for :retry
+    let mut last_request: Option<String> = history.last().map(|(input, _)| input.clone());
+    let mut last_suggested: Option<String> = history.last().map(|(_, command)| command.clone());
+    // Repeated requests reuse the command accepted for them instead of asking the agent
+    // again; like the history, the cache file isn't touched with --no-history
+    let cache_file = if no_history { None } else { cache::path() };
+    let mut cache = if no_cache { None } else { Some(TranslationCache::open(cache_file.as_deref())) };
+
     // If initial natural language command provided, process it first
-    if let Some(nl_cmd) = initial_nl_command {
-        println!("Translating: {} (this may take a few seconds...)", nl_cmd);
-        match generate_command(&nl_cmd, &history, agent) {
+    if let Some(cmd) = initial_nl_command.as_deref().and_then(literal_command) {
+        if !cmd.is_empty() {
+            if let Some(code) = execute(&mut pshell, cmd) {
+                last_status = code;
+            }
+        }
+        println!();
+    } else if let Some(nl_cmd) = initial_nl_command {
+        last_request = Some(nl_cmd.clone());
+        match suggest(cache.as_mut(), &nl_cmd, &nl_cmd, &history, agent, &model, timeout) {
             Ok(suggested) => {
-                // Record history pair
-                history.push((nl_cmd.clone(), suggested.clone()));
-                // Allow user to edit before execution
+                last_suggested = Some(suggested.clone());
+                // Allow user to edit before execution; declining a destructive one cancels
                 let edit_prompt = "cmd> ";
-                match rl.readline_with_initial(edit_prompt, (&suggested, "")) {
+                let edited = if confirm_if_destructive(&suggested) {
+                    rl.readline_with_initial(edit_prompt, (&suggested, ""))
+                } else {
+                    Err(ReadlineError::Interrupted)
+                };
+                match edited {
                     Ok(cmdline) => {
                         let cmd = cmdline.trim();
                         if !cmd.is_empty() {
                             let _ = rl.add_history_entry(&cmdline);
-                            match pshell.run(cmd) {
-                                Ok((_code, out)) => {
-                                    if !out.is_empty() {
-                                        print!("{}", out);
-                                        if !out.ends_with('\n') {
-                                            println!();
-                                        }
-                                    }
-                                }
-                                Err(e) => eprintln!("exec error: {e}"),
+                            if let Some(cache) = cache.as_mut() {
+                                cache.accept(&nl_cmd, cmd);
+                            }
+                            remember(&mut history, history_file.as_deref(), &nl_cmd, cmd);
+                            // Output is streamed as the command runs
+                            if let Some(code) = execute(&mut pshell, cmd) {
+                                last_status = code;
                             }
                         }
                     }
@@ -122,15 +223,24 @@ fn main() {
                     }
                 }
             }
+            Err(GenerateError::Cancelled) => {
+                println!("Cancelled.");
+                last_suggested = None;
+            }
             Err(e) => {
                 eprintln!("Agent error: {}", e);
+                last_suggested = None;
             }
         }
         println!(); // Add blank line before interactive prompt
     }
     
     loop {
-        match rl.readline(prompt) {
+        if let Some(helper) = rl.helper_mut() {
+            helper.set_cwd(pshell.cwd());
+        }
+        let prompt = format!("lmsh:{}> ", display_dir(pshell.cwd()));
+        match rl.readline(&prompt) {
             Ok(line) => {
                 let trimmed = line.trim();
                 if trimmed == "exit" || trimmed == "quit" { break; }
@@ -142,11 +252,41 @@ fn main() {
                     continue;
                 }
 
-                // Natural language -> Agent -> suggested shell command
-                match generate_command(trimmed, &history, agent) {
+                // `!cmd` runs cmd as typed: no agent call, no confirmation, no history pair
+                if let Some(cmd) = literal_command(trimmed) {
+                    if !cmd.is_empty() {
+                        if let Some(code) = execute(&mut pshell, cmd) {
+                            last_status = code;
+                        }
+                    }
+                    continue;
+                }
+
+                // :retry asks again for the last request, saying the command it got was wrong
+                let retrying = trimmed == ":retry";
+                let (request, prompt) = if retrying {
+                    let Some(input) = last_request.clone() else {
+                        println!("Nothing to retry yet.");
+                        continue;
+                    };
+                    let prompt = match &last_suggested {
+                        Some(command) => retry_prompt(&input, command),
+                        None => input.clone(), // the agent failed last time, so there's no command to rule out
+                    };
+                    (input, prompt)
+                } else {
+                    (trimmed.to_string(), trimmed.to_string())
+                };
+                last_request = Some(request.clone());
+
+                // Natural language -> Agent -> suggested shell command; :retry skips the cache
+                let lookup = if retrying { None } else { cache.as_mut() };
+                match suggest(lookup, &request, &prompt, &history, agent, &model, timeout) {
                     Ok(suggested) => {
-                        // Record history pair (user_input, generated_command)
-                        history.push((trimmed.to_string(), suggested.clone()));
+                        last_suggested = Some(suggested.clone());
+                        if !confirm_if_destructive(&suggested) {
+                            continue;
+                        }
                         // Allow user to edit before execution
                         let edit_prompt = "cmd> ";
                         let edited = rl
@@ -157,17 +297,13 @@ fn main() {
                                 let cmd = cmdline.trim();
                                 if cmd.is_empty() { continue; }
                                 let _ = rl.add_history_entry(&cmdline);
-                                match pshell.run(cmd) {
-                                    Ok((_code, out)) => {
-                                        if !out.is_empty() {
-                                            print!("{}", out);
-                                            // Only add newline if output doesn't already end with one
-                                            if !out.ends_with('\n') {
-                                                println!();
-                                            }
-                                        }
-                                    }
-                                    Err(e) => eprintln!("exec error: {e}"),
+                                if let Some(cache) = cache.as_mut() {
+                                    cache.accept(&request, cmd);
+                                }
+                                remember(&mut history, history_file.as_deref(), &request, cmd);
+                                // Output is streamed as the command runs
+                                if let Some(code) = execute(&mut pshell, cmd) {
+                                    last_status = code;
                                 }
                             }
                             Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
@@ -176,8 +312,15 @@ fn main() {
                             }
                         }
                     }
+                    Err(GenerateError::Cancelled) => {
+                        // User aborted the generation - back to the prompt
+                        println!("Cancelled.");
+                        last_suggested = None;
+                        continue;
+                    }
                     Err(e) => {
                         eprintln!("Agent error: {}", e);
+                        last_suggested = None;
                         // Fallback: let user type a raw shell command
                         let fallback = rl.readline("cmd> ");
                         match fallback {
@@ -185,17 +328,9 @@ fn main() {
                                 let cmd = cmdline.trim();
                                 if cmd.is_empty() { continue; }
                                 let _ = rl.add_history_entry(&cmdline);
-                                match pshell.run(cmd) {
-                                    Ok((_code, out)) => {
-                                        if !out.is_empty() {
-                                            print!("{}", out);
-                                            // Only add newline if output doesn't already end with one
-                                            if !out.ends_with('\n') {
-                                                println!();
-                                            }
-                                        }
-                                    }
-                                    Err(e) => eprintln!("exec error: {e}"),
+                                // Output is streamed as the command runs
+                                if let Some(code) = execute(&mut pshell, cmd) {
+                                    last_status = code;
                                 }
                             }
                             Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
@@ -211,10 +346,152 @@ fn main() {
             }
         }
     }
+    if last_status != 0 {
+        std::process::exit(last_status);
+    }
+}
+
+/// The command for `request`: the one accepted for it before if `cache` has it, else
+/// a fresh one from the agent for `prompt`
+fn suggest(
+    cache: Option<&mut TranslationCache>,
+    request: &str,
+    prompt: &str,
+    history: &[(String, String)],
+    agent: Agent,
+    model: &str,
+    timeout: Duration,
+) -> Result<String, GenerateError> {
+    if let Some(command) = cache.and_then(|c| c.get(request)) {
+        println!("Cached: {request} (:retry asks the agent again)");
+        return Ok(command);
+    }
+    println!("Translating: {request} (this may take a few seconds, Ctrl-C to cancel...)");
+    generate_command(prompt, history, agent, model, timeout)
+}
+
+/// The shell command of a `!command` input, which skips translation (empty for a
+/// bare `!`); None for natural language
+fn literal_command(input: &str) -> Option<&str> {
+    input.strip_prefix('!').map(str::trim)
+}
+
+/// Record a request with the command actually run for it (as edited), both for the
+/// agent's context and in the history file; declined suggestions are never kept
+fn remember(history: &mut Vec<(String, String)>, file: Option<&std::path::Path>, input: &str, command: &str) {
+    history.push((input.to_string(), command.to_string()));
+    if let Some(file) = file {
+        history::append(file, input, command);
+    }
+}
+
+/// The request again, with the command it got last time ruled out
+fn retry_prompt(input: &str, rejected: &str) -> String {
+    format!("{input}\n\nThe previous command was wrong: <COMMAND>{rejected}</COMMAND>\nTry a different approach.")
+}
+
+/// Directory for the prompt, with the home directory shown as `~`
+fn display_dir(dir: &std::path::Path) -> String {
+    let home = env::var_os("HOME").filter(|h| !h.is_empty()).map(std::path::PathBuf::from);
+    match home.as_deref().and_then(|h| dir.strip_prefix(h).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => dir.display().to_string(),
+    }
 }
 
 // --- Agent integration ---
-fn generate_command(nl_prompt: &str, history: &[(String, String)], agent: Agent) -> Result<String, String> {
+enum GenerateError {
+    /// User pressed Ctrl-C while the agent was running
+    Cancelled,
+    /// The agent was killed after running longer than the timeout
+    TimedOut { name: String, after: Duration },
+    Failed(String),
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerateError::Cancelled => write!(f, "cancelled"),
+            GenerateError::TimedOut { name, after } => {
+                write!(f, "'{}' timed out after {}s (raise it with --timeout)", name, after.as_secs())
+            }
+            GenerateError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Run an agent to completion, killing it if the user presses Ctrl-C or it
+/// outlives `timeout`. The child runs in its own process group so the terminal's SIGINT only
+/// reaches lmsh, which then kills the whole group and reaps the child.
+fn run_interruptible(mut cmd: Command, name: &str, timeout: Duration) -> Result<Output, GenerateError> {
+    use std::io::Read;
+    use std::os::unix::process::CommandExt;
+
+    cmd.stdin(Stdio::null()).process_group(0);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| GenerateError::Failed(format!("failed to spawn '{name}': {e}")))?;
+
+    // Drain pipes on threads so a chatty child can't block on a full pipe
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        pipe.map(|mut p| {
+            std::thread::spawn(move || {
+                let mut buf = Vec::new();
+                let _ = p.read_to_end(&mut buf);
+                buf
+            })
+        })
+    };
+    let stdout_reader = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let stderr_reader = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    let previous = unsafe { libc::signal(libc::SIGINT, on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+    let started = Instant::now();
+    let status = loop {
+        let killed_by = if INTERRUPTED.load(Ordering::SeqCst) {
+            Some(GenerateError::Cancelled)
+        } else if started.elapsed() >= timeout {
+            Some(GenerateError::TimedOut { name: name.to_string(), after: timeout })
+        } else {
+            None
+        };
+        if let Some(err) = killed_by {
+            unsafe {
+                libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+            }
+            let _ = child.wait();
+            break Err(err);
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => break Err(GenerateError::Failed(format!("failed to wait for '{name}': {e}"))),
+        }
+    };
+    unsafe {
+        libc::signal(libc::SIGINT, previous);
+    }
+
+    let stdout = stdout_reader.and_then(|h| h.join().ok()).unwrap_or_default();
+    let stderr = stderr_reader.and_then(|h| h.join().ok()).unwrap_or_default();
+    status.map(|status| Output { status, stdout, stderr })
+}
+
+fn generate_command(
+    nl_prompt: &str,
+    history: &[(String, String)],
+    agent: Agent,
+    model: &str,
+    timeout: Duration,
+) -> Result<String, GenerateError> {
     // Build the user prompt with history and explicit instructions
     let user_prompt = build_user_prompt_with_history(history, nl_prompt);
 
@@ -223,45 +500,47 @@ fn generate_command(nl_prompt: &str, history: &[(String, String)], agent: Agent)
 
     match agent {
         Agent::Claude => {
-            let output = Command::new("claude")
-                .arg("-p")
+            let mut cmd = Command::new("claude");
+            cmd.arg("-p")
                 .arg(&user_prompt)
                 .arg("--append-system-prompt")
                 .arg(system_prompt)
                 .arg("--model")
-                .arg("haiku")
+                .arg(model)
                 .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .output()
-                .map_err(|e| format!("failed to spawn 'claude': {e}"))?;
+                .stderr(Stdio::piped());
+            let output = run_interruptible(cmd, "claude", timeout)?;
 
             if !output.status.success() {
                 let stderr = String::from_utf8_lossy(&output.stderr);
-                return Err(format!("claude exited with status {}: {}", output.status, stderr.trim()));
+                return Err(GenerateError::Failed(format!(
+                    "claude exited with status {}: {}",
+                    output.status,
+                    stderr.trim()
+                )));
             }
 
             let stdout = String::from_utf8_lossy(&output.stdout);
             extract_command_from_output(&stdout)
-                .ok_or_else(|| "could not extract a command from Claude output".to_string())
+                .ok_or_else(|| GenerateError::Failed("could not extract a command from Claude output".to_string()))
         }
         Agent::Codex => {
             // Codex doesn't have --append-system-prompt, so we build a combined prompt
             let combined_prompt = format!("{}\n\n{}", system_prompt, user_prompt);
-            let output = Command::new("codex")
-                .arg("exec")
+            let mut cmd = Command::new("codex");
+            cmd.arg("exec")
                 .arg(&combined_prompt)
                 .stdout(Stdio::piped())
-                .stderr(Stdio::null()) // Ignore stderr as codex streams progress there
-                .output()
-                .map_err(|e| format!("failed to spawn 'codex': {e}"))?;
+                .stderr(Stdio::null()); // Ignore stderr as codex streams progress there
+            let output = run_interruptible(cmd, "codex", timeout)?;
 
             if !output.status.success() {
-                return Err(format!("codex exited with status {}", output.status));
+                return Err(GenerateError::Failed(format!("codex exited with status {}", output.status)));
             }
 
             let stdout = String::from_utf8_lossy(&output.stdout);
             extract_command_from_output(&stdout)
-                .ok_or_else(|| "could not extract a command from Codex output".to_string())
+                .ok_or_else(|| GenerateError::Failed("could not extract a command from Codex output".to_string()))
         }
     }
 }
@@ -362,4 +641,287 @@ fn extract_from_fence(s: &str) -> Option<String> {
     None
 }
 
+// --- Destructive command check ---
+
+/// Commands that get a y/N confirmation before they reach the editor. Each is a
+/// command word followed by arguments that must all appear; a short flag like `-r`
+/// also matches inside a cluster such as `-rf`. LMSH_DANGEROUS_PATTERNS adds more,
+/// comma-separated.
+const DANGEROUS_PATTERNS: &[&str] = &[
+    "rm -r",
+    "rm -R",
+    "rm --recursive",
+    "dd",
+    "mkfs",
+    "shred",
+    "wipefs",
+    "fdisk",
+    "find -delete",
+    "chmod -R",
+    "chown -R",
+    "git reset --hard",
+    "git clean -f",
+    "git push --force",
+    "git push -f",
+    "git branch -D",
+    "git checkout .",
+    "git restore .",
+];
+
+/// Wrappers that run the command after them, so `sudo rm -rf x` is checked as `rm -rf x`
+const COMMAND_PREFIXES: &[&str] = &["sudo", "doas", "env", "nohup", "time", "nice", "xargs", "exec", "command"];
+
+/// The first pattern some subcommand of `cmd` matches, from the defaults and LMSH_DANGEROUS_PATTERNS
+fn destructive_match(cmd: &str) -> Option<String> {
+    let extra = env::var("LMSH_DANGEROUS_PATTERNS").unwrap_or_default();
+    let patterns: Vec<&str> = DANGEROUS_PATTERNS
+        .iter()
+        .copied()
+        .chain(extra.split(',').map(str::trim).filter(|p| !p.is_empty()))
+        .collect();
+    matching_pattern(cmd, &patterns).map(str::to_string)
+}
+
+fn matching_pattern<'a>(cmd: &str, patterns: &[&'a str]) -> Option<&'a str> {
+    let subcommands = split_subcommands(cmd);
+    patterns.iter().copied().find(|pattern| {
+        let mut words = pattern.split_whitespace();
+        let Some(program) = words.next() else {
+            return false;
+        };
+        let args: Vec<&str> = words.collect();
+        subcommands.iter().any(|sub| {
+            let mut tokens = sub.iter().map(String::as_str).skip_while(|t| {
+                COMMAND_PREFIXES.contains(t) || (t.contains('=') && !t.starts_with('-')) || t.starts_with('-')
+            });
+            let Some(first) = tokens.next() else {
+                return false;
+            };
+            // Match the program by name, so /bin/rm and mkfs.ext4 count too
+            let name = first.rsplit('/').next().unwrap_or(first);
+            if name != program && !name.starts_with(&format!("{program}.")) {
+                return false;
+            }
+            let rest: Vec<&str> = tokens.collect();
+            args.iter().all(|arg| rest.iter().any(|t| arg_matches(arg, t)))
+        })
+    })
+}
+
+fn arg_matches(pattern: &str, token: &str) -> bool {
+    if pattern == token {
+        return true;
+    }
+    // -r matches a cluster of short flags like -rf or -fR... but only the exact letter
+    let is_short = |s: &str| s.len() >= 2 && s.starts_with('-') && !s.starts_with("--");
+    pattern.len() == 2
+        && is_short(pattern)
+        && is_short(token)
+        && token[1..].chars().all(|c| c.is_ascii_alphabetic())
+        && token[1..].contains(&pattern[1..])
+}
+
+/// Words of each subcommand joined by `|`, `||`, `&&`, `;`, `&` or a newline.
+/// Quotes group words and keep separators inside them literal.
+fn split_subcommands(cmd: &str) -> Vec<Vec<String>> {
+    let mut subcommands: Vec<Vec<String>> = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = cmd.chars().peekable();
+
+    let end_word = |words: &mut Vec<String>, word: &mut String, in_word: &mut bool| {
+        if *in_word {
+            words.push(std::mem::take(word));
+            *in_word = false;
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                    in_word = true;
+                }
+            }
+            (None, '|' | '&' | ';' | '\n' | '(' | ')' | '`') => {
+                end_word(&mut words, &mut word, &mut in_word);
+                if !words.is_empty() {
+                    subcommands.push(std::mem::take(&mut words));
+                }
+            }
+            (None, c) if c.is_whitespace() => end_word(&mut words, &mut word, &mut in_word),
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    end_word(&mut words, &mut word, &mut in_word);
+    if !words.is_empty() {
+        subcommands.push(words);
+    }
+    subcommands
+}
+
+/// Ask before editing a command that matches a dangerous pattern. Returns true to go on.
+fn confirm_if_destructive(cmd: &str) -> bool {
+    let Some(pattern) = destructive_match(cmd) else {
+        return true;
+    };
+    println!("⚠️  This command looks destructive (matches `{pattern}`):\n    {cmd}");
+    let confirmed = read_key("Edit and run it anyway? [y/N] ") == Some('y');
+    if !confirmed {
+        println!("Skipped.");
+    }
+    confirmed
+}
+
+/// Read a single keystroke (no Enter needed) with the terminal briefly in non-canonical mode
+fn read_key(prompt: &str) -> Option<char> {
+    use std::io::{Read, Write};
+
+    print!("{prompt}");
+    let _ = std::io::stdout().flush();
+    let fd = libc::STDIN_FILENO;
+    let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+    let raw = unsafe { libc::tcgetattr(fd, &mut saved) } == 0;
+    if raw {
+        let mut attrs = saved;
+        attrs.c_lflag &= !(libc::ICANON | libc::ECHO);
+        attrs.c_cc[libc::VMIN] = 1;
+        attrs.c_cc[libc::VTIME] = 0;
+        unsafe {
+            libc::tcsetattr(fd, libc::TCSANOW, &attrs);
+        }
+    }
+    let mut byte = [0u8; 1];
+    let key = match std::io::stdin().read(&mut byte) {
+        Ok(1) => Some((byte[0] as char).to_ascii_lowercase()),
+        _ => None,
+    };
+    if raw {
+        unsafe {
+            libc::tcsetattr(fd, libc::TCSANOW, &saved);
+        }
+    }
+    println!();
+    key
+}
+
 // --- Shell execution ---
+
+/// Run a command in the persistent shell with its output streamed (stderr comes
+/// through the PTY as well), marking a failure with a dim `[exit N]` line.
+/// Returns the exit code, or None if the command couldn't be run at all.
+fn execute(pshell: &mut Shell, cmd: &str) -> Option<i32> {
+    match pshell.run(cmd, true) {
+        Ok((code, _)) => {
+            if code != 0 {
+                if io::stdout().is_terminal() {
+                    println!("\x1b[2m[exit {code}]\x1b[0m");
+                } else {
+                    println!("[exit {code}]");
+                }
+            }
+            Some(code)
+        }
+        Err(e) => {
+            eprintln!("exec error: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(cmd: &str) -> Option<&'static str> {
+        matching_pattern(cmd, DANGEROUS_PATTERNS)
+    }
+
+    #[test]
+    fn test_destructive_patterns() {
+        assert_eq!(check("rm -rf build"), Some("rm -r"));
+        assert_eq!(check("rm -fR /tmp/x"), Some("rm -R"));
+        assert_eq!(check("rm -r -f old"), Some("rm -r"));
+        assert_eq!(check("/bin/rm --recursive dir"), Some("rm --recursive"));
+        assert_eq!(check("dd if=/dev/zero of=/dev/sda"), Some("dd"));
+        assert_eq!(check("mkfs.ext4 /dev/sdb1"), Some("mkfs"));
+        assert_eq!(check("git reset --hard HEAD~1"), Some("git reset --hard"));
+        assert_eq!(check("git clean -fd"), Some("git clean -f"));
+        assert_eq!(check("find . -name '*.o' -delete"), Some("find -delete"));
+
+        assert_eq!(check("rm notes.txt"), None);
+        assert_eq!(check("ls -lR"), None);
+        assert_eq!(check("git reset --soft HEAD~1"), None);
+        assert_eq!(check("git status"), None);
+        assert_eq!(check("rm --force-all"), None);
+        assert_eq!(check("echo dd"), None);
+        assert_eq!(check("grep -r 'rm -rf' ."), None, "quoted text is an argument, not a command");
+    }
+
+    #[test]
+    fn test_destructive_subcommands_are_checked_separately() {
+        assert_eq!(check("cd build && rm -rf *"), Some("rm -r"));
+        assert_eq!(check("make clean; git push --force"), Some("git push --force"));
+        assert_eq!(check("find . -name '*.tmp' | xargs rm -rf"), Some("rm -r"));
+        assert_eq!(check("sudo FOO=1 dd if=a of=b"), Some("dd"));
+        assert_eq!(check("ls || rm -rf x"), Some("rm -r"));
+        assert_eq!(check("echo \"a && rm -rf b\""), None);
+        assert_eq!(check("ls | grep rm"), None);
+    }
+
+    #[test]
+    fn test_split_subcommands() {
+        assert_eq!(
+            split_subcommands("a 'b c' && d|e"),
+            vec![vec!["a".to_string(), "b c".to_string()], vec!["d".to_string()], vec!["e".to_string()]]
+        );
+        assert_eq!(matching_pattern("terraform destroy -auto-approve", &["terraform destroy"]), Some("terraform destroy"));
+    }
+
+    #[test]
+    fn test_literal_command() {
+        assert_eq!(literal_command("!ls -la"), Some("ls -la"));
+        assert_eq!(literal_command("! git status "), Some("git status"));
+        assert_eq!(literal_command("!"), Some(""));
+        assert_eq!(literal_command("list files"), None);
+        assert_eq!(literal_command("show files named !x"), None);
+    }
+
+    #[test]
+    fn test_retry_prompt_rules_out_the_last_command() {
+        let history = vec![("list big files".to_string(), "ls -S".to_string())];
+        let prompt = build_user_prompt_with_history(&history, &retry_prompt("list big files", "ls -S"));
+        assert!(prompt.ends_with(
+            "list big files\n\nThe previous command was wrong: <COMMAND>ls -S</COMMAND>\nTry a different approach."
+        ));
+    }
+
+    #[test]
+    fn test_agent_timeout() {
+        assert_eq!(parse_timeout("45"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_timeout("0"), None);
+        assert_eq!(parse_timeout("soon"), None);
+
+        let mut cmd = Command::new("sleep");
+        cmd.arg("10").stdout(Stdio::piped());
+        let started = Instant::now();
+        match run_interruptible(cmd, "sleep", Duration::from_millis(200)) {
+            Err(GenerateError::TimedOut { name, .. }) => assert_eq!(name, "sleep"),
+            Err(e) => panic!("expected a timeout, got: {e}"),
+            Ok(output) => panic!("expected a timeout, got exit {}", output.status),
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
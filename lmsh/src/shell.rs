@@ -1,6 +1,9 @@
 use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use std::collections::hash_map::RandomState;
 use std::env;
+use std::hash::{BuildHasher, Hasher};
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 const SENTINEL_PREFIX: &str = "<LMEND:";
 const SENTINEL_SUFFIX: &str = ">";
@@ -12,6 +15,10 @@ pub struct Shell {
     child: Box<dyn Child + Send>,
     reader: Box<dyn Read + Send>,
     writer: Box<dyn Write + Send>,
+    // Shell's working directory as of the last command, so `cd` carries over
+    cwd: PathBuf,
+    // "<LMEND:{nonce}:", random per shell so command output can't pass for the sentinel
+    sentinel: String,
 }
 
 impl Shell {
@@ -39,8 +46,9 @@ impl Shell {
         cmd.env("LMSHELL", "1");
 
         // Set the working directory to the current directory
-        if let Ok(cwd) = env::current_dir() {
-            cmd.cwd(cwd);
+        let cwd = env::current_dir().unwrap_or_default();
+        if !cwd.as_os_str().is_empty() {
+            cmd.cwd(&cwd);
         }
 
         let child = pair
@@ -70,24 +78,35 @@ impl Shell {
             child,
             reader,
             writer,
+            cwd,
+            sentinel: format!("{}{:016x}:", SENTINEL_PREFIX, sentinel_nonce()),
         };
 
         // Disable ZLE and TTY echo to prevent command echo and prompt repainting
         // This keeps aliases/functions from .zshrc but stops prompt repainting and TTY echo
         // -echonl also suppresses echoing of newlines
-        let _ = shell.run("unsetopt zle; unsetopt prompt_cr; PS1=''; stty -echo -echonl");
+        let _ = shell.run("unsetopt zle; unsetopt prompt_cr; PS1=''; stty -echo -echonl", false);
 
         Ok(shell)
     }
 
+    // Working directory the shell was left in by the last command
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
     // Runs a command in the persistent shell, returning (exit_code, output)
     // Simple implementation: write the command + sentinel, then read until the sentinel is observed.
-    pub fn run(&mut self, cmd: &str) -> Result<(i32, String), String> {
+    // With `stream`, output is also printed to stdout as it arrives (ANSI stripped), so
+    // long-running commands show progress; the returned output is the same either way.
+    pub fn run(&mut self, cmd: &str, stream: bool) -> Result<(i32, String), String> {
         // Append a sentinel that prints to the TTY to avoid being captured by pipes/redirections.
-        // Use a distinctive marker that's unlikely to appear in normal output.
+        // The marker carries this shell's nonce, so output that merely looks like it
+        // (e.g. `echo '<LMEND:0>'`) doesn't end the read early.
+        // The sentinel also carries $PWD, so a `cd` in the command is picked up.
         let to_send = format!(
-            "{}; printf '{}%d{}\\n' $? > /dev/tty\r",
-            cmd, SENTINEL_PREFIX, SENTINEL_SUFFIX
+            "{}; printf '{}%d:%s{}\\n' $? \"$PWD\" > /dev/tty\r",
+            cmd, self.sentinel, SENTINEL_SUFFIX
         );
 
         self.writer
@@ -101,6 +120,9 @@ impl Shell {
         let mut tmp = [0u8; 4096];
         let mut exit_code: Option<i32> = None;
         let mut sent_start: Option<usize> = None;
+        let mut cwd: Option<PathBuf> = None;
+        let mut printer = StreamPrinter::default();
+        let mut streamed = 0; // bytes of buf already printed
 
         loop {
             let n = self
@@ -112,15 +134,28 @@ impl Shell {
                 break;
             }
             buf.extend_from_slice(&tmp[..n]);
-            if let Some((s, _e, code)) = find_sentinel(&buf) {
+            if let Some((s, _e, code, dir)) = find_sentinel(&buf, self.sentinel.as_bytes()) {
                 exit_code = Some(code);
                 sent_start = Some(s);
+                cwd = dir;
                 break;
             }
+            if stream {
+                let end = streamed + stream_safe_len(&buf[streamed..], self.sentinel.as_bytes());
+                printer.print(&buf[streamed..end]);
+                streamed = end;
+            }
         }
 
         let exit_code = exit_code.ok_or_else(|| "shell terminated before sentinel".to_string())?;
         let sent_start = sent_start.ok_or_else(|| "no sentinel found in output".to_string())?;
+        if let Some(dir) = cwd {
+            self.cwd = dir;
+        }
+        if stream {
+            printer.print(&buf[streamed.min(sent_start)..sent_start]);
+            printer.finish();
+        }
 
         // Output before sentinel is the command output
         let output_bytes = if buf.len() >= sent_start { &buf[..sent_start] } else { &buf[..] };
@@ -136,6 +171,126 @@ impl Shell {
     }
 }
 
+/// Prints streamed output the way a captured run would show it: ANSI stripped,
+/// leading whitespace dropped, and ending on a newline.
+#[derive(Default)]
+struct StreamPrinter {
+    decoder: Utf8Decoder,
+    started: bool,
+    at_line_start: bool,
+}
+
+impl StreamPrinter {
+    fn print(&mut self, bytes: &[u8]) {
+        let text = self.decoder.decode(bytes);
+        self.print_text(&text);
+    }
+
+    fn print_text(&mut self, text: &str) {
+        let text = strip_ansi_codes(text);
+        let text = if self.started { text.as_str() } else { text.trim_start() };
+        if text.is_empty() {
+            return;
+        }
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(text.as_bytes());
+        let _ = stdout.flush();
+        self.started = true;
+        self.at_line_start = text.ends_with('\n');
+    }
+
+    fn finish(&mut self) {
+        let rest = self.decoder.finish();
+        self.print_text(&rest);
+        if self.started && !self.at_line_start {
+            println!();
+        }
+    }
+}
+
+/// Decodes UTF-8 that arrives in arbitrary chunks. A character split across two
+/// reads is held back until the rest of its bytes arrive, rather than becoming
+/// two replacement characters; bytes that can never be valid are replaced.
+#[derive(Default)]
+struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let mut out = String::new();
+        let mut pos = 0;
+        while pos < self.pending.len() {
+            match std::str::from_utf8(&self.pending[pos..]) {
+                Ok(s) => {
+                    out.push_str(s);
+                    pos = self.pending.len();
+                }
+                Err(e) => {
+                    let valid = &self.pending[pos..pos + e.valid_up_to()];
+                    out.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                    pos += e.valid_up_to();
+                    match e.error_len() {
+                        Some(n) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            pos += n;
+                        }
+                        // Incomplete character at the end: wait for the next read
+                        None => break,
+                    }
+                }
+            }
+        }
+        self.pending.drain(..pos);
+        out
+    }
+
+    /// Whatever is still held back, once no more bytes are coming
+    fn finish(&mut self) -> String {
+        let rest = String::from_utf8_lossy(&self.pending).to_string();
+        self.pending.clear();
+        rest
+    }
+}
+
+/// How much of `bytes` can be printed now: everything except a trailing piece that
+/// may be the start of the sentinel or of an ANSI escape sequence, either of which
+/// could be completed by the next read. Split UTF-8 is left to Utf8Decoder.
+fn stream_safe_len(bytes: &[u8], pre: &[u8]) -> usize {
+    let mut end = bytes.len();
+
+    // Possible sentinel: a '<' starting a prefix of it, or the whole prefix with its line unfinished
+    if let Some(lt) = bytes.iter().rposition(|&b| b == b'<') {
+        let rest = &bytes[lt..];
+        if pre.starts_with(rest) || (rest.starts_with(pre) && !rest.contains(&b'\n')) {
+            end = lt;
+        }
+    }
+
+    // Unterminated OSC (ESC ] ... BEL or ESC \), e.g. a terminal title
+    if let Some(osc) = bytes[..end].windows(2).rposition(|w| w == b"\x1b]") {
+        let body = &bytes[osc + 2..end];
+        if !body.contains(&0x07) && !body.windows(2).any(|w| w == b"\x1b\\") {
+            end = osc;
+        }
+    }
+    // Unterminated CSI (ESC [ ... letter), or a lone ESC at the end
+    if let Some(esc) = bytes[..end].iter().rposition(|&b| b == 0x1b) {
+        let seq = &bytes[esc + 1..end];
+        let complete = match seq.first() {
+            None => false,
+            Some(b'[') => seq[1..].iter().any(|b| b.is_ascii_alphabetic()),
+            Some(_) => true,
+        };
+        if !complete {
+            end = esc;
+        }
+    }
+
+    end
+}
+
 fn strip_ansi_codes(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
     let mut chars = s.chars().peekable();
@@ -182,9 +337,22 @@ fn strip_ansi_codes(s: &str) -> String {
     result
 }
 
-fn find_sentinel(buf: &[u8]) -> Option<(usize, usize, i32)> {
-    // Looks for <LMEND:NUM> pattern; returns (start_index, end_index_exclusive, num)
-    let pre = SENTINEL_PREFIX.as_bytes();
+/// Random per-shell part of the sentinel
+fn sentinel_nonce() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish()
+}
+
+fn find_sentinel(buf: &[u8], pre: &[u8]) -> Option<(usize, usize, i32, Option<PathBuf>)> {
+    // Looks for <pre>NUM:PWD> (pre being "<LMEND:{nonce}:"); returns (start_index, end_index_exclusive, num, pwd)
+    // The directory may itself contain '>', so it runs to the last '>' on the line.
     let suf = SENTINEL_SUFFIX.as_bytes()[0]; // '>'
     let hay = buf;
     let mut i = 0;
@@ -200,10 +368,16 @@ fn find_sentinel(buf: &[u8]) -> Option<(usize, usize, i32)> {
                 if b == suf {
                     if has_digit {
                         let end = j + 1;
-                        return Some((start, end, val));
+                        return Some((start, end, val, None));
                     } else {
                         break;
                     }
+                } else if b == b':' && has_digit {
+                    // Wait for the whole line, so the directory isn't cut short
+                    let line_end = j + hay[j..].iter().position(|&c| c == b'\n')?;
+                    let close = j + hay[j..line_end].iter().rposition(|&c| c == suf)?;
+                    let dir = String::from_utf8_lossy(&hay[j + 1..close]).to_string();
+                    return Some((start, close + 1, val, Some(PathBuf::from(dir))));
                 } else if (b as char).is_ascii_digit() {
                     has_digit = true;
                     val = val.saturating_mul(10).saturating_add((b - b'0') as i32);
@@ -217,3 +391,68 @@ fn find_sentinel(buf: &[u8]) -> Option<(usize, usize, i32)> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentinel_ignores_lookalike_output() {
+        let pre = b"<LMEND:00c0ffee00c0ffee:";
+        // What the PTY shows for `echo '<LMEND:0>'; echo '<LMEND:1:/tmp>'` before the real marker
+        let buf = b"<LMEND:0>\r\n<LMEND:1:/tmp>\r\n<LMEND:00c0ffee00c0ffee:0:/home/me>\r\n";
+        let (start, _, code, dir) = find_sentinel(buf, pre).expect("sentinel");
+        assert_eq!(&buf[..start], b"<LMEND:0>\r\n<LMEND:1:/tmp>\r\n");
+        assert_eq!(code, 0);
+        assert_eq!(dir, Some(PathBuf::from("/home/me")));
+
+        assert_eq!(find_sentinel(b"<LMEND:0>\r\n<LMEND:1:/tmp>\r\n", pre), None);
+        // Only a '<' that could grow into this shell's sentinel is held back while streaming
+        assert_eq!(stream_safe_len(b"<LMEND:0>\r\n", pre), 11);
+        assert_eq!(stream_safe_len(b"done\r\n<LMEND:00c0", pre), 6);
+    }
+
+    #[test]
+    fn test_sentinel_pwd_with_spaces_and_colons_split_across_reads() {
+        let pre = b"<LMEND:00c0ffee00c0ffee:";
+        let full = b"out\r\n<LMEND:00c0ffee00c0ffee:127:/tmp/my dir:v2>x>\r\n";
+        // Every read that stops short of the end of the marker's line waits for more
+        for cut in 0..full.len() {
+            assert_eq!(find_sentinel(&full[..cut], pre), None, "cut at {cut}");
+        }
+        let (start, end, code, dir) = find_sentinel(full, pre).expect("sentinel");
+        assert_eq!(&full[..start], b"out\r\n");
+        assert_eq!(&full[end..], b"\r\n");
+        assert_eq!(code, 127);
+        assert_eq!(dir, Some(PathBuf::from("/tmp/my dir:v2>x")));
+    }
+
+    #[test]
+    fn test_stream_safe_len_holds_a_partial_sentinel_until_it_diverges() {
+        let pre = b"<LMEND:00c0ffee00c0ffee:";
+        // The first read ends inside what could be the sentinel: print only "a <= b "
+        let mut buf = b"a <= b <LMEND:00c0".to_vec();
+        assert_eq!(stream_safe_len(&buf, pre), 7);
+        // The next read shows it was ordinary output, so all of it is released
+        buf.extend_from_slice(b"de>\r\n");
+        assert_eq!(stream_safe_len(&buf, pre), buf.len());
+        // A complete prefix is held until its line ends
+        assert_eq!(stream_safe_len(b"<LMEND:00c0ffee00c0ffee:0:/t", pre), 0);
+        assert_eq!(stream_safe_len(b"x\x1b[3", pre), 1, "unfinished CSI");
+    }
+
+    #[test]
+    fn test_utf8_decoder_joins_split_chars_and_replaces_invalid_bytes() {
+        let mut decoder = Utf8Decoder::default();
+        let bytes = "né✓".as_bytes();
+        // "✓" is three bytes; cut it after the first one
+        assert_eq!(decoder.decode(&bytes[..4]), "né");
+        assert_eq!(decoder.decode(&bytes[4..]), "✓");
+
+        assert_eq!(decoder.decode(b"a\xffb"), "a\u{FFFD}b");
+        // A truncated char left at the end comes out replaced on finish
+        assert_eq!(decoder.decode(&bytes[3..5]), "");
+        assert_eq!(decoder.finish(), "\u{FFFD}");
+        assert_eq!(decoder.finish(), "");
+    }
+}
@@ -9,7 +9,7 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local, TimeZone, Utc};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -21,12 +21,16 @@ use ratatui::{
     widgets::{List, ListItem, ListState, Paragraph},
     Frame, Terminal,
 };
-use serde::Serialize;
-use std::io::{self, stdout};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use std::io::{self, stdout, BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use std::collections::{HashMap, HashSet};
+use unicode_width::UnicodeWidthChar;
 use tantivy::{
-    collector::TopDocs,
+    collector::{DocSetCollector, TopDocs},
     query::{AllQuery, BooleanQuery, BoostQuery, Occur, PhraseQuery, QueryParser, TermQuery},
     schema::{IndexRecordOption, Value},
     snippet::SnippetGenerator,
@@ -43,9 +47,11 @@ struct Theme {
     selection_snippet_fg: Color,
     snippet_fg: Color,
     match_fg: Color,
+    match_bg: Color, // MATCH block in the preview
     search_bg: Color,
     placeholder_fg: Color,
     accent: Color,
+    badge_fg: Color, // Text on accent-colored badges (CMD, SELECT, input prompts)
     dim_fg: Color,
     keycap_bg: Color,
     user_bubble_bg: Color,
@@ -56,6 +62,14 @@ struct Theme {
     codex_source: Color,
     separator_fg: Color,
     scope_label_fg: Color,
+    success_fg: Color,
+    error_fg: Color,
+    highlight_fg: Color, // Search hits and warning banners
+    highlight_bg: Color,
+    query_highlight_fg: Color, // Query terms in the full view
+    query_highlight_bg: Color,
+    // Added to selections and highlights; lets the monochrome theme mark them without color
+    emphasis: Modifier,
 }
 
 impl Theme {
@@ -66,9 +80,11 @@ impl Theme {
             selection_snippet_fg: Color::Rgb(180, 180, 180),
             snippet_fg: Color::Rgb(120, 120, 120),
             match_fg: Color::Yellow,
+            match_bg: Color::Rgb(50, 40, 30),
             search_bg: Color::Rgb(30, 30, 35),
             placeholder_fg: Color::Rgb(100, 100, 100),
             accent: Color::Cyan,
+            badge_fg: Color::Black,
             dim_fg: Color::Rgb(100, 100, 100),
             keycap_bg: Color::Rgb(60, 60, 65),
             user_bubble_bg: Color::Rgb(30, 45, 55),
@@ -79,14 +95,156 @@ impl Theme {
             codex_source: Color::Rgb(80, 200, 120),
             separator_fg: Color::Rgb(60, 60, 65),
             scope_label_fg: Color::Rgb(140, 140, 140),
+            success_fg: Color::Green,
+            error_fg: Color::Red,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::Yellow,
+            query_highlight_fg: Color::White,
+            query_highlight_bg: Color::Rgb(30, 80, 180),
+            emphasis: Modifier::empty(),
         }
     }
+
+    fn light() -> Self {
+        Self {
+            selection_bg: Color::Rgb(218, 224, 235),
+            selection_header_fg: Color::Rgb(0, 100, 140),
+            selection_snippet_fg: Color::Rgb(60, 60, 60),
+            snippet_fg: Color::Rgb(110, 110, 110),
+            match_fg: Color::Rgb(170, 90, 0),
+            match_bg: Color::Rgb(252, 240, 215),
+            search_bg: Color::Rgb(236, 236, 240),
+            placeholder_fg: Color::Rgb(150, 150, 150),
+            accent: Color::Rgb(0, 120, 160),
+            badge_fg: Color::White,
+            dim_fg: Color::Rgb(130, 130, 130),
+            keycap_bg: Color::Rgb(212, 212, 220),
+            user_bubble_bg: Color::Rgb(224, 237, 248),
+            user_label: Color::Rgb(20, 110, 160),
+            claude_bubble_bg: Color::Rgb(250, 236, 226),
+            codex_bubble_bg: Color::Rgb(227, 244, 231),
+            claude_source: Color::Rgb(200, 100, 0),
+            codex_source: Color::Rgb(30, 140, 70),
+            separator_fg: Color::Rgb(200, 200, 205),
+            scope_label_fg: Color::Rgb(100, 100, 100),
+            success_fg: Color::Rgb(30, 130, 50),
+            error_fg: Color::Rgb(190, 30, 30),
+            highlight_fg: Color::Black,
+            highlight_bg: Color::Rgb(255, 221, 100),
+            query_highlight_fg: Color::Black,
+            query_highlight_bg: Color::Rgb(180, 205, 245),
+            emphasis: Modifier::empty(),
+        }
+    }
+
+    /// No colors at all (NO_COLOR): selections and highlights are shown reversed instead
+    fn monochrome() -> Self {
+        Self {
+            selection_bg: Color::Reset,
+            selection_header_fg: Color::Reset,
+            selection_snippet_fg: Color::Reset,
+            snippet_fg: Color::Reset,
+            match_fg: Color::Reset,
+            match_bg: Color::Reset,
+            search_bg: Color::Reset,
+            placeholder_fg: Color::Reset,
+            accent: Color::Reset,
+            badge_fg: Color::Reset,
+            dim_fg: Color::Reset,
+            keycap_bg: Color::Reset,
+            user_bubble_bg: Color::Reset,
+            user_label: Color::Reset,
+            claude_bubble_bg: Color::Reset,
+            codex_bubble_bg: Color::Reset,
+            claude_source: Color::Reset,
+            codex_source: Color::Reset,
+            separator_fg: Color::Reset,
+            scope_label_fg: Color::Reset,
+            success_fg: Color::Reset,
+            error_fg: Color::Reset,
+            highlight_fg: Color::Reset,
+            highlight_bg: Color::Reset,
+            query_highlight_fg: Color::Reset,
+            query_highlight_bg: Color::Reset,
+            emphasis: Modifier::REVERSED,
+        }
+    }
+
+    /// Theme for `--theme`, given NO_COLOR and COLORFGBG from the environment.
+    /// A non-empty NO_COLOR always wins; `auto` picks light only when COLORFGBG
+    /// reports a light background.
+    fn resolve(choice: ThemeChoice, no_color: Option<&str>, colorfgbg: Option<&str>) -> Self {
+        if no_color.is_some_and(|v| !v.is_empty()) {
+            return Self::monochrome();
+        }
+        match choice {
+            ThemeChoice::Dark => Self::dark(),
+            ThemeChoice::Light => Self::light(),
+            ThemeChoice::Auto if colorfgbg.is_some_and(colorfgbg_is_light) => Self::light(),
+            ThemeChoice::Auto => Self::dark(),
+        }
+    }
+
+    fn selection(&self) -> Style {
+        Style::default().bg(self.selection_bg).add_modifier(self.emphasis)
+    }
+
+    fn highlight(&self) -> Style {
+        Style::default()
+            .bg(self.highlight_bg)
+            .fg(self.highlight_fg)
+            .add_modifier(self.emphasis)
+    }
+}
+
+/// `--theme` value
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum ThemeChoice {
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+impl ThemeChoice {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(Self::Auto),
+            "dark" => Some(Self::Dark),
+            "light" => Some(Self::Light),
+            _ => None,
+        }
+    }
+}
+
+/// COLORFGBG is "fg;bg" (rxvt adds a middle field); background colors 7 and
+/// 9-15 are the light ANSI colors
+fn colorfgbg_is_light(value: &str) -> bool {
+    value
+        .rsplit(';')
+        .next()
+        .and_then(|bg| bg.trim().parse::<u8>().ok())
+        .is_some_and(|bg| bg == 7 || (9..=15).contains(&bg))
 }
 
 // ============================================================================
 // Terminal Size Constants
 // ============================================================================
 
+/// First messages wrapping to at least this many lines are folded in the preview by default.
+const PREVIEW_FOLD_MIN_LINES: usize = 4;
+
+/// Pane rows per line of the LAST message shown in the preview (a 36-row pane shows 6);
+/// MATCH gets twice as many. Never fewer than the minimum, so short panes keep a readable message.
+const PREVIEW_ROWS_PER_MESSAGE_LINE: usize = 6;
+const PREVIEW_MIN_MESSAGE_LINES: usize = 2;
+
+/// Typing pause before the query is re-run, so fast typing searches once, not per key.
+const FILTER_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Number of parsed conversations kept for instant re-entry into full view.
+const CONVERSATION_CACHE_SIZE: usize = 8;
+
 /// Minimum terminal width to properly display all session fields without truncation.
 /// Fields: row# + session_id + project + branch + lines + date + annotations
 const MIN_TERMINAL_WIDTH: u16 = 110;
@@ -95,7 +253,7 @@ const MIN_TERMINAL_WIDTH: u16 = 110;
 // Session Data
 // ============================================================================
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 struct Session {
     session_id: String,
     agent: String,
@@ -106,6 +264,8 @@ struct Session {
     modified: String,
     modified_ts: u64,         // Epoch milliseconds for reliable sorting
     lines: i64,
+    message_count: Option<i64>, // Conversation turns; None for indexes built before the field
+    attachment_count: i64,    // Image/document content blocks (0 for older indexes)
     #[serde(rename = "file_path")]
     export_path: String,
     first_msg_role: String,
@@ -117,9 +277,52 @@ struct Session {
     is_sidechain: bool,       // Sub-agent session
     claude_home: String,      // Source Claude home directory
     custom_title: String,     // User-assigned session name (from /rename)
+    #[serde(skip)]
+    code_langs: Vec<String>,  // Best-effort detected code languages, most frequent first
+    #[serde(skip)]
+    tool_generated: bool,     // Decided at load time, while the first messages are at hand
+    #[serde(skip)]
+    messages_loaded: bool,    // first/last message text present (see App::ensure_messages)
+}
+
+/// Prompt fragments the tooling itself sends via `claude -p` / `codex exec`:
+/// the session query action, `aichat query`, lmsh command generation, and the
+/// summary action.
+/// Sessions whose first user message contains one of these are treated as tool-generated.
+/// (Helper sessions marked `sessionType: "helper"` are already skipped by the indexer.)
+const TOOL_SESSION_MARKERS: &[&str] = &[
+    "There is a log of a past conversation with an AI agent in this file:",
+    "Read the session transcript at ",
+    "return ONLY the command wrapped in <COMMAND></COMMAND> tags",
+    SUMMARY_PROMPT,
+];
+
+/// How lmsh prompts begin when the marker above is out of reach: the indexer keeps
+/// only the first 200 chars (newlines folded to spaces), and lmsh puts its
+/// "Previous conversation:" history (Claude) or system prompt (Codex) first.
+const TOOL_SESSION_PREFIXES: &[&str] = &[
+    "Previous conversation: User: ",
+    "DO NOT READ CLAUDE.MD; You are an expert shell command generator.",
+];
+
+/// Whether a session's first user message (or first message, for older
+/// indexes) is one of the tooling's own prompts.
+fn is_tool_prompt(first_user_msg: &str, first_msg: &str) -> bool {
+    let first = if first_user_msg.is_empty() { first_msg } else { first_user_msg };
+    TOOL_SESSION_MARKERS.iter().any(|m| first.contains(m))
+        || TOOL_SESSION_PREFIXES.iter().any(|p| first.trim_start().starts_with(p))
 }
 
 impl Session {
+    fn has_attachments(&self) -> bool {
+        self.attachment_count > 0
+    }
+
+    /// Heuristic: was this session created by claude-code-tools rather than the user?
+    fn is_tool_generated(&self) -> bool {
+        self.tool_generated
+    }
+
     fn project_name(&self) -> &str {
         if self.project.is_empty() {
             std::path::Path::new(&self.cwd)
@@ -151,6 +354,24 @@ impl Session {
         format_time_ago(&self.modified)
     }
 
+    /// Seconds from created to modified; None when either timestamp doesn't parse
+    fn duration_secs(&self) -> Option<i64> {
+        let created = DateTime::parse_from_rfc3339(&self.created).ok()?;
+        let modified = DateTime::parse_from_rfc3339(&self.modified).ok()?;
+        Some(modified.signed_duration_since(created).num_seconds().max(0))
+    }
+
+    /// Size column text: transcript lines ("295L"), or turns ("42M") with --show messages
+    fn size_display(&self, show_messages: bool) -> String {
+        if !show_messages {
+            format!("{}L", self.lines)
+        } else if let Some(count) = self.message_count {
+            format!("{}M", count)
+        } else {
+            "?M".to_string()
+        }
+    }
+
     /// Session ID display with annotations: abc12345 (t) (r) (s)
     /// For Codex, extracts UUID (last 36 chars) from session_id
     fn session_id_display(&self) -> String {
@@ -307,6 +528,15 @@ impl Session {
     }
 }
 
+/// Per-session ranking components from search_tantivy, kept for the explain popup
+#[derive(Debug, Clone, Default)]
+struct ScoreBreakdown {
+    tantivy_score: f32, // Raw BM25 score (including phrase boost)
+    recency_mult: f32,  // 1.0 + exp(-age / half_life)
+    final_score: f32,   // tantivy_score * recency_mult
+    rank: usize,        // 1-based position in the ranked results
+}
+
 // ============================================================================
 // App State
 // ============================================================================
@@ -320,19 +550,25 @@ struct App {
     preview_scroll: usize,
     should_quit: bool,
     should_select: Option<Session>,
+    should_select_many: Option<Vec<Session>>, // Enter in multi-select mode with rows marked
     total_sessions: usize,
     scope_global: bool,
     launch_cwd: String,
-    index_path: String, // Path to Tantivy index for keyword search
+    search_index: Option<SearchIndex>, // Opened once; None if the index can't be read
     search_snippets: HashMap<String, String>, // session_id -> matching snippet from content
+    search_scores: HashMap<String, ScoreBreakdown>, // session_id -> ranking components
+    search_matched_terms: HashMap<String, Vec<String>>, // session_id -> query terms it contains
 
     // Filter state - inclusion-based (true = include this type)
     include_original: bool,   // true by default - include original sessions
     include_sub: bool,        // false by default - exclude sub-agents
     include_trimmed: bool,    // true by default - include trimmed sessions
     include_continued: bool,  // true by default - include continued sessions
+    include_tool: bool,       // false by default - exclude tool-generated sessions
+    filter_has_attachments: bool, // Only sessions with image/document blocks (--has-attachments)
     filter_agent: Option<String>, // None = all, Some("claude"), Some("codex")
     filter_min_lines: Option<i64>,
+    filter_min_messages: Option<i64>, // Minimum conversation turns (--min-messages)
     filter_after_date: Option<String>,  // YYYYMMDD - modified date must be >= this
     filter_after_date_display: Option<String>, // User-friendly display format
     filter_before_date: Option<String>, // YYYYMMDD - modified date must be <= this
@@ -343,16 +579,41 @@ struct App {
     // Command mode (: prefix)
     command_mode: bool,
 
+    // List, action menu, full view and view search, innermost last (see ViewLevel)
+    view_stack: Vec<ViewLevel>,
+
     // Full conversation view
-    full_view_mode: bool,
     full_content: String,
+    // Visual (wrapped) row at the top of the full view
     full_content_scroll: usize,
+    // Row where each content line starts, plus the total row count, as wrapped at
+    // the last render; empty until then, when rows and lines coincide
+    full_view_rows: Vec<usize>,
+    // Content rows the full view showed at the last render
+    full_view_height: usize,
+    // t in full view: chronological list of the session's tool calls
+    tool_timeline_mode: bool,
+    // r in full view: show Codex reasoning summaries (hidden by default)
+    show_reasoning: bool,
+    // e in full view / (x) action: file to open in $EDITOR once the main loop suspends the TUI
+    pending_editor: Option<String>,
+    // C-l: clear the terminal before the next draw, repainting every cell
+    force_redraw: bool,
+    // List-view key bindings, from ~/.cctools/keys.toml over the defaults
+    keymap: KeyMap,
+    // z / Enter in full view: first lines of the tool-result blocks shown as a one-line summary
+    collapsed_tool_blocks: HashSet<usize>,
+    // Content line to put back at the top at the next render, once collapsing has moved rows
+    full_view_anchor: Option<usize>,
+    tool_timeline: Vec<ToolCall>,
+    tool_timeline_selected: usize,
 
     // View mode search (/pattern like less)
-    view_search_mode: bool,      // Entering search pattern
     view_search_pattern: String, // Current search pattern
     view_search_matches: Vec<usize>, // Line numbers with matches
     view_search_current: usize,  // Current match index
+    view_search_messages_only: bool, // Skip tool-result/metadata lines when matching (m toggles)
+    view_search_flags: ViewSearchFlags, // Whole word (C-w) and match case (C-c) while typing
 
     // Original query match navigation (blue highlights)
     query_match_lines: Vec<usize>,  // Line numbers with original query matches
@@ -366,14 +627,14 @@ struct App {
     input_mode: Option<InputMode>,
     input_buffer: String,
 
-    // Action mode for Enter (view/actions)
-    action_mode: Option<ActionMode>,
+    // Action menu for Enter (view/actions)
     action_modal_selected: usize,
     selected_action: Option<String>,
 
     // Filter modal
     filter_modal_open: bool,
     filter_modal_selected: usize,
+    filter_modal_query: Option<String>, // Typed after `/` in the modal: narrows its items by name
 
     // Scope modal (/ key)
     scope_modal_open: bool,
@@ -382,37 +643,343 @@ struct App {
 
     // Branch filter (Ctrl+B) - only effective when not in global mode
     filter_branch: Option<String>,
+    // Code language filter (--lang); matches any detected language in a session
+    filter_lang: Option<String>,
+    filter_project: Option<String>, // Case-insensitive substring of the project name (--project)
+    available_langs: Vec<String>, // Languages discovered across sessions, for the filter modal
+    available_homes: Vec<String>, // Claude homes of the loaded sessions, for the filter modal
     launch_branch: String, // Current git branch at launch (for default value)
 
     // Result limit
-    max_results: Option<usize>, // Limit number of displayed results (--num-results / -n)
+    max_results: Option<usize>, // --limit / --num-results / -n: keep the top N once ranked and sorted
+
+    // Sort order: the key (Relevance by default, set by --sort / C-s / C-o) and its
+    // direction; C-r flips the direction of non-relevance sorts
+    sort_key: SortKey,
+    sort_descending: bool,
+    // --group-by: header rows between runs of the same project / agent / home
+    group_by: GroupBy,
+
+    // Phrase mode: treat the whole query as one exact phrase (--phrase / C-t)
+    phrase_mode: bool,
+
+    // Regex mode: match the query as a regex instead of Tantivy search (--regex / M-r)
+    regex_mode: bool,
+    regex_error: Option<String>, // Set while the query doesn't compile; results are kept
+    regex_matches: Option<(String, HashMap<String, String>)>, // (pattern, search_regex result) of the last scan
+    search_error: Option<String>, // Set when the index couldn't run the last keyword search
+
+    // Fuzzy mode: with no keyword hits, match the query against project/branch names (--fuzzy / M-f)
+    fuzzy_mode: bool,
+    fuzzy_fallback: bool, // The current results came from the fuzzy fallback
 
-    // Sort mode: false = relevance (default), true = time (reverse chronological)
-    sort_by_time: bool,
+    // Multi-select mode (M-m): Space marks rows, Enter outputs all marked sessions.
+    // Marks are indices into sessions, so they survive query and filter changes.
+    multi_select: bool,
+    selected_set: HashSet<usize>,
+
+    // What plain Enter does (--enter-action); Alt/Ctrl-Enter always opens the action menu
+    enter_action: EnterAction,
+    // Ask before Esc quits with active filters (--no-confirm-exit turns this off)
+    confirm_exit: bool,
+
+    // Template for the copy-permalink action (--permalink-template)
+    permalink_template: String,
 
     // Exit confirmation
     confirming_exit: bool,
-    // Delete confirmation
-    confirming_delete: bool,
+    // Trash / delete confirmation, for the selected session
+    confirming_delete: Option<DeleteMode>,
+    purge_enabled: bool, // --purge: offer permanent deletion in the actions menu
 
     // Temporary status message (e.g., "Copied to clipboard")
     status_message: Option<String>,
+    // Copies that reached neither the clipboard nor the terminal; printed to stderr on exit
+    manual_copies: Vec<String>,
+    // Persistent banner when SESSION_LIMIT was hit (C-x dismisses)
+    truncation_warning: Option<String>,
+    // Cached session list column widths (reset by filter())
+    list_widths: Option<ListColumnWidths>,
+    // Cached sessions per --group-by header, reset along with list_widths
+    list_group_counts: Option<HashMap<String, usize>>,
+    // Time of the last query edit not yet searched for (see FILTER_DEBOUNCE)
+    filter_pending_since: Option<std::time::Instant>,
+    // One-line summaries from the "summarize" action, and the request in flight
+    summaries: SummaryStore,
+    summary_job: Option<SummaryJob>,
+    // Last-viewed snapshots and what changed since then for the open full view
+    track_views: bool,
+    view_snapshots: ViewSnapshots,
+    view_changes: Option<SessionChanges>,
+    changes_modal_open: bool,
+
+    // Preview match navigation (C-n / C-p)
+    preview_matches: Vec<String>,           // Highlighted windows around each query match
+    preview_match_session: Option<String>,  // session_id the preview matches belong to
+    preview_match_current: usize,           // Current match index in preview_matches
+    preview_jump_to_match: bool,            // Scroll preview to the MATCH section on next render
+    preview_first_expanded: bool,           // Tab: show long FIRST messages in full instead of folded
+    preview_lines: Option<usize>,           // --preview-lines: fixed LAST cap instead of one from the pane height
+    snippet_chars: usize,                   // --snippet-chars: length of keyword match snippets
+    // --preview-context: messages around the match (or middle) loaded from the session file
+    preview_context_enabled: bool,
+    preview_context: Option<(String, String, PreviewContext)>, // (session_id, query, context)
+
+    // Blank line between list items; off (--compact / M-l) fits ~50% more rows
+    list_separator: bool,
+    // Date column format pinned with M-t; None picks one from the terminal width
+    date_format: Option<DateFormat>,
+    // Size column shows message counts instead of transcript lines (--show messages)
+    show_messages: bool,
+
+    // Relevance explanation popup (C-e)
+    explain_modal_open: bool,
+    explain_terms: Vec<(String, Vec<usize>)>, // keyword -> char positions in session content
+
+    // Full metadata popup for the selected session (C-d)
+    details_modal_open: bool,
+    // Result of the last "verify" action: (session_id, discrepancies; empty = in sync)
+    verify_result: Option<(String, Vec<String>)>,
+
+    // Parsed full-view content keyed by (session_id, file mtime)
+    conversation_cache: ConversationCache,
 }
 
 #[derive(Clone, PartialEq)]
 enum InputMode {
     MinLines,   // :m - waiting for number
+    MinMessages, // Filter modal - waiting for number of turns
     Agent,      // :a - waiting for 1 or 2
     JumpToLine, // C-g - waiting for line number
     AfterDate,  // :> - waiting for date
+    Since,      // (w) in the filter menu - waiting for a relative age like 7d
     BeforeDate, // :< - waiting for date
     ScopeDir,   // Custom directory for scope filter
     Branch,     // C-b - waiting for branch name
+    Project,    // Filter modal - waiting for part of a project name
+    KeepTurns,  // (n) action - waiting for the number of turns to keep at each end
 }
 
-#[derive(Clone, PartialEq)]
-enum ActionMode {
-    ActionMenu,  // User pressed Enter, showing flattened action menu
+impl InputMode {
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            InputMode::MinLines | InputMode::MinMessages | InputMode::JumpToLine | InputMode::KeepTurns
+        )
+    }
+
+    /// Longest input accepted; keeps a runaway paste from flooding the prompt
+    fn max_len(&self) -> usize {
+        match self {
+            InputMode::MinLines | InputMode::MinMessages | InputMode::JumpToLine | InputMode::KeepTurns => 9,
+            InputMode::AfterDate | InputMode::BeforeDate => 32,
+            InputMode::Since => 8,
+            InputMode::ScopeDir | InputMode::Branch | InputMode::Project => 1024,
+            InputMode::Agent => 0,
+        }
+    }
+
+    fn accepts(&self, c: char) -> bool {
+        if self.is_numeric() {
+            c.is_ascii_digit()
+        } else {
+            !c.is_control()
+        }
+    }
+}
+
+/// A screen stacked over the session list. Esc closes the innermost one and shows
+/// the one it was opened from, e.g. a / search returns to the full view and a full
+/// view opened from the action menu returns to the menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ViewLevel {
+    ActionMenu, // User pressed Enter, showing flattened action menu
+    FullView,   // Whole session transcript
+    ViewSearch, // Typing a /pattern in the full view
+}
+
+/// What Enter does on a selected session (`enter_action` in config.json, next to keys.toml)
+#[derive(Clone, Copy, Default, PartialEq)]
+enum EnterAction {
+    #[default]
+    Modal,  // Show the action menu (default)
+    View,   // Open full view directly
+    Select, // Hand the session off to Python's action menu
+}
+
+impl EnterAction {
+    fn parse(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "view" => EnterAction::View,
+            "select" => EnterAction::Select,
+            _ => EnterAction::Modal,
+        }
+    }
+}
+
+/// Result order (--sort key[:asc|:desc]). Relevance is Tantivy rank with a query and
+/// newest first without one; the other keys sort on session fields.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum SortKey {
+    #[default]
+    Relevance,
+    Modified,
+    Created,
+    Lines,
+    Messages,
+    Project,
+}
+
+impl SortKey {
+    const NAMES: &'static [(&'static str, SortKey)] = &[
+        ("relevance", SortKey::Relevance),
+        ("modified", SortKey::Modified),
+        ("created", SortKey::Created),
+        ("lines", SortKey::Lines),
+        ("messages", SortKey::Messages),
+        ("project", SortKey::Project),
+    ];
+
+    /// Parse `key` or `key:asc` / `key:desc` into (key, descending)
+    fn parse(spec: &str) -> Option<(Self, bool)> {
+        let (name, dir) = spec.trim().split_once(':').unwrap_or((spec.trim(), ""));
+        let key = Self::NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, k)| k)?;
+        let descending = match dir.to_ascii_lowercase().as_str() {
+            "" => key.descending_by_default(),
+            "desc" => true,
+            "asc" => false,
+            _ => return None,
+        };
+        Some((key, descending))
+    }
+
+    fn name(self) -> &'static str {
+        Self::NAMES.iter().find(|&&(_, k)| k == self).map_or("relevance", |&(n, _)| n)
+    }
+
+    /// Next key in the C-o cycle through the list columns, ending back at relevance
+    fn next(self) -> Self {
+        match self {
+            SortKey::Relevance => SortKey::Modified,
+            SortKey::Modified | SortKey::Created => SortKey::Lines,
+            SortKey::Lines => SortKey::Messages,
+            SortKey::Messages => SortKey::Project,
+            SortKey::Project => SortKey::Relevance,
+        }
+    }
+
+    /// Newest/largest first for numbers and dates, A-Z for names
+    fn descending_by_default(self) -> bool {
+        self != SortKey::Project
+    }
+
+    /// Field order for the non-relevance keys (numeric for counts and timestamps)
+    fn compare(self, a: &Session, b: &Session) -> std::cmp::Ordering {
+        let created = |s: &Session| DateTime::parse_from_rfc3339(&s.created).map(|d| d.timestamp()).ok();
+        match self {
+            SortKey::Relevance => std::cmp::Ordering::Equal,
+            SortKey::Modified => a.modified_ts.cmp(&b.modified_ts),
+            SortKey::Created => created(a).cmp(&created(b)).then_with(|| a.created.cmp(&b.created)),
+            SortKey::Lines => a.lines.cmp(&b.lines),
+            SortKey::Messages => a.message_count.cmp(&b.message_count),
+            SortKey::Project => a.project_name().to_lowercase().cmp(&b.project_name().to_lowercase()),
+        }
+    }
+}
+
+/// --group-by: sessions sharing a key are listed together under a header row
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum GroupBy {
+    #[default]
+    None,
+    Project,
+    Agent,
+    ClaudeHome,
+}
+
+impl GroupBy {
+    const NAMES: &'static [(&'static str, GroupBy)] = &[
+        ("none", GroupBy::None),
+        ("project", GroupBy::Project),
+        ("agent", GroupBy::Agent),
+        ("home", GroupBy::ClaudeHome),
+    ];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name.trim()))
+            .map(|&(_, g)| g)
+    }
+
+    /// Header text of the group `s` is listed under
+    fn key(self, s: &Session) -> &str {
+        match self {
+            GroupBy::None => "",
+            GroupBy::Project => s.project_name(),
+            GroupBy::Agent => s.agent_display(),
+            GroupBy::ClaudeHome if s.agent == "codex" => "Codex",
+            GroupBy::ClaudeHome if s.claude_home.is_empty() => "default home",
+            GroupBy::ClaudeHome => &s.claude_home,
+        }
+    }
+}
+
+/// Date column format. The list picks one from the terminal width unless M-t has
+/// pinned it; Relative is the "3d ago" text from format_time_ago.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum DateFormat {
+    Full,
+    Medium,
+    Compact,
+    Relative,
+}
+
+impl DateFormat {
+    /// Widest text the format produces ("11/27 - 11/29 15:23", "11/27 - 11/29", "35mo", "just now")
+    fn width(self) -> usize {
+        match self {
+            DateFormat::Full => 19,
+            DateFormat::Medium => 13,
+            DateFormat::Compact => 4,
+            DateFormat::Relative => 8,
+        }
+    }
+
+    /// Widest format that fits in `available` columns
+    fn for_width(available: usize) -> Self {
+        if available >= DateFormat::Full.width() {
+            DateFormat::Full
+        } else if available >= DateFormat::Medium.width() {
+            DateFormat::Medium
+        } else {
+            DateFormat::Compact
+        }
+    }
+
+    /// Next step in the M-t cycle; None goes back to choosing by width
+    fn next(current: Option<Self>) -> Option<Self> {
+        match current {
+            None => Some(DateFormat::Full),
+            Some(DateFormat::Full) => Some(DateFormat::Medium),
+            Some(DateFormat::Medium) => Some(DateFormat::Compact),
+            Some(DateFormat::Compact) => Some(DateFormat::Relative),
+            Some(DateFormat::Relative) => None,
+        }
+    }
+
+    fn format(self, s: &Session) -> String {
+        match self {
+            DateFormat::Full => s.date_display(),
+            DateFormat::Medium => s.date_medium(),
+            DateFormat::Compact => s.date_compact(),
+            DateFormat::Relative => s.time_ago(),
+        }
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -422,11 +989,18 @@ enum FilterMenuItem {
     IncludeSub,
     IncludeTrimmed,
     IncludeContinued,  // Internally "continued", displayed as "rollover" to user
+    IncludeTool,
+    HasAttachments,
     AgentAll,
     AgentClaude,
     AgentCodex,
     MinLines,
+    MinMessages,
+    Language,
+    ClaudeHome,
+    Project,
     AfterDate,
+    Since,
     BeforeDate,
 }
 
@@ -438,15 +1012,37 @@ impl FilterMenuItem {
             FilterMenuItem::IncludeSub,
             FilterMenuItem::IncludeTrimmed,
             FilterMenuItem::IncludeContinued,
+            FilterMenuItem::IncludeTool,
+            FilterMenuItem::HasAttachments,
             FilterMenuItem::AgentAll,
             FilterMenuItem::AgentClaude,
             FilterMenuItem::AgentCodex,
             FilterMenuItem::MinLines,
+            FilterMenuItem::MinMessages,
+            FilterMenuItem::Language,
+            FilterMenuItem::ClaudeHome,
+            FilterMenuItem::Project,
             FilterMenuItem::AfterDate,
+            FilterMenuItem::Since,
             FilterMenuItem::BeforeDate,
         ]
     }
 
+    /// Items whose name contains `query`, ignoring case (all of them for no query)
+    fn matching(query: Option<&str>) -> Vec<FilterMenuItem> {
+        let query = query.unwrap_or("").to_lowercase();
+        FilterMenuItem::all()
+            .into_iter()
+            .filter(|item| item.name().to_lowercase().contains(&query))
+            .collect()
+    }
+
+    /// The label without its `(x) ` shortcut
+    fn name(&self) -> &str {
+        let label = self.label();
+        label.split_once(") ").map_or(label, |(_, name)| name)
+    }
+
     fn label(&self) -> &str {
         match self {
             FilterMenuItem::ClearAll => "(x) Reset to defaults",
@@ -454,11 +1050,18 @@ impl FilterMenuItem {
             FilterMenuItem::IncludeSub => "(s) Include sub-agent sessions",
             FilterMenuItem::IncludeTrimmed => "(t) Include trimmed sessions",
             FilterMenuItem::IncludeContinued => "(r) Include rollover sessions",
+            FilterMenuItem::IncludeTool => "(h) Include tool-generated",
+            FilterMenuItem::HasAttachments => "(m) Only with attachments",
             FilterMenuItem::AgentAll => "(a) All agents",
             FilterMenuItem::AgentClaude => "(d) Claude only",
             FilterMenuItem::AgentCodex => "(e) Codex only",
             FilterMenuItem::MinLines => "(l) Minimum lines",
+            FilterMenuItem::MinMessages => "(n) Minimum messages",
+            FilterMenuItem::Language => "(g) Code language",
+            FilterMenuItem::ClaudeHome => "(c) Claude home",
+            FilterMenuItem::Project => "(p) Project name",
             FilterMenuItem::AfterDate => "(>) After date",
+            FilterMenuItem::Since => "(w) Within the last (7d, 2w, 3mo)",
             FilterMenuItem::BeforeDate => "(<) Before date",
         }
     }
@@ -470,11 +1073,18 @@ impl FilterMenuItem {
             FilterMenuItem::IncludeSub => 's',
             FilterMenuItem::IncludeTrimmed => 't',
             FilterMenuItem::IncludeContinued => 'r',
+            FilterMenuItem::IncludeTool => 'h',
+            FilterMenuItem::HasAttachments => 'm',
             FilterMenuItem::AgentAll => 'a',
             FilterMenuItem::AgentClaude => 'd',
             FilterMenuItem::AgentCodex => 'e',
             FilterMenuItem::MinLines => 'l',
+            FilterMenuItem::MinMessages => 'n',
+            FilterMenuItem::Language => 'g',
+            FilterMenuItem::ClaudeHome => 'c',
+            FilterMenuItem::Project => 'p',
             FilterMenuItem::AfterDate => '>',
+            FilterMenuItem::Since => 'w',
             FilterMenuItem::BeforeDate => '<',
         }
     }
@@ -486,32 +1096,61 @@ enum ActionMenuItem {
     Path,       // (p) Show session file path
     Copy,       // (c) Copy session file
     CopyId,     // (i) Copy session ID to clipboard - handled in Rust
+    CopyPath,   // (y) Copy the session file's path to clipboard - handled in Rust
+    Verify,     // (f) Compare indexed first/last messages with the live file - handled in Rust
+    Summarize,  // (a) One-sentence summary via `claude -p` - handled in Rust
+    Permalink,  // (u) Copy cctools:// reference to clipboard - handled in Rust
     Export,     // (e) Export to text file (.txt)
+    ExportMarkdown, // (m) Export the transcript as Markdown (.md) - handled in Rust
+    Replay,     // (w) Write the user prompts as a replay script - handled in Rust
+    Editor,     // (x) Open the session file in $EDITOR - handled in Rust
     Query,      // (q) Query the session
     Resume,     // (r) Resume as-is
     Clone,      // (l) Clone session + resume clone
     Trim,       // (t) Trim + resume
+    KeepEnds,   // (n) Write a copy with only the first/last N turns - handled in Rust
     SmartTrim,  // (s) Smart trim + resume
     Continue,   // (o) Rollover - internally "continue", displayed as "rollover" to user
-    Delete,     // (d) Delete session file (with confirmation)
+    Trash,      // (d) Move session file to ~/.cctools/trash (with confirmation) - handled in Rust
+    Delete,     // (D) Delete session file permanently (with confirmation), only with --purge
+}
+
+/// What the delete confirmation modal is confirming
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DeleteMode {
+    Trash, // Move the file aside, recoverable by hand
+    Purge, // Delete the file (handed to Python)
 }
 
 impl ActionMenuItem {
-    fn all() -> Vec<ActionMenuItem> {
-        vec![
+    /// Menu items in order; permanent deletion only when `purge` (--purge) is on
+    fn all(purge: bool) -> Vec<ActionMenuItem> {
+        let mut items = vec![
             ActionMenuItem::View,
             ActionMenuItem::Path,
             ActionMenuItem::Copy,
             ActionMenuItem::CopyId,
+            ActionMenuItem::CopyPath,
+            ActionMenuItem::Verify,
+            ActionMenuItem::Summarize,
+            ActionMenuItem::Permalink,
             ActionMenuItem::Export,
+            ActionMenuItem::ExportMarkdown,
+            ActionMenuItem::Replay,
+            ActionMenuItem::Editor,
             ActionMenuItem::Query,
             ActionMenuItem::Resume,
             ActionMenuItem::Clone,
             ActionMenuItem::Trim,
+            ActionMenuItem::KeepEnds,
             ActionMenuItem::SmartTrim,
             ActionMenuItem::Continue,
-            ActionMenuItem::Delete,
-        ]
+            ActionMenuItem::Trash,
+        ];
+        if purge {
+            items.push(ActionMenuItem::Delete);
+        }
+        items
     }
 
     fn label(&self) -> &str {
@@ -520,14 +1159,23 @@ impl ActionMenuItem {
             ActionMenuItem::Path => "(p) Show session file path",
             ActionMenuItem::Copy => "(c) Copy session file",
             ActionMenuItem::CopyId => "(i) Copy session ID to clipboard",
+            ActionMenuItem::CopyPath => "(y) Copy session file path",
+            ActionMenuItem::Verify => "(f) Verify index against live file",
+            ActionMenuItem::Summarize => "(a) Summarize with Claude",
+            ActionMenuItem::Permalink => "(u) Copy permalink to clipboard",
             ActionMenuItem::Export => "(e) Export to text file (.txt)",
+            ActionMenuItem::ExportMarkdown => "(m) Export to Markdown (.md)",
+            ActionMenuItem::Replay => "(w) Write replay script (user prompts)",
+            ActionMenuItem::Editor => "(x) Open session file in $EDITOR",
             ActionMenuItem::Query => "(q) Query the session",
             ActionMenuItem::Resume => "(r) Resume as-is",
             ActionMenuItem::Clone => "(l) Clone session + resume clone",
             ActionMenuItem::Trim => "(t) Trim + resume...",
+            ActionMenuItem::KeepEnds => "(n) Keep first/last N turns...",
             ActionMenuItem::SmartTrim => "(s) Smart trim + resume...",
             ActionMenuItem::Continue => "(o) Rollover: handoff work to fresh session...",
-            ActionMenuItem::Delete => "(d) Delete session",
+            ActionMenuItem::Trash => "(d) Move session to trash",
+            ActionMenuItem::Delete => "(D) Delete session permanently",
         }
     }
 
@@ -537,14 +1185,23 @@ impl ActionMenuItem {
             ActionMenuItem::Path => 'p',
             ActionMenuItem::Copy => 'c',
             ActionMenuItem::CopyId => 'i',
+            ActionMenuItem::CopyPath => 'y',
+            ActionMenuItem::Verify => 'f',
+            ActionMenuItem::Summarize => 'a',
+            ActionMenuItem::Permalink => 'u',
             ActionMenuItem::Export => 'e',
+            ActionMenuItem::ExportMarkdown => 'm',
+            ActionMenuItem::Replay => 'w',
+            ActionMenuItem::Editor => 'x',
             ActionMenuItem::Query => 'q',
             ActionMenuItem::Resume => 'r',
             ActionMenuItem::Clone => 'l',
             ActionMenuItem::Trim => 't',
+            ActionMenuItem::KeepEnds => 'n',
             ActionMenuItem::SmartTrim => 's',
             ActionMenuItem::Continue => 'o',
-            ActionMenuItem::Delete => 'd',
+            ActionMenuItem::Trash => 'd',
+            ActionMenuItem::Delete => 'D',
         }
     }
 
@@ -556,13 +1213,22 @@ impl ActionMenuItem {
             ActionMenuItem::Path => "path",
             ActionMenuItem::Copy => "copy",
             ActionMenuItem::CopyId => "copy_id",  // Handled in Rust
+            ActionMenuItem::CopyPath => "copy_path",  // Handled in Rust
+            ActionMenuItem::Verify => "verify",   // Handled in Rust
+            ActionMenuItem::Summarize => "summarize",  // Handled in Rust
+            ActionMenuItem::Permalink => "permalink",  // Handled in Rust
             ActionMenuItem::Export => "export",
+            ActionMenuItem::ExportMarkdown => "export_md",  // Handled in Rust
+            ActionMenuItem::Replay => "replay",  // Handled in Rust
+            ActionMenuItem::Editor => "editor",  // Handled in Rust
             ActionMenuItem::Query => "query",
             ActionMenuItem::Resume => "resume",
             ActionMenuItem::Clone => "clone",
             ActionMenuItem::Trim => "suppress_resume",
+            ActionMenuItem::KeepEnds => "keep_ends",  // Handled in Rust
             ActionMenuItem::SmartTrim => "smart_trim_resume",
             ActionMenuItem::Continue => "continue",  // "rollover" in UI
+            ActionMenuItem::Trash => "trash",  // Handled in Rust
             ActionMenuItem::Delete => "delete",
         }
     }
@@ -580,10 +1246,155 @@ impl ActionMenuItem {
     }
 }
 
+/// Home directory: $HOME if set, else the platform lookup (passwd entry).
+/// None in environments with neither (some CI/daemon contexts).
+fn home_dir() -> Option<std::path::PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(dirs::home_dir)
+}
+
+/// Index and TUI state: `$XDG_DATA_HOME/cctools` when XDG_DATA_HOME is set, else
+/// ~/.cctools (the same rule the Python indexer follows for the index and trash)
+fn cctools_data_dir() -> Option<std::path::PathBuf> {
+    cctools_dir("XDG_DATA_HOME")
+}
+
+/// keys.toml and config.json: `$XDG_CONFIG_HOME/cctools` when XDG_CONFIG_HOME is set,
+/// else ~/.cctools
+fn cctools_config_dir() -> Option<std::path::PathBuf> {
+    cctools_dir("XDG_CONFIG_HOME")
+}
+
+/// `<$var>/cctools` when the XDG variable holds an absolute path, as `dirs` reads it on
+/// Linux but on every platform (macOS `dirs` ignores XDG); otherwise ~/.cctools, so
+/// unset variables keep everything where earlier versions put it. An existing
+/// ~/.cctools stays in use until the XDG directory exists, so setting the variable
+/// later doesn't strand summaries, trash and ui-state (same rule as config.py).
+fn cctools_dir(var: &str) -> Option<std::path::PathBuf> {
+    cctools_dir_in(std::env::var_os(var), home_dir())
+}
+
+fn cctools_dir_in(
+    xdg: Option<std::ffi::OsString>,
+    home: Option<std::path::PathBuf>,
+) -> Option<std::path::PathBuf> {
+    let legacy = home.map(|h| h.join(".cctools"));
+    xdg.map(std::path::PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join("cctools"))
+        .filter(|dir| dir.exists() || !legacy.as_ref().is_some_and(|l| l.exists()))
+        .or(legacy)
+}
+
+/// `path` with `~` expanded and, if relative, joined onto `base`; no trailing slash,
+/// so it compares equal to the cwd values in the index.
+fn absolute_path(path: &str, base: &str) -> String {
+    let path = if path.starts_with('~') {
+        expand_tilde(path)
+    } else if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("{}/{}", base, path)
+    };
+    match path.trim_end_matches('/') {
+        "" => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Expand a leading `~` or `~/`; other paths (and `~` without a known home) are
+/// returned unchanged.
+fn expand_tilde(path: &str) -> String {
+    expand_tilde_in(path, home_dir().as_deref().and_then(|h| h.to_str()))
+}
+
+fn expand_tilde_in(path: &str, home: Option<&str>) -> String {
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => path.to_string(),
+    }
+}
+
+/// Show paths under the home directory as `~/...`.
+fn abbreviate_home(path: &str) -> String {
+    abbreviate_home_in(path, home_dir().as_deref().and_then(|h| h.to_str()))
+}
+
+fn abbreviate_home_in(path: &str, home: Option<&str>) -> String {
+    let home = home.map(|h| h.trim_end_matches('/')).filter(|h| !h.is_empty());
+    match home.and_then(|h| path.strip_prefix(h)) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("~{}", rest),
+        _ => path.to_string(),
+    }
+}
+
+/// Where a copy ended up
+#[derive(Debug, PartialEq)]
+enum CopyOutcome {
+    Clipboard, // System clipboard (arboard)
+    Terminal,  // OSC 52 escape; the terminal sets its clipboard, also over SSH
+    Manual,    // Neither worked; the caller shows the value to copy by hand
+}
+
+/// Copy to the system clipboard, falling back to OSC 52 on terminals that support it
+/// (headless/SSH sessions have no clipboard for arboard to reach).
+fn copy_to_clipboard(text: &str) -> CopyOutcome {
+    #[cfg(feature = "clipboard")]
+    if let Ok(mut clipboard) = arboard::Clipboard::new() {
+        if clipboard.set_text(text).is_ok() {
+            return CopyOutcome::Clipboard;
+        }
+    }
+
+    let env = |name: &str| std::env::var(name).unwrap_or_default();
+    if osc52_supported(&env("TERM"), &env("TERM_PROGRAM"), std::env::var_os("TMUX").is_some()) {
+        use std::io::Write;
+        let mut out = io::stdout();
+        if write!(out, "\x1b]52;c;{}\x07", encode_base64(text.as_bytes()))
+            .and_then(|_| out.flush())
+            .is_ok()
+        {
+            return CopyOutcome::Terminal;
+        }
+    }
+    CopyOutcome::Manual
+}
+
+/// Best-effort OSC 52 detection from the environment; terminals can't be queried for it.
+/// tmux forwards OSC 52 with its default `set-clipboard external`.
+fn osc52_supported(term: &str, term_program: &str, in_tmux: bool) -> bool {
+    const TERMS: &[&str] = &["kitty", "alacritty", "foot", "wezterm", "ghostty", "tmux"];
+    const PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "ghostty", "tmux"];
+    in_tmux || TERMS.iter().any(|t| term.contains(t)) || PROGRAMS.contains(&term_program)
+}
+
+/// Standard base64 with padding (for OSC 52 payloads).
+fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
 /// Get the current git branch name, or empty string if not in a git repo
-fn get_current_git_branch() -> String {
-    std::process::Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+fn get_current_git_branch(dir: &str) -> String {
+    let mut git = std::process::Command::new("git");
+    if !dir.is_empty() {
+        git.current_dir(dir);
+    }
+    git.args(["rev-parse", "--abbrev-ref", "HEAD"])
         .output()
         .ok()
         .and_then(|output| {
@@ -604,7 +1415,7 @@ impl App {
         let launch_cwd = std::env::current_dir()
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_default();
-        let launch_branch = get_current_git_branch();
+        let launch_branch = get_current_git_branch(&launch_cwd);
 
         let mut app = Self {
             sessions,
@@ -615,18 +1426,24 @@ impl App {
             preview_scroll: 0,
             should_quit: false,
             should_select: None,
+            should_select_many: None,
             total_sessions: total,
             scope_global: false,
             launch_cwd,
-            index_path,
+            search_index: SearchIndex::open(&index_path).ok(),
             search_snippets: HashMap::new(),
+            search_matched_terms: HashMap::new(),
+            search_scores: HashMap::new(),
             // Filter state
             include_original: true,   // Include original by default
             include_sub: false,       // Exclude sub-agents by default
+            include_tool: false,      // Exclude tool-generated sessions by default
+            filter_has_attachments: false,
             include_trimmed: true,    // Include trimmed by default
             include_continued: true,  // Include continued by default
             filter_agent: None,
             filter_min_lines: None,
+            filter_min_messages: None,
             filter_after_date: None,
             filter_after_date_display: None,
             filter_before_date: None,
@@ -636,14 +1453,26 @@ impl App {
             // Command mode
             command_mode: false,
             // Full view mode
-            full_view_mode: false,
+            view_stack: Vec::new(),
             full_content: String::new(),
             full_content_scroll: 0,
+            full_view_rows: Vec::new(),
+            full_view_height: 20,
+            tool_timeline_mode: false,
+            show_reasoning: false,
+            pending_editor: None,
+            force_redraw: false,
+            keymap: KeyMap::default(),
+            collapsed_tool_blocks: HashSet::new(),
+            full_view_anchor: None,
+            tool_timeline: Vec::new(),
+            tool_timeline_selected: 0,
             // View mode search
-            view_search_mode: false,
             view_search_pattern: String::new(),
             view_search_matches: Vec::new(),
             view_search_current: 0,
+            view_search_messages_only: false,
+            view_search_flags: ViewSearchFlags::default(),
             // Original query match navigation
             query_match_lines: Vec::new(),
             query_match_current: 0,
@@ -654,40 +1483,102 @@ impl App {
             input_mode: None,
             input_buffer: String::new(),
             // Action mode
-            action_mode: None,
             action_modal_selected: 0,
             selected_action: None,
             // Filter modal
             filter_modal_open: false,
             filter_modal_selected: 0,
+            filter_modal_query: None,
             // Scope modal
             scope_modal_open: false,
             scope_modal_selected: 0,
             filter_dir: None,
             // Branch filter
             filter_branch: None,
+            filter_lang: None,
+            filter_project: None,
+            available_langs: Vec::new(),
+            available_homes: Vec::new(),
             launch_branch,
             // Result limit
             max_results: None,
             // Sort mode
-            sort_by_time: false,
+            sort_key: SortKey::Relevance,
+            sort_descending: true,
+            group_by: GroupBy::None,
+            // Phrase mode
+            phrase_mode: false,
+            regex_mode: false,
+            regex_error: None,
+            regex_matches: None,
+            search_error: None,
+            fuzzy_mode: false,
+            fuzzy_fallback: false,
+            multi_select: false,
+            selected_set: HashSet::new(),
+            // Enter behavior
+            enter_action: EnterAction::Modal,
+            confirm_exit: true,
+            permalink_template: PERMALINK_DEFAULT_TEMPLATE.to_string(),
             // Exit confirmation
             confirming_exit: false,
-            // Delete confirmation
-            confirming_delete: false,
+            // Trash / delete confirmation
+            confirming_delete: None,
+            purge_enabled: false,
             // Status message
             status_message: None,
+            manual_copies: Vec::new(),
+            truncation_warning: None,
+            list_widths: None,
+            list_group_counts: None,
+            track_views: true,
+            preview_context_enabled: false,
+            preview_lines: None,
+            snippet_chars: DEFAULT_SNIPPET_CHARS,
+            preview_context: None,
+            list_separator: true,
+            date_format: None,
+            show_messages: false,
+            summaries: SummaryStore::open(
+                cctools_data_dir().map(|d| d.join("summaries.json")),
+            ),
+            summary_job: None,
+            filter_pending_since: None,
+            view_snapshots: ViewSnapshots::open(
+                cctools_data_dir().map(|d| d.join("view-snapshots.json")),
+            ),
+            view_changes: None,
+            changes_modal_open: false,
+            // Preview match navigation
+            preview_matches: Vec::new(),
+            preview_match_session: None,
+            preview_match_current: 0,
+            preview_jump_to_match: false,
+            preview_first_expanded: false,
+            // Relevance explanation popup
+            explain_modal_open: false,
+            explain_terms: Vec::new(),
+            // Session details popup
+            details_modal_open: false,
+            verify_result: None,
+            // Parsed conversation cache
+            conversation_cache: ConversationCache::default(),
         };
+        app.available_langs = discover_code_langs(&app.sessions);
+        app.available_homes = discover_claude_homes(&app.sessions);
         app.filter();
         app
     }
 
     fn new_with_options(sessions: Vec<Session>, index_path: String, cli: &CliOptions) -> Self {
         let total = sessions.len();
-        let launch_cwd = std::env::current_dir()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_default();
-        let launch_branch = get_current_git_branch();
+        // --cwd stands in for the directory we were launched from
+        let launch_cwd = cli.launch_cwd.clone().unwrap_or_else(|| {
+            std::env::current_dir()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        let launch_branch = get_current_git_branch(&launch_cwd);
 
         // Parse date filters if provided
         let (after_date, after_display) = cli.after_date.as_ref()
@@ -709,22 +1600,28 @@ impl App {
             preview_scroll: 0,
             should_quit: false,
             should_select: None,
+            should_select_many: None,
             total_sessions: total,
             // --dir overrides -g: if filter_dir is set, scope_global is effectively false
             scope_global: if cli.filter_dir.is_some() { false } else { cli.global_search },
             launch_cwd,
-            index_path,
+            search_index: SearchIndex::open(&index_path).ok(),
             search_snippets: HashMap::new(),
+            search_matched_terms: HashMap::new(),
+            search_scores: HashMap::new(),
             // Filter state from CLI
             // Defaults: show original + trimmed + continued (not sub-agents)
             // Subtractive flags (--no-*) exclude types from defaults
             // Additive flag (--sub-agent) adds sub-agents to defaults
             include_original: !cli.no_original,
             include_sub: cli.include_sub,
+            include_tool: cli.include_tool,
+            filter_has_attachments: cli.has_attachments,
             include_trimmed: !cli.no_trimmed,
             include_continued: !cli.no_rollover,
             filter_agent: cli.agent_filter.clone(),
             filter_min_lines: cli.min_lines,
+            filter_min_messages: cli.min_messages,
             filter_after_date: after_date,
             filter_after_date_display: after_display,
             filter_before_date: before_date,
@@ -734,14 +1631,26 @@ impl App {
             // Command mode
             command_mode: false,
             // Full view mode
-            full_view_mode: false,
+            view_stack: Vec::new(),
             full_content: String::new(),
             full_content_scroll: 0,
+            full_view_rows: Vec::new(),
+            full_view_height: 20,
+            tool_timeline_mode: false,
+            show_reasoning: false,
+            pending_editor: None,
+            force_redraw: false,
+            keymap: KeyMap::default(),
+            collapsed_tool_blocks: HashSet::new(),
+            full_view_anchor: None,
+            tool_timeline: Vec::new(),
+            tool_timeline_selected: 0,
             // View mode search
-            view_search_mode: false,
             view_search_pattern: String::new(),
             view_search_matches: Vec::new(),
             view_search_current: 0,
+            view_search_messages_only: false,
+            view_search_flags: ViewSearchFlags::default(),
             // Original query match navigation
             query_match_lines: Vec::new(),
             query_match_current: 0,
@@ -752,30 +1661,92 @@ impl App {
             input_mode: None,
             input_buffer: String::new(),
             // Action mode
-            action_mode: None,
             action_modal_selected: 0,
             selected_action: None,
             // Filter modal
             filter_modal_open: false,
             filter_modal_selected: 0,
+            filter_modal_query: None,
             // Scope modal
             scope_modal_open: false,
             scope_modal_selected: 0,
             filter_dir: cli.filter_dir.clone(),
             // Branch filter
             filter_branch: cli.filter_branch.clone(),
+            filter_lang: cli.filter_lang.clone(),
+            filter_project: cli.filter_project.clone(),
+            available_langs: Vec::new(),
+            available_homes: Vec::new(),
             launch_branch,
             // Result limit
             max_results: cli.num_results,
-            // Sort mode (--by-time sorts by last-modified, default is relevance)
-            sort_by_time: cli.sort_by_time,
+            // Sort mode (--sort / --by-time, default is relevance)
+            sort_key: cli.sort_key,
+            sort_descending: cli.sort_descending,
+            group_by: cli.group_by,
+            // Phrase mode (--phrase forces exact-phrase matching)
+            phrase_mode: cli.phrase_mode,
+            regex_mode: cli.regex_mode,
+            regex_error: None,
+            regex_matches: None,
+            search_error: None,
+            fuzzy_mode: cli.fuzzy_mode,
+            fuzzy_fallback: false,
+            multi_select: false,
+            selected_set: HashSet::new(),
+            // Enter behavior (--enter-action modal|view|select)
+            enter_action: cli.enter_action,
+            confirm_exit: !cli.no_confirm_exit,
+            permalink_template: cli
+                .permalink_template
+                .clone()
+                .unwrap_or_else(|| PERMALINK_DEFAULT_TEMPLATE.to_string()),
             // Exit confirmation
             confirming_exit: false,
-            // Delete confirmation
-            confirming_delete: false,
+            // Trash / delete confirmation
+            confirming_delete: None,
+            purge_enabled: cli.purge,
             // Status message
             status_message: None,
+            manual_copies: Vec::new(),
+            truncation_warning: None,
+            list_widths: None,
+            list_group_counts: None,
+            track_views: !cli.no_view_tracking,
+            preview_context_enabled: cli.preview_context,
+            preview_lines: cli.preview_lines,
+            snippet_chars: cli.snippet_chars.unwrap_or(DEFAULT_SNIPPET_CHARS),
+            preview_context: None,
+            list_separator: !cli.compact,
+            date_format: None,
+            show_messages: cli.show_messages,
+            summaries: SummaryStore::open(
+                cctools_data_dir().map(|d| d.join("summaries.json")),
+            ),
+            summary_job: None,
+            filter_pending_since: None,
+            view_snapshots: ViewSnapshots::open(
+                cctools_data_dir().map(|d| d.join("view-snapshots.json")),
+            ),
+            view_changes: None,
+            changes_modal_open: false,
+            // Preview match navigation
+            preview_matches: Vec::new(),
+            preview_match_session: None,
+            preview_match_current: 0,
+            preview_jump_to_match: false,
+            preview_first_expanded: false,
+            // Relevance explanation popup
+            explain_modal_open: false,
+            explain_terms: Vec::new(),
+            // Session details popup
+            details_modal_open: false,
+            verify_result: None,
+            // Parsed conversation cache
+            conversation_cache: ConversationCache::default(),
         };
+        app.available_langs = discover_code_langs(&app.sessions);
+        app.available_homes = discover_claude_homes(&app.sessions);
         app.filter();
 
         // Restore scroll/selection state from CLI if provided
@@ -797,6 +1768,26 @@ impl App {
     }
 
     fn filter(&mut self) {
+        self.filter_pending_since = None;
+
+        // An unfinished pattern keeps the previous results instead of clearing them
+        // Case-insensitive like keyword search; `(?-i)` in the pattern turns that off
+        let regex = if self.regex_mode && !self.query.trim().is_empty() {
+            match regex::RegexBuilder::new(&self.query).case_insensitive(true).build() {
+                Ok(re) => Some(re),
+                Err(_) => {
+                    self.regex_error = Some("invalid pattern".to_string());
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        self.regex_error = None;
+        self.search_error = None;
+        self.fuzzy_fallback = false;
+        let project_needle = self.filter_project.as_ref().map(|p| p.to_lowercase());
+
         self.filtered = self
             .sessions
             .iter()
@@ -856,6 +1847,16 @@ impl App {
                     }
                 }
 
+                // Tool-generated sessions (query action, lmsh, ...) are noise by default
+                if !self.include_tool && s.is_tool_generated() {
+                    return false;
+                }
+
+                // Attachment filter
+                if self.filter_has_attachments && !s.has_attachments() {
+                    return false;
+                }
+
                 // Agent filter
                 if let Some(ref agent) = self.filter_agent {
                     if s.agent != *agent {
@@ -879,6 +1880,27 @@ impl App {
                     }
                 }
 
+                // Min messages filter (sessions from older indexes have no count; keep them)
+                if let (Some(min), Some(count)) = (self.filter_min_messages, s.message_count) {
+                    if count < min {
+                        return false;
+                    }
+                }
+
+                // Code language filter
+                if let Some(ref lang) = self.filter_lang {
+                    if !s.code_langs.contains(lang) {
+                        return false;
+                    }
+                }
+
+                // Project filter
+                if let Some(ref needle) = project_needle {
+                    if !s.project_name().to_lowercase().contains(needle.as_str()) {
+                        return false;
+                    }
+                }
+
                 // Date filters (applied to modified date)
                 if let Some(ref after_date) = self.filter_after_date {
                     if let Some(session_date) = extract_date_for_comparison(&s.modified) {
@@ -901,65 +1923,131 @@ impl App {
             .map(|(i, _)| i)
             .collect();
 
-        // If there's a keyword query, use Tantivy full-text search
-        if !self.query.trim().is_empty() {
-            let (snippets, ranked_ids) = search_tantivy(
-                &self.index_path,
-                &self.query,
-                self.filter_claude_home.as_deref(),
-                self.filter_codex_home.as_deref(),
-            );
+        if let Some(re) = regex {
+            // Regex mode bypasses Tantivy: scan stored content and first/last messages,
+            // once per pattern, so toggling other filters doesn't rescan every document
+            let snippets = match self.regex_matches.take() {
+                Some((pattern, snippets)) if pattern == self.query => snippets,
+                _ => self
+                    .search_index
+                    .as_ref()
+                    .map(|idx| idx.search_regex(&re))
+                    .unwrap_or_default(),
+            };
+            self.regex_matches = Some((self.query.clone(), snippets.clone()));
+            self.filtered.retain(|&i| snippets.contains_key(&self.sessions[i].session_id));
+            // No relevance score for regex matches, so newest first
+            self.filtered.sort_by(|&a, &b| {
+                self.sessions[b].modified_ts.cmp(&self.sessions[a].modified_ts)
+            });
+            self.search_snippets = snippets;
+            self.search_scores.clear();
+            self.search_matched_terms.clear();
+        } else if !self.query.trim().is_empty() {
+            // Keyword query: use Tantivy full-text search
+            let results = self.search_index.as_ref().map(|idx| {
+                idx.search_tantivy(
+                    &self.query,
+                    self.filter_claude_home.as_deref(),
+                    self.filter_codex_home.as_deref(),
+                    self.phrase_mode,
+                    self.snippet_chars,
+                )
+            });
+            let (snippets, ranked_ids, scores, matched_terms) = match results {
+                Some(Ok(results)) => results,
+                Some(Err(e)) => {
+                    self.search_error = Some(e.to_string());
+                    SearchResults::default()
+                }
+                None => SearchResults::default(),
+            };
             if !snippets.is_empty() {
-                // Store snippets for rendering
+                // Store snippets for rendering and scores for the explain popup
                 self.search_snippets = snippets.clone();
+                self.search_scores = scores;
+                self.search_matched_terms = matched_terms;
                 // Filter to only sessions that match the Tantivy search
                 self.filtered.retain(|&i| {
                     snippets.contains_key(&self.sessions[i].session_id)
                 });
 
-                if self.sort_by_time {
-                    // Sort by modified_ts (numeric epoch ms, reverse chronological)
-                    self.filtered.sort_by(|&a, &b| {
-                        self.sessions[b].modified_ts.cmp(&self.sessions[a].modified_ts)
-                    });
-                } else {
-                    // Reorder filtered by Tantivy ranking (phrase + recency boosted)
-                    // Build position map for ranking
-                    let rank_pos: HashMap<&str, usize> = ranked_ids
-                        .iter()
-                        .enumerate()
-                        .map(|(pos, id)| (id.as_str(), pos))
-                        .collect();
-
-                    // Sort filtered by position in ranked_ids (lower = higher rank)
-                    self.filtered.sort_by_key(|&i| {
-                        rank_pos
-                            .get(self.sessions[i].session_id.as_str())
-                            .copied()
-                            .unwrap_or(usize::MAX)
-                    });
-                }
+                // Reorder filtered by Tantivy ranking (phrase + recency boosted);
+                // other sort keys re-sort below
+                // Build position map for ranking
+                let rank_pos: HashMap<&str, usize> = ranked_ids
+                    .iter()
+                    .enumerate()
+                    .map(|(pos, id)| (id.as_str(), pos))
+                    .collect();
+
+                // Sort filtered by position in ranked_ids (lower = higher rank)
+                self.filtered.sort_by_key(|&i| {
+                    rank_pos
+                        .get(self.sessions[i].session_id.as_str())
+                        .copied()
+                        .unwrap_or(usize::MAX)
+                });
             } else {
                 // No Tantivy matches - clear results and snippets
                 self.search_snippets.clear();
-                self.filtered.clear();
+                self.search_scores.clear();
+                self.search_matched_terms.clear();
+                if self.fuzzy_mode {
+                    // Likely a misspelled project/branch: closest names first
+                    self.filtered = fuzzy_name_matches(&self.sessions, &self.filtered, self.query.trim());
+                    self.fuzzy_fallback = !self.filtered.is_empty();
+                } else {
+                    self.filtered.clear();
+                }
             }
         } else {
             // Clear snippets when no query - sort by time (most recent first)
             self.search_snippets.clear();
+            self.search_scores.clear();
+            self.search_matched_terms.clear();
             self.filtered.sort_by(|&a, &b| {
                 self.sessions[b].modified_ts.cmp(&self.sessions[a].modified_ts)
             });
         }
 
-        // Apply max_results limit if specified
-        if let Some(limit) = self.max_results {
-            self.filtered.truncate(limit);
+        // Explicit sort keys replace the order above; the sort is stable, so ties keep it
+        if self.sort_key != SortKey::Relevance {
+            self.filtered.sort_by(|&a, &b| {
+                let ord = self.sort_key.compare(&self.sessions[a], &self.sessions[b]);
+                if self.sort_descending { ord.reverse() } else { ord }
+            });
+        }
+
+        self.apply_result_limit();
+
+        // Groups keep their best-placed session's position, and their own order within
+        if self.group_by != GroupBy::None {
+            let (group_by, sessions) = (self.group_by, &self.sessions);
+            let mut group_pos: HashMap<&str, usize> = HashMap::new();
+            for &i in &self.filtered {
+                let next = group_pos.len();
+                group_pos.entry(group_by.key(&sessions[i])).or_insert(next);
+            }
+            self.filtered.sort_by_key(|&i| group_pos[group_by.key(&sessions[i])]);
         }
 
         self.selected = 0;
         self.list_scroll = 0;
+        self.list_widths = None;
+        self.list_group_counts = None;
         self.preview_scroll = 0;
+        // Query or filters changed - preview matches must be recomputed
+        self.preview_match_session = None;
+        self.preview_matches.clear();
+    }
+
+    /// The one place results are cut to `max_results`: after ranking and sorting, so
+    /// the TUI, --json, --count and --stats all see the same top N
+    fn apply_result_limit(&mut self) {
+        if let Some(limit) = self.max_results {
+            self.filtered.truncate(limit);
+        }
     }
 
     fn selected_session(&self) -> Option<&Session> {
@@ -968,33 +2056,226 @@ impl App {
             .map(|&i| &self.sessions[i])
     }
 
-    fn on_char(&mut self, c: char) {
-        self.query.push(c);
-        self.filter();
+    /// (rank, count) of a search hit among the hits the active filters kept, so hits
+    /// they removed don't count towards either
+    fn visible_rank(&self, scores: &ScoreBreakdown) -> (usize, usize) {
+        let kept: Vec<usize> = self
+            .filtered
+            .iter()
+            .filter_map(|&i| self.search_scores.get(&self.sessions[i].session_id))
+            .map(|sc| sc.rank)
+            .collect();
+        (kept.iter().filter(|&&rank| rank < scores.rank).count() + 1, kept.len())
     }
 
-    fn on_backspace(&mut self) {
-        self.query.pop();
-        self.filter();
+    /// Fetch the first/last message text that load_sessions left out, for rows
+    /// about to be shown. A session missing from the index is only tried once.
+    fn ensure_messages(&mut self, idx: usize) {
+        if self.sessions[idx].messages_loaded {
+            return;
+        }
+        let messages = self
+            .search_index
+            .as_ref()
+            .and_then(|index| index.session_messages(&self.sessions[idx].export_path));
+        let s = &mut self.sessions[idx];
+        s.messages_loaded = true;
+        if let Some((first, last, first_user)) = messages {
+            s.first_msg_content = first;
+            s.last_msg_content = last;
+            s.first_user_msg_content = first_user;
+        }
     }
 
-    fn has_active_filters(&self) -> bool {
-        !self.query.is_empty()
-            || self.filter_min_lines.is_some()
-            || self.filter_after_date.is_some()
-            || self.filter_before_date.is_some()
-            || self.filter_agent.is_some()
-            || self.filter_branch.is_some()
-            || !self.include_original
-            || self.include_sub
+    /// Collect a finished background summary, if any.
+    fn poll_summary_job(&mut self) {
+        use std::sync::mpsc::TryRecvError;
+
+        let Some(ref job) = self.summary_job else {
+            return;
+        };
+        let result = match job.rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("summary worker exited unexpectedly".to_string()),
+        };
+        let session_id = job.session_id.clone();
+        self.summary_job = None;
+        match result {
+            Ok(summary) => {
+                self.status_message = Some(match self.summaries.insert(session_id, summary) {
+                    Ok(()) => "Summary ready (shown in preview and C-d details)".to_string(),
+                    Err(e) => format!("Summary ready, but not saved: {}", e),
+                });
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Summary failed: {}", e));
+            }
+        }
+    }
+
+    fn on_char(&mut self, c: char) {
+        self.query.push(c);
+        self.schedule_filter();
+    }
+
+    /// Re-run the query once typing pauses; see poll_pending_filter.
+    fn schedule_filter(&mut self) {
+        self.filter_pending_since = Some(std::time::Instant::now());
+    }
+
+    /// Called from the event loop: run the deferred filter after FILTER_DEBOUNCE.
+    fn poll_pending_filter(&mut self) {
+        if self
+            .filter_pending_since
+            .is_some_and(|since| since.elapsed() >= FILTER_DEBOUNCE)
+        {
+            self.filter();
+        }
+    }
+
+    /// Run a deferred filter now, before a key that acts on the results.
+    fn flush_pending_filter(&mut self) {
+        if self.filter_pending_since.is_some() {
+            self.filter();
+        }
+    }
+
+    /// Append a typed character to the input prompt, dropping anything the
+    /// current mode doesn't accept or that would exceed its length cap.
+    fn push_input_char(&mut self, c: char) {
+        let Some(ref mode) = self.input_mode else {
+            return;
+        };
+        if mode.accepts(c) && self.input_buffer.chars().count() < mode.max_len() {
+            self.input_buffer.push(c);
+        }
+    }
+
+    /// Handle a bracketed paste. Only the first non-empty line is used, so a
+    /// trailing newline in the clipboard never acts as Enter.
+    fn on_paste(&mut self, text: &str) {
+        let Some(line) = text.lines().map(str::trim).find(|l| !l.is_empty()) else {
+            return;
+        };
+        let clean = || line.chars().filter(|c| !c.is_control());
+
+        if self.input_mode.is_some() {
+            for c in clean() {
+                self.push_input_char(c);
+            }
+        } else if self.full_view_open() {
+            if self.top_level() == Some(ViewLevel::ViewSearch) {
+                self.view_search_pattern.extend(clean());
+            }
+        } else if !(self.confirming_exit
+            || self.details_modal_open
+            || self.explain_modal_open
+            || self.confirming_delete.is_some()
+            || self.scope_modal_open
+            || self.filter_modal_open
+            || self.top_level() == Some(ViewLevel::ActionMenu)
+            || self.command_mode)
+        {
+            // Search box: one re-filter for the whole paste instead of one per char
+            self.query.extend(clean());
+            self.filter();
+        }
+    }
+
+    fn on_backspace(&mut self) {
+        self.query.pop();
+        self.schedule_filter();
+    }
+
+    fn has_active_filters(&self) -> bool {
+        !self.query.is_empty()
+            || self.filter_min_lines.is_some()
+            || self.filter_min_messages.is_some()
+            || self.filter_after_date.is_some()
+            || self.filter_before_date.is_some()
+            || self.filter_agent.is_some()
+            || self.filter_branch.is_some()
+            || self.filter_lang.is_some()
+            || self.filter_project.is_some()
+            || !self.include_original
+            || self.include_sub
+            || self.include_tool
+            || self.filter_has_attachments
             || !self.include_trimmed
             || !self.include_continued
     }
 
+    /// What a KeyMap binding does in the list view
+    fn run_key_action(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::Quit => self.should_quit = true,
+            KeyAction::Command => self.command_mode = true,
+            KeyAction::OpenScope => {
+                self.scope_modal_open = true;
+                self.scope_modal_selected = 0;
+            }
+            KeyAction::OpenFilter => {
+                self.filter_modal_open = true;
+                self.filter_modal_selected = 0;
+                self.filter_modal_query = None;
+            }
+            KeyAction::Goto => {
+                // Jump mode (go to line)
+                self.input_mode = Some(InputMode::JumpToLine);
+                self.input_buffer.clear();
+            }
+            // Relevance <-> time
+            KeyAction::SortToggle => self.toggle_time_sort(),
+            // Next list column (date, lines, messages, project, off)
+            KeyAction::SortCycle => self.cycle_sort_key(),
+            KeyAction::SortReverse => self.reverse_sort(),
+            KeyAction::PhraseToggle => {
+                // Terms <-> exact phrase
+                self.phrase_mode = !self.phrase_mode;
+                self.filter();
+            }
+            KeyAction::RegexToggle => {
+                // Tantivy keywords <-> regex over content
+                self.regex_mode = !self.regex_mode;
+                self.filter();
+            }
+            KeyAction::FuzzyToggle => {
+                // The fuzzy project/branch fallback for keyword misses
+                self.fuzzy_mode = !self.fuzzy_mode;
+                self.filter();
+            }
+            // The blank line between list items
+            KeyAction::CompactToggle => self.list_separator = !self.list_separator,
+            // Pin the date column to the next format
+            KeyAction::DateFormat => self.date_format = DateFormat::next(self.date_format),
+            KeyAction::MultiSelect => self.toggle_multi_select(),
+            // Why the selected session ranks where it does
+            KeyAction::Explain => self.open_explain(),
+            // Full untruncated metadata for the selected session
+            KeyAction::Details => self.details_modal_open = self.selected_session().is_some(),
+            // Query matches in the preview pane
+            KeyAction::MatchNext => self.preview_match_next(),
+            KeyAction::MatchPrev => self.preview_match_prev(),
+            KeyAction::PageUp => self.page_up(10),
+            KeyAction::PreviewFold => {
+                // Fold/unfold long FIRST messages in the preview
+                self.preview_first_expanded = !self.preview_first_expanded;
+                self.preview_scroll = 0;
+            }
+            // The session-limit banner
+            KeyAction::DismissBanner => self.truncation_warning = None,
+            KeyAction::Redraw => self.force_redraw = true,
+        }
+    }
+
     fn on_escape(&mut self) {
         if self.query.is_empty() {
             // If there are active filters, show confirmation before exiting
-            if self.has_active_filters() {
+            if self.multi_select {
+                // Leave multi-select mode before quitting
+                self.toggle_multi_select();
+            } else if self.confirm_exit && self.has_active_filters() {
                 self.confirming_exit = true;
             } else {
                 self.should_quit = true;
@@ -1026,6 +2307,24 @@ impl App {
         }
     }
 
+    /// Whether a --group-by header goes above result `i` (always for the first)
+    fn starts_group(&self, i: usize) -> bool {
+        self.group_by != GroupBy::None
+            && (i == 0 || {
+                let key = |i: usize| self.group_by.key(&self.sessions[self.filtered[i]]);
+                key(i) != key(i - 1)
+            })
+    }
+
+    /// List rows results `start..end` take, including the group headers among them.
+    /// The first shown result always gets its group's header.
+    fn list_rows(&self, start: usize, end: usize, lines_per_item: usize) -> usize {
+        let grouped = self.group_by != GroupBy::None;
+        (start..end)
+            .map(|i| lines_per_item + usize::from(grouped && (i == start || self.starts_group(i))))
+            .sum()
+    }
+
     fn page_down(&mut self, lines: usize) {
         if !self.filtered.is_empty() {
             self.selected = (self.selected + lines).min(self.filtered.len() - 1);
@@ -1040,6 +2339,81 @@ impl App {
         }
     }
 
+    /// M-m: enter or leave multi-select mode. Leaving drops the marks so they
+    /// can't silently change what a later Enter does.
+    fn toggle_multi_select(&mut self) {
+        self.multi_select = !self.multi_select;
+        if !self.multi_select {
+            self.selected_set.clear();
+        }
+    }
+
+    /// Space in multi-select mode: mark/unmark the highlighted row and move down.
+    fn toggle_mark(&mut self) {
+        self.flush_pending_filter();
+        let Some(&idx) = self.filtered.get(self.selected) else {
+            return;
+        };
+        if !self.selected_set.remove(&idx) {
+            self.selected_set.insert(idx);
+        }
+        self.on_down();
+    }
+
+    /// Marked sessions, including ones hidden by the current query, newest first.
+    fn marked_sessions(&self) -> Vec<Session> {
+        let mut indices: Vec<usize> = self.selected_set.iter().copied().collect();
+        indices.sort_unstable();
+        indices.into_iter().map(|i| self.sessions[i].clone()).collect()
+    }
+
+    /// Confirmed (d): move the selected session's file to ~/.cctools/trash and drop it
+    /// from the list. The index lists it until the next `aichat search` prunes missing files.
+    fn trash_selected(&mut self) {
+        let Some(&idx) = self.filtered.get(self.selected) else {
+            return;
+        };
+        let Some(trash_dir) = cctools_data_dir().map(|d| d.join("trash")) else {
+            self.status_message = Some("Failed to move session to trash: no home directory".to_string());
+            return;
+        };
+        self.status_message = Some(match move_to_trash(std::path::Path::new(&self.sessions[idx].export_path), &trash_dir) {
+            Ok(dest) => {
+                self.remove_session(idx);
+                format!("Moved to {} (the index updates on the next aichat search)", abbreviate_home(&dest.to_string_lossy()))
+            }
+            Err(e) => format!("Failed to move session to trash: {}", e),
+        });
+    }
+
+    /// Drop sessions[idx], keeping the selection on the row that takes its place
+    fn remove_session(&mut self, idx: usize) {
+        self.sessions.remove(idx);
+        self.total_sessions = self.total_sessions.saturating_sub(1);
+        let shift = |i: usize| if i > idx { i - 1 } else { i };
+        self.filtered = self.filtered.iter().filter(|&&i| i != idx).map(|&i| shift(i)).collect();
+        self.selected_set = self.selected_set.iter().filter(|&&i| i != idx).map(|&i| shift(i)).collect();
+        self.selected = self.selected.min(self.filtered.len().saturating_sub(1));
+        self.list_widths = None;
+        self.list_group_counts = None;
+        self.preview_scroll = 0;
+    }
+
+    /// Enter in multi-select mode: hand off every marked session at once.
+    /// Returns false when nothing is marked, so Enter acts on the row as usual.
+    fn on_enter_marked(&mut self) -> bool {
+        if !self.multi_select || self.selected_set.is_empty() {
+            return false;
+        }
+        let marked: Vec<usize> = self.selected_set.iter().copied().collect();
+        for idx in marked {
+            self.ensure_messages(idx);
+        }
+        self.should_select_many = Some(self.marked_sessions());
+        self.should_quit = true;
+        true
+    }
+
     fn toggle_scope(&mut self) {
         self.scope_global = !self.scope_global;
         self.filter();
@@ -1056,12 +2430,7 @@ impl App {
         };
 
         // Show ~/path for short paths, ~/.../<dir> for long paths
-        let home = std::env::var("HOME").unwrap_or_default();
-        let path = if !home.is_empty() && dir_to_show.starts_with(&home) {
-            format!("~{}", &dir_to_show[home.len()..])
-        } else {
-            dir_to_show.clone()
-        };
+        let path = abbreviate_home(&dir_to_show);
         if path.len() > 35 {
             let last = std::path::Path::new(&dir_to_show)
                 .file_name()
@@ -1081,6 +2450,51 @@ impl App {
         self.preview_scroll = self.preview_scroll.saturating_add(lines);
     }
 
+    /// Select a session by (partial) id, widening scope and type filters if needed
+    /// so a permalink resolves no matter where the TUI was launched.
+    fn jump_to_session(&mut self, agent: &str, id: &str) -> bool {
+        let is_match = |s: &Session, exact: bool| {
+            (agent.is_empty() || s.agent == agent)
+                && if exact { s.session_id == id } else { s.session_id.starts_with(id) || s.session_id.ends_with(id) }
+        };
+        let Some(idx) = self
+            .sessions
+            .iter()
+            .position(|s| is_match(s, true))
+            .or_else(|| self.sessions.iter().position(|s| is_match(s, false)))
+        else {
+            self.status_message = Some(format!("Session not found: {}", id));
+            return false;
+        };
+
+        if !self.filtered.contains(&idx) {
+            let s = &self.sessions[idx];
+            self.scope_global = true;
+            self.filter_dir = None;
+            self.include_sub |= s.is_sidechain;
+            self.include_tool |= s.is_tool_generated();
+            match s.derivation_type.as_str() {
+                "" => self.include_original = true,
+                "trimmed" => self.include_trimmed = true,
+                "continued" => self.include_continued = true,
+                _ => {}
+            }
+            self.query.clear();
+            self.filter();
+        }
+        match self.filtered.iter().position(|&i| i == idx) {
+            Some(pos) => {
+                self.selected = pos;
+                self.preview_scroll = 0;
+                true
+            }
+            None => {
+                self.status_message = Some(format!("Session {} is hidden by active filters", id));
+                false
+            }
+        }
+    }
+
     fn jump_to_row(&mut self, row: usize) {
         if row > 0 && row <= self.filtered.len() {
             self.selected = row - 1; // Convert 1-indexed to 0-indexed
@@ -1111,31 +2525,18 @@ impl App {
             return MIN_TERMINAL_WIDTH; // Fallback to default
         }
 
-        // Calculate max widths for each field (same logic as render_session_list)
-        let row_num_width = self.filtered.len().to_string().len().max(2);
-
-        let mut max_session_id_len = 0usize;
-        let mut max_project_len = 0usize;
-        let mut max_branch_len = 0usize;
-        let mut max_lines_len = 0usize;
-        for &idx in &self.filtered {
-            let s = &self.sessions[idx];
-            max_session_id_len = max_session_id_len.max(s.session_id_display().len());
-            max_project_len = max_project_len.max(s.project_name().len());
-            max_branch_len = max_branch_len.max(s.branch_display().len());
-            max_lines_len = max_lines_len.max(format!("{}L", s.lines).len());
-        }
-        // Apply same min/max constraints as render_session_list
-        max_session_id_len = max_session_id_len.max(8).min(18);
-        max_project_len = max_project_len.max(10).min(40);
-        max_branch_len = max_branch_len.max(8).min(35);
-        max_lines_len = max_lines_len.max(4);
+        // Same column widths as render_session_list
+        let w = self
+            .list_widths
+            .unwrap_or_else(|| ListColumnWidths::compute(&self.sessions, &self.filtered, self.show_messages));
+        let attachment_col = if w.attachments { ATTACHMENT_GLYPH_WIDTH } else { 0 };
+        let mark_col = if self.multi_select { MARK_GLYPH_WIDTH } else { 0 };
 
-        // Fixed overhead: row_num + space + icon/agent (8) + 4 separators (12) + padding (2)
-        let fixed_overhead = row_num_width + 1 + 8 + 12 + 2;
+        // Fixed overhead: mark + row_num + space + icon/agent (8) + attachment glyph + 4 separators (12) + padding (2)
+        let fixed_overhead = mark_col + w.row_num + 1 + 8 + attachment_col + 12 + 2;
 
         // Non-date width
-        let non_date_width = fixed_overhead + max_session_id_len + max_project_len + max_branch_len + max_lines_len;
+        let non_date_width = fixed_overhead + w.session_id + w.project + w.branch + w.lines;
 
         // Full date format needs ~19 chars ("11/27 - 11/29 15:23")
         // Add some extra margin for the 70/30 split (list gets 70% of content area)
@@ -1157,14 +2558,155 @@ impl App {
             return;
         }
 
-        let pattern_lower = self.view_search_pattern.to_lowercase();
+        let message_mask = self
+            .view_search_messages_only
+            .then(|| message_line_mask(&self.full_content));
         for (i, line) in self.full_content.lines().enumerate() {
-            if line.to_lowercase().contains(&pattern_lower) {
+            if message_mask.as_ref().is_some_and(|mask| !mask[i]) {
+                continue;
+            }
+            let chars: Vec<char> = line.chars().collect();
+            if !find_view_matches(&chars, &self.view_search_pattern, self.view_search_flags).is_empty() {
                 self.view_search_matches.push(i);
             }
         }
     }
 
+    /// Copy `text` for the user and report how it got there in the status bar
+    fn copy(&mut self, text: String) {
+        self.copy_as("Copied", text);
+    }
+
+    /// copy() with the status saying what was copied, e.g. "Copied path: ..."
+    fn copy_as(&mut self, copied: &str, text: String) {
+        self.status_message = Some(match copy_to_clipboard(&text) {
+            CopyOutcome::Clipboard => format!("{}: {}", copied, text),
+            CopyOutcome::Terminal => format!("{} via terminal (OSC 52): {}", copied, text),
+            CopyOutcome::Manual => {
+                let msg = format!("Clipboard unavailable, copy manually: {}", text);
+                self.manual_copies.push(text);
+                msg
+            }
+        });
+    }
+
+    /// C-o: sort by the next column in its natural direction, or back to relevance
+    fn cycle_sort_key(&mut self) {
+        self.sort_key = self.sort_key.next();
+        self.sort_descending = self.sort_key.descending_by_default();
+        self.filter();
+    }
+
+    /// C-s: toggle between relevance and newest first
+    fn toggle_time_sort(&mut self) {
+        self.sort_key = if self.sort_key == SortKey::Relevance { SortKey::Modified } else { SortKey::Relevance };
+        self.sort_descending = true;
+        self.filter();
+    }
+
+    /// C-r: flip the direction of the active sort (relevance has none)
+    fn reverse_sort(&mut self) {
+        if self.sort_key != SortKey::Relevance {
+            self.sort_descending = !self.sort_descending;
+            self.filter();
+        }
+    }
+
+    /// Flip view-search between all lines and message lines only, keeping the
+    /// current position if it is still a match.
+    fn toggle_view_search_scope(&mut self) {
+        self.view_search_messages_only = !self.view_search_messages_only;
+        if self.view_search_pattern.is_empty() {
+            return;
+        }
+        self.update_view_search_matches();
+        if let Some(pos) = self
+            .view_search_matches
+            .iter()
+            .position(|&line| line >= self.top_line())
+        {
+            self.view_search_current = pos;
+            self.scroll_to_line(self.view_search_matches[pos]);
+        }
+    }
+
+    /// Visual row where content `line` starts in the full view
+    fn row_of_line(&self, line: usize) -> usize {
+        if self.full_view_rows.is_empty() {
+            return line;
+        }
+        let last = self.full_view_rows.len() - 1;
+        self.full_view_rows[line.min(last)]
+    }
+
+    /// Content line shown at the top of the full view
+    fn top_line(&self) -> usize {
+        if self.full_view_rows.is_empty() {
+            return self.full_content_scroll;
+        }
+        self.full_view_rows
+            .partition_point(|&row| row <= self.full_content_scroll)
+            .saturating_sub(1)
+    }
+
+    /// Scroll the full view so content `line` is at the top
+    fn scroll_to_line(&mut self, line: usize) {
+        self.full_content_scroll = self.row_of_line(line);
+    }
+
+    /// The innermost open screen; None on the plain list
+    fn top_level(&self) -> Option<ViewLevel> {
+        self.view_stack.last().copied()
+    }
+
+    /// Full view, with or without a / search being typed over it
+    fn full_view_open(&self) -> bool {
+        self.view_stack.contains(&ViewLevel::FullView)
+    }
+
+    fn open_level(&mut self, level: ViewLevel) {
+        if self.top_level() != Some(level) {
+            self.view_stack.push(level);
+        }
+    }
+
+    /// Esc: close the innermost screen
+    fn back(&mut self) {
+        self.view_stack.pop();
+    }
+
+    /// Close `level` and anything opened from it
+    fn close_level(&mut self, level: ViewLevel) {
+        if let Some(pos) = self.view_stack.iter().position(|&l| l == level) {
+            self.view_stack.truncate(pos);
+        }
+    }
+
+    /// Terminal resized: the transcript rewraps to the new width at the next draw,
+    /// so put the content line now at the top back there. The list and preview
+    /// clamp their own scroll as they render.
+    fn on_resize(&mut self) {
+        if self.full_view_open() && self.full_view_anchor.is_none() {
+            self.full_view_anchor = Some(self.top_line());
+        }
+    }
+
+    /// Rows of the whole wrapped transcript
+    fn full_view_total_rows(&self) -> usize {
+        self.full_view_rows
+            .last()
+            .copied()
+            .unwrap_or_else(|| self.full_content.lines().count())
+    }
+
+    /// Content line of the match n/N last landed on, while a view search is active
+    fn current_view_match_line(&self) -> Option<usize> {
+        if self.view_search_pattern.is_empty() {
+            return None;
+        }
+        self.view_search_matches.get(self.view_search_current).copied()
+    }
+
     /// Jump to next search match in view mode
     fn view_search_next(&mut self) {
         if self.view_search_matches.is_empty() {
@@ -1173,7 +2715,7 @@ impl App {
 
         // Move to next match index (wrap around if at end)
         self.view_search_current = (self.view_search_current + 1) % self.view_search_matches.len();
-        self.full_content_scroll = self.view_search_matches[self.view_search_current];
+        self.scroll_to_line(self.view_search_matches[self.view_search_current]);
     }
 
     /// Jump to previous search match in view mode
@@ -1188,7 +2730,7 @@ impl App {
         } else {
             self.view_search_current -= 1;
         }
-        self.full_content_scroll = self.view_search_matches[self.view_search_current];
+        self.scroll_to_line(self.view_search_matches[self.view_search_current]);
     }
 
     /// Jump to next original query match in view mode (blue highlights)
@@ -1199,7 +2741,7 @@ impl App {
 
         // Move to next match index (wrap around if at end)
         self.query_match_current = (self.query_match_current + 1) % self.query_match_lines.len();
-        self.full_content_scroll = self.query_match_lines[self.query_match_current];
+        self.scroll_to_line(self.query_match_lines[self.query_match_current]);
     }
 
     /// Jump to previous original query match in view mode (blue highlights)
@@ -1214,69 +2756,501 @@ impl App {
         } else {
             self.query_match_current -= 1;
         }
-        self.full_content_scroll = self.query_match_lines[self.query_match_current];
+        self.scroll_to_line(self.query_match_lines[self.query_match_current]);
     }
-}
 
-// ============================================================================
-// UI Rendering
-// ============================================================================
+    /// Lines of the full view that the original query matches (blue highlights)
+    fn update_query_match_lines(&mut self) {
+        self.query_match_lines.clear();
+        self.query_match_current = 0;
+        if self.query.is_empty() {
+            return;
+        }
+        if let Some(gen) = self.search_index.as_ref().and_then(|index| index.line_highlighter(&self.query)) {
+            for (idx, line) in self.full_content.lines().enumerate() {
+                let html = gen.snippet(line).to_html();
+                if html.contains("<b>") {
+                    self.query_match_lines.push(idx);
+                }
+            }
+        }
+    }
 
-fn render(frame: &mut Frame, app: &mut App) {
-    let t = Theme::dark();
+    /// Full-view text for `session`: the cached `conversation`, or with reasoning
+    /// shown, a fresh parse that keeps it. The cache also feeds the preview and
+    /// summaries, which leave reasoning out.
+    fn with_reasoning(&self, session: &Session, conversation: String) -> String {
+        if !self.show_reasoning || !session.export_path.ends_with(".jsonl") {
+            return conversation;
+        }
+        match std::fs::read_to_string(&session.export_path) {
+            Ok(raw) => parse_jsonl_to_conversation(&raw, true),
+            Err(_) => conversation,
+        }
+    }
 
-    // Full view mode - take over entire screen
-    if app.full_view_mode {
-        render_full_conversation(frame, app, &t);
-        return;
+    /// r in full view: show or hide Codex reasoning, keeping matches in step
+    fn toggle_reasoning(&mut self) {
+        self.show_reasoning = !self.show_reasoning;
+        let Some(session) = self.selected_session().cloned() else {
+            return;
+        };
+        let conversation = self
+            .conversation_cache
+            .load(&session.session_id, &session.export_path);
+        self.full_content = self.with_reasoning(&session, conversation);
+        self.collapsed_tool_blocks.clear();
+        self.update_query_match_lines();
+        self.update_view_search_matches();
+    }
+
+    /// z in full view: collapse every tool result to its summary line, or expand
+    /// them all again once they all are
+    fn toggle_all_tool_results(&mut self) {
+        let starts: Vec<usize> = tool_result_blocks(&self.full_content)
+            .into_iter()
+            .map(|(start, _)| start)
+            .collect();
+        let top = self.top_line();
+        if starts.iter().all(|start| self.collapsed_tool_blocks.contains(start)) {
+            self.collapsed_tool_blocks.clear();
+        } else {
+            self.collapsed_tool_blocks.extend(starts);
+        }
+        self.full_view_anchor = Some(top);
     }
 
-    let area = frame.area();
+    /// Enter in full view: collapse or expand the tool result at the top of the view,
+    /// or the next one below it
+    fn toggle_tool_result_at_top(&mut self) {
+        let top = self.top_line();
+        let Some((start, _)) = tool_result_blocks(&self.full_content)
+            .into_iter()
+            .find(|&(start, len)| start + len > top)
+        else {
+            return;
+        };
+        if !self.collapsed_tool_blocks.remove(&start) {
+            self.collapsed_tool_blocks.insert(start);
+        }
+        self.full_view_anchor = Some(start.max(top));
+    }
 
-    // Status bar height: 1 for shortcuts, +1 if we have annotations OR active filters
-    let show_legend = app.has_annotations();
-    let has_filters = !app.include_original
-        || app.include_sub
-        || !app.include_trimmed
-        || !app.include_continued
-        || app.filter_agent.is_some()
-        || app.filter_min_lines.is_some()
-        || app.filter_after_date.is_some()
-        || app.filter_before_date.is_some();
-    let status_height = if show_legend || has_filters { 2 } else { 1 };
+    /// t in full view: list the tool calls of the loaded transcript, starting at the
+    /// first call at or below the current scroll position
+    fn toggle_tool_timeline(&mut self) {
+        self.tool_timeline_mode = !self.tool_timeline_mode;
+        if self.tool_timeline_mode {
+            self.tool_timeline = tool_timeline(&self.full_content);
+            self.tool_timeline_selected = self
+                .tool_timeline
+                .iter()
+                .position(|call| call.line >= self.top_line())
+                .unwrap_or(0);
+        }
+    }
 
-    // Main layout
-    let main_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),            // Search bar
-            Constraint::Length(1),            // Spacing
-            Constraint::Min(0),               // Content
-            Constraint::Length(1),            // Spacing
-            Constraint::Length(status_height), // Status bar (+ legend if annotations)
-        ])
-        .split(area);
+    /// Load match windows for the selected session from its indexed content.
+    /// Cached per session, so repeated C-n/C-p presses don't re-read the index.
+    fn ensure_preview_matches(&mut self) {
+        let Some(session) = self.selected_session() else {
+            return;
+        };
+        if self.preview_match_session.as_deref() == Some(session.session_id.as_str()) {
+            return;
+        }
+        let session_id = session.session_id.clone();
+        let content = self
+            .search_index
+            .as_ref()
+            .and_then(|index| index.session_content(&session.export_path))
+            .unwrap_or_default();
+        let keywords = query_keywords(&self.query);
+        let keyword_refs: Vec<&str> = keywords.iter().map(|k| k.as_str()).collect();
+        self.preview_matches = find_match_windows(&content, &keyword_refs, 200);
+        self.preview_match_session = Some(session_id);
+        self.preview_match_current = 0;
+    }
+
+    /// Context messages for the selected session, loaded from its file on first use.
+    /// Cached per session and query; the file is only re-parsed when it changes.
+    fn ensure_preview_context(&mut self) -> Option<&PreviewContext> {
+        let session = self.selected_session()?.clone();
+        let is_cached = self
+            .preview_context
+            .as_ref()
+            .is_some_and(|(id, query, _)| *id == session.session_id && *query == self.query);
+        if !is_cached {
+            let conversation = self
+                .conversation_cache
+                .load(&session.session_id, &session.export_path);
+            let context = PreviewContext::around(&conversation, &query_keywords(&self.query));
+            self.preview_context = Some((session.session_id, self.query.clone(), context));
+        }
+        self.preview_context.as_ref().map(|(_, _, context)| context)
+    }
 
-    // Search bar with margins
-    let search_area = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Min(0),
-            Constraint::Length(1),
-        ])
-        .split(main_layout[0]);
+    /// Jump to next query match in the preview pane
+    fn preview_match_next(&mut self) {
+        if self.query.trim().is_empty() {
+            return;
+        }
+        let is_fresh = self.preview_match_session.as_deref()
+            != self.selected_session().map(|s| s.session_id.as_str());
+        self.ensure_preview_matches();
+        if self.preview_matches.is_empty() {
+            return;
+        }
+        // First press shows the first match; later presses advance (wrap around at end)
+        if !is_fresh {
+            self.preview_match_current = (self.preview_match_current + 1) % self.preview_matches.len();
+        }
+        self.preview_jump_to_match = true;
+    }
 
-    render_search_bar(frame, app, &t, search_area[1]);
+    /// Jump to previous query match in the preview pane
+    fn preview_match_prev(&mut self) {
+        if self.query.trim().is_empty() {
+            return;
+        }
+        self.ensure_preview_matches();
+        if self.preview_matches.is_empty() {
+            return;
+        }
+        // Move to previous match index (wrap around if at beginning)
+        if self.preview_match_current == 0 {
+            self.preview_match_current = self.preview_matches.len() - 1;
+        } else {
+            self.preview_match_current -= 1;
+        }
+        self.preview_jump_to_match = true;
+    }
 
-    // Content area with padding
-    let content_area = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Min(0),
-            Constraint::Length(1),
-        ])
+    /// Open the relevance explanation popup for the selected session.
+    /// Term positions are computed on demand from the indexed content.
+    fn open_explain(&mut self) {
+        if self.query.trim().is_empty() {
+            return;
+        }
+        let Some(session) = self.selected_session() else {
+            return;
+        };
+        let content = self
+            .search_index
+            .as_ref()
+            .and_then(|index| index.session_content(&session.export_path))
+            .unwrap_or_default();
+        let chars_lower: Vec<char> = content.to_lowercase().chars().collect();
+        self.explain_terms = query_keywords(&self.query)
+            .into_iter()
+            .map(|kw| {
+                let positions = keyword_positions(&chars_lower, &kw);
+                (kw, positions)
+            })
+            .collect();
+        self.explain_modal_open = true;
+    }
+
+    /// Preview match windows for the selected session, if they have been loaded
+    fn active_preview_matches(&self) -> Option<&[String]> {
+        let session = self.selected_session()?;
+        if self.preview_match_session.as_deref() == Some(session.session_id.as_str())
+            && !self.preview_matches.is_empty()
+        {
+            Some(&self.preview_matches)
+        } else {
+            None
+        }
+    }
+}
+
+// ============================================================================
+// Key Bindings
+// ============================================================================
+
+/// List-view actions ~/.cctools/keys.toml can move to other keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyAction {
+    Quit,
+    Command,
+    OpenScope,
+    OpenFilter,
+    Goto,
+    SortToggle,
+    SortCycle,
+    SortReverse,
+    PhraseToggle,
+    RegexToggle,
+    FuzzyToggle,
+    CompactToggle,
+    DateFormat,
+    MultiSelect,
+    Explain,
+    Details,
+    MatchNext,
+    MatchPrev,
+    PageUp,
+    PreviewFold,
+    DismissBanner,
+    Redraw,
+}
+
+/// (name in keys.toml, action, default chord)
+const KEY_ACTIONS: &[(&str, KeyAction, &str)] = &[
+    ("quit", KeyAction::Quit, "ctrl-c"),
+    ("command", KeyAction::Command, ":"),
+    ("open_scope", KeyAction::OpenScope, "/"),
+    ("open_filter", KeyAction::OpenFilter, "ctrl-f"),
+    ("goto", KeyAction::Goto, "ctrl-g"),
+    ("sort_toggle", KeyAction::SortToggle, "ctrl-s"),
+    ("sort_cycle", KeyAction::SortCycle, "ctrl-o"),
+    ("sort_reverse", KeyAction::SortReverse, "ctrl-r"),
+    ("phrase_toggle", KeyAction::PhraseToggle, "ctrl-t"),
+    ("regex_toggle", KeyAction::RegexToggle, "alt-r"),
+    ("fuzzy_toggle", KeyAction::FuzzyToggle, "alt-f"),
+    ("compact_toggle", KeyAction::CompactToggle, "alt-l"),
+    ("date_format", KeyAction::DateFormat, "alt-t"),
+    ("multi_select", KeyAction::MultiSelect, "alt-m"),
+    ("explain", KeyAction::Explain, "ctrl-e"),
+    ("details", KeyAction::Details, "ctrl-d"),
+    ("match_next", KeyAction::MatchNext, "ctrl-n"),
+    ("match_prev", KeyAction::MatchPrev, "ctrl-p"),
+    ("page_up", KeyAction::PageUp, "ctrl-u"),
+    ("preview_fold", KeyAction::PreviewFold, "tab"),
+    ("dismiss_banner", KeyAction::DismissBanner, "ctrl-x"),
+    ("redraw", KeyAction::Redraw, "ctrl-l"),
+];
+
+/// Named keys a chord can end in, besides single characters
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("esc", KeyCode::Esc),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("space", KeyCode::Char(' ')),
+    ("backspace", KeyCode::Backspace),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+/// A key plus the Ctrl/Alt modifiers that matter for matching it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// "ctrl-f", "c-f", "alt-r", "m-r", "/", "tab", "ctrl-space"
+    fn parse(spec: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec.trim();
+        // Modifier prefixes, as long as a key is left after them ("-" alone is the key)
+        while let Some((prefix, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" | "c" => modifiers |= KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => modifiers |= KeyModifiers::ALT,
+                _ => return None,
+            }
+            rest = key;
+        }
+        let lower = rest.to_ascii_lowercase();
+        let code = match KEY_NAMES.iter().find(|(name, _)| *name == lower) {
+            Some(&(_, code)) => code,
+            None => {
+                let mut chars = rest.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                KeyCode::Char(c)
+            }
+        };
+        Some(Self::new(code, modifiers))
+    }
+
+    /// Terminals differ on Shift and on the case of Ctrl letters, so neither counts
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    /// Footer form: "C-f", "M-r", "/", "Tab"
+    fn label(self) -> String {
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("C-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("M-");
+        }
+        match self.code {
+            KeyCode::Char(' ') => label.push_str("Space"),
+            KeyCode::Char(c) => label.push(c),
+            code => label.push_str(&format!("{:?}", code)),
+        }
+        label
+    }
+}
+
+/// Which list-view action each chord runs: the defaults in KEY_ACTIONS, with
+/// ~/.cctools/keys.toml on top
+#[derive(Clone, Debug, PartialEq)]
+struct KeyMap {
+    bindings: Vec<(KeyAction, KeyChord)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self {
+            bindings: KEY_ACTIONS
+                .iter()
+                .filter_map(|&(_, action, chord)| Some((action, KeyChord::parse(chord)?)))
+                .collect(),
+        }
+    }
+}
+
+impl KeyMap {
+    /// The defaults plus the bindings in `path`, and what in it couldn't be used.
+    /// A missing file is just the defaults.
+    fn load(path: &std::path::Path) -> (Self, Vec<String>) {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => (Self::default(), Vec::new()),
+        }
+    }
+
+    /// Lines of `action = "chord"`; `"none"` unbinds the action. Each binding
+    /// replaces the action's default chord and takes its chord from any other action.
+    fn parse(text: &str) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut problems = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+                continue;
+            }
+            let spec = line.split_once('=').and_then(|(name, value)| {
+                let value = value.trim();
+                let quote = value.chars().next().filter(|q| *q == '"' || *q == '\'')?;
+                let (spec, _) = value[1..].split_once(quote)?;
+                Some((name.trim().trim_matches('"'), spec))
+            });
+            let Some((name, spec)) = spec else {
+                problems.push(format!("line {}: expected action = \"key\"", n + 1));
+                continue;
+            };
+            let Some(&(_, action, _)) = KEY_ACTIONS.iter().find(|(known, _, _)| *known == name) else {
+                problems.push(format!("line {}: unknown action '{}'", n + 1, name));
+                continue;
+            };
+            if spec.eq_ignore_ascii_case("none") {
+                keymap.bindings.retain(|&(a, _)| a != action);
+            } else if let Some(chord) = KeyChord::parse(spec) {
+                keymap.bindings.retain(|&(a, c)| a != action && c != chord);
+                keymap.bindings.push((action, chord));
+            } else {
+                problems.push(format!("line {}: can't read key '{}'", n + 1, spec));
+            }
+        }
+        (keymap, problems)
+    }
+
+    fn action(&self, key: &KeyEvent) -> Option<KeyAction> {
+        let chord = KeyChord::new(key.code, key.modifiers);
+        self.bindings.iter().find(|&&(_, c)| c == chord).map(|&(action, _)| action)
+    }
+
+    /// Keycap text for `action` (" C-f "), or None when it's unbound
+    fn keycap(&self, action: KeyAction) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|&&(a, _)| a == action)
+            .map(|&(_, chord)| format!(" {} ", chord.label()))
+    }
+}
+
+// ============================================================================
+// UI Rendering
+// ============================================================================
+
+fn render(frame: &mut Frame, app: &mut App, t: &Theme) {
+    // Full view mode - take over entire screen
+    if app.full_view_open() {
+        render_full_conversation(frame, app, t);
+        if app.changes_modal_open {
+            render_changes_modal(frame, app, t, frame.area());
+        }
+        return;
+    }
+
+    let area = frame.area();
+
+    // Status bar height: 1 for shortcuts, +1 if we have annotations OR active filters
+    let show_legend = app.has_annotations();
+    let has_filters = !app.include_original
+        || app.include_sub
+        || app.include_tool
+        || app.filter_has_attachments
+        || !app.include_trimmed
+        || !app.include_continued
+        || app.filter_agent.is_some()
+        || app.filter_min_lines.is_some()
+        || app.filter_min_messages.is_some()
+        || app.filter_lang.is_some()
+        || app.filter_project.is_some()
+        || app.filter_after_date.is_some()
+        || app.filter_before_date.is_some();
+    let status_height = if show_legend || has_filters { 2 } else { 1 };
+
+    // Main layout
+    let main_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),            // Search bar
+            Constraint::Length(1),            // Spacing
+            Constraint::Min(0),               // Content
+            Constraint::Length(1),            // Spacing
+            Constraint::Length(status_height), // Status bar (+ legend if annotations)
+        ])
+        .split(area);
+
+    // Search bar with margins
+    let search_area = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(main_layout[0]);
+
+    render_search_bar(frame, app, t, search_area[1]);
+
+    // Session-limit banner in the spacing row below the search bar
+    if let Some(ref warning) = app.truncation_warning {
+        let dismiss_key = app.keymap.keycap(KeyAction::DismissBanner);
+        render_truncation_banner(frame, t, main_layout[1], warning, dismiss_key);
+    }
+
+    // Content area with padding
+    let content_area = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
         .split(main_layout[2]);
 
     // Split content: 70% list, padding, 30% preview
@@ -1289,8 +3263,8 @@ fn render(frame: &mut Frame, app: &mut App) {
         ])
         .split(content_area[1]);
 
-    render_session_list(frame, app, &t, content_layout[0]);
-    render_preview(frame, app, &t, content_layout[2]);
+    render_session_list(frame, app, t, content_layout[0]);
+    render_preview(frame, app, t, content_layout[2]);
 
     // Status bar with padding
     let status_area = Layout::default()
@@ -1302,41 +3276,51 @@ fn render(frame: &mut Frame, app: &mut App) {
         ])
         .split(main_layout[4]);
 
-    render_status_bar(frame, app, &t, status_area[1], show_legend);
+    render_status_bar(frame, app, t, status_area[1], show_legend);
 
     // Terminal width warning (shown above status bar when too narrow)
     let min_width = app.min_width_for_full_display();
     if area.width < min_width {
-        render_width_warning(frame, area, min_width, status_height);
+        render_width_warning(frame, t, area, min_width, status_height);
     }
 
     // Filter modal overlay
     if app.filter_modal_open {
-        render_filter_modal(frame, app, &t, area);
+        render_filter_modal(frame, app, t, area);
     }
 
     // Scope modal overlay
     if app.scope_modal_open {
-        render_scope_modal(frame, app, &t, area);
+        render_scope_modal(frame, app, t, area);
     }
 
     // Action menu modal overlay
-    if matches!(app.action_mode, Some(ActionMode::ActionMenu)) {
-        render_action_modal(frame, app, &t, area);
+    if app.top_level() == Some(ViewLevel::ActionMenu) {
+        render_action_modal(frame, app, t, area);
+    }
+
+    // Relevance explanation popup overlay
+    if app.explain_modal_open {
+        render_explain_modal(frame, app, t, area);
+    }
+
+    // Session details popup overlay
+    if app.details_modal_open {
+        render_details_modal(frame, app, t, area);
     }
 
     // Exit confirmation modal overlay
     if app.confirming_exit {
-        render_exit_confirmation_modal(frame, &t, area);
+        render_exit_confirmation_modal(frame, t, area);
     }
 
     // Delete confirmation modal overlay
-    if app.confirming_delete {
-        render_delete_confirmation_modal(frame, app, &t, area);
+    if let Some(mode) = app.confirming_delete {
+        render_delete_confirmation_modal(frame, app, mode, t, area);
     }
 }
 
-fn render_width_warning(frame: &mut Frame, area: Rect, min_width: u16, status_height: u16) {
+fn render_width_warning(frame: &mut Frame, t: &Theme, area: Rect, min_width: u16, status_height: u16) {
     // Render a bright warning bar just above the status bar
     let warning_y = area.height.saturating_sub(status_height + 1);
     let warning_area = Rect::new(0, warning_y, area.width, 1);
@@ -1350,14 +3334,29 @@ fn render_width_warning(frame: &mut Frame, area: Rect, min_width: u16, status_he
     let warning = Paragraph::new(Span::styled(
         msg,
         Style::default()
-            .fg(Color::Black)
-            .bg(Color::Yellow)
+            .fg(t.highlight_fg)
+            .bg(t.highlight_bg)
             .add_modifier(Modifier::BOLD),
     ));
 
     frame.render_widget(warning, warning_area);
 }
 
+fn render_truncation_banner(frame: &mut Frame, t: &Theme, area: Rect, warning: &str, dismiss_key: Option<String>) {
+    let mut spans = vec![Span::styled(
+        format!(" ⚠ {} ", warning),
+        Style::default()
+            .fg(t.highlight_fg)
+            .bg(t.highlight_bg)
+            .add_modifier(Modifier::BOLD),
+    )];
+    if let Some(key) = dismiss_key {
+        spans.push(Span::styled(key, Style::default().bg(t.keycap_bg)));
+        spans.push(Span::styled(" dismiss", Style::default().fg(t.dim_fg)));
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
 fn render_exit_confirmation_modal(frame: &mut Frame, t: &Theme, area: Rect) {
     use ratatui::widgets::{Block, Borders, Clear};
 
@@ -1404,7 +3403,7 @@ fn render_exit_confirmation_modal(frame: &mut Frame, t: &Theme, area: Rect) {
     frame.render_widget(paragraph, inner);
 }
 
-fn render_delete_confirmation_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
+fn render_delete_confirmation_modal(frame: &mut Frame, app: &App, mode: DeleteMode, t: &Theme, area: Rect) {
     use ratatui::widgets::{Block, Borders, Clear};
 
     // Get session info for display
@@ -1427,7 +3426,10 @@ fn render_delete_confirmation_modal(frame: &mut Frame, app: &App, t: &Theme, are
 
     // Modal border
     let block = Block::default()
-        .title(" Delete Session? ")
+        .title(match mode {
+            DeleteMode::Trash => " Move Session to Trash? ",
+            DeleteMode::Purge => " Delete Session? ",
+        })
         .borders(Borders::ALL)
         .style(Style::default().bg(t.search_bg));
     frame.render_widget(block, modal_area);
@@ -1438,7 +3440,7 @@ fn render_delete_confirmation_modal(frame: &mut Frame, app: &App, t: &Theme, are
     let keycap = Style::default().bg(t.keycap_bg);
     let label = Style::default();
     let dim = Style::default().fg(t.dim_fg);
-    let warn = Style::default().fg(Color::Red);
+    let warn = Style::default().fg(t.error_fg);
 
     // Build branch display (show branch if non-empty)
     let branch_span = if branch.is_empty() {
@@ -1462,13 +3464,20 @@ fn render_delete_confirmation_modal(frame: &mut Frame, app: &App, t: &Theme, are
             branch_span,
         ]),
         Line::from(vec![]),
-        Line::from(vec![
-            Span::styled("This action cannot be undone!", warn),
-        ]),
+        Line::from(match mode {
+            DeleteMode::Trash => vec![Span::styled("The file moves to ~/.cctools/trash/", dim)],
+            DeleteMode::Purge => vec![Span::styled("This action cannot be undone!", warn)],
+        }),
         Line::from(vec![]),
         Line::from(vec![
             Span::styled(" Enter ", keycap),
-            Span::styled(" confirm delete", label),
+            Span::styled(
+                match mode {
+                    DeleteMode::Trash => " move to trash",
+                    DeleteMode::Purge => " confirm delete",
+                },
+                label,
+            ),
         ]),
         Line::from(vec![
             Span::styled("  Esc  ", keycap),
@@ -1480,12 +3489,279 @@ fn render_delete_confirmation_modal(frame: &mut Frame, app: &App, t: &Theme, are
     frame.render_widget(paragraph, inner);
 }
 
+fn render_explain_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
+    use ratatui::widgets::{Block, Borders, Clear};
+
+    let Some(session) = app.selected_session() else {
+        return;
+    };
+    let scores = app.search_scores.get(&session.session_id);
+
+    // Center the modal - height grows with the number of query terms
+    let modal_width = 64u16.min(area.width);
+    let modal_height = (10 + app.explain_terms.len() as u16).min(area.height);
+    let x = (area.width.saturating_sub(modal_width)) / 2;
+    let y = (area.height.saturating_sub(modal_height)) / 2;
+    let modal_area = Rect::new(x, y, modal_width, modal_height);
+
+    frame.render_widget(Clear, modal_area);
+
+    let block = Block::default()
+        .title(" Why this rank? ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(t.search_bg));
+    frame.render_widget(block, modal_area);
+
+    let inner = Rect::new(x + 2, y + 1, modal_width.saturating_sub(4), modal_height.saturating_sub(2));
+
+    let dim = Style::default().fg(t.dim_fg);
+    let label = Style::default();
+    let value = Style::default().fg(t.match_fg);
+
+    let mut lines: Vec<Line> = Vec::new();
+    lines.push(Line::from(vec![
+        Span::styled("Session: ", dim),
+        Span::styled(session.session_id_display(), label),
+        Span::styled("  ", dim),
+        Span::styled(session.project_name().to_string(), label),
+    ]));
+
+    if let Some(sc) = scores {
+        let (rank, count) = app.visible_rank(sc);
+        lines.push(Line::from(vec![
+            Span::styled("Rank: ", dim),
+            Span::styled(format!("{} of {}", rank, count), value),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Tantivy score: ", dim),
+            Span::styled(format!("{:.3}", sc.tantivy_score), value),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Recency multiplier: ", dim),
+            Span::styled(format!("×{:.3}", sc.recency_mult), value),
+            Span::styled(format!("  (modified {})", session.date_compact()), dim),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Final score: ", dim),
+            Span::styled(format!("{:.3}", sc.final_score), value),
+        ]));
+    } else {
+        lines.push(Line::from(Span::styled("No score available for this session.", dim)));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Matched terms (char positions in content):", dim)));
+    let positions_width = (inner.width as usize).saturating_sub(24);
+    for (term, positions) in &app.explain_terms {
+        let detail = if positions.is_empty() {
+            "not found".to_string()
+        } else {
+            let shown: Vec<String> = positions.iter().map(|p| p.to_string()).collect();
+            format!("{}× at {}", positions.len(), truncate(&shown.join(", "), positions_width))
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {} ", truncate(term, 16)), value),
+            Span::styled(detail, label),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Esc", Style::default().bg(t.keycap_bg)),
+        Span::styled(" close", dim),
+    ]));
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+fn render_details_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
+    use ratatui::widgets::{Block, Borders, Clear, Wrap};
+
+    let Some(session) = app.selected_session() else {
+        return;
+    };
+
+    let or_dash = |s: &str| if s.is_empty() { "-".to_string() } else { s.to_string() };
+    let mut rows: Vec<(&str, String)> = vec![
+        ("Session ID", session.session_id.clone()),
+        ("Agent", session.agent.clone()),
+        ("Project", or_dash(&session.project)),
+        ("Branch", or_dash(&session.branch)),
+        ("CWD", or_dash(&session.cwd)),
+        ("Created", or_dash(&session.created)),
+        ("Modified", or_dash(&session.modified)),
+        ("Lines", session.lines.to_string()),
+        ("File", session.export_path.clone()),
+        (
+            "Derivation",
+            if session.derivation_type.is_empty() { "original".to_string() } else { session.derivation_type.clone() },
+        ),
+        ("Sub-agent", if session.is_sidechain { "yes" } else { "no" }.to_string()),
+        ("Home", or_dash(&session.claude_home)),
+    ];
+    if !session.custom_title.is_empty() {
+        rows.insert(1, ("Title", session.custom_title.clone()));
+    }
+    if !session.code_langs.is_empty() {
+        rows.push(("Languages", session.code_langs.join(", ")));
+    }
+    if let Some(summary) = app.summaries.get(&session.session_id) {
+        rows.push(("Summary", summary.to_string()));
+    }
+
+    let dim = Style::default().fg(t.dim_fg);
+    let value = Style::default().fg(t.match_fg);
+
+    let mut lines: Vec<Line> = rows
+        .into_iter()
+        .map(|(k, v)| {
+            Line::from(vec![
+                Span::styled(format!("{:<11} ", k), dim),
+                Span::styled(v, value),
+            ])
+        })
+        .collect();
+    // Index check from the explicit "verify" action
+    if let Some((_, findings)) = app
+        .verify_result
+        .as_ref()
+        .filter(|(id, _)| *id == session.session_id)
+    {
+        lines.push(Line::from(""));
+        if findings.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "✓ Index matches the live file",
+                Style::default().fg(t.success_fg),
+            )));
+        } else {
+            let warn = Style::default().fg(t.error_fg);
+            for finding in findings {
+                lines.push(Line::from(Span::styled(format!("✗ {}", finding), warn)));
+            }
+            lines.push(Line::from(Span::styled(
+                "Index is stale: run `aichat build-index` to refresh it",
+                dim,
+            )));
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("Esc", Style::default().bg(t.keycap_bg)),
+        Span::styled(" close", dim),
+    ]));
+
+    // Wide enough for typical paths; long values wrap instead of truncating
+    let modal_width = 100u16.min(area.width.saturating_sub(4)).max(20);
+    let inner_width = modal_width.saturating_sub(4).max(1) as usize;
+    let wrapped_height: usize = lines
+        .iter()
+        .map(|l| l.width().max(1).div_ceil(inner_width))
+        .sum();
+    let modal_height = (wrapped_height as u16 + 2).min(area.height);
+    let x = (area.width.saturating_sub(modal_width)) / 2;
+    let y = (area.height.saturating_sub(modal_height)) / 2;
+    let modal_area = Rect::new(x, y, modal_width, modal_height);
+
+    frame.render_widget(Clear, modal_area);
+
+    let block = Block::default()
+        .title(" Session details ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(t.search_bg));
+    frame.render_widget(block, modal_area);
+
+    let inner = Rect::new(x + 2, y + 1, modal_width.saturating_sub(4), modal_height.saturating_sub(2));
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
+}
+
+fn render_changes_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
+    use ratatui::widgets::{Block, Borders, Clear, Wrap};
+
+    let Some(ref changes) = app.view_changes else {
+        return;
+    };
+
+    let dim = Style::default().fg(t.dim_fg);
+    let added = Style::default().fg(t.success_fg);
+    let removed = Style::default().fg(t.error_fg);
+
+    let viewed = Local
+        .timestamp_opt(changes.viewed_at, 0)
+        .single()
+        .map(|d| d.format("%m/%d %H:%M").to_string())
+        .unwrap_or_default();
+    let delta = changes.new_lines as i64 - changes.old_lines as i64;
+    let mut lines = vec![
+        Line::from(Span::styled(format!("Last viewed {}", viewed), dim)),
+        Line::from(format!(
+            "Lines: {} → {} ({:+})",
+            changes.old_lines, changes.new_lines, delta
+        )),
+        Line::from(""),
+    ];
+    for (label, change) in [("First", &changes.first), ("Last", &changes.last)] {
+        if let Some((before, after)) = change {
+            lines.push(Line::from(Span::styled(format!("{} message:", label), dim)));
+            lines.push(Line::from(Span::styled(format!("- {}", before), removed)));
+            lines.push(Line::from(Span::styled(format!("+ {}", after), added)));
+        }
+    }
+    // Height available for appended lines (borders + hint)
+    let budget = (area.height as usize).saturating_sub(lines.len() + 6);
+    match changes.appended {
+        Some(ref new_lines) => {
+            lines.push(Line::from(Span::styled(format!("{} lines added:", new_lines.len()), dim)));
+            for line in new_lines.iter().take(budget) {
+                lines.push(Line::from(Span::styled(format!("+ {}", line), added)));
+            }
+            if new_lines.len() > budget {
+                lines.push(Line::from(Span::styled(
+                    format!("… {} more", new_lines.len() - budget),
+                    dim,
+                )));
+            }
+        }
+        None => {
+            lines.push(Line::from(Span::styled(
+                "Earlier content changed too (e.g. trimmed); only the message summary is compared",
+                dim,
+            )));
+        }
+    }
+    lines.push(Line::from(""));
+    let mut hint = Vec::new();
+    if changes.appended.is_some() {
+        hint.push(Span::styled("Enter", Style::default().bg(t.keycap_bg)));
+        hint.push(Span::styled(" jump to added lines  ", dim));
+    }
+    hint.push(Span::styled("Esc", Style::default().bg(t.keycap_bg)));
+    hint.push(Span::styled(" close", dim));
+    lines.push(Line::from(hint));
+
+    let modal_width = 100u16.min(area.width.saturating_sub(4)).max(20);
+    let modal_height = (lines.len() as u16 + 2).min(area.height);
+    let x = (area.width.saturating_sub(modal_width)) / 2;
+    let y = (area.height.saturating_sub(modal_height)) / 2;
+    let modal_area = Rect::new(x, y, modal_width, modal_height);
+
+    frame.render_widget(Clear, modal_area);
+    let block = Block::default()
+        .title(" Changes since last view ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(t.search_bg));
+    frame.render_widget(block, modal_area);
+
+    let inner = Rect::new(x + 2, y + 1, modal_width.saturating_sub(4), modal_height.saturating_sub(2));
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
+}
+
 fn render_action_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
     use ratatui::widgets::{Block, Borders, Clear};
 
-    // Center the modal - sized for 11 action items + Esc hint
+    // Center the modal - sized for all action items + Esc hint
     let modal_width = 54u16;
-    let modal_height = 15u16; // 11 items + 1 hint + 2 border + 1 padding
+    let modal_height = ActionMenuItem::all(app.purge_enabled).len() as u16 + 3; // items + 1 hint + 2 border
     let x = (area.width.saturating_sub(modal_width)) / 2;
     let y = (area.height.saturating_sub(modal_height)) / 2;
     let modal_area = Rect::new(x, y, modal_width, modal_height);
@@ -1500,13 +3776,13 @@ fn render_action_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
 
     let inner = Rect::new(x + 2, y + 1, modal_width - 4, modal_height - 2);
 
-    let items = ActionMenuItem::all();
+    let items = ActionMenuItem::all(app.purge_enabled);
     let mut lines: Vec<Line> = Vec::new();
 
     for (i, item) in items.iter().enumerate() {
         let is_selected = i == app.action_modal_selected;
         let style = if is_selected {
-            Style::default().bg(t.selection_bg).fg(t.selection_header_fg)
+            t.selection().fg(t.selection_header_fg)
         } else {
             Style::default()
         };
@@ -1532,7 +3808,9 @@ fn render_filter_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
 
     // Center the modal
     let modal_width = 42u16;
-    let modal_height = 13u16; // 9 items + 2 border + 2 padding
+    // Sized for every item, so the modal doesn't shrink as the typed filter narrows it
+    let query_line = app.filter_modal_query.is_some() as u16;
+    let modal_height = FilterMenuItem::all().len() as u16 + query_line + 2; // items + filter + 2 border
     let x = (area.width.saturating_sub(modal_width)) / 2;
     let y = (area.height.saturating_sub(modal_height)) / 2;
     let modal_area = Rect::new(x, y, modal_width, modal_height);
@@ -1542,7 +3820,7 @@ fn render_filter_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
 
     // Modal border
     let block = Block::default()
-        .title(" Filters (|) ")
+        .title(" Filters (|) · / to narrow ")
         .borders(Borders::ALL)
         .style(Style::default().bg(t.search_bg));
     frame.render_widget(block, modal_area);
@@ -1550,9 +3828,19 @@ fn render_filter_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
     // Inner content area
     let inner = Rect::new(x + 2, y + 1, modal_width - 4, modal_height - 2);
 
-    let items = FilterMenuItem::all();
+    let items = FilterMenuItem::matching(app.filter_modal_query.as_deref());
     let mut lines: Vec<Line> = Vec::new();
 
+    if let Some(query) = &app.filter_modal_query {
+        lines.push(Line::from(vec![
+            Span::styled("/", Style::default().fg(t.match_fg)),
+            Span::raw(format!("{}█", query)),
+        ]));
+        if items.is_empty() {
+            lines.push(Line::from(Span::styled("  No matching filters", Style::default().fg(t.dim_fg))));
+        }
+    }
+
     for (i, item) in items.iter().enumerate() {
         let is_selected = i == app.filter_modal_selected;
 
@@ -1563,6 +3851,8 @@ fn render_filter_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
             FilterMenuItem::IncludeSub => if app.include_sub { " [ON]" } else { " [off]" }.to_string(),
             FilterMenuItem::IncludeTrimmed => if app.include_trimmed { " [ON]" } else { " [off]" }.to_string(),
             FilterMenuItem::IncludeContinued => if app.include_continued { " [ON]" } else { " [off]" }.to_string(),
+            FilterMenuItem::IncludeTool => if app.include_tool { " [ON]" } else { " [off]" }.to_string(),
+            FilterMenuItem::HasAttachments => if app.filter_has_attachments { " [ON]" } else { " [off]" }.to_string(),
             FilterMenuItem::AgentAll => if app.filter_agent.is_none() { " ●" } else { " ○" }.to_string(),
             FilterMenuItem::AgentClaude => if app.filter_agent.as_deref() == Some("claude") { " ●" } else { " ○" }.to_string(),
             FilterMenuItem::AgentCodex => if app.filter_agent.as_deref() == Some("codex") { " ●" } else { " ○" }.to_string(),
@@ -1570,10 +3860,36 @@ fn render_filter_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
                 Some(n) => format!(" [≥{}]", n),
                 None => " [Any]".to_string(),
             },
+            FilterMenuItem::MinMessages => match app.filter_min_messages {
+                Some(n) => format!(" [≥{}]", n),
+                None => " [Any]".to_string(),
+            },
+            FilterMenuItem::Language => match &app.filter_lang {
+                Some(lang) => match app.available_langs.iter().position(|l| l == lang) {
+                    Some(i) => format!(" [{} {}/{}]", lang, i + 1, app.available_langs.len()),
+                    None => format!(" [{}]", lang),
+                },
+                None if app.available_langs.is_empty() => " [None found]".to_string(),
+                None => " [Any]".to_string(),
+            },
+            FilterMenuItem::ClaudeHome => match &app.filter_claude_home {
+                Some(home) => match app.available_homes.iter().position(|h| h == home) {
+                    Some(i) => format!(" [{} {}/{}]", truncate(&abbreviate_home(home), 12), i + 1, app.available_homes.len()),
+                    None => format!(" [{}]", truncate(&abbreviate_home(home), 16)),
+                },
+                None if app.available_homes.is_empty() => " [None found]".to_string(),
+                None => " [Any]".to_string(),
+            },
+            FilterMenuItem::Project => match &app.filter_project {
+                Some(p) => format!(" [{}]", truncate(p, 16)),
+                None => " [Any]".to_string(),
+            },
             FilterMenuItem::AfterDate => match &app.filter_after_date_display {
                 Some(d) => format!(" [>{}]", d),
                 None => " [None]".to_string(),
             },
+            // Sets the after date, which shows the resulting cutoff
+            FilterMenuItem::Since => String::new(),
             FilterMenuItem::BeforeDate => match &app.filter_before_date_display {
                 Some(d) => format!(" [<{}]", d),
                 None => " [None]".to_string(),
@@ -1581,7 +3897,7 @@ fn render_filter_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
         };
 
         let style = if is_selected {
-            Style::default().bg(t.selection_bg).fg(t.selection_header_fg)
+            t.selection().fg(t.selection_header_fg)
         } else {
             Style::default()
         };
@@ -1623,13 +3939,8 @@ fn render_scope_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
 
     // Build menu items based on current state
     // Show full path if short, ~/.../<dir> if long (same logic as scope_display)
-    let home = std::env::var("HOME").unwrap_or_default();
     let cwd_display = {
-        let path = if !home.is_empty() && app.launch_cwd.starts_with(&home) {
-            format!("~{}", &app.launch_cwd[home.len()..])
-        } else {
-            app.launch_cwd.clone()
-        };
+        let path = abbreviate_home(&app.launch_cwd);
         if path.len() > 50 {
             let last = std::path::Path::new(&app.launch_cwd)
                 .file_name()
@@ -1659,7 +3970,7 @@ fn render_scope_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
         let is_selected = i == app.scope_modal_selected;
 
         let style = if is_selected {
-            Style::default().bg(t.selection_bg).fg(t.selection_header_fg)
+            t.selection().fg(t.selection_header_fg)
         } else {
             Style::default()
         };
@@ -1670,12 +3981,7 @@ fn render_scope_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
         // For custom directory, show the path and branch if set
         let suffix = if i == 2 {
             if let Some(ref dir) = app.filter_dir {
-                let home = std::env::var("HOME").unwrap_or_default();
-                let dir_display = if !home.is_empty() && dir.starts_with(&home) {
-                    format!("~{}", &dir[home.len()..])
-                } else {
-                    dir.clone()
-                };
+                let dir_display = abbreviate_home(dir);
                 // Add branch if set
                 let full_display = if let Some(ref branch) = app.filter_branch {
                     format!(" [{}:{}]", dir_display, branch)
@@ -1710,20 +4016,33 @@ fn render_scope_modal(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
     frame.render_widget(paragraph, inner);
 }
 
+/// "12/340 sessions": the selected row (1-indexed) over the result count
+fn session_count_label(app: &App) -> String {
+    if app.filtered.is_empty() {
+        "0 sessions".to_string()
+    } else {
+        format!("{}/{} sessions", app.selected + 1, app.filtered.len())
+    }
+}
+
 fn render_search_bar(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
-    // Layout: [search...] [N sessions] / ~/path/to/dir
+    // Layout: [search...] [12/340 sessions] / ~/path/to/dir
     // Give more space to directory path by making search box smaller
-    let scope_label = app.scope_display();
-    let session_count = format!("{} sessions", app.filtered.len());
+    let scope_label = if app.regex_mode {
+        format!("regex · {}", app.scope_display())
+    } else {
+        app.scope_display()
+    };
+    let session_count = session_count_label(app);
 
     // Right side: " | N | / path "
     // Calculate widths: separator(3) + count + separator(3) + keycap(3) + scope + padding(2)
-    let right_side_width = 3 + session_count.len() + 3 + 3 + scope_label.len() + 2;
+    let right_side_width = 3 + session_count.chars().count() + 3 + 3 + scope_label.chars().count() + 2;
     // Make search box smaller to give more space to directory path (shift right side left by ~20 chars)
     let search_width = (area.width as usize).saturating_sub(right_side_width + 32);
 
     let middle_line = if app.query.is_empty() {
-        let placeholder = " Search...";
+        let placeholder = if app.regex_mode { " Regex..." } else { " Search..." };
         let padding = search_width.saturating_sub(placeholder.len());
         Line::from(vec![
             Span::styled(placeholder, Style::default().fg(t.placeholder_fg)),
@@ -1735,12 +4054,23 @@ fn render_search_bar(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
             Span::styled(format!(" {}", scope_label), Style::default().fg(t.scope_label_fg)),
         ])
     } else {
-        let query_len = 1 + app.query.chars().count() + 1;
+        // Phrase mode indicator (or a bad regex) sits right after the cursor
+        let (tag, tag_style) = match app.regex_error {
+            Some(ref err) if app.regex_mode => (format!("  {}", err), Style::default().fg(t.dim_fg)),
+            _ if app.search_error.is_some() => ("  search failed".to_string(), Style::default().fg(t.dim_fg)),
+            _ if app.fuzzy_fallback => ("  ~fuzzy project/branch".to_string(), Style::default().fg(t.dim_fg)),
+            _ if app.phrase_mode && !app.regex_mode => {
+                ("  \"phrase\"".to_string(), Style::default().fg(t.match_fg))
+            }
+            _ => (String::new(), Style::default()),
+        };
+        let query_len = 1 + app.query.chars().count() + 1 + tag.chars().count();
         let padding = search_width.saturating_sub(query_len);
         Line::from(vec![
             Span::raw(" "),
             Span::raw(&app.query),
             Span::styled("█", Style::default().fg(t.accent)),
+            Span::styled(tag, tag_style),
             Span::raw(" ".repeat(padding)),
             Span::styled(" │ ", Style::default().fg(t.separator_fg)),
             Span::styled(&session_count, Style::default().fg(t.dim_fg)),
@@ -1767,6 +4097,135 @@ fn render_search_bar(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
     frame.render_widget(paragraph, area);
 }
 
+/// Marks sessions with image/document attachments in the list
+const ATTACHMENT_GLYPH: &str = "▣ ";
+const ATTACHMENT_GLYPH_WIDTH: usize = 2;
+
+/// Marks rows picked in multi-select mode; the column only exists in that mode
+const MARK_GLYPH: &str = "✓ ";
+const MARK_GLYPH_WIDTH: usize = 2;
+
+/// Rows built on each side of the visible list window, so the rows a one-step
+/// scroll brings in already have their messages loaded
+const LIST_OVERSCAN: usize = 2;
+
+/// Per-column widths for the session list, computed over the whole filtered set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ListColumnWidths {
+    row_num: usize,
+    session_id: usize,
+    project: usize,
+    branch: usize,
+    lines: usize,
+    attachments: bool, // Reserve a column for the attachment glyph
+}
+
+impl ListColumnWidths {
+    fn compute(sessions: &[Session], filtered: &[usize], show_messages: bool) -> Self {
+        // Calculate max widths for each field - no artificial caps, show full names
+        let mut session_id = 0usize;
+        let mut project = 0usize;
+        let mut branch = 0usize;
+        let mut lines = 0usize;
+        for &idx in filtered {
+            let s = &sessions[idx];
+            session_id = session_id.max(s.session_id_display().len());
+            project = project.max(s.project_name().len());
+            branch = branch.max(s.branch_display().len());
+            lines = lines.max(s.size_display(show_messages).len());
+        }
+        // Ensure minimums and reasonable maximums
+        Self {
+            row_num: filtered.len().to_string().len().max(2),
+            session_id: session_id.clamp(8, 18),
+            project: project.clamp(10, 40),
+            branch: branch.clamp(8, 35),
+            lines: lines.max(4),
+            attachments: filtered.iter().any(|&idx| sessions[idx].has_attachments()),
+        }
+    }
+}
+
+/// Header label padded to `width`, with the sort arrow when it's the active column.
+/// Falls back to the label's initial (e.g. "L" over "295L") when the full label doesn't fit.
+fn list_header_cell(label: &str, arrow: Option<&str>, width: usize, right_align: bool) -> String {
+    let mut text = match arrow {
+        Some(arrow) => format!("{} {}", label, arrow),
+        None => label.to_string(),
+    };
+    if text.chars().count() > width {
+        text = format!("{}{}", label[..1].to_uppercase(), arrow.unwrap_or(""));
+    }
+    if right_align {
+        format!("{:>width$}", text, width = width)
+    } else {
+        format!("{:<width$}", text, width = width)
+    }
+}
+
+/// Header row aligned with the session list columns. Branch and date widths are
+/// passed separately since they shrink with the terminal width.
+fn render_list_header<'a>(
+    app: &App,
+    t: &Theme,
+    widths: &ListColumnWidths,
+    branch_width: usize,
+    date_width: usize,
+) -> Line<'a> {
+    let arrow = if app.sort_descending { "▼" } else { "▲" };
+    let arrow_for = |key: SortKey| (app.sort_key == key).then_some(arrow);
+    let style_for = |key: Option<SortKey>| {
+        if key == Some(app.sort_key) {
+            Style::default().fg(t.accent).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(t.dim_fg)
+        }
+    };
+    let sep = Span::styled(" | ", Style::default().fg(t.separator_fg));
+    let (size_label, size_key) = if app.show_messages {
+        ("messages", SortKey::Messages)
+    } else {
+        ("lines", SortKey::Lines)
+    };
+
+    let mut spans = vec![
+        // Mark + row number + agent icon/abbreviation ("● CLD ") + attachment glyph
+        Span::raw(" ".repeat(
+            if app.multi_select { MARK_GLYPH_WIDTH } else { 0 }
+                + widths.row_num
+                + 1
+                + 6
+                + if widths.attachments { ATTACHMENT_GLYPH_WIDTH } else { 0 },
+        )),
+        Span::styled(list_header_cell("session", None, widths.session_id, false), style_for(None)),
+        sep.clone(),
+        Span::styled(
+            list_header_cell("project", arrow_for(SortKey::Project), widths.project, false),
+            style_for(Some(SortKey::Project)),
+        ),
+        sep.clone(),
+        Span::styled(list_header_cell("branch", None, branch_width, false), style_for(None)),
+        sep.clone(),
+        Span::styled(
+            list_header_cell(size_label, arrow_for(size_key), widths.lines, true),
+            style_for(Some(size_key)),
+        ),
+        sep,
+        Span::styled(
+            list_header_cell("date", arrow_for(SortKey::Modified), date_width, true),
+            style_for(Some(SortKey::Modified)),
+        ),
+    ];
+    // Keys without a list column are named after the last one
+    if matches!(app.sort_key, SortKey::Messages | SortKey::Created | SortKey::Lines) && app.sort_key != size_key {
+        spans.push(Span::styled(
+            format!("  {} {}", app.sort_key.name(), arrow),
+            style_for(Some(app.sort_key)),
+        ));
+    }
+    Line::from(spans)
+}
+
 fn render_session_list(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect) {
     let available_width = area.width.saturating_sub(2) as usize;
 
@@ -1781,46 +4240,31 @@ fn render_session_list(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect)
         return;
     }
 
-    // Calculate field widths based on max values
-    let row_num_width = app.filtered.len().to_string().len().max(2);
+    // Column widths span all results so alignment doesn't shift while scrolling;
+    // the pass is cached until filter() changes the result set
+    let widths = *app
+        .list_widths
+        .get_or_insert_with(|| ListColumnWidths::compute(&app.sessions, &app.filtered, app.show_messages));
+    let row_num_width = widths.row_num;
+    let max_session_id_len = widths.session_id;
+    let max_project_len = widths.project;
+    let max_branch_len = widths.branch;
+    let max_lines_len = widths.lines;
+    let attachment_col = if widths.attachments { ATTACHMENT_GLYPH_WIDTH } else { 0 };
+    let mark_col = if app.multi_select { MARK_GLYPH_WIDTH } else { 0 };
     let sep = " | ";
 
-    // Calculate max widths for each field - no artificial caps, show full names
-    let mut max_session_id_len = 0usize;
-    let mut max_project_len = 0usize;
-    let mut max_branch_len = 0usize;
-    let mut max_lines_len = 0usize;
-    for &idx in &app.filtered {
-        let s = &app.sessions[idx];
-        max_session_id_len = max_session_id_len.max(s.session_id_display().len());
-        max_project_len = max_project_len.max(s.project_name().len());
-        max_branch_len = max_branch_len.max(s.branch_display().len());
-        max_lines_len = max_lines_len.max(format!("{}L", s.lines).len());
-    }
-    // Ensure minimums and reasonable maximums
-    max_session_id_len = max_session_id_len.max(8).min(18);
-    max_project_len = max_project_len.max(10).min(40);
-    max_branch_len = max_branch_len.max(8).min(35);
-    max_lines_len = max_lines_len.max(4);
-
     // Calculate available width and determine date format
-    // Fixed overhead: row_num + space + icon/agent (8) + 4 separators (12) + padding (2)
-    let fixed_overhead = row_num_width + 1 + 8 + 12 + 2;
+    // Fixed overhead: mark + row_num + space + icon/agent (8) + 4 separators (12) + padding (2)
+    let fixed_overhead = mark_col + row_num_width + 1 + 8 + attachment_col + 12 + 2;
     let available_width = area.width as usize;
 
     // Width needed for non-date fields
     let non_date_width = fixed_overhead + max_session_id_len + max_project_len + max_branch_len + max_lines_len;
     let remaining_for_date = available_width.saturating_sub(non_date_width);
 
-    // Determine date format based on available space
-    // Full: ~19 chars ("11/27 - 11/29 15:23"), Medium: ~13 chars ("11/27 - 11/29"), Compact: ~4 chars ("35d")
-    let date_format = if remaining_for_date >= 19 {
-        "full"
-    } else if remaining_for_date >= 13 {
-        "medium"
-    } else {
-        "compact"
-    };
+    // Determine date format based on available space, unless M-t pinned one
+    let date_format = app.date_format.unwrap_or_else(|| DateFormat::for_width(remaining_for_date));
 
     // If even medium date doesn't fit well, also truncate branch more aggressively
     let effective_branch_len = if remaining_for_date < 13 && max_branch_len > 15 {
@@ -1831,18 +4275,52 @@ fn render_session_list(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect)
         max_branch_len
     };
 
-    // Calculate max date length based on format
-    let max_date_len = match date_format {
-        "full" => 19,
-        "medium" => 13,
-        _ => 4,
-    };
+    let max_date_len = date_format.width();
+
+    // Column header row, with an arrow on the active sort column
+    let list_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(area);
+    let header = render_list_header(app, t, &widths, effective_branch_len, max_date_len);
+    frame.render_widget(Paragraph::new(header), list_layout[0]);
+    let area = list_layout[1];
+
+    // Calculate visible items (header + snippet, plus the blank separator unless compact)
+    let lines_per_item = if app.list_separator { 3 } else { 2 };
+    let visible_items = (area.height as usize) / lines_per_item;
+    let height = area.height as usize;
+
+    if app.selected < app.list_scroll {
+        app.list_scroll = app.selected;
+    } else if app.group_by != GroupBy::None {
+        // Header rows take room too: scroll until the selection fits below them
+        app.list_scroll = app.list_scroll.max(app.selected.saturating_sub(visible_items));
+        while app.list_scroll < app.selected && app.list_rows(app.list_scroll, app.selected + 1, lines_per_item) > height {
+            app.list_scroll += 1;
+        }
+    } else if app.selected >= app.list_scroll + visible_items && visible_items > 0 {
+        app.list_scroll = app.selected - visible_items + 1;
+    }
 
-    let items: Vec<ListItem> = app
-        .filtered
+    // Only build rows for the visible window plus a small overscan - large result
+    // sets would otherwise format every session on every frame
+    let window_start = app.list_scroll.min(app.filtered.len());
+    let mut window_end = (window_start + visible_items.max(1)).min(app.filtered.len());
+    while window_end > window_start + 1 && app.list_rows(window_start, window_end, lines_per_item) > height {
+        window_end -= 1;
+    }
+    let build_start = window_start.saturating_sub(LIST_OVERSCAN);
+    let build_end = (window_end + LIST_OVERSCAN).min(app.filtered.len());
+    for i in build_start..build_end {
+        app.ensure_messages(app.filtered[i]);
+    }
+
+    let session_items: Vec<ListItem> = app.filtered[build_start..build_end]
         .iter()
         .enumerate()
-        .map(|(i, &idx)| {
+        .map(|(offset, &idx)| {
+            let i = build_start + offset;
             let s = &app.sessions[idx];
             let is_selected = i == app.selected;
             let row_num = i + 1; // 1-indexed
@@ -1873,19 +4351,31 @@ fn render_session_list(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect)
             let session_display = format!("{:<width$}", s.session_id_display(), width = max_session_id_len);
             let project_padded = format!("{:<width$}", truncate(s.project_name(), max_project_len), width = max_project_len);
             let branch_padded = format!("{:<width$}", truncate(s.branch_display(), effective_branch_len), width = effective_branch_len);
-            let lines_str = format!("{:>width$}", format!("{}L", s.lines), width = max_lines_len);
+            let lines_str = format!("{:>width$}", s.size_display(app.show_messages), width = max_lines_len);
 
-            // Choose date format based on available space
-            let date_text = match date_format {
-                "full" => s.date_display(),
-                "medium" => s.date_medium(),
-                _ => s.date_compact(),
-            };
+            let date_text = date_format.format(s);
             let date_str = format!("{:>width$}", date_text, width = max_date_len);
 
-            let header_spans = vec![
+            let mut header_spans = Vec::new();
+            if app.multi_select {
+                header_spans.push(Span::styled(
+                    if app.selected_set.contains(&idx) { MARK_GLYPH } else { "  " },
+                    Style::default().fg(t.match_fg).add_modifier(Modifier::BOLD),
+                ));
+            }
+            header_spans.extend([
                 Span::styled(format!("{} ", row_num_str), Style::default().fg(t.dim_fg)),
                 Span::styled(format!("{} {} ", agent_icon, agent_abbrev), Style::default().fg(source_color)),
+                Span::styled(
+                    if !widths.attachments {
+                        ""
+                    } else if s.has_attachments() {
+                        ATTACHMENT_GLYPH
+                    } else {
+                        "  "
+                    },
+                    Style::default().fg(t.accent),
+                ),
                 Span::styled(session_display, Style::default().fg(t.dim_fg)),
                 Span::styled(sep, sep_style),
                 Span::styled(project_padded, header_style),
@@ -1895,7 +4385,11 @@ fn render_session_list(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect)
                 Span::styled(lines_str, header_style),
                 Span::styled(sep, sep_style),
                 Span::styled(date_str, Style::default().fg(t.dim_fg)),
-            ];
+            ]);
+            if app.track_views && app.view_snapshots.is_updated(s) {
+                // Changed since it was last opened in full view
+                header_spans.push(Span::styled(" ↻", Style::default().fg(t.accent)));
+            }
 
             // Snippet: show last_msg when no query, highlighted match when searching
             let snippet_style = if is_selected {
@@ -1910,7 +4404,7 @@ fn render_session_list(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect)
             let snippet_width = available_width.saturating_sub(row_num_width + 1);
 
             // Build custom title prefix if present
-            let title_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+            let title_style = Style::default().fg(t.accent).add_modifier(Modifier::BOLD);
             let (title_prefix, title_len) = if !s.custom_title.is_empty() {
                 let prefix = format!("[{}] ", s.custom_title);
                 let len = prefix.len();
@@ -1921,17 +4415,24 @@ fn render_session_list(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect)
             let effective_snippet_width = snippet_width.saturating_sub(title_len);
 
             let snippet_line = if app.query.is_empty() {
-                // No query: show last message content
-                let snippet = truncate(&s.last_msg_content, effective_snippet_width);
+                // No query: show the start of the last message, labelled so it
+                // doesn't read as a continuation
+                let label = "last: ";
+                let snippet = truncate(&s.last_msg_content, effective_snippet_width.saturating_sub(label.len()));
                 let mut spans = vec![Span::styled(indent.clone(), snippet_style)];
                 if let Some(ref tp) = title_prefix {
                     spans.push(Span::styled(tp.clone(), title_style));
                 }
-                spans.push(Span::styled(format!("...{}", snippet), snippet_style));
+                spans.push(Span::styled(label, Style::default().fg(t.dim_fg)));
+                spans.push(Span::styled(snippet, snippet_style));
                 Line::from(spans)
             } else {
                 // With query: use Tantivy snippet with HTML tags for highlighting
                 if let Some(snippet_html) = app.search_snippets.get(&s.session_id) {
+                    // Which of several query terms this session hit, ahead of the snippet
+                    let chips = matched_term_chips(app.search_matched_terms.get(&s.session_id), t);
+                    let chips_width: usize = chips.iter().map(|c| c.content.chars().count()).sum();
+                    let effective_snippet_width = effective_snippet_width.saturating_sub(chips_width);
                     // Truncate the plain text version but render with HTML tags
                     let snippet_plain = strip_html_tags(snippet_html);
                     let truncated_plain = truncate(&snippet_plain, effective_snippet_width);
@@ -1940,63 +4441,188 @@ fn render_session_list(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect)
                     if let Some(ref tp) = title_prefix {
                         spans.push(Span::styled(tp.clone(), title_style));
                     }
+                    spans.extend(chips);
                     // Truncate HTML snippet approximately (allow extra for tags)
                     let html_truncated: String = snippet_html.chars().take(effective_snippet_width + 50).collect();
                     spans.extend(render_snippet_with_html_tags(&html_truncated, snippet_style, highlight_style));
                     Line::from(spans)
                 } else {
                     let first_content = if !s.first_user_msg_content.is_empty() { &s.first_user_msg_content } else { &s.first_msg_content };
-                    let snippet = truncate(first_content, effective_snippet_width);
+                    let label = "first: ";
+                    let snippet = truncate(first_content, effective_snippet_width.saturating_sub(label.len()));
                     let mut spans = vec![Span::styled(indent.clone(), snippet_style)];
                     if let Some(ref tp) = title_prefix {
                         spans.push(Span::styled(tp.clone(), title_style));
                     }
-                    spans.push(Span::styled(format!("...{}", snippet), snippet_style));
+                    spans.push(Span::styled(label, Style::default().fg(t.dim_fg)));
+                    spans.push(Span::styled(snippet, snippet_style));
                     Line::from(spans)
                 }
             };
 
-            let lines = vec![
-                Line::from(header_spans),
-                snippet_line,
-                Line::from(""),
-            ];
+            let mut lines = vec![Line::from(header_spans), snippet_line];
+            if app.list_separator {
+                lines.push(Line::from(""));
+            }
 
             if is_selected {
-                ListItem::new(lines).style(Style::default().bg(t.selection_bg))
+                ListItem::new(lines).style(t.selection())
             } else {
                 ListItem::new(lines)
             }
         })
         .collect();
 
+    // Group headers go between the session items; they are never selected, so the
+    // selection still counts sessions only and up/down step over them
+    if app.group_by != GroupBy::None && app.list_group_counts.is_none() {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for &i in &app.filtered {
+            *counts.entry(app.group_by.key(&app.sessions[i]).to_string()).or_default() += 1;
+        }
+        app.list_group_counts = Some(counts);
+    }
+    let group_counts = app.list_group_counts.as_ref().filter(|_| app.group_by != GroupBy::None);
+    let mut items: Vec<ListItem> = Vec::with_capacity(session_items.len());
+    let mut selected_item = None;
+    let mut window_item = 0;
+    for (offset, item) in session_items.into_iter().enumerate() {
+        let i = build_start + offset;
+        if i == window_start {
+            window_item = items.len();
+        }
+        if let Some(counts) = group_counts {
+            if offset == 0 || i == window_start || app.starts_group(i) {
+                let key = app.group_by.key(&app.sessions[app.filtered[i]]);
+                items.push(ListItem::new(Line::from(vec![
+                    Span::styled(format!("── {} ", key), Style::default().fg(t.dim_fg).add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("({})", counts.get(key).copied().unwrap_or(0)), Style::default().fg(t.dim_fg)),
+                ])));
+            }
+        }
+        if i == app.selected {
+            selected_item = Some(items.len());
+        }
+        items.push(item);
+    }
+
     let list = List::new(items);
 
-    // Calculate visible items (3 lines per item)
-    let lines_per_item = 3;
-    let visible_items = (area.height as usize) / lines_per_item;
+    // Items start at build_start and include the headers, so the offset and
+    // selection are item positions rather than session indices
+    let mut list_state = ListState::default();
+    list_state.select(selected_item);
+    *list_state.offset_mut() = window_item;
 
-    if app.selected < app.list_scroll {
-        app.list_scroll = app.selected;
-    } else if app.selected >= app.list_scroll + visible_items && visible_items > 0 {
-        app.list_scroll = app.selected - visible_items + 1;
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// Messages shown in the preview's CONTEXT section, and wrapped lines shown per message
+const PREVIEW_CONTEXT_MESSAGES: usize = 3;
+const PREVIEW_CONTEXT_LINES: usize = 4;
+
+/// A few consecutive messages from inside a session for the preview pane.
+/// The first and last messages are left out since the index already shows them.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct PreviewContext {
+    messages: Vec<(bool, String)>, // (is_user, text)
+    start: usize,                  // Index of the first shown message
+    total: usize,                  // Messages in the session
+    matched: bool,                 // Centered on a query match rather than the middle
+}
+
+impl PreviewContext {
+    /// Center on the first inner message containing a keyword, else on the middle.
+    fn around(conversation: &str, keywords: &[String]) -> Self {
+        let messages = conversation_messages(conversation);
+        let total = messages.len();
+        if total <= 2 {
+            return Self { total, ..Self::default() };
+        }
+        let inner = 1..total - 1;
+        let hit = inner.clone().find(|&i| {
+            let text = messages[i].1.to_lowercase();
+            keywords.iter().any(|k| text.contains(k.as_str()))
+        });
+        let center = hit.unwrap_or(total / 2);
+        let len = PREVIEW_CONTEXT_MESSAGES.min(inner.len());
+        let start = center
+            .saturating_sub(len / 2)
+            .clamp(inner.start, inner.end - len);
+        Self {
+            messages: messages[start..start + len].to_vec(),
+            start,
+            total,
+            matched: hit.is_some(),
+        }
     }
+}
 
-    let mut list_state = ListState::default();
-    list_state.select(Some(app.selected));
-    *list_state.offset_mut() = app.list_scroll;
+/// Split full-view text ("> " user / "⏺ " assistant, indented continuations) into messages.
+fn conversation_messages(conversation: &str) -> Vec<(bool, String)> {
+    let mut messages: Vec<(bool, String)> = Vec::new();
+    for line in conversation.lines() {
+        if let Some(text) = line.strip_prefix("> ") {
+            messages.push((true, text.to_string()));
+        } else if let Some(text) = line.strip_prefix("⏺ ") {
+            messages.push((false, text.to_string()));
+        } else if let (Some(text), Some((_, last))) = (line.strip_prefix("  "), messages.last_mut()) {
+            last.push('\n');
+            last.push_str(text);
+        }
+    }
+    messages
+}
 
-    frame.render_stateful_widget(list, area, &mut list_state);
+/// (LAST message, MATCH snippet) line caps for a preview pane `height` rows tall,
+/// or from a fixed --preview-lines cap
+fn preview_line_caps(height: usize, fixed: Option<usize>) -> (usize, usize) {
+    let message = fixed.unwrap_or(height / PREVIEW_ROWS_PER_MESSAGE_LINE).max(PREVIEW_MIN_MESSAGE_LINES);
+    (message, message * 2)
+}
+
+/// One wrapped line of a FIRST/LAST bubble, with query words in the match color.
+/// Padding is counted on the unstyled text, so the bubble background stays rectangular.
+fn preview_bubble_line(wrapped: &str, bubble_width: usize, bubble_bg: Color, query: &str, t: &Theme) -> Line<'static> {
+    let padding = bubble_width.saturating_sub(wrapped.chars().count());
+    let mut spans = vec![Span::styled(" ", Style::default().bg(bubble_bg))];
+    spans.extend(highlight_keywords_in_line(
+        wrapped,
+        query,
+        Style::default().bg(bubble_bg),
+        t.highlight().add_modifier(Modifier::BOLD),
+    ));
+    spans.push(Span::styled(" ".repeat(padding + 1), Style::default().bg(bubble_bg)));
+    Line::from(spans)
 }
 
 fn render_preview(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect) {
+    if let Some(&idx) = app.filtered.get(app.selected) {
+        app.ensure_messages(idx);
+    }
+    // Loaded before borrowing the session; cached after the first render of each session
+    let context = if app.preview_context_enabled {
+        app.ensure_preview_context().cloned()
+    } else {
+        None
+    };
     let Some(s) = app.selected_session() else {
         return;
     };
 
     let bubble_width = area.width.saturating_sub(4) as usize;
+    let (message_cap, match_cap) = preview_line_caps(area.height as usize, app.preview_lines);
     let mut lines: Vec<Line> = Vec::new();
 
+    // Cached one-line summary from the "summarize" action
+    if let Some(summary) = app.summaries.get(&s.session_id) {
+        lines.push(Line::from(Span::styled(" ── SUMMARY ── ", Style::default().fg(t.dim_fg))));
+        for wrapped in wrap_text(summary, bubble_width) {
+            lines.push(Line::from(Span::styled(format!(" {}", wrapped), Style::default().fg(t.accent))));
+        }
+        lines.push(Line::from(""));
+    }
+
     // First user message - prefer first_user_msg_content (skips meta messages),
     // fall back to first_msg_content for backwards compatibility
     let first_preview_content = if !s.first_user_msg_content.is_empty() {
@@ -2024,48 +4650,61 @@ fn render_preview(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect) {
             Span::styled(role_label, Style::default().fg(label_color).add_modifier(Modifier::BOLD)),
         ]));
 
-        for wrapped in wrap_text(first_preview_content, bubble_width).iter().take(6) {
-            let padding = bubble_width.saturating_sub(wrapped.chars().count());
-            lines.push(Line::from(vec![
-                Span::styled(" ", Style::default().bg(bubble_bg)),
-                Span::styled(wrapped.clone(), Style::default().bg(bubble_bg)),
-                Span::styled(" ".repeat(padding + 1), Style::default().bg(bubble_bg)),
-            ]));
+        // Long first messages (e.g., context prompts) fold to one line so LAST/MATCH stay visible
+        let wrapped_lines = wrap_text(first_preview_content, bubble_width);
+        let is_folded = !app.preview_first_expanded && wrapped_lines.len() >= PREVIEW_FOLD_MIN_LINES;
+        let shown = if is_folded { 1 } else { wrapped_lines.len() };
+        for wrapped in wrapped_lines.iter().take(shown) {
+            lines.push(preview_bubble_line(wrapped, bubble_width, bubble_bg, &app.query, t));
+        }
+        if is_folded {
+            lines.push(Line::from(Span::styled(
+                format!(" [+{} lines · Tab to expand]", wrapped_lines.len() - 1),
+                Style::default().fg(t.dim_fg),
+            )));
         }
 
         lines.push(Line::from(""));
     }
 
     // Search snippet - show matching content when searching (with keyword highlighting)
+    // After C-n/C-p, show the current match window from the full content instead
+    let mut match_header_line: Option<usize> = None;
     if !app.query.is_empty() {
-        if let Some(snippet) = app.search_snippets.get(&s.session_id) {
+        let (snippet, match_label) = match app.active_preview_matches() {
+            Some(windows) => (
+                Some(&windows[app.preview_match_current.min(windows.len() - 1)]),
+                format!(" ── MATCH {}/{} ── ", app.preview_match_current + 1, windows.len()),
+            ),
+            None => (app.search_snippets.get(&s.session_id), " ── MATCH ── ".to_string()),
+        };
+        if let Some(snippet) = snippet {
             if !snippet.is_empty() {
-                lines.push(Line::from(vec![
-                    Span::styled(" ── MATCH ── ", Style::default().fg(t.accent).add_modifier(Modifier::BOLD)),
-                ]));
+                match_header_line = Some(lines.len());
+                let mut header = vec![Span::styled(match_label, Style::default().fg(t.accent).add_modifier(Modifier::BOLD))];
+                header.extend(matched_term_chips(app.search_matched_terms.get(&s.session_id), t));
+                lines.push(Line::from(header));
 
                 // Styles for the match snippet
-                let match_bg = Color::Rgb(50, 40, 30); // Warm/highlighted background
-                let base_style = Style::default().bg(match_bg).fg(t.accent);
-                let highlight_style = Style::default().bg(Color::Yellow).fg(Color::Black).add_modifier(Modifier::BOLD);
+                let base_style = Style::default().bg(t.match_bg).fg(t.accent);
+                let highlight_style = t.highlight().add_modifier(Modifier::BOLD);
 
                 // Strip HTML tags for wrapping calculation, but use original for display
                 let snippet_plain = strip_html_tags(snippet);
-                // Display 12 lines (50% more than original 8)
-                for wrapped in wrap_text(snippet, bubble_width + 7).iter().take(12) {
+                for wrapped in wrap_text(snippet, bubble_width + 7).iter().take(match_cap) {
                     // Account for <b></b> tags in padding calculation
                     let visible_chars = strip_html_tags(wrapped).chars().count();
                     let padding = bubble_width.saturating_sub(visible_chars);
 
                     // Build line with HTML tag-based highlighting
                     let mut line_spans: Vec<Span> = Vec::new();
-                    line_spans.push(Span::styled(" ", Style::default().bg(match_bg)));
+                    line_spans.push(Span::styled(" ", Style::default().bg(t.match_bg)));
 
                     // Parse <b>...</b> tags for highlighting
                     let highlighted = render_snippet_with_html_tags(wrapped, base_style, highlight_style);
                     line_spans.extend(highlighted);
 
-                    line_spans.push(Span::styled(" ".repeat(padding + 1), Style::default().bg(match_bg)));
+                    line_spans.push(Span::styled(" ".repeat(padding + 1), Style::default().bg(t.match_bg)));
                     lines.push(Line::from(line_spans));
                 }
 
@@ -2074,6 +4713,42 @@ fn render_preview(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect) {
         }
     }
 
+    // Messages from the middle of the session (or around the first match)
+    if let Some(context) = context.filter(|c| !c.messages.is_empty()) {
+        lines.push(Line::from(Span::styled(
+            format!(
+                " ── CONTEXT · {} {}-{} of {} ── ",
+                if context.matched { "around match," } else { "messages" },
+                context.start + 1,
+                context.start + context.messages.len(),
+                context.total
+            ),
+            Style::default().fg(t.dim_fg),
+        )));
+        for (is_user, text) in &context.messages {
+            let (role_label, label_color, bubble_bg) = if *is_user {
+                ("User", t.user_label, t.user_bubble_bg)
+            } else if s.agent == "claude" {
+                ("Claude", t.claude_source, t.claude_bubble_bg)
+            } else {
+                ("Codex", t.codex_source, t.codex_bubble_bg)
+            };
+            lines.push(Line::from(Span::styled(
+                format!(" {}", role_label),
+                Style::default().fg(label_color).add_modifier(Modifier::BOLD),
+            )));
+            for wrapped in wrap_text(text, bubble_width).iter().take(PREVIEW_CONTEXT_LINES) {
+                let padding = bubble_width.saturating_sub(wrapped.chars().count());
+                lines.push(Line::from(vec![
+                    Span::styled(" ", Style::default().bg(bubble_bg)),
+                    Span::styled(wrapped.clone(), Style::default().bg(bubble_bg)),
+                    Span::styled(" ".repeat(padding + 1), Style::default().bg(bubble_bg)),
+                ]));
+            }
+        }
+        lines.push(Line::from(""));
+    }
+
     // Last message - labeled as "LAST MESSAGE" (if different from first)
     if !s.last_msg_content.is_empty() && s.last_msg_content != s.first_msg_content {
         let (role_label, label_color, bubble_bg) = if s.last_msg_role == "user" {
@@ -2089,13 +4764,16 @@ fn render_preview(frame: &mut Frame, app: &mut App, t: &Theme, area: Rect) {
             Span::styled(role_label, Style::default().fg(label_color).add_modifier(Modifier::BOLD)),
         ]));
 
-        for wrapped in wrap_text(&s.last_msg_content, bubble_width).iter().take(6) {
-            let padding = bubble_width.saturating_sub(wrapped.chars().count());
-            lines.push(Line::from(vec![
-                Span::styled(" ", Style::default().bg(bubble_bg)),
-                Span::styled(wrapped.clone(), Style::default().bg(bubble_bg)),
-                Span::styled(" ".repeat(padding + 1), Style::default().bg(bubble_bg)),
-            ]));
+        for wrapped in wrap_text(&s.last_msg_content, bubble_width).iter().take(message_cap) {
+            lines.push(preview_bubble_line(wrapped, bubble_width, bubble_bg, &app.query, t));
+        }
+    }
+
+    // Scroll the MATCH section into view after match navigation
+    if app.preview_jump_to_match {
+        app.preview_jump_to_match = false;
+        if let Some(line) = match_header_line {
+            app.preview_scroll = line;
         }
     }
 
@@ -2113,20 +4791,39 @@ fn render_status_bar(frame: &mut Frame, app: &App, t: &Theme, area: Rect, show_l
     // Show status message if present (e.g., "Copied to clipboard")
     if let Some(ref msg) = app.status_message {
         let status_line = Line::from(vec![
-            Span::styled(format!(" ✓ {} ", msg), Style::default().fg(Color::Green)),
+            Span::styled(format!(" ✓ {} ", msg), Style::default().fg(t.success_fg)),
             Span::styled(" (press any key to dismiss)", Style::default().fg(t.dim_fg)),
         ]);
         frame.render_widget(Paragraph::new(status_line), area);
         return;
     }
 
+    // Background summary in progress
+    if let Some(ref job) = app.summary_job {
+        let status_line = Line::from(Span::styled(
+            format!(
+                " {} Summarizing session {}... ",
+                job.spinner(),
+                job.session_id.chars().take(8).collect::<String>()
+            ),
+            Style::default().fg(t.accent),
+        ));
+        frame.render_widget(Paragraph::new(status_line), area);
+        return;
+    }
+
     // Check if we have any active filters (need third row for legend or filters)
     let has_filters = !app.include_original
         || app.include_sub
+        || app.include_tool
+        || app.filter_has_attachments
         || !app.include_trimmed
         || !app.include_continued
         || app.filter_agent.is_some()
         || app.filter_min_lines.is_some()
+        || app.filter_min_messages.is_some()
+        || app.filter_lang.is_some()
+        || app.filter_project.is_some()
         || app.filter_after_date.is_some()
         || app.filter_before_date.is_some()
         || (!app.scope_global && app.filter_branch.is_some());
@@ -2160,17 +4857,35 @@ fn render_status_bar(frame: &mut Frame, app: &App, t: &Theme, area: Rect, show_l
         // Input mode indicator
         let prompt = match mode {
             InputMode::MinLines => format!(" Min lines: {}█ ", app.input_buffer),
+            InputMode::MinMessages => format!(" Min messages: {}█ ", app.input_buffer),
             InputMode::Agent => " Agent: 1=Claude 2=Codex 0=All ".to_string(),
             InputMode::JumpToLine => format!(" Go to row: {}█ ", app.input_buffer),
             InputMode::AfterDate => format!(" After date: {}█ (any format) ", app.input_buffer),
+            InputMode::Since => format!(" Within the last: {}█ (Nd, Nw, Nmo, Ny) ", app.input_buffer),
             InputMode::BeforeDate => format!(" Before date: {}█ (any format) ", app.input_buffer),
             InputMode::ScopeDir => format!(" Scope: {}█ (dir:branch | :branch | empty=global) ", app.input_buffer),
             InputMode::Branch => format!(" Branch: {}█ (Enter=apply, empty=clear) ", app.input_buffer),
+            InputMode::Project => format!(" Project: {}█ (part of the name, empty=clear) ", app.input_buffer),
+            InputMode::KeepTurns => format!(" Keep first/last N turns: {}█ (writes a trimmed copy) ", app.input_buffer),
         };
-        nav_spans.push(Span::styled(prompt, Style::default().bg(t.accent).fg(Color::Black)));
+        nav_spans.push(Span::styled(prompt, Style::default().bg(t.accent).fg(t.badge_fg)));
+    } else if app.multi_select {
+        // Multi-select mode: marking keys replace the usual shortcuts
+        nav_spans.extend([
+            Span::styled(" SELECT ", Style::default().bg(t.accent).fg(t.badge_fg)),
+            Span::styled(format!(" {} marked ", app.selected_set.len()), filter_active),
+            Span::styled("│ ", dim),
+            Span::styled(" Space ", keycap),
+            Span::styled(" mark ", label),
+            Span::styled(" Enter ", keycap),
+            Span::styled(" output marked ", label),
+            Span::styled("│ ", dim),
+            Span::styled(" M-m ", keycap),
+            Span::styled(" done ", label),
+        ]);
     } else if app.command_mode {
         // Command mode indicator
-        nav_spans.push(Span::styled(" CMD ", Style::default().bg(t.accent).fg(Color::Black)));
+        nav_spans.push(Span::styled(" CMD ", Style::default().bg(t.accent).fg(t.badge_fg)));
         nav_spans.push(Span::styled(" :x clear :o orig :s sub :t trim :c cont :a agent :m lines :> after :< before ", label));
     } else {
         // Normal mode - single line with all shortcuts
@@ -2180,32 +4895,65 @@ fn render_status_bar(frame: &mut Frame, app: &App, t: &Theme, area: Rect, show_l
         nav_spans.extend([
             Span::styled(" ↑/↓ PgUp/Dn Home/End ", keycap),
             Span::styled(" nav ", label),
-            Span::styled("│ ", dim),
-            Span::styled(" C-g ", keycap),
-            Span::styled(" goto ", label),
         ]);
+        if let Some(key) = app.keymap.keycap(KeyAction::Goto) {
+            nav_spans.extend([Span::styled("│ ", dim), Span::styled(key, keycap), Span::styled(" goto ", label)]);
+        }
 
         if has_selection {
+            let enter_label = match app.enter_action {
+                EnterAction::Modal => " actions ",
+                EnterAction::View => " view ",
+                EnterAction::Select => " select ",
+            };
             nav_spans.extend([
                 Span::styled("│ ", dim),
                 Span::styled(" Enter ", keycap),
-                Span::styled(" actions ", label),
+                Span::styled(enter_label, label),
             ]);
+            if let Some(key) = app.keymap.keycap(KeyAction::Details) {
+                nav_spans.extend([Span::styled(key, keycap), Span::styled(" details ", label)]);
+            }
+            if app.enter_action != EnterAction::Modal {
+                nav_spans.extend([
+                    Span::styled(" M-Enter ", keycap),
+                    Span::styled(" actions ", label),
+                ]);
+            }
+            if !app.query.is_empty() {
+                nav_spans.push(Span::styled("│ ", dim));
+                let next = app.keymap.keycap(KeyAction::MatchNext);
+                let prev = app.keymap.keycap(KeyAction::MatchPrev);
+                if let (Some(next), Some(prev)) = (next, prev) {
+                    // " C-n " + " C-p " reads as " C-n/p "
+                    let prev = prev.trim();
+                    let prev = match next.trim().rsplit_once('-').zip(prev.rsplit_once('-')) {
+                        Some(((a, _), (b, key))) if a == b => key,
+                        _ => prev,
+                    };
+                    nav_spans.extend([
+                        Span::styled(format!("{}/{} ", next.trim_end(), prev), keycap),
+                        Span::styled(" match ", label),
+                    ]);
+                }
+                if let Some(key) = app.keymap.keycap(KeyAction::Explain) {
+                    nav_spans.extend([Span::styled(key, keycap), Span::styled(" why ", label)]);
+                }
+            }
         }
 
-        nav_spans.extend([
-            Span::styled("│ ", dim),
-            Span::styled(" / ", keycap),
-            Span::styled(" dir[:branch] ", label),
-        ]);
+        let sort_label = if app.sort_key == SortKey::Relevance { " time-sort " } else { " match-sort " };
+        for (action, text) in [
+            (KeyAction::OpenScope, " dir[:branch] "),
+            (KeyAction::OpenFilter, " filter "),
+            (KeyAction::SortToggle, sort_label),
+        ] {
+            if let Some(key) = app.keymap.keycap(action) {
+                nav_spans.extend([Span::styled("│ ", dim), Span::styled(key, keycap), Span::styled(text, label)]);
+            }
+        }
 
         nav_spans.extend([
-            Span::styled("│ ", dim),
-            Span::styled(" C-f ", keycap),
-            Span::styled(" filter ", label),
-            Span::styled("│ ", dim),
-            Span::styled(" C-s ", keycap),
-            Span::styled(if app.sort_by_time { " match-sort " } else { " time-sort " }, label),
             Span::styled("│ ", dim),
             Span::styled(" Esc ", keycap),
             Span::styled(" quit", label),
@@ -2240,6 +4988,12 @@ fn render_status_bar(frame: &mut Frame, app: &App, t: &Theme, area: Rect, show_l
         if app.include_sub {
             row3_spans.push(Span::styled(" [+sub]", filter_active));
         }
+        if app.include_tool {
+            row3_spans.push(Span::styled(" [+tool]", filter_active));
+        }
+        if app.filter_has_attachments {
+            row3_spans.push(Span::styled(" [attach]", filter_active));
+        }
         if !app.include_trimmed {
             row3_spans.push(Span::styled(" [-trim]", filter_active));
         }
@@ -2252,6 +5006,15 @@ fn render_status_bar(frame: &mut Frame, app: &App, t: &Theme, area: Rect, show_l
         if let Some(min) = app.filter_min_lines {
             row3_spans.push(Span::styled(format!(" [≥{}L]", min), filter_active));
         }
+        if let Some(min) = app.filter_min_messages {
+            row3_spans.push(Span::styled(format!(" [≥{} msgs]", min), filter_active));
+        }
+        if let Some(ref lang) = app.filter_lang {
+            row3_spans.push(Span::styled(format!(" [lang:{}]", lang), filter_active));
+        }
+        if let Some(ref project) = app.filter_project {
+            row3_spans.push(Span::styled(format!(" [proj:{}]", project), filter_active));
+        }
         if let Some(ref date) = app.filter_after_date_display {
             row3_spans.push(Span::styled(format!(" [>{}]", date), filter_active));
         }
@@ -2330,24 +5093,17 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
     let content_width = layout[1].width.saturating_sub(2) as usize;
 
     // Original query highlighting (blue) - pre-process with SnippetGenerator
-    let query_highlight = Style::default().bg(Color::Rgb(30, 80, 180)).fg(Color::White).add_modifier(Modifier::BOLD);
+    let query_highlight = Style::default()
+        .bg(t.query_highlight_bg)
+        .fg(t.query_highlight_fg)
+        .add_modifier(Modifier::BOLD | t.emphasis);
     let mut query_html_lines: Vec<String> = Vec::new();
     if !app.query.is_empty() {
-        if let Ok(index) = Index::open_in_dir(&app.index_path) {
-            if let Ok(content_field) = index.schema().get_field("content") {
-                let query_parser = QueryParser::for_index(&index, vec![content_field]);
-                let parsed_query = query_parser.parse_query_lenient(&app.query).0;
-                if let Ok(reader) = index.reader() {
-                    let searcher = reader.searcher();
-                    if let Ok(mut gen) = SnippetGenerator::create(&searcher, &*parsed_query, content_field) {
-                        gen.set_max_num_chars(10000); // Large enough for full lines
-                        for line in app.full_content.lines() {
-                            let html = gen.snippet(line).to_html();
-                            let merged = merge_adjacent_highlights(&html);
-                            query_html_lines.push(if merged.is_empty() { line.to_string() } else { merged });
-                        }
-                    }
-                }
+        if let Some(gen) = app.search_index.as_ref().and_then(|index| index.line_highlighter(&app.query)) {
+            for line in app.full_content.lines() {
+                let html = gen.snippet(line).to_html();
+                let merged = merge_adjacent_highlights(&html);
+                query_html_lines.push(if merged.is_empty() { line.to_string() } else { merged });
             }
         }
     }
@@ -2355,12 +5111,19 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
 
     // View search highlighting (yellow) - from / command
     let search_pattern = &app.view_search_pattern;
-    let search_highlight = Style::default().bg(Color::Yellow).fg(Color::Black);
+    let search_highlight = t.highlight();
+    let view_flags = app.view_search_flags;
+    // The current match (where n/N landed) uses the accent color instead
+    let current_match_line = app.current_view_match_line();
+    let current_highlight = Style::default()
+        .bg(t.accent)
+        .fg(t.badge_fg)
+        .add_modifier(Modifier::BOLD | t.emphasis);
 
     // Content - full conversation with styled messages
     // Track current message context for continuation lines
     #[derive(Clone, Copy, PartialEq)]
-    enum MsgContext { None, User, Assistant }
+    enum MsgContext { None, User, Assistant, Reasoning }
     let mut context = MsgContext::None;
 
     // Helper to get HTML version of content (skipping prefix chars)
@@ -2372,11 +5135,26 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
         }
     };
 
+    // Collapsed tool results: the first line becomes the summary, the rest take no rows
+    let mut hidden_lines: HashSet<usize> = HashSet::new();
+    let mut collapsed_len: HashMap<usize, usize> = HashMap::new();
+    for (start, len) in tool_result_blocks(&app.full_content) {
+        if app.collapsed_tool_blocks.contains(&start) {
+            hidden_lines.extend(start + 1..start + len);
+            collapsed_len.insert(start, len);
+        }
+    }
+
     let content_lines: Vec<Line> = app
         .full_content
         .lines()
         .enumerate()
         .map(|(idx, line)| {
+            let search_highlight = if current_match_line == Some(idx) {
+                current_highlight
+            } else {
+                search_highlight
+            };
             if line.starts_with("> ") {
                 // User message - skip "> " (2 chars)
                 context = MsgContext::User;
@@ -2389,7 +5167,7 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
                     Span::styled(" User ", Style::default().fg(t.user_label).add_modifier(Modifier::BOLD)),
                     Span::styled(" ", base_style),
                 ];
-                spans.extend(render_with_dual_highlighting(&html_content, search_pattern, base_style, query_highlight, search_highlight));
+                spans.extend(render_with_dual_highlighting(&html_content, search_pattern, base_style, query_highlight, search_highlight, view_flags));
                 spans.push(Span::styled(" ".repeat(padding), base_style));
                 Line::from(spans)
             } else if line.starts_with("⏺ ") {
@@ -2405,9 +5183,23 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
                     Span::styled(label_with_space, Style::default().fg(assistant_fg).add_modifier(Modifier::BOLD)),
                     Span::styled(" ", base_style),
                 ];
-                spans.extend(render_with_dual_highlighting(&html_content, search_pattern, base_style, query_highlight, search_highlight));
+                spans.extend(render_with_dual_highlighting(&html_content, search_pattern, base_style, query_highlight, search_highlight, view_flags));
                 spans.push(Span::styled(" ".repeat(padding), base_style));
                 Line::from(spans)
+            } else if line.starts_with(REASONING_PREFIX) {
+                // Codex reasoning - dim italic under a label, no bubble
+                context = MsgContext::Reasoning;
+                let html_content = get_html_content(idx, 2, line);
+                let base_style = Style::default().fg(t.dim_fg).add_modifier(Modifier::ITALIC);
+                let mut spans = vec![Span::styled(REASONING_LABEL, base_style.add_modifier(Modifier::BOLD))];
+                spans.extend(render_with_dual_highlighting(&html_content, search_pattern, base_style, query_highlight, search_highlight, view_flags));
+                Line::from(spans)
+            } else if let Some(&len) = collapsed_len.get(&idx) {
+                context = MsgContext::None;
+                Line::from(Span::styled(
+                    format!("  ⎿ [output: {} line{}]", len, if len == 1 { "" } else { "s" }),
+                    Style::default().fg(t.dim_fg).add_modifier(Modifier::ITALIC),
+                ))
             } else if line.starts_with("  ⎿") {
                 // Tool result - style as dimmed (2 spaces + ⎿ character)
                 context = MsgContext::None;
@@ -2415,7 +5207,7 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
                 let html_content = get_html_content(idx, 3, line);
                 let base_style = Style::default().fg(t.dim_fg);
                 let mut spans = vec![Span::styled("      ", base_style)];
-                spans.extend(render_with_dual_highlighting(&html_content, search_pattern, base_style, query_highlight, search_highlight));
+                spans.extend(render_with_dual_highlighting(&html_content, search_pattern, base_style, query_highlight, search_highlight, view_flags));
                 Line::from(spans)
             } else if line.is_empty() {
                 // Empty line - keep context for multi-paragraph messages
@@ -2432,7 +5224,7 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
                             Span::styled("      ", Style::default()),
                             Span::styled(" ", base_style),
                         ];
-                        spans.extend(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight));
+                        spans.extend(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight, view_flags));
                         spans.push(Span::styled(" ".repeat(padding), base_style));
                         Line::from(spans)
                     }
@@ -2445,20 +5237,26 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
                             Span::styled(" ".repeat(label_width), Style::default()),
                             Span::styled(" ", base_style),
                         ];
-                        spans.extend(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight));
+                        spans.extend(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight, view_flags));
                         spans.push(Span::styled(" ".repeat(padding), base_style));
                         Line::from(spans)
                     }
+                    MsgContext::Reasoning => {
+                        let base_style = Style::default().fg(t.dim_fg).add_modifier(Modifier::ITALIC);
+                        let mut spans = vec![Span::styled(" ".repeat(REASONING_LABEL.chars().count()), base_style)];
+                        spans.extend(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight, view_flags));
+                        Line::from(spans)
+                    }
                     MsgContext::None => {
                         let base_style = Style::default();
-                        Line::from(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight))
+                        Line::from(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight, view_flags))
                     }
                 }
             } else {
                 // Plain line outside message context (metadata, etc.)
                 let html_line = if use_query_html { &query_html_lines[idx] } else { line };
                 let base_style = Style::default();
-                Line::from(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight))
+                Line::from(render_with_dual_highlighting(html_line, search_pattern, base_style, query_highlight, search_highlight, view_flags))
             }
         })
         .collect();
@@ -2466,22 +5264,40 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
     // Track total lines for footer display
     let total_lines = app.full_content.lines().count();
 
+    // Wrap here rather than in the Paragraph, so scrolling, paging and match jumps
+    // can work in visual rows and land where the line is actually drawn
+    let wrap_width = layout[1].width as usize;
+    let mut rows: Vec<Line> = Vec::with_capacity(content_lines.len());
+    app.full_view_rows.clear();
+    for (idx, line) in content_lines.into_iter().enumerate() {
+        app.full_view_rows.push(rows.len());
+        if !hidden_lines.contains(&idx) {
+            rows.extend(wrap_styled_line(line, wrap_width));
+        }
+    }
+    app.full_view_rows.push(rows.len());
+    app.full_view_height = (layout[1].height as usize).max(1);
+    if let Some(line) = app.full_view_anchor.take() {
+        app.scroll_to_line(line);
+    }
+
     // Clamp scroll to valid range
-    let max_scroll = content_lines.len().saturating_sub(1);
+    let max_scroll = rows.len().saturating_sub(1);
     if app.full_content_scroll > max_scroll {
         app.full_content_scroll = max_scroll;
     }
 
-    // Manually skip lines to scroll (so scroll works on content lines, not visual lines)
-    // This ensures search navigation jumps to the correct content line
-    let visible_lines: Vec<Line> = content_lines
+    let visible_lines: Vec<Line> = rows
         .into_iter()
         .skip(app.full_content_scroll)
+        .take(app.full_view_height)
         .collect();
 
-    let content = Paragraph::new(visible_lines)
-        .wrap(ratatui::widgets::Wrap { trim: false });
-    frame.render_widget(content, layout[1]);
+    if app.tool_timeline_mode {
+        render_tool_timeline(frame, app, t, layout[1]);
+    } else {
+        frame.render_widget(Paragraph::new(visible_lines), layout[1]);
+    }
 
     // Footer - navigation hints or search input
     let keycap = Style::default().bg(t.keycap_bg);
@@ -2489,13 +5305,39 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
     let dim = Style::default().fg(t.dim_fg);
     let highlight = Style::default().fg(t.match_fg);
 
-    let footer = if app.view_search_mode {
+    let footer = if app.tool_timeline_mode {
+        let position = if app.tool_timeline.is_empty() {
+            "No tool calls".to_string()
+        } else {
+            format!("Tool call {}/{}", app.tool_timeline_selected + 1, app.tool_timeline.len())
+        };
+        Line::from(vec![
+            Span::styled(" ↑↓/jk ", keycap),
+            Span::styled(" select ", label),
+            Span::styled(" │ ", dim),
+            Span::styled(" Enter ", keycap),
+            Span::styled(" jump to call ", label),
+            Span::styled(" │ ", dim),
+            Span::styled(" t/Esc ", keycap),
+            Span::styled(" transcript", label),
+            Span::styled(format!("  {}", position), dim),
+        ])
+    } else if app.top_level() == Some(ViewLevel::ViewSearch) {
         // Search input mode
         Line::from(vec![
             Span::styled(" /", Style::default().fg(t.accent)),
             Span::styled(&app.view_search_pattern, label),
             Span::styled("█", Style::default().fg(t.accent)),
             Span::styled("  [Enter: search original; keywords+Enter: search; Esc: cancel]", dim),
+            Span::styled(format!("  {}", view_search_scope_label(app)), dim),
+            Span::styled(
+                format!(
+                    "  [C-w whole word: {}] [C-c match case: {}]",
+                    if app.view_search_flags.whole_word { "on" } else { "off" },
+                    if app.view_search_flags.case_sensitive { "on" } else { "off" },
+                ),
+                dim,
+            ),
         ])
     } else if !app.view_search_pattern.is_empty() || app.query_nav_mode {
         // Active search mode - either view search (yellow) or query nav (blue/original)
@@ -2524,9 +5366,19 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
             };
             ("[original]".to_string(), info)
         };
+        let mut flags = String::new();
+        if !app.view_search_pattern.is_empty() {
+            if app.view_search_flags.whole_word {
+                flags.push_str(" [whole word]");
+            }
+            if app.view_search_flags.case_sensitive {
+                flags.push_str(" [match case]");
+            }
+        }
         Line::from(vec![
             Span::styled(" /", Style::default().fg(t.accent)),
             Span::styled(pattern_display, highlight),
+            Span::styled(flags, dim),
             Span::styled(format!("  {} ", match_info), dim),
             Span::styled(" │ ", dim),
             Span::styled(" f ", keycap),
@@ -2534,16 +5386,26 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
             Span::styled(" d ", keycap),
             Span::styled(" prev ", label),
             Span::styled(" │ ", dim),
+            Span::styled(" m ", keycap),
+            Span::styled(format!(" {} ", view_search_scope_label(app)), label),
+            Span::styled(" │ ", dim),
             Span::styled(" Esc ", keycap),
             Span::styled(" clear ", label),
             Span::styled(
-                format!("  Line {}/{}", app.full_content_scroll + 1, total_lines),
+                format!("  Line {}/{}", app.top_line() + 1, total_lines),
                 dim,
             ),
         ])
     } else {
         // Normal mode - show navigation hints
-        Line::from(vec![
+        let mut spans = Vec::new();
+        if app.view_changes.is_some() {
+            spans.push(Span::styled(" ↻ updated since last view ", Style::default().fg(t.accent)));
+            spans.push(Span::styled(" c ", keycap));
+            spans.push(Span::styled(" changes ", label));
+            spans.push(Span::styled(" │ ", dim));
+        }
+        spans.extend(vec![
             Span::styled(" ↑↓/jk ", keycap),
             Span::styled(" scroll ", label),
             Span::styled(" │ ", dim),
@@ -2552,25 +5414,171 @@ fn render_full_conversation(frame: &mut Frame, app: &mut App, t: &Theme) {
             Span::styled(" │ ", dim),
             Span::styled(" / ", keycap),
             Span::styled(" search ", label),
+            Span::styled(" m ", keycap),
+            Span::styled(format!(" {} ", view_search_scope_label(app)), label),
             Span::styled(" │ ", dim),
             Span::styled(" Home/End ", keycap),
             Span::styled(" jump ", label),
             Span::styled(" │ ", dim),
+            Span::styled(" t ", keycap),
+            Span::styled(" tools ", label),
+            Span::styled(" │ ", dim),
+            Span::styled(" e ", keycap),
+            Span::styled(" editor ", label),
+            Span::styled(" │ ", dim),
+            Span::styled(" z/Enter ", keycap),
+            Span::styled(" fold output ", label),
+            Span::styled(" │ ", dim),
+        ]);
+        if app.selected_session().is_some_and(|s| s.agent == "codex") {
+            spans.push(Span::styled(" r ", keycap));
+            let shown = if app.show_reasoning { "hide" } else { "show" };
+            spans.push(Span::styled(format!(" {} reasoning ", shown), label));
+            spans.push(Span::styled(" │ ", dim));
+        }
+        spans.extend(vec![
             Span::styled(" Space/Esc/q ", keycap),
             Span::styled(" back", label),
             Span::styled(
-                format!("  Line {}/{}", app.full_content_scroll + 1, total_lines),
+                format!("  Line {}/{}", app.top_line() + 1, total_lines),
                 dim,
             ),
-        ])
+        ]);
+        Line::from(spans)
     };
     frame.render_widget(Paragraph::new(footer), layout[2]);
 }
 
+/// Tool calls of the full-view session, two rows each: the call, then its result
+fn render_tool_timeline(frame: &mut Frame, app: &App, t: &Theme, area: Rect) {
+    let dim = Style::default().fg(t.dim_fg);
+    if app.tool_timeline.is_empty() {
+        frame.render_widget(Paragraph::new(Line::from(Span::styled(" No tool calls in this session", dim))), area);
+        return;
+    }
+
+    // Keep the selected call on screen
+    let visible = (area.height as usize / 2).max(1);
+    let first = (app.tool_timeline_selected + 1).saturating_sub(visible);
+    let width = area.width as usize;
+    let number_width = app.tool_timeline.len().to_string().len();
+
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, call) in app.tool_timeline.iter().enumerate().skip(first).take(visible) {
+        let selected = i == app.tool_timeline_selected;
+        let row = if selected { t.selection() } else { Style::default() };
+        let prefix = format!(" {:>w$}. ", i + 1, w = number_width);
+        let used = prefix.chars().count() + call.name.chars().count() + 2;
+        lines.push(Line::from(vec![
+            Span::styled(prefix, row.fg(t.dim_fg)),
+            Span::styled(call.name.clone(), row.fg(t.accent).add_modifier(Modifier::BOLD)),
+            Span::styled("  ", row),
+            Span::styled(truncate(&call.input, width.saturating_sub(used)), row),
+        ]));
+        let indent = " ".repeat(number_width + 4);
+        let result = match &call.result {
+            Some(first_line) if call.result_lines > 1 => {
+                format!("{}⎿ {}  (+{} lines)", indent, first_line, call.result_lines - 1)
+            }
+            Some(first_line) => format!("{}⎿ {}", indent, first_line),
+            None => format!("{}⎿ (no result)", indent),
+        };
+        lines.push(Line::from(Span::styled(truncate(&result, width), dim)));
+    }
+    frame.render_widget(Paragraph::new(lines), area);
+}
+
+fn view_search_scope_label(app: &App) -> &'static str {
+    if app.view_search_messages_only {
+        "[messages only]"
+    } else {
+        "[all lines]"
+    }
+}
+
 // ============================================================================
 // Helpers
 // ============================================================================
 
+/// For each line of a full-view conversation, whether it belongs to a user or
+/// assistant message (as opposed to a tool result or metadata line). Mirrors the
+/// message-context tracking in render_full_view.
+fn message_line_mask(content: &str) -> Vec<bool> {
+    let mut in_message = false;
+    content
+        .lines()
+        .map(|line| {
+            if line.starts_with("> ") || line.starts_with("⏺ ") || line.starts_with(REASONING_PREFIX) {
+                in_message = true;
+            } else if line.starts_with("  ⎿") {
+                in_message = false;
+            }
+            in_message && !line.is_empty()
+        })
+        .collect()
+}
+
+/// Split a styled line into rows at most `width` columns wide (wide CJK and emoji
+/// chars take two), breaking after the last space that fits (mid-word when there
+/// is none), much like Paragraph's word wrap
+fn wrap_styled_line(line: Line<'_>, width: usize) -> Vec<Line<'static>> {
+    let cells: Vec<(char, Style, usize)> = line
+        .spans
+        .iter()
+        .flat_map(|span| {
+            let style = line.style.patch(span.style);
+            span.content.chars().map(move |c| (c, style, c.width().unwrap_or(0)))
+        })
+        .collect();
+    let to_line = |cells: &[(char, Style, usize)]| {
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        let mut text = String::new();
+        let mut style = Style::default();
+        for (i, &(c, cell_style, _)) in cells.iter().enumerate() {
+            if i > 0 && cell_style != style {
+                spans.push(Span::styled(std::mem::take(&mut text), style));
+            }
+            style = cell_style;
+            text.push(c);
+        }
+        if !text.is_empty() {
+            spans.push(Span::styled(text, style));
+        }
+        Line::from(spans)
+    };
+
+    if width == 0 || cells.iter().map(|&(.., w)| w).sum::<usize>() <= width {
+        return vec![to_line(&cells)];
+    }
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < cells.len() {
+        // The cells that fit in `width` columns - always at least one, so a wide char
+        // in a one-column pane still moves on
+        let mut fit = start;
+        let mut used = 0;
+        while fit < cells.len() && (fit == start || used + cells[fit].2 <= width) {
+            used += cells[fit].2;
+            fit += 1;
+        }
+        if fit == cells.len() {
+            rows.push(to_line(&cells[start..]));
+            break;
+        }
+        // A space just past the edge still counts: the row is full and the space is dropped
+        let (end, next) = match cells[start + 1..=fit].iter().rposition(|&(c, ..)| c == ' ') {
+            Some(i) => {
+                let space = start + 1 + i;
+                ((space + 1).min(fit), space + 1)
+            }
+            None => (fit, fit),
+        };
+        rows.push(to_line(&cells[start..end]));
+        start = next;
+    }
+    rows
+}
+
 fn truncate(s: &str, max: usize) -> String {
     // Guard against edge cases that would cause underflow or empty results
     if max == 0 {
@@ -2785,6 +5793,16 @@ fn highlight_keywords_in_line<'a>(
     spans
 }
 
+/// `[auth] [token] ` chips for the query terms a session matched (none if no terms)
+fn matched_term_chips(terms: Option<&Vec<String>>, t: &Theme) -> Vec<Span<'static>> {
+    let chip = Style::default().fg(t.match_fg).add_modifier(Modifier::BOLD);
+    terms
+        .into_iter()
+        .flatten()
+        .flat_map(|term| [Span::styled(format!("[{}]", term), chip), Span::raw(" ")])
+        .collect()
+}
+
 /// Render snippet with Tantivy's <b> tags as highlighted spans.
 /// Parses <b>...</b> tags and applies highlight_style to matched text.
 fn render_snippet_with_html_tags<'a>(
@@ -2863,6 +5881,7 @@ fn render_with_dual_highlighting<'a>(
     base_style: Style,
     query_highlight: Style,   // Blue for original query
     view_highlight: Style,    // Yellow for view search
+    view_flags: ViewSearchFlags,
 ) -> Vec<Span<'a>> {
     // First pass: parse <b> tags and build a list of (text, is_query_match)
     let mut segments: Vec<(String, bool)> = Vec::new();
@@ -2907,29 +5926,17 @@ fn render_with_dual_highlighting<'a>(
         } else {
             // Apply view search highlighting within this segment
             let segment_base = if is_query_match { query_highlight } else { base_style };
-            let pattern_lower = view_pattern.to_lowercase();
-            let text_lower = text.to_lowercase();
             let text_chars: Vec<char> = text.chars().collect();
-            let pattern_chars: Vec<char> = pattern_lower.chars().collect();
-            let text_lower_chars: Vec<char> = text_lower.chars().collect();
 
             let mut last_end = 0;
-            let mut i = 0;
-            while i + pattern_chars.len() <= text_lower_chars.len() {
-                let match_found = (0..pattern_chars.len())
-                    .all(|j| text_lower_chars[i + j] == pattern_chars[j]);
-                if match_found {
-                    if i > last_end {
-                        let before: String = text_chars[last_end..i].iter().collect();
-                        spans.push(Span::styled(before, segment_base));
-                    }
-                    let matched: String = text_chars[i..i + pattern_chars.len()].iter().collect();
-                    spans.push(Span::styled(matched, view_highlight));
-                    last_end = i + pattern_chars.len();
-                    i = last_end;
-                } else {
-                    i += 1;
+            for (start, end) in find_view_matches(&text_chars, view_pattern, view_flags) {
+                if start > last_end {
+                    let before: String = text_chars[last_end..start].iter().collect();
+                    spans.push(Span::styled(before, segment_base));
                 }
+                let matched: String = text_chars[start..end].iter().collect();
+                spans.push(Span::styled(matched, view_highlight));
+                last_end = end;
             }
             if last_end < text_chars.len() {
                 let remaining: String = text_chars[last_end..].iter().collect();
@@ -2945,59 +5952,47 @@ fn render_with_dual_highlighting<'a>(
     spans
 }
 
-/// Highlight search pattern matches in text, returning spans with base and highlight styles
-fn highlight_search_in_text<'a>(
-    text: &str,
-    pattern: &str,
-    base_style: Style,
-    highlight_style: Style,
-) -> Vec<Span<'a>> {
-    if pattern.is_empty() {
-        return vec![Span::styled(text.to_string(), base_style)];
-    }
+/// Options for the full view's / search
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct ViewSearchFlags {
+    whole_word: bool,     // Matches must not touch a word character on either side
+    case_sensitive: bool, // Otherwise letters compare case-insensitively
+}
 
-    let pattern_lower = pattern.to_lowercase();
-    let text_lower = text.to_lowercase();
-    let mut spans: Vec<Span> = Vec::new();
-    let mut last_end = 0;
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
 
-    // Find all occurrences of pattern (case-insensitive)
-    let text_chars: Vec<char> = text.chars().collect();
-    let pattern_chars: Vec<char> = pattern_lower.chars().collect();
-    let text_lower_chars: Vec<char> = text_lower.chars().collect();
+/// Char ranges (start, end) of `pattern` in `text`, left to right and non-overlapping.
+fn find_view_matches(text: &[char], pattern: &str, flags: ViewSearchFlags) -> Vec<(usize, usize)> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let same = |a: char, b: char| {
+        if flags.case_sensitive {
+            a == b
+        } else {
+            a == b || a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
 
+    let mut matches = Vec::new();
+    if pattern.is_empty() {
+        return matches;
+    }
     let mut i = 0;
-    while i + pattern_chars.len() <= text_lower_chars.len() {
-        let match_found = (0..pattern_chars.len())
-            .all(|j| text_lower_chars[i + j] == pattern_chars[j]);
-
-        if match_found {
-            // Add text before match
-            if i > last_end {
-                let before: String = text_chars[last_end..i].iter().collect();
-                spans.push(Span::styled(before, base_style));
-            }
-            // Add highlighted match
-            let matched: String = text_chars[i..i + pattern_chars.len()].iter().collect();
-            spans.push(Span::styled(matched, highlight_style));
-            last_end = i + pattern_chars.len();
-            i = last_end;
+    while i + pattern.len() <= text.len() {
+        let end = i + pattern.len();
+        let found = (0..pattern.len()).all(|j| same(text[i + j], pattern[j]))
+            && (!flags.whole_word
+                || ((i == 0 || !is_word_char(text[i - 1]))
+                    && (end == text.len() || !is_word_char(text[end]))));
+        if found {
+            matches.push((i, end));
+            i = end;
         } else {
             i += 1;
         }
     }
-
-    // Add remaining text
-    if last_end < text_chars.len() {
-        let remaining: String = text_chars[last_end..].iter().collect();
-        spans.push(Span::styled(remaining, base_style));
-    }
-
-    if spans.is_empty() {
-        spans.push(Span::styled(text.to_string(), base_style));
-    }
-
-    spans
+    matches
 }
 
 /// Parse a flexible date string into (YYYYMMDD, display_format) for comparison and display
@@ -3049,6 +6044,24 @@ fn parse_flexible_date(input: &str) -> Option<(String, String)> {
     None
 }
 
+/// Parse a relative age like `7d`, `2w`, `3mo` or `1y` into the (YYYYMMDD, display)
+/// cutoff that many days/weeks/months/years before today, in parse_flexible_date's formats
+fn parse_relative_date(input: &str) -> Option<(String, String)> {
+    let input = input.trim().to_lowercase();
+    let split = input.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = input.split_at(split);
+    let count: u32 = count.parse().ok()?;
+    let today = Local::now().date_naive();
+    let cutoff = match unit {
+        "d" => today.checked_sub_days(chrono::Days::new(count as u64))?,
+        "w" => today.checked_sub_days(chrono::Days::new(count as u64 * 7))?,
+        "mo" => today.checked_sub_months(chrono::Months::new(count))?,
+        "y" => today.checked_sub_months(chrono::Months::new(count.checked_mul(12)?))?,
+        _ => return None,
+    };
+    Some((cutoff.format("%Y%m%d").to_string(), cutoff.format("%m/%d/%y").to_string()))
+}
+
 /// Extract YYYYMMDD from an ISO timestamp for comparison
 fn extract_date_for_comparison(timestamp: &str) -> Option<String> {
     // Try to parse as RFC3339 or similar
@@ -3136,36 +6149,122 @@ fn format_time_ago(modified: &str) -> String {
 // Index Loading
 // ============================================================================
 
-fn load_sessions(index_path: &str, limit: usize) -> Result<Vec<Session>> {
+/// Index schema this build reads. Mirrors INDEX_SCHEMA_VERSION in search_index.py,
+/// which stamps it into the index's VERSION file as `<package version>+index<N>`.
+const INDEX_SCHEMA_VERSION: u32 = 5;
+
+/// Fields load_sessions requires (keep in sync with session_from_fields)
+const LOAD_SESSIONS_REQUIRED_FIELDS: &[&str] = &[
+    "session_id", "agent", "project", "branch", "cwd", "created", "modified",
+    "modified_ts", "lines", "export_path", "first_msg_role", "first_msg_content",
+    "last_msg_role", "last_msg_content", "derivation_type", "is_sidechain",
+];
+/// Fields load_sessions uses when present (absent in older indexes)
+const LOAD_SESSIONS_OPTIONAL_FIELDS: &[&str] = &[
+    "first_user_msg_content", "claude_home", "custom_title", "message_count",
+    "attachment_count", "code_langs", "list_meta",
+];
+/// Fields search_tantivy requires; without them keyword search returns no results
+const SEARCH_REQUIRED_FIELDS: &[&str] = &["content", "session_id", "modified"];
+/// Fields search_tantivy uses when present
+const SEARCH_OPTIONAL_FIELDS: &[&str] = &["claude_home"];
+
+/// Attempts made to read the index before giving up (covers a concurrent indexer commit)
+const INDEX_LOAD_ATTEMPTS: u32 = 4;
+/// Initial backoff between index read attempts; doubles after each failure
+const INDEX_LOAD_BACKOFF_MS: u64 = 100;
+
+/// Sessions read so far out of the documents found, for the startup spinner
+#[derive(Default)]
+struct LoadProgress {
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+/// Quiet period before the spinner shows, so fast loads don't flash it
+const SPINNER_DELAY_MS: u64 = 200;
+
+/// Run `work` while a stderr spinner shows its progress (with `show`). The TUI
+/// takes over the screen afterwards, so the line is cleared once `work` returns.
+fn with_loading_spinner<T>(show: bool, work: impl FnOnce(&LoadProgress) -> T) -> T {
+    let progress = LoadProgress::default();
+    if !show {
+        return work(&progress);
+    }
+    let (stop_tx, stop_rx) = std::sync::mpsc::channel::<()>();
+    std::thread::scope(|scope| {
+        let progress = &progress;
+        scope.spawn(move || {
+            if stop_rx.recv_timeout(Duration::from_millis(SPINNER_DELAY_MS)).is_ok() {
+                return;
+            }
+            let mut stderr = io::stderr();
+            for frame in SPINNER_FRAMES.iter().cycle() {
+                let (done, total) = (progress.done.load(Ordering::Relaxed), progress.total.load(Ordering::Relaxed));
+                let count = if total > 0 { format!(" {}/{}", done, total) } else { String::new() };
+                let _ = write!(stderr, "\r{} Loading sessions...{}", frame, count);
+                let _ = stderr.flush();
+                if stop_rx.recv_timeout(Duration::from_millis(80)).is_ok() {
+                    break;
+                }
+            }
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+        });
+        let result = work(progress);
+        let _ = stop_tx.send(());
+        result
+    })
+}
+
+/// Load sessions, retrying with backoff if the index is mid-commit.
+/// The indexer may be writing while we read, which can surface transient open/read errors.
+/// With `with_messages` false, the first/last message text is left empty to keep
+/// startup light; the TUI fetches it per session as rows are shown.
+fn load_sessions(
+    index_path: &str,
+    limit: usize,
+    with_messages: bool,
+    progress: Option<&LoadProgress>,
+) -> Result<Vec<Session>> {
+    // No index at all is not transient - fail immediately with the build hint
+    if !std::path::Path::new(index_path).join("meta.json").exists() {
+        return load_sessions_once(index_path, limit, with_messages, progress);
+    }
+
+    let mut backoff = Duration::from_millis(INDEX_LOAD_BACKOFF_MS);
+    let mut attempt = 1;
+    loop {
+        match load_sessions_once(index_path, limit, with_messages, progress) {
+            Ok(sessions) => return Ok(sessions),
+            // Still unopenable after the retries: broken rather than mid-commit
+            Err(e) if attempt >= INDEX_LOAD_ATTEMPTS && e.is::<IndexError>() => return Err(e),
+            Err(e) if attempt >= INDEX_LOAD_ATTEMPTS => {
+                return Err(e.context("Index is being updated, try again in a moment"));
+            }
+            Err(_) => {
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+fn load_sessions_once(
+    index_path: &str,
+    limit: usize,
+    with_messages: bool,
+    progress: Option<&LoadProgress>,
+) -> Result<Vec<Session>> {
     // Open index FIRST, then get schema from it (not build our own!)
-    let index = Index::open_in_dir(index_path)
-        .context("Failed to open index. Run 'aichat build-index' first.")?;
+    let index = open_index(index_path)?;
 
     let schema = index.schema();
 
-    // Look up fields by name from the actual index schema
-    let session_id_field = schema.get_field("session_id").context("missing session_id")?;
-    let agent_field = schema.get_field("agent").context("missing agent")?;
-    let project_field = schema.get_field("project").context("missing project")?;
-    let branch_field = schema.get_field("branch").context("missing branch")?;
-    let cwd_field = schema.get_field("cwd").context("missing cwd")?;
-    let created_field = schema.get_field("created").context("missing created")?;
-    let modified_field = schema.get_field("modified").context("missing modified")?;
-    let modified_ts_field = schema.get_field("modified_ts").context("missing modified_ts")?;
-    let lines_field = schema.get_field("lines").context("missing lines")?;
-    let export_path_field = schema.get_field("export_path").context("missing export_path")?;
-    let first_msg_role_field = schema.get_field("first_msg_role").context("missing first_msg_role")?;
-    let first_msg_content_field = schema.get_field("first_msg_content").context("missing first_msg_content")?;
-    let last_msg_role_field = schema.get_field("last_msg_role").context("missing last_msg_role")?;
-    let last_msg_content_field = schema.get_field("last_msg_content").context("missing last_msg_content")?;
-    // first_user_msg_content may not exist in older indexes, so make it optional
-    let first_user_msg_content_field = schema.get_field("first_user_msg_content").ok();
-    let derivation_type_field = schema.get_field("derivation_type").context("missing derivation_type")?;
-    let is_sidechain_field = schema.get_field("is_sidechain").context("missing is_sidechain")?;
-    // claude_home may not exist in older indexes, so make it optional
-    let claude_home_field = schema.get_field("claude_home").ok();
-    // custom_title may not exist in older indexes, so make it optional
-    let custom_title_field = schema.get_field("custom_title").ok();
+    for name in LOAD_SESSIONS_REQUIRED_FIELDS {
+        schema.get_field(name).with_context(|| format!("missing {name}"))?;
+    }
 
     let reader = index
         .reader_builder()
@@ -3174,244 +6273,749 @@ fn load_sessions(index_path: &str, limit: usize) -> Result<Vec<Session>> {
         .context("Failed to create reader")?;
 
     let searcher = reader.searcher();
-    let top_docs = searcher
-        .search(&AllQuery, &TopDocs::with_limit(limit * 2))
-        .context("Search failed")?;
-
     let mut sessions = Vec::new();
-    for (_score, doc_address) in top_docs {
-        let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
 
-        let get_text = |field| -> String {
-            doc.get_first(field)
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string()
-        };
+    // The indexer copies each session's list fields into the list_meta fast column,
+    // so they can be read without decompressing every stored document (content and all)
+    let has_list_meta = schema.get_field("list_meta").is_ok_and(|f| schema.get_field_entry(f).is_fast());
+    if has_list_meta {
+        if let Some(progress) = progress {
+            progress.done.store(0, Ordering::Relaxed);
+            progress.total.store(searcher.num_docs() as usize, Ordering::Relaxed);
+        }
+        let mut json = String::new();
+        for segment_reader in searcher.segment_readers() {
+            let Some(column) = segment_reader.fast_fields().str("list_meta")? else {
+                continue;
+            };
+            for doc_id in segment_reader.doc_ids_alive() {
+                json.clear();
+                if let Some(ord) = column.term_ords(doc_id).next() {
+                    column.ord_to_str(ord, &mut json)?;
+                }
+                let fields: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json).unwrap_or_default();
+                sessions.push(session_from_fields(&fields, with_messages));
+                if let Some(progress) = progress {
+                    progress.done.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    } else {
+        // Older indexes: read the stored documents
+        let top_docs = searcher
+            .search(&AllQuery, &TopDocs::with_limit(limit * 2))
+            .context("Search failed")?;
+        if let Some(progress) = progress {
+            progress.done.store(0, Ordering::Relaxed);
+            progress.total.store(top_docs.len(), Ordering::Relaxed);
+        }
+        for (_score, doc_address) in top_docs {
+            let doc: tantivy::TantivyDocument = searcher.doc(doc_address)?;
+            let fields = doc
+                .field_values()
+                .filter_map(|(field, value)| {
+                    let value = if let Some(s) = value.as_str() {
+                        serde_json::Value::from(s)
+                    } else if let Some(n) = value.as_i64() {
+                        serde_json::Value::from(n)
+                    } else {
+                        serde_json::Value::from(value.as_u64()?)
+                    };
+                    Some((schema.get_field_name(field).to_string(), value))
+                })
+                .collect();
+            sessions.push(session_from_fields(&fields, with_messages));
+            if let Some(progress) = progress {
+                progress.done.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
 
-        let lines = doc
-            .get_first(lines_field)
-            .and_then(|v| v.as_i64())
-            .unwrap_or(0);
+    // Sort by modified_ts (numeric epoch ms) for reliable time ordering
+    sessions.sort_by(|a, b| b.modified_ts.cmp(&a.modified_ts));
+    sessions.truncate(limit);
 
-        let modified_ts = doc
-            .get_first(modified_ts_field)
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0);
+    Ok(sessions)
+}
 
-        let is_sidechain_str = get_text(is_sidechain_field);
+/// A Session from its indexed fields by name; fields missing from older indexes
+/// are left empty. The message text is dropped unless `with_messages`.
+fn session_from_fields(fields: &serde_json::Map<String, serde_json::Value>, with_messages: bool) -> Session {
+    let text = |name: &str| fields.get(name).and_then(|v| v.as_str()).unwrap_or("");
+    let int = |name: &str| fields.get(name).and_then(|v| v.as_i64());
+
+    let first_msg = text("first_msg_content");
+    let first_user_msg = text("first_user_msg_content");
+    let message_text = |m: &str| if with_messages { m.to_string() } else { String::new() };
+
+    Session {
+        session_id: text("session_id").to_string(),
+        agent: text("agent").to_string(),
+        project: text("project").to_string(),
+        branch: text("branch").to_string(),
+        cwd: text("cwd").to_string(),
+        created: text("created").to_string(),
+        modified: text("modified").to_string(),
+        modified_ts: fields.get("modified_ts").and_then(|v| v.as_u64()).unwrap_or(0),
+        lines: int("lines").unwrap_or(0),
+        message_count: int("message_count"),
+        attachment_count: int("attachment_count").unwrap_or(0),
+        export_path: text("export_path").to_string(),
+        first_msg_role: text("first_msg_role").to_string(),
+        first_msg_content: message_text(first_msg),
+        last_msg_role: text("last_msg_role").to_string(),
+        last_msg_content: message_text(text("last_msg_content")),
+        first_user_msg_content: message_text(first_user_msg),
+        derivation_type: text("derivation_type").to_string(),
+        is_sidechain: text("is_sidechain") == "true",
+        claude_home: text("claude_home").to_string(),
+        custom_title: text("custom_title").to_string(),
+        // Comma-separated, most frequent first, as the indexer detected them (for --lang)
+        code_langs: text("code_langs").split(',').filter(|l| !l.is_empty()).map(str::to_string).collect(),
+        tool_generated: is_tool_prompt(first_user_msg, first_msg),
+        messages_loaded: with_messages,
+    }
+}
 
-        // Get claude_home if field exists, otherwise empty string
-        let claude_home = claude_home_field
-            .map(|f| get_text(f))
-            .unwrap_or_default();
+/// Map a code fence info string (e.g. "rs", "py3", "sh") to a canonical language name.
+/// Returns None for untagged fences and non-code tags like "text" or "diff".
+/// Mirrors CODE_LANG_ALIASES in search_index.py, which detects the indexed code_langs.
+fn normalize_code_lang(tag: &str) -> Option<&'static str> {
+    let lang = match tag.trim().to_lowercase().as_str() {
+        "rust" | "rs" => "rust",
+        "python" | "py" | "python3" | "py3" | "ipython" => "python",
+        "javascript" | "js" | "jsx" | "mjs" | "node" => "javascript",
+        "typescript" | "ts" | "tsx" => "typescript",
+        "bash" | "sh" | "shell" | "zsh" | "fish" | "console" | "shell-session" => "shell",
+        "go" | "golang" => "go",
+        "c" | "h" => "c",
+        "cpp" | "c++" | "cc" | "cxx" | "hpp" => "cpp",
+        "csharp" | "cs" | "c#" => "csharp",
+        "java" => "java",
+        "kotlin" | "kt" => "kotlin",
+        "swift" => "swift",
+        "ruby" | "rb" => "ruby",
+        "php" => "php",
+        "lua" => "lua",
+        "haskell" | "hs" => "haskell",
+        "elixir" | "ex" | "exs" => "elixir",
+        "scala" => "scala",
+        "sql" => "sql",
+        "html" => "html",
+        "css" | "scss" => "css",
+        "json" | "jsonl" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "dockerfile" | "docker" => "dockerfile",
+        _ => return None,
+    };
+    Some(lang)
+}
 
-        // Get custom_title if field exists, otherwise empty string
-        let custom_title = custom_title_field
-            .map(|f| get_text(f))
-            .unwrap_or_default();
+/// All languages detected across sessions, ordered by number of sessions using them.
+fn discover_code_langs(sessions: &[Session]) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for s in sessions {
+        for lang in &s.code_langs {
+            *counts.entry(lang.as_str()).or_default() += 1;
+        }
+    }
+    let mut langs: Vec<(&str, usize)> = counts.into_iter().collect();
+    langs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    langs.into_iter().map(|(l, _)| l.to_string()).collect()
+}
 
-        // Get first_user_msg_content if field exists, otherwise empty string
-        let first_user_msg_content = first_user_msg_content_field
-            .map(|f| get_text(f))
-            .unwrap_or_default();
+/// Distinct Claude homes of the Claude sessions, A-Z (Codex sessions share the field
+/// for their own home, which --codex-home filters)
+fn discover_claude_homes(sessions: &[Session]) -> Vec<String> {
+    let homes: std::collections::BTreeSet<&str> = sessions
+        .iter()
+        .filter(|s| s.agent != "codex" && !s.claude_home.is_empty())
+        .map(|s| s.claude_home.as_str())
+        .collect();
+    homes.into_iter().map(str::to_string).collect()
+}
 
-        sessions.push(Session {
-            session_id: get_text(session_id_field),
-            agent: get_text(agent_field),
-            project: get_text(project_field),
-            branch: get_text(branch_field),
-            cwd: get_text(cwd_field),
-            created: get_text(created_field),
-            modified: get_text(modified_field),
-            modified_ts,
-            lines,
-            export_path: get_text(export_path_field),
-            first_msg_role: get_text(first_msg_role_field),
-            first_msg_content: get_text(first_msg_content_field),
-            last_msg_role: get_text(last_msg_role_field),
-            last_msg_content: get_text(last_msg_content_field),
-            first_user_msg_content,
-            derivation_type: get_text(derivation_type_field),
-            is_sidechain: is_sidechain_str == "true",
-            claude_home,
-            custom_title,
-        });
+/// Sessions whose project or branch name fuzzy-matches `query`, best score first
+/// (newest first among equal scores).
+fn fuzzy_name_matches(sessions: &[Session], candidates: &[usize], query: &str) -> Vec<usize> {
+    let matcher = SkimMatcherV2::default();
+    let mut scored: Vec<(i64, usize)> = candidates
+        .iter()
+        .filter_map(|&i| {
+            let s = &sessions[i];
+            fuzzy_name_score(&matcher, &[s.project_name(), s.branch_display()], query).map(|score| (score, i))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(sessions[b.1].modified_ts.cmp(&sessions[a.1].modified_ts)));
+    scored.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Best fuzzy score of `query` against any of `names`; None when none match
+fn fuzzy_name_score(matcher: &SkimMatcherV2, names: &[&str], query: &str) -> Option<i64> {
+    if query.is_empty() {
+        return None;
     }
+    names.iter().filter_map(|name| matcher.fuzzy_match(name, query)).max()
+}
 
-    // Sort by modified_ts (numeric epoch ms) for reliable time ordering
-    sessions.sort_by(|a, b| b.modified_ts.cmp(&a.modified_ts));
-    sessions.truncate(limit);
+/// Characters of context kept on each side of a regex match in list snippets
+const REGEX_SNIPPET_CONTEXT: usize = 60;
+
+/// Keyword snippet length unless --snippet-chars says otherwise; the manual fallback
+/// window is half of it
+const DEFAULT_SNIPPET_CHARS: usize = 200;
+const MIN_SNIPPET_CHARS: usize = 20;
+
+/// Snippet around the first match of `re` in `text`, with the match in <b> tags like
+/// Tantivy's snippets so the list renders both the same way.
+fn regex_snippet(re: &regex::Regex, text: &str) -> Option<String> {
+    let m = re.find(text).filter(|m| !m.is_empty())?;
+    let before: String = {
+        let mut chars: Vec<char> = text[..m.start()].chars().rev().take(REGEX_SNIPPET_CONTEXT).collect();
+        chars.reverse();
+        chars.into_iter().collect()
+    };
+    let after: String = text[m.end()..].chars().take(REGEX_SNIPPET_CONTEXT).collect();
+    let snippet = format!("{}<b>{}</b>{}", before, m.as_str(), after);
+    Some(snippet.split_whitespace().collect::<Vec<_>>().join(" "))
+}
 
-    Ok(sessions)
+/// Why the search index couldn't be used
+#[derive(Debug)]
+enum IndexError {
+    Missing(String),         // nothing indexed at this path yet
+    Corrupt(String, String), // (path, reason): index files present but unreadable
+    SearchFailed(String),    // the open index couldn't run a query
 }
 
-/// Search Tantivy index for sessions matching keyword query.
-/// Returns (snippets_map, ranked_session_ids) where:
-/// - snippets_map: session_id -> snippet for lookup
-/// - ranked_session_ids: session_ids in score order (highest first)
-fn search_tantivy(
-    index_path: &str,
-    query_str: &str,
-    filter_claude_home: Option<&str>,
-    filter_codex_home: Option<&str>,
-) -> (HashMap<String, String>, Vec<String>) {
-    // Return empty if query is empty
-    if query_str.trim().is_empty() {
-        return (HashMap::new(), Vec::new());
+impl std::fmt::Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexError::Missing(path) => {
+                write!(f, "No search index at {}. Run 'aichat search' or 'aichat build-index' to build it.", path)
+            }
+            IndexError::Corrupt(path, reason) => write!(
+                f,
+                "Search index at {} is unreadable ({}). Rebuild it with 'aichat clear-index' then 'aichat build-index'.",
+                path, reason
+            ),
+            IndexError::SearchFailed(reason) => write!(f, "search failed: {}", reason),
+        }
     }
+}
 
-    let result: Option<(HashMap<String, String>, Vec<String>)> = (|| {
-        let index = Index::open_in_dir(index_path).ok()?;
-        let schema = index.schema();
+impl std::error::Error for IndexError {}
+
+/// Open the index at `index_path`, telling an absent index from a broken one
+fn open_index(index_path: &str) -> Result<Index, IndexError> {
+    if !std::path::Path::new(index_path).join("meta.json").exists() {
+        return Err(IndexError::Missing(index_path.to_string()));
+    }
+    Index::open_in_dir(index_path).map_err(|e| IndexError::Corrupt(index_path.to_string(), e.to_string()))
+}
 
-        // Get fields for search and ranking
-        let content_field = schema.get_field("content").ok()?;
-        let session_id_field = schema.get_field("session_id").ok()?;
-        let modified_field = schema.get_field("modified").ok()?;
-        let claude_home_field = schema.get_field("claude_home").ok();
+/// (snippets_map, ranked_session_ids, scores_map, matched_terms) returned by search_tantivy
+type SearchResults = (
+    HashMap<String, String>,
+    Vec<String>,
+    HashMap<String, ScoreBreakdown>,
+    HashMap<String, Vec<String>>,
+);
+
+/// The Tantivy index with a reader opened once per run. The reader reloads
+/// itself after commits, so results stay live without reopening the index.
+struct SearchIndex {
+    index: Index,
+    reader: tantivy::IndexReader,
+}
 
+impl SearchIndex {
+    fn open(index_path: &str) -> Result<Self, IndexError> {
+        let index = open_index(index_path)?;
         let reader = index
             .reader_builder()
             .reload_policy(ReloadPolicy::OnCommitWithDelay)
             .try_into()
+            .map_err(|e| IndexError::Corrupt(index_path.to_string(), e.to_string()))?;
+        Ok(Self { index, reader })
+    }
+
+    /// The indexed document of the session exported to `export_path`
+    fn session_doc(&self, export_path: &str) -> Option<tantivy::TantivyDocument> {
+        let export_path_field = self.index.schema().get_field("export_path").ok()?;
+        let searcher = self.reader.searcher();
+        // export_path uses the raw tokenizer, so the full path is a single term
+        let term = Term::from_field_text(export_path_field, export_path);
+        let top_docs = searcher
+            .search(&TermQuery::new(term, IndexRecordOption::Basic), &TopDocs::with_limit(1))
             .ok()?;
-        let searcher = reader.searcher();
-
-        // Create query parser for content field
-        let query_parser = QueryParser::for_index(&index, vec![content_field]);
-
-        // Parse the base query with lenient parsing
-        let base_query = query_parser.parse_query_lenient(query_str).0;
+        let (_, address) = top_docs.first()?;
+        searcher.doc(*address).ok()
+    }
+
+    /// The indexed full-text content of the session exported to `export_path`
+    fn session_content(&self, export_path: &str) -> Option<String> {
+        let content_field = self.index.schema().get_field("content").ok()?;
+        let doc = self.session_doc(export_path)?;
+        doc.get_first(content_field)?.as_str().map(|s| s.to_string())
+    }
+
+    /// Highlights `query` within one line of text at a time, for marking the original
+    /// query's hits in the full view
+    fn line_highlighter(&self, query: &str) -> Option<SnippetGenerator> {
+        let content_field = self.index.schema().get_field("content").ok()?;
+        let query_parser = QueryParser::for_index(&self.index, vec![content_field]);
+        let parsed_query = query_parser.parse_query_lenient(query).0;
+        let mut gen = SnippetGenerator::create(&self.reader.searcher(), &*parsed_query, content_field).ok()?;
+        gen.set_max_num_chars(10000); // Large enough for full lines
+        Some(gen)
+    }
+
+    /// (first_msg_content, last_msg_content, first_user_msg_content) for the
+    /// session exported to `export_path`, which load_sessions may have skipped.
+    fn session_messages(&self, export_path: &str) -> Option<(String, String, String)> {
+        let schema = self.index.schema();
+        let doc = self.session_doc(export_path)?;
+        let text = |name: &str| {
+            schema
+                .get_field(name)
+                .ok()
+                .and_then(|f| doc.get_first(f))
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        Some((text("first_msg_content"), text("last_msg_content"), text("first_user_msg_content")))
+    }
 
-        // Phrase boosting: multi-word queries get 5x boost for exact phrase match
-        let words: Vec<&str> = query_str.split_whitespace().collect();
-        let content_query: Box<dyn tantivy::query::Query> = if words.len() > 1 {
-            // Create phrase query for exact match
-            let terms: Vec<Term> = words
+    /// Match `re` against every session's indexed content. Returns session_id -> snippet.
+    /// A full scan of stored documents, since Tantivy's term index can't answer regexes.
+    fn search_regex(&self, re: &regex::Regex) -> HashMap<String, String> {
+        let result: Option<HashMap<String, String>> = (|| {
+            let schema = self.index.schema();
+            let session_id_field = schema.get_field("session_id").ok()?;
+            let content_field = schema.get_field("content").ok()?;
+            let message_fields: Vec<_> = ["first_msg_content", "last_msg_content"]
                 .iter()
-                .map(|w| Term::from_field_text(content_field, &w.to_lowercase()))
+                .filter_map(|name| schema.get_field(name).ok())
                 .collect();
-            let phrase_query = PhraseQuery::new(terms);
-            let boosted_phrase = BoostQuery::new(Box::new(phrase_query), 5.0);
-
-            // Combine: boosted phrase OR base query
-            Box::new(BooleanQuery::new(vec![
-                (Occur::Should, Box::new(boosted_phrase) as Box<dyn tantivy::query::Query>),
-                (Occur::Should, Box::new(base_query) as Box<dyn tantivy::query::Query>),
-            ]))
-        } else {
-            Box::new(base_query)
-        };
+            let searcher = self.reader.searcher();
+            let docs = searcher.search(&AllQuery, &DocSetCollector).ok()?;
 
-        // Build final query with claude_home filter if field exists and filters provided
-        let final_query: Box<dyn tantivy::query::Query> = if let Some(home_field) = claude_home_field {
-            // Build home filter: match either claude_home OR codex_home
-            let mut home_clauses: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
-
-            if let Some(ch) = filter_claude_home {
-                let term = Term::from_field_text(home_field, ch);
-                home_clauses.push((Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
-            }
-            if let Some(cx) = filter_codex_home {
-                let term = Term::from_field_text(home_field, cx);
-                home_clauses.push((Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+            let mut snippets = HashMap::new();
+            for address in docs {
+                let Ok(doc) = searcher.doc::<tantivy::TantivyDocument>(address) else {
+                    continue;
+                };
+                let text = |field| doc.get_first(field).and_then(|v| v.as_str()).unwrap_or("");
+                let snippet = std::iter::once(content_field)
+                    .chain(message_fields.iter().copied())
+                    .find_map(|field| regex_snippet(re, text(field)));
+                if let Some(snippet) = snippet {
+                    if let Some(id) = doc.get_first(session_id_field).and_then(|v| v.as_str()) {
+                        snippets.insert(id.to_string(), snippet);
+                    }
+                }
             }
+            Some(snippets)
+        })();
+        result.unwrap_or_default()
+    }
+
+    /// Search Tantivy index for sessions matching keyword query.
+    /// Returns (snippets_map, ranked_session_ids, scores_map, matched_terms) where:
+    /// - snippets_map: session_id -> snippet for lookup
+    /// - ranked_session_ids: session_ids in score order (highest first)
+    /// - scores_map: session_id -> raw score, recency multiplier, final score, rank
+    /// - matched_terms: session_id -> the query terms its content contains (multi-term queries only)
+    fn search_tantivy(
+        &self,
+        query_str: &str,
+        filter_claude_home: Option<&str>,
+        filter_codex_home: Option<&str>,
+        phrase_only: bool,
+        snippet_chars: usize,
+    ) -> Result<SearchResults, IndexError> {
+        // Return empty if query is empty
+        if query_str.trim().is_empty() {
+            return Ok(SearchResults::default());
+        }
 
-            if home_clauses.is_empty() {
-                // No home filter specified, just use content query
-                content_query
-            } else {
-                // Combine: content query AND (claude_home OR codex_home)
-                let home_filter = BooleanQuery::new(home_clauses);
+        let index = &self.index;
+        let schema = index.schema();
+
+        // Get fields for search and ranking; an index without them can't be searched
+        let field = |name: &str| {
+            schema
+                .get_field(name)
+                .map_err(|_| IndexError::SearchFailed(format!("index has no {} field", name)))
+        };
+        let content_field = field("content")?;
+        let session_id_field = field("session_id")?;
+        let modified_field = field("modified")?;
+
+        // Queries that reduce to no terms (e.g. only punctuation) match nothing
+        let result: Option<Result<SearchResults, IndexError>> = (|| {
+            let claude_home_field = schema.get_field("claude_home").ok();
+
+            let searcher = self.reader.searcher();
+
+            // Create query parser for content field
+            let query_parser = QueryParser::for_index(index, vec![content_field]);
+
+            // Parse the base query with lenient parsing
+            let base_query = query_parser.parse_query_lenient(query_str).0;
+
+            // Phrase boosting: multi-word queries get 5x boost for exact phrase match
+            let words: Vec<&str> = query_str.split_whitespace().collect();
+            // AND / OR / -word / "phrase" build an explicit boolean query instead
+            let boolean_clauses = if phrase_only { None } else { parse_boolean_query(query_str) };
+            let content_query: Box<dyn tantivy::query::Query> = if phrase_only {
+                // Phrase mode: the whole query must match as one exact phrase, no term OR'ing.
+                // Tokenize with the content field's analyzer so paths/punctuation split like the index.
+                build_exact_phrase_query(index, content_field, query_str)?
+            } else if let Some(ref clauses) = boolean_clauses {
+                build_boolean_query(index, content_field, clauses)?
+            } else if words.len() > 1 {
+                // Create phrase query for exact match
+                let terms: Vec<Term> = words
+                    .iter()
+                    .map(|w| Term::from_field_text(content_field, &w.to_lowercase()))
+                    .collect();
+                let phrase_query = PhraseQuery::new(terms);
+                let boosted_phrase = BoostQuery::new(Box::new(phrase_query), 5.0);
+
+                // Combine: boosted phrase OR base query
                 Box::new(BooleanQuery::new(vec![
-                    (Occur::Must, content_query),
-                    (Occur::Must, Box::new(home_filter) as Box<dyn tantivy::query::Query>),
+                    (Occur::Should, Box::new(boosted_phrase) as Box<dyn tantivy::query::Query>),
+                    (Occur::Should, Box::new(base_query) as Box<dyn tantivy::query::Query>),
                 ]))
-            }
-        } else {
-            // No claude_home field in schema, just use content query
-            content_query
-        };
+            } else {
+                Box::new(base_query)
+            };
+
+            // Build final query with claude_home filter if field exists and filters provided
+            let final_query: Box<dyn tantivy::query::Query> = if let Some(home_field) = claude_home_field {
+                // Build home filter: match either claude_home OR codex_home
+                let mut home_clauses: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
 
-        // Search with high limit
-        let top_docs = searcher.search(&*final_query, &TopDocs::with_limit(2000)).ok()?;
+                if let Some(ch) = filter_claude_home {
+                    let term = Term::from_field_text(home_field, ch);
+                    home_clauses.push((Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+                }
+                if let Some(cx) = filter_codex_home {
+                    let term = Term::from_field_text(home_field, cx);
+                    home_clauses.push((Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+                }
 
-        // Create snippet generator from the query (re-parse since base_query was moved)
-        let snippet_query = query_parser.parse_query_lenient(query_str).0;
-        let snippet_generator: Option<SnippetGenerator> = SnippetGenerator::create(&searcher, &*snippet_query, content_field)
-            .ok()
-            .map(|mut g| { g.set_max_num_chars(200); g });
+                if home_clauses.is_empty() {
+                    // No home filter specified, just use content query
+                    content_query
+                } else {
+                    // Combine: content query AND (claude_home OR codex_home)
+                    let home_filter = BooleanQuery::new(home_clauses);
+                    Box::new(BooleanQuery::new(vec![
+                        (Occur::Must, content_query),
+                        (Occur::Must, Box::new(home_filter) as Box<dyn tantivy::query::Query>),
+                    ]))
+                }
+            } else {
+                // No claude_home field in schema, just use content query
+                content_query
+            };
 
-        // Fallback: extract keywords for manual snippet extraction if generator unavailable
-        let query_clean = query_str.trim_matches('"').trim_matches('\'');
-        let query_lower = query_clean.to_lowercase();
-        let keywords: Vec<&str> = query_lower.split_whitespace().collect();
+            // Search with high limit
+            let top_docs = match searcher.search(&*final_query, &TopDocs::with_limit(2000)) {
+                Ok(top_docs) => top_docs,
+                Err(e) => return Some(Err(IndexError::SearchFailed(e.to_string()))),
+            };
 
-        // Recency ranking: 7-day half-life exponential decay
-        let now = Utc::now().timestamp() as f64;
-        let half_life_secs = 7.0 * 24.0 * 3600.0; // 7 days
+            // Create snippet generator from the query (re-parse since base_query was moved)
+            let snippet_query = if phrase_only {
+                build_exact_phrase_query(index, content_field, query_str)?
+            } else if let Some(ref clauses) = boolean_clauses {
+                build_boolean_query(index, content_field, clauses)?
+            } else {
+                query_parser.parse_query_lenient(query_str).0
+            };
+            let snippet_generator: Option<SnippetGenerator> = SnippetGenerator::create(&searcher, &*snippet_query, content_field)
+                .ok()
+                .map(|mut g| { g.set_max_num_chars(snippet_chars); g });
 
-        // Collect results with scores and apply recency boost
-        let mut scored_results: Vec<(f32, String, String)> = top_docs
-            .iter()
-            .filter_map(|(score, doc_address)| {
-                let doc: tantivy::TantivyDocument = searcher.doc(*doc_address).ok()?;
-                let session_id = doc.get_first(session_id_field)?.as_str()?.to_string();
-                let content = doc.get_first(content_field)?.as_str()?;
-                let modified = doc.get_first(modified_field)?.as_str().unwrap_or("");
-
-                // Parse modified timestamp and compute recency boost
-                let modified_ts = DateTime::parse_from_rfc3339(modified)
-                    .map(|dt| dt.timestamp() as f64)
-                    .unwrap_or(0.0);
-                let age = (now - modified_ts).max(0.0);
-                let recency_mult = 1.0 + (-age / half_life_secs).exp();
-
-                let final_score = *score * recency_mult as f32;
-                // Use Tantivy's snippet generator if available, else fallback to manual extraction
-                // Keep <b> tags for highlighting - they'll be parsed when rendering
-                // For multi-word queries WITHOUT quotes, this is an OR search - highlight any keyword
-                let is_multi_word = keywords.len() > 1;
-                let snippet = if let Some(ref gen) = snippet_generator {
-                    let tantivy_snippet = gen.snippet(content);
-                    let html = tantivy_snippet.to_html();
-                    if html.is_empty() {
-                        // Fallback if Tantivy snippet is empty
-                        extract_snippet(content, &keywords, 100)
-                    } else if is_multi_word {
-                        // Multi-word query: Tantivy's highlighting is unreliable for OR queries
-                        // Re-highlight all keywords (case-insensitive, substring-aware)
-                        rehighlight_keywords(&html, &keywords)
-                    } else if html.contains("<b>") {
-                        // Single keyword: Tantivy found something to highlight - use it
-                        merge_adjacent_highlights(&html)
+            // Fallback: extract keywords for manual snippet extraction if generator unavailable
+            let query_clean = query_str.trim_matches('"').trim_matches('\'');
+            let query_lower = query_clean.to_lowercase();
+            // Operators and excluded words are not highlighted
+            let clause_keywords: Vec<String> = boolean_clauses
+                .iter()
+                .flatten()
+                .filter(|(occur, _)| *occur != Occur::MustNot)
+                .map(|(_, QueryClause::Word(text) | QueryClause::Phrase(text))| text.to_lowercase())
+                .collect();
+            let keywords: Vec<&str> = if phrase_only {
+                // Highlight the phrase as a whole rather than its individual words
+                vec![query_lower.trim()]
+            } else if boolean_clauses.is_some() {
+                clause_keywords.iter().map(String::as_str).collect()
+            } else {
+                query_lower.split_whitespace().collect()
+            };
+
+            // Which query terms each document contains, for the matched-term chips.
+            // Only telling when several terms are OR'ed together.
+            let term_docs: Vec<(String, HashSet<tantivy::DocAddress>)> = if phrase_only || words.len() < 2 {
+                Vec::new()
+            } else {
+                query_terms(index, content_field, query_str)
+                    .into_iter()
+                    .filter_map(|(text, term)| {
+                        let query = TermQuery::new(term, IndexRecordOption::Basic);
+                        Some((text, searcher.search(&query, &DocSetCollector).ok()?))
+                    })
+                    .collect()
+            };
+
+            // Recency ranking: 7-day half-life exponential decay
+            let now = Utc::now().timestamp() as f64;
+            let half_life_secs = 7.0 * 24.0 * 3600.0; // 7 days
+
+            // Collect results with scores and apply recency boost
+            let mut scored_results: Vec<(ScoreBreakdown, String, String, Vec<String>)> = top_docs
+                .iter()
+                .filter_map(|(score, doc_address)| {
+                    let doc: tantivy::TantivyDocument = searcher.doc(*doc_address).ok()?;
+                    let session_id = doc.get_first(session_id_field)?.as_str()?.to_string();
+                    let content = doc.get_first(content_field)?.as_str()?;
+                    let modified = doc.get_first(modified_field)?.as_str().unwrap_or("");
+
+                    // Parse modified timestamp and compute recency boost
+                    let modified_ts = DateTime::parse_from_rfc3339(modified)
+                        .map(|dt| dt.timestamp() as f64)
+                        .unwrap_or(0.0);
+                    let age = (now - modified_ts).max(0.0);
+                    let recency_mult = 1.0 + (-age / half_life_secs).exp();
+
+                    let final_score = *score * recency_mult as f32;
+                    let breakdown = ScoreBreakdown {
+                        tantivy_score: *score,
+                        recency_mult: recency_mult as f32,
+                        final_score,
+                        rank: 0, // Assigned after re-sorting
+                    };
+                    // Use Tantivy's snippet generator if available, else fallback to manual extraction
+                    // Keep <b> tags for highlighting - they'll be parsed when rendering
+                    // For multi-word queries WITHOUT quotes, this is an OR search - highlight any keyword
+                    let is_multi_word = keywords.len() > 1;
+                    let snippet = if let Some(ref gen) = snippet_generator {
+                        let tantivy_snippet = gen.snippet(content);
+                        let html = tantivy_snippet.to_html();
+                        if html.is_empty() {
+                            // Fallback if Tantivy snippet is empty
+                            extract_snippet(content, &keywords, snippet_chars / 2)
+                        } else if is_multi_word {
+                            // Multi-word query: Tantivy's highlighting is unreliable for OR queries
+                            // Re-highlight all keywords (case-insensitive, substring-aware)
+                            rehighlight_keywords(&html, &keywords)
+                        } else if html.contains("<b>") {
+                            // Single keyword: Tantivy found something to highlight - use it
+                            merge_adjacent_highlights(&html)
+                        } else {
+                            // Tantivy returned text but no highlights - use custom extraction
+                            extract_snippet(content, &keywords, snippet_chars / 2)
+                        }
                     } else {
-                        // Tantivy returned text but no highlights - use custom extraction
-                        extract_snippet(content, &keywords, 100)
-                    }
-                } else {
-                    extract_snippet(content, &keywords, 100)
-                };
-                Some((final_score, session_id, snippet))
-            })
-            .collect();
+                        extract_snippet(content, &keywords, snippet_chars / 2)
+                    };
+                    let matched: Vec<String> = term_docs
+                        .iter()
+                        .filter(|(_, docs)| docs.contains(doc_address))
+                        .map(|(text, _)| text.clone())
+                        .collect();
+                    Some((breakdown, session_id, snippet, matched))
+                })
+                .collect();
+
+            // Re-sort by final score (descending) - recency-adjusted ranking
+            scored_results.sort_by(|a, b| {
+                b.0.final_score
+                    .partial_cmp(&a.0.final_score)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            // Build the snippet map, the ranked ID list, and the score map
+            let mut snippets: HashMap<String, String> = HashMap::new();
+            let mut ranked_ids: Vec<String> = Vec::new();
+            let mut scores: HashMap<String, ScoreBreakdown> = HashMap::new();
+            let mut matched_terms: HashMap<String, Vec<String>> = HashMap::new();
+            for (pos, (mut breakdown, id, snippet, matched)) in scored_results.into_iter().enumerate() {
+                breakdown.rank = pos + 1;
+                ranked_ids.push(id.clone());
+                scores.insert(id.clone(), breakdown);
+                if !matched.is_empty() {
+                    matched_terms.insert(id.clone(), matched);
+                }
+                snippets.insert(id, snippet);
+            }
+
+            Some(Ok((snippets, ranked_ids, scores, matched_terms)))
+        })();
+
+        result.unwrap_or_else(|| Ok(SearchResults::default()))
+    }
+}
+
+/// Distinct index terms of a keyword query, in query order, tokenized like the content
+/// field. Excluded (`-word`) words and boolean operators are skipped.
+fn query_terms(index: &Index, content_field: tantivy::schema::Field, query_str: &str) -> Vec<(String, Term)> {
+    let Ok(mut analyzer) = index.tokenizer_for_field(content_field) else {
+        return Vec::new();
+    };
+    let mut terms: Vec<(String, Term)> = Vec::new();
+    for word in query_str.split_whitespace() {
+        if word.starts_with('-') || matches!(word, "AND" | "OR" | "NOT") {
+            continue;
+        }
+        let mut stream = analyzer.token_stream(word);
+        while stream.advance() {
+            let text = &stream.token().text;
+            if !terms.iter().any(|(seen, _)| seen == text) {
+                terms.push((text.clone(), Term::from_field_text(content_field, text)));
+            }
+        }
+    }
+    terms
+}
 
-        // Re-sort by final score (descending) - recency-adjusted ranking
-        scored_results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+/// One operand of a boolean keyword query
+#[derive(Clone, Debug, PartialEq)]
+enum QueryClause {
+    Word(String),   // Tokenized like the content field; several tokens match as a phrase
+    Phrase(String), // "quoted words": exact phrase, boosted like the multi-word phrase match
+}
 
-        // Build both the snippet map and the ranked ID list
-        let mut snippets: HashMap<String, String> = HashMap::new();
-        let mut ranked_ids: Vec<String> = Vec::new();
-        for (_, id, snippet) in scored_results {
-            ranked_ids.push(id.clone());
-            snippets.insert(id, snippet);
+/// Split a keyword query with operators into boolean clauses: words on either side of
+/// `AND` are required, `-word` and `NOT word` are excluded, and the rest (adjacent or
+/// joined by `OR`) are optional. As in Lucene, once any clause is required the optional
+/// ones only raise the ranking. None for plain words, which keep the default ranking.
+fn parse_boolean_query(query: &str) -> Option<Vec<(Occur, QueryClause)>> {
+    enum Token {
+        And,
+        Or,
+        Not,
+        Operand(bool, QueryClause), // (negated, clause)
+    }
+
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = query.chars().peekable();
+    let mut has_operator = false;
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
         }
+        let negated = c == '-';
+        if negated {
+            chars.next();
+        }
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            has_operator = true;
+            if !phrase.trim().is_empty() {
+                tokens.push(Token::Operand(negated, QueryClause::Phrase(phrase.trim().to_string())));
+            }
+            continue;
+        }
+        let mut word = String::new();
+        while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace()) {
+            word.push(c);
+            chars.next();
+        }
+        let token = match (negated, word.as_str()) {
+            (_, "") => continue,
+            (false, "AND") => Token::And,
+            (false, "OR") => Token::Or,
+            (false, "NOT") => Token::Not,
+            (negated, _) => Token::Operand(negated, QueryClause::Word(word)),
+        };
+        has_operator |= negated || !matches!(token, Token::Operand(..));
+        tokens.push(token);
+    }
+    if !has_operator {
+        return None;
+    }
+
+    let joined_by_and = |i: usize| matches!(tokens.get(i), Some(Token::And));
+    let clauses = tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, token)| {
+            let Token::Operand(negated, clause) = token else {
+                return None;
+            };
+            let occur = if *negated || (i > 0 && matches!(tokens[i - 1], Token::Not)) {
+                Occur::MustNot
+            } else if (i > 0 && joined_by_and(i - 1)) || joined_by_and(i + 1) {
+                Occur::Must
+            } else {
+                Occur::Should
+            };
+            Some((occur, clause.clone()))
+        })
+        .collect();
+    Some(clauses)
+}
 
-        Some((snippets, ranked_ids))
-    })();
+/// Tantivy query for clauses from parse_boolean_query. Only exclusions means
+/// everything except them.
+fn build_boolean_query(
+    index: &Index,
+    content_field: tantivy::schema::Field,
+    clauses: &[(Occur, QueryClause)],
+) -> Option<Box<dyn tantivy::query::Query>> {
+    let mut subqueries: Vec<(Occur, Box<dyn tantivy::query::Query>)> = clauses
+        .iter()
+        .filter_map(|(occur, clause)| {
+            let query = match clause {
+                QueryClause::Word(word) => build_exact_phrase_query(index, content_field, word)?,
+                QueryClause::Phrase(phrase) => {
+                    Box::new(BoostQuery::new(build_exact_phrase_query(index, content_field, phrase)?, 5.0))
+                }
+            };
+            Some((*occur, query))
+        })
+        .collect();
+    if subqueries.is_empty() {
+        return None;
+    }
+    if subqueries.iter().all(|(occur, _)| *occur == Occur::MustNot) {
+        subqueries.push((Occur::Must, Box::new(AllQuery)));
+    }
+    Some(Box::new(BooleanQuery::new(subqueries)))
+}
 
-    result.unwrap_or_default()
+/// Build a query requiring the exact phrase in content (single-token input becomes a term query).
+fn build_exact_phrase_query(
+    index: &Index,
+    content_field: tantivy::schema::Field,
+    query_str: &str,
+) -> Option<Box<dyn tantivy::query::Query>> {
+    let query_clean = query_str.trim().trim_matches('"').trim_matches('\'');
+    let mut analyzer = index.tokenizer_for_field(content_field).ok()?;
+    let mut stream = analyzer.token_stream(query_clean);
+    let mut terms: Vec<Term> = Vec::new();
+    while stream.advance() {
+        terms.push(Term::from_field_text(content_field, &stream.token().text));
+    }
+    match terms.len() {
+        0 => None,
+        1 => Some(Box::new(TermQuery::new(
+            terms.remove(0),
+            IndexRecordOption::WithFreqsAndPositions,
+        ))),
+        _ => Some(Box::new(PhraseQuery::new(terms))),
+    }
 }
 
 /// Re-highlight all keywords in a snippet (case-insensitive, including substrings).
@@ -3617,6 +7221,81 @@ fn extract_snippet(content: &str, keywords: &[&str], window_chars: usize) -> Str
     format!("{}...", snippet_text)
 }
 
+/// Lowercased keywords from a query, with surrounding quotes stripped.
+/// Matches the keyword extraction used for snippet fallbacks in search_tantivy.
+fn query_keywords(query: &str) -> Vec<String> {
+    let query_clean = query.trim_matches('"').trim_matches('\'');
+    query_clean
+        .to_lowercase()
+        .split_whitespace()
+        .map(|w| w.to_string())
+        .collect()
+}
+
+/// Non-overlapping char positions of a lowercase keyword within lowercased content chars.
+fn keyword_positions(chars_lower: &[char], keyword: &str) -> Vec<usize> {
+    let kw_chars: Vec<char> = keyword.chars().collect();
+    let mut positions = Vec::new();
+    if kw_chars.is_empty() {
+        return positions;
+    }
+    let mut i = 0;
+    while i + kw_chars.len() <= chars_lower.len() {
+        if chars_lower[i..i + kw_chars.len()] == kw_chars[..] {
+            positions.push(i);
+            i += kw_chars.len();
+        } else {
+            i += 1;
+        }
+    }
+    positions
+}
+
+/// Find every keyword occurrence in content and return a highlighted window per match.
+/// Matches falling inside an earlier window are folded into it, so each window is distinct.
+fn find_match_windows(content: &str, keywords: &[&str], window_chars: usize) -> Vec<String> {
+    let chars: Vec<char> = content.chars().collect();
+    let chars_lower: Vec<char> = content.to_lowercase().chars().collect();
+    // Lowercasing can change char counts for some scripts; bail out rather than misalign
+    if chars.len() != chars_lower.len() {
+        return Vec::new();
+    }
+
+    // Collect match start positions (char indices) for all keywords
+    let mut positions: Vec<(usize, usize)> = Vec::new();
+    for keyword in keywords {
+        let kw_len = keyword.chars().count();
+        for pos in keyword_positions(&chars_lower, keyword) {
+            positions.push((pos, kw_len));
+        }
+    }
+    positions.sort_by_key(|p| p.0);
+
+    let half_window = window_chars / 2;
+    let mut windows = Vec::new();
+    let mut covered_until = 0usize;
+    for (pos, len) in positions {
+        if !windows.is_empty() && pos + len <= covered_until {
+            continue;
+        }
+        let start = pos.saturating_sub(half_window);
+        let end = (pos + len + half_window).min(chars.len());
+        covered_until = end;
+
+        let text: String = chars[start..end].iter().collect();
+        let mut window = String::new();
+        if start > 0 {
+            window.push_str("...");
+        }
+        window.push_str(&rehighlight_keywords(text.trim(), keywords));
+        if end < chars.len() {
+            window.push_str("...");
+        }
+        windows.push(window);
+    }
+    windows
+}
+
 // ============================================================================
 // JSONL Parsing for Full Conversation View
 // ============================================================================
@@ -3627,72 +7306,133 @@ fn execute_action_item(app: &mut App, item: ActionMenuItem) {
     match item {
         ActionMenuItem::View => {
             // View: enter full view mode (stays in Rust)
-            if let Some(session) = app.selected_session() {
-                let raw_content = std::fs::read_to_string(&session.export_path)
-                    .unwrap_or_else(|_| "Error loading content".to_string());
-                app.full_content = if session.export_path.ends_with(".jsonl") {
-                    parse_jsonl_to_conversation(&raw_content)
+            if let Some(session) = app.selected_session().cloned() {
+                let conversation = app
+                    .conversation_cache
+                    .load(&session.session_id, &session.export_path);
+                // Snapshots are of the reasoning-free text, so toggling it isn't a change
+                app.view_changes = if app.track_views {
+                    let (changes, saved) = app.view_snapshots.record(&session, &conversation);
+                    if let Err(e) = saved {
+                        app.status_message = Some(format!("View snapshot not saved: {}", e));
+                    }
+                    changes
                 } else {
-                    raw_content
+                    None
                 };
+                app.full_content = app.with_reasoning(&session, conversation);
+                app.collapsed_tool_blocks.clear();
+                app.changes_modal_open = false;
                 app.full_content_scroll = 0;
-                app.full_view_mode = true;
-                app.view_search_mode = false;
+                app.open_level(ViewLevel::FullView);
+                app.tool_timeline_mode = false;
                 app.view_search_pattern.clear();
                 app.view_search_matches.clear();
                 app.view_search_current = 0;
-
-                // Build query match lines using SnippetGenerator
-                app.query_match_lines.clear();
-                app.query_match_current = 0;
-                if !app.query.is_empty() {
-                    if let Ok(index) = Index::open_in_dir(&app.index_path) {
-                        if let Ok(content_field) = index.schema().get_field("content") {
-                            let query_parser = QueryParser::for_index(&index, vec![content_field]);
-                            let parsed_query = query_parser.parse_query_lenient(&app.query).0;
-                            if let Ok(reader) = index.reader() {
-                                let searcher = reader.searcher();
-                                if let Ok(mut gen) = SnippetGenerator::create(&searcher, &*parsed_query, content_field) {
-                                    gen.set_max_num_chars(10000);
-                                    for (idx, line) in app.full_content.lines().enumerate() {
-                                        let html = gen.snippet(line).to_html();
-                                        if html.contains("<b>") {
-                                            app.query_match_lines.push(idx);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+                app.update_query_match_lines();
             }
-            app.action_mode = None;
+            // The menu stays under the full view, for Esc to return to
             app.action_modal_selected = 0;
         }
         ActionMenuItem::CopyId => {
             // Copy session ID to clipboard (handled in Rust)
+            if let Some(id) = app.selected_session().map(|s| s.session_id.clone()) {
+                app.copy(id);
+            }
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::CopyPath => {
+            // Copy the session file's path, e.g. to open it in an editor
+            if let Some(path) = app.selected_session().map(|s| s.export_path.clone()) {
+                app.copy_as("Copied path", path);
+            }
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::Permalink => {
+            // Copy a stable reference that `aichat search <uri>` can reopen
             if let Some(session) = app.selected_session() {
-                match arboard::Clipboard::new() {
-                    Ok(mut clipboard) => {
-                        if clipboard.set_text(&session.session_id).is_ok() {
-                            app.status_message =
-                                Some(format!("Copied: {}", session.session_id));
-                        } else {
-                            app.status_message = Some("Failed to copy to clipboard".to_string());
-                        }
-                    }
-                    Err(_) => {
-                        app.status_message = Some("Clipboard not available".to_string());
-                    }
+                let link = format_permalink(&app.permalink_template, session);
+                app.copy(link);
+            }
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::Verify => {
+            // Verify: re-read the file and show any drift in the details popup
+            if let Some(session) = app.selected_session() {
+                let findings = verify_session_against_file(session);
+                app.verify_result = Some((session.session_id.clone(), findings));
+                app.details_modal_open = true;
+            }
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::Summarize => {
+            // Summarize: cached summaries show immediately, otherwise ask Claude in the background
+            if let Some(session) = app.selected_session().cloned() {
+                if app.summaries.get(&session.session_id).is_some() {
+                    app.details_modal_open = true;
+                } else if app.summary_job.is_some() {
+                    app.status_message = Some("A summary is already being generated".to_string());
+                } else {
+                    let conversation = app
+                        .conversation_cache
+                        .load(&session.session_id, &session.export_path);
+                    app.summary_job = Some(SummaryJob::spawn(session.session_id, clip_for_summary(&conversation)));
                 }
             }
-            app.action_mode = None;
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::ExportMarkdown => {
+            if let Some(session) = app.selected_session() {
+                app.status_message = Some(match write_markdown_export(session) {
+                    Ok(path) => format!("Wrote {}", path.display()),
+                    Err(e) => format!("Failed to export Markdown: {}", e),
+                });
+            }
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::Replay => {
+            // Replay: write the user prompts, in order, as a script next to where we were launched
+            if let Some(session) = app.selected_session() {
+                app.status_message = Some(match write_replay_script(session) {
+                    Ok((path, 0)) => format!("No user prompts found; wrote {}", path.display()),
+                    Ok((path, n)) => format!("Wrote {} prompts to {}", n, path.display()),
+                    Err(e) => format!("Failed to write replay script: {}", e),
+                });
+            }
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::Editor => {
+            // The main loop owns the terminal, so it launches the editor on its next pass
+            app.pending_editor = app.selected_session().map(|s| s.export_path.clone());
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::KeepEnds => {
+            // Ask how many turns to keep at each end; Enter writes the copy
+            if app.selected_session().is_some() {
+                app.input_mode = Some(InputMode::KeepTurns);
+                app.input_buffer.clear();
+            }
+            app.close_level(ViewLevel::ActionMenu);
+            app.action_modal_selected = 0;
+        }
+        ActionMenuItem::Trash => {
+            // Trash: confirm, then move the file aside (see App::trash_selected)
+            app.confirming_delete = Some(DeleteMode::Trash);
+            app.close_level(ViewLevel::ActionMenu);
             app.action_modal_selected = 0;
         }
         ActionMenuItem::Delete => {
             // Delete: show confirmation modal before executing
-            app.confirming_delete = true;
-            app.action_mode = None;
+            app.confirming_delete = Some(DeleteMode::Purge);
+            app.close_level(ViewLevel::ActionMenu);
             app.action_modal_selected = 0;
         }
         _ => {
@@ -3702,510 +7442,2643 @@ fn execute_action_item(app: &mut App, item: ActionMenuItem) {
                 app.selected_action = Some(item.action_string().to_string());
                 app.should_quit = true;
             }
-            app.action_mode = None;
+            app.close_level(ViewLevel::ActionMenu);
             app.action_modal_selected = 0;
         }
     }
 }
 
-/// Parse JSONL file content into conversational text format.
-/// Handles both Claude and Codex JSONL formats.
-/// Returns text with "> " prefix for user messages and "⏺ " for assistant messages.
-fn parse_jsonl_to_conversation(content: &str) -> String {
-    let mut output = String::new();
-    let mut last_role: Option<String> = None;
+/// Move `file` into `trash_dir` (created if needed) under its own name, or with a
+/// `.1`, `.2`, ... suffix when an earlier one is already there. Returns the new path.
+fn move_to_trash(file: &std::path::Path, trash_dir: &std::path::Path) -> io::Result<std::path::PathBuf> {
+    let name = file
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?
+        .to_string_lossy()
+        .to_string();
+    std::fs::create_dir_all(trash_dir)?;
+    let dest = (0..)
+        .map(|n| trash_dir.join(if n == 0 { name.clone() } else { format!("{}.{}", name, n) }))
+        .find(|path| !path.exists())
+        .expect("unbounded suffixes");
+    if std::fs::rename(file, &dest).is_err() {
+        // The trash may be on another filesystem, which rename can't cross
+        std::fs::copy(file, &dest)?;
+        std::fs::remove_file(file)?;
+    }
+    Ok(dest)
+}
 
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
+/// Program to open session files with: $VISUAL, then $EDITOR (either may carry
+/// arguments, e.g. "code --wait"), else whichever pager is installed.
+fn editor_command(
+    visual: Option<String>,
+    editor: Option<String>,
+    installed: impl Fn(&str) -> bool,
+) -> Option<String> {
+    [visual, editor]
+        .into_iter()
+        .flatten()
+        .map(|cmd| cmd.trim().to_string())
+        .find(|cmd| !cmd.is_empty())
+        .or_else(|| ["less", "more"].into_iter().find(|p| installed(p)).map(String::from))
+}
 
-        // Parse JSON line
-        let json: serde_json::Value = match serde_json::from_str(line) {
-            Ok(v) => v,
-            Err(_) => continue,
-        };
+fn is_installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
 
-        // Try to extract message based on format
-        let (role, text) = extract_message_from_json(&json);
+/// Hand the terminal to an editor on `path` and take it back when it exits.
+/// Returns the status line to show, if any.
+fn open_in_editor(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    path: &str,
+) -> io::Result<Option<String>> {
+    if !std::path::Path::new(path).is_file() {
+        return Ok(Some(format!("Session file not found: {}", path)));
+    }
+    let Some(editor) = editor_command(
+        std::env::var("VISUAL").ok(),
+        std::env::var("EDITOR").ok(),
+        is_installed,
+    ) else {
+        return Ok(Some("Set $EDITOR (or $VISUAL) to open session files".to_string()));
+    };
 
-        if let (Some(role), Some(text)) = (role, text) {
-            // Skip empty messages
-            if text.trim().is_empty() {
-                continue;
-            }
+    disable_raw_mode()?;
+    execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen)?;
+    // Through the shell so the editor variable can carry its own arguments
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+    terminal.clear()?;
+
+    Ok(match status {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(format!("{} exited with {}", editor, status)),
+        Err(e) => Some(format!("Failed to run {}: {}", editor, e)),
+    })
+}
 
-            // Add blank line between different roles
-            if let Some(ref last) = last_role {
-                if last != &role && !output.is_empty() {
-                    output.push('\n');
-                }
-            }
+/// Small most-recently-used cache of parsed conversations, so re-entering full view
+/// for the same session skips reparsing. Entries are invalidated when the file mtime changes.
+#[derive(Default)]
+struct ConversationCache {
+    entries: Vec<(String, std::time::SystemTime, String)>, // (session_id, mtime, content), newest first
+}
 
-            // Format based on role
-            let prefix = if role == "user" { "> " } else { "⏺ " };
+impl ConversationCache {
+    fn get(&mut self, session_id: &str, mtime: std::time::SystemTime) -> Option<String> {
+        let pos = self
+            .entries
+            .iter()
+            .position(|(id, t, _)| id == session_id && *t == mtime)?;
+        let entry = self.entries.remove(pos);
+        let content = entry.2.clone();
+        self.entries.insert(0, entry);
+        Some(content)
+    }
 
-            // Split text into lines and prefix the first line
-            let lines: Vec<&str> = text.lines().collect();
-            for (i, line) in lines.iter().enumerate() {
-                if i == 0 {
-                    output.push_str(prefix);
-                    output.push_str(line);
-                } else {
-                    // Continuation lines - indent to align with content
-                    output.push_str("  ");
-                    output.push_str(line);
-                }
-                output.push('\n');
-            }
+    fn insert(&mut self, session_id: &str, mtime: std::time::SystemTime, content: String) {
+        self.entries.retain(|(id, _, _)| id != session_id);
+        self.entries.insert(0, (session_id.to_string(), mtime, content));
+        self.entries.truncate(CONVERSATION_CACHE_SIZE);
+    }
 
-            last_role = Some(role);
+    /// Return the full-view text for a session file, parsing only on a cache miss
+    fn load(&mut self, session_id: &str, export_path: &str) -> String {
+        let mtime = std::fs::metadata(export_path).and_then(|m| m.modified()).ok();
+        if let Some(content) = mtime.and_then(|t| self.get(session_id, t)) {
+            return content;
+        }
+
+        let raw_content = match std::fs::read_to_string(export_path) {
+            Ok(c) => c,
+            Err(_) => return "Error loading content".to_string(),
+        };
+        let content = if export_path.ends_with(".jsonl") {
+            parse_jsonl_to_conversation(&raw_content, false)
+        } else {
+            raw_content
+        };
+        if let Some(t) = mtime {
+            self.insert(session_id, t, content.clone());
         }
+        content
     }
+}
 
-    output
+/// A JSON map of per-session entries in ~/.cctools that several TUI instances may share.
+///
+/// Writes are last-writer-wins per entry, merged: if the file changed on disk since we
+/// last read or wrote it (another instance saved), it is re-read and only the entry
+/// being saved is overlaid before writing it back. Writes go to a temp file that is
+/// renamed into place, so readers never see a half-written file.
+#[derive(Default)]
+struct StateFile {
+    path: Option<std::path::PathBuf>,
+    pretty: bool,
+    // (mtime, len) when we last read or wrote the file; None if it didn't exist
+    stamp: Option<(std::time::SystemTime, u64)>,
 }
 
-/// Extract role and text from a JSON entry (handles Claude and Codex formats).
-fn extract_message_from_json(json: &serde_json::Value) -> (Option<String>, Option<String>) {
-    let entry_type = json.get("type").and_then(|v| v.as_str());
+impl StateFile {
+    fn new(path: Option<std::path::PathBuf>, pretty: bool) -> Self {
+        Self { path, pretty, stamp: None }
+    }
 
-    match entry_type {
-        // Claude format: {"type": "user" | "assistant", "message": {...}}
-        Some("user") | Some("assistant") => {
-            let role = entry_type.map(|s| s.to_string());
-            let text = extract_claude_message_text(json);
-            (role, text)
-        }
+    fn current_stamp(&self) -> Option<(std::time::SystemTime, u64)> {
+        let meta = std::fs::metadata(self.path.as_ref()?).ok()?;
+        Some((meta.modified().ok()?, meta.len()))
+    }
 
-        // Codex format: {"type": "response_item", "payload": {"role": "user" | "assistant", ...}}
-        Some("response_item") => {
-            if let Some(payload) = json.get("payload") {
-                let role = payload
-                    .get("role")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-                let text = extract_codex_message_text(payload);
-                (role, text)
-            } else {
-                (None, None)
+    /// Read the map; a missing or corrupt file is an empty one.
+    fn load<V: serde::de::DeserializeOwned>(&mut self) -> HashMap<String, V> {
+        self.stamp = self.current_stamp();
+        self.path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    /// Persist `entries[key]`, first merging in whatever other instances saved since our
+    /// last read. On return `entries` holds the merged map. The directory is created if
+    /// needed; a failed write is returned for the status bar.
+    fn save_entry<V>(&mut self, entries: &mut HashMap<String, V>, key: &str) -> Result<(), String>
+    where
+        V: Serialize + serde::de::DeserializeOwned,
+    {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        if self.current_stamp() != self.stamp {
+            let mut merged: HashMap<String, V> = self.load();
+            if let Some(value) = entries.remove(key) {
+                merged.insert(key.to_string(), value);
             }
+            *entries = merged;
         }
-
-        // Codex format: {"type": "event_msg", "payload": {"type": "user_message", "message": "..."}}
-        Some("event_msg") => {
-            if let Some(payload) = json.get("payload") {
-                let msg_type = payload.get("type").and_then(|v| v.as_str());
-                match msg_type {
-                    Some("user_message") => {
-                        let text = payload
-                            .get("message")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string());
-                        (Some("user".to_string()), text)
-                    }
-                    _ => (None, None),
-                }
-            } else {
-                (None, None)
+        let json = if self.pretty {
+            serde_json::to_string_pretty(entries)
+        } else {
+            serde_json::to_string(entries)
+        };
+        let json = json.map_err(|e| e.to_string())?;
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&tmp, json))
+            .and_then(|_| std::fs::rename(&tmp, &path));
+        match written {
+            Ok(()) => {
+                self.stamp = self.current_stamp();
+                Ok(())
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&tmp);
+                Err(format!("couldn't write {}: {}", path.display(), e))
             }
         }
-
-        _ => (None, None),
     }
 }
 
-/// Extract text from Claude message format.
-/// User: {"message": {"content": "text"}}
-/// Assistant: {"message": {"content": [{"type": "text", "text": "..."}]}}
-fn extract_claude_message_text(json: &serde_json::Value) -> Option<String> {
-    let message = json.get("message")?;
-    let content = message.get("content")?;
+/// Sent ahead of the conversation, so it's the start of the summary session's first
+/// message and TOOL_SESSION_MARKERS can spot it
+const SUMMARY_PROMPT: &str = "Summarize in one sentence what was accomplished in the following coding-agent session. Reply with the sentence only.";
+/// Conversations longer than this are sent as head + tail to keep the prompt small.
+const SUMMARY_INPUT_MAX_CHARS: usize = 24_000;
+const SPINNER_FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Session summaries keyed by session id, persisted in ~/.cctools/summaries.json
+/// so each session is only sent to Claude once.
+#[derive(Default)]
+struct SummaryStore {
+    file: StateFile,
+    entries: HashMap<String, String>,
+}
 
-    // User messages have string content
-    if let Some(text) = content.as_str() {
-        return Some(text.to_string());
+impl SummaryStore {
+    fn open(path: Option<std::path::PathBuf>) -> Self {
+        let mut file = StateFile::new(path, true);
+        let entries = file.load();
+        Self { file, entries }
     }
 
-    // Assistant messages have array of content blocks
-    if let Some(blocks) = content.as_array() {
-        let mut texts = Vec::new();
-        for block in blocks {
-            if let Some(block_type) = block.get("type").and_then(|v| v.as_str()) {
-                match block_type {
-                    "text" => {
-                        if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-                            texts.push(text.to_string());
-                        }
-                    }
-                    "tool_use" => {
-                        // Show tool name
-                        if let Some(name) = block.get("name").and_then(|v| v.as_str()) {
-                            texts.push(format!("[Tool: {}]", name));
-                        }
-                        // Index tool input content (code, commands, etc.)
-                        if let Some(input) = block.get("input").and_then(|v| v.as_object()) {
-                            for value in input.values() {
-                                if let Some(s) = value.as_str() {
-                                    if !s.is_empty() {
-                                        texts.push(s.to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
-        if !texts.is_empty() {
-            return Some(texts.join("\n"));
-        }
+    fn get(&self, session_id: &str) -> Option<&str> {
+        self.entries.get(session_id).map(|s| s.as_str())
     }
 
-    None
+    /// Kept in memory even when saving fails, which only costs a repeat API call later
+    fn insert(&mut self, session_id: String, summary: String) -> Result<(), String> {
+        self.entries.insert(session_id.clone(), summary);
+        self.file.save_entry(&mut self.entries, &session_id)
+    }
 }
 
-/// Extract text from Codex message format.
-/// {"content": [{"type": "input_text" | "output_text", "text": "..."}]}
-fn extract_codex_message_text(payload: &serde_json::Value) -> Option<String> {
-    let content = payload.get("content")?.as_array()?;
+/// Keep the start (the task) and end (the outcome) of long conversations.
+fn clip_for_summary(conversation: &str) -> String {
+    let total = conversation.chars().count();
+    if total <= SUMMARY_INPUT_MAX_CHARS {
+        return conversation.to_string();
+    }
+    let half = SUMMARY_INPUT_MAX_CHARS / 2;
+    let head: String = conversation.chars().take(half).collect();
+    let tail: String = conversation.chars().skip(total - half).collect();
+    format!("{}\n\n[... middle of session omitted ...]\n\n{}", head, tail)
+}
 
-    let mut texts = Vec::new();
-    for block in content {
-        if let Some(block_type) = block.get("type").and_then(|v| v.as_str()) {
-            match block_type {
-                "input_text" | "output_text" => {
-                    if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
-                        texts.push(text.to_string());
-                    }
-                }
-                "tool_use" | "function_call" => {
-                    if let Some(name) = block.get("name").and_then(|v| v.as_str()) {
-                        texts.push(format!("[Tool: {}]", name));
-                    }
-                    // Index tool input content (code, commands, etc.)
-                    if let Some(input) = block.get("input").and_then(|v| v.as_object()) {
-                        for value in input.values() {
-                            if let Some(s) = value.as_str() {
-                                if !s.is_empty() {
-                                    texts.push(s.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
-                _ => {}
-            }
+/// Run `claude -p` with the prompt and conversation on stdin and return the first
+/// non-empty line.
+fn run_claude_summary(conversation: &str) -> Result<String, String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("claude")
+        .arg("-p")
+        .arg("--model")
+        .arg("haiku")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to spawn 'claude': {e}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(format!("{SUMMARY_PROMPT}\n\n{conversation}").as_bytes())
+            .map_err(|e| format!("failed to write to 'claude': {e}"))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to wait for 'claude': {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("claude exited with status {}: {}", output.status, stderr.trim()));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| "claude returned an empty summary".to_string())
+}
+
+/// A summary request running on a background thread, polled from the event loop.
+struct SummaryJob {
+    session_id: String,
+    started: std::time::Instant,
+    rx: std::sync::mpsc::Receiver<Result<String, String>>,
+}
+
+impl SummaryJob {
+    fn spawn(session_id: String, conversation: String) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(run_claude_summary(&conversation));
+        });
+        Self {
+            session_id,
+            started: std::time::Instant::now(),
+            rx,
         }
     }
 
-    if !texts.is_empty() {
-        Some(texts.join("\n"))
-    } else {
-        None
+    fn spinner(&self) -> char {
+        let tick = self.started.elapsed().as_millis() / 80;
+        SPINNER_FRAMES[tick as usize % SPINNER_FRAMES.len()]
     }
 }
 
-// ============================================================================
-// JSON Output
-// ============================================================================
+/// What a session looked like the last time it was opened in full view.
+#[derive(Clone, Serialize, Deserialize)]
+struct ViewSnapshot {
+    hash: u64,         // FNV-1a of the parsed conversation
+    lines: usize,      // Line count of the parsed conversation
+    first: String,     // First message line
+    last: String,      // Last message line
+    modified_ts: u64,  // Indexed mtime when viewed (drives the list marker)
+    viewed_at: i64,    // Epoch seconds
+}
 
-fn output_json(app: &App, limit: Option<usize>) -> Result<()> {
-    use serde_json::json;
+/// Changes in a session since its last full view.
+struct SessionChanges {
+    viewed_at: i64,
+    old_lines: usize,
+    new_lines: usize,
+    // Lines added at the end, when everything previously seen is unchanged
+    appended: Option<Vec<String>>,
+    first: Option<(String, String)>, // (before, after) when the first message changed
+    last: Option<(String, String)>,  // (before, after) when the last message changed
+}
 
-    // Output as JSONL (one JSON object per line) for easy piping and jq processing
-    for &idx in app.filtered.iter().take(limit.unwrap_or(usize::MAX)) {
-        let s = &app.sessions[idx];
-        let obj = json!({
-            "session_id": s.session_id,
-            "agent": s.agent,
-            "project": s.project,
-            "branch": s.branch,
-            "cwd": s.cwd,
-            "lines": s.lines,
-            "created": s.created,
-            "modified": s.modified,
-            "first_msg": if !s.first_user_msg_content.is_empty() { &s.first_user_msg_content } else { &s.first_msg_content },
-            "last_msg": s.last_msg_content,
-            "file_path": s.export_path,
-            "derivation_type": s.derivation_type,
-            "is_sidechain": s.is_sidechain,
-            "custom_title": s.custom_title,
-            "snippet": app.search_snippets.get(&s.session_id).map(|s| strip_html_tags(s)),
-        });
-        println!("{}", serde_json::to_string(&obj)?);
+/// Stable (cross-version) hash of the first `n` lines, so a session that only grew
+/// can be recognized by re-hashing the previously seen prefix.
+fn hash_lines(content: &str, n: usize) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for line in content.lines().take(n) {
+        for byte in line.bytes().chain(std::iter::once(b'\n')) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
     }
-    Ok(())
+    hash
 }
 
-// CLI Options
-// ============================================================================
+impl ViewSnapshot {
+    fn of(content: &str, modified_ts: u64) -> Self {
+        let mut messages = content
+            .lines()
+            .filter(|l| l.starts_with("> ") || l.starts_with("⏺ "))
+            .map(truncate_like_indexer);
+        let first = messages.next().unwrap_or_default();
+        let last = messages.next_back().unwrap_or_else(|| first.clone());
+        let lines = content.lines().count();
+        Self {
+            hash: hash_lines(content, lines),
+            lines,
+            first,
+            last,
+            modified_ts,
+            viewed_at: Utc::now().timestamp(),
+        }
+    }
 
-struct CliOptions {
-    output_file: Option<std::path::PathBuf>,
-    claude_home: Option<String>,
-    codex_home: Option<String>,
-    global_search: bool,
-    filter_dir: Option<String>, // --dir: filter to specific directory (overrides -g)
-    num_results: Option<usize>,
-    // Subtractive flags: --no-original, --no-trimmed, --no-rollover exclude types from defaults
-    no_original: bool,
-    no_trimmed: bool,
-    no_rollover: bool,
-    // Additive flag: --sub-agent adds sub-agents to defaults
-    include_sub: bool,
-    min_lines: Option<i64>,
-    after_date: Option<String>,
-    before_date: Option<String>,
-    agent_filter: Option<String>,
-    query: Option<String>,
-    json_output: bool,
-    sort_by_time: bool,  // --by-time: sort by last-modified time instead of relevance
-    filter_branch: Option<String>, // --branch: filter to specific git branch
-    // Scroll/selection state restoration
-    selected: Option<usize>,    // --selected: restore selected row index
-    list_scroll: Option<usize>, // --scroll: restore scroll offset
+    /// None when the content is unchanged since this snapshot.
+    fn changes_in(&self, content: &str) -> Option<SessionChanges> {
+        let current = ViewSnapshot::of(content, 0);
+        if current.hash == self.hash && current.lines == self.lines {
+            return None;
+        }
+        let appended = (current.lines > self.lines && hash_lines(content, self.lines) == self.hash)
+            .then(|| content.lines().skip(self.lines).map(str::to_string).collect());
+        let changed = |before: &str, after: &str| (before != after).then(|| (before.to_string(), after.to_string()));
+        Some(SessionChanges {
+            viewed_at: self.viewed_at,
+            old_lines: self.lines,
+            new_lines: current.lines,
+            appended,
+            first: changed(&self.first, &current.first),
+            last: changed(&self.last, &current.last),
+        })
+    }
 }
 
-fn parse_cli_args() -> CliOptions {
-    let args: Vec<String> = std::env::args().collect();
+/// Snapshots of viewed sessions keyed by session id, persisted in
+/// ~/.cctools/view-snapshots.json.
+#[derive(Default)]
+struct ViewSnapshots {
+    file: StateFile,
+    entries: HashMap<String, ViewSnapshot>,
+}
 
-    // Helper to get value after a flag
-    let get_arg_value = |flag: &str| -> Option<String> {
-        args.iter()
-            .position(|a| a == flag)
-            .and_then(|i| args.get(i + 1))
-            .map(|s| s.to_string())
-    };
+impl ViewSnapshots {
+    fn open(path: Option<std::path::PathBuf>) -> Self {
+        let mut file = StateFile::new(path, false);
+        let entries = file.load();
+        Self { file, entries }
+    }
 
-    // Helper to check if flag exists
-    let has_flag = |flag: &str| -> bool {
-        args.iter().any(|a| a == flag)
-    };
+    /// The index saw a newer version than the one last viewed.
+    fn is_updated(&self, session: &Session) -> bool {
+        self.entries
+            .get(&session.session_id)
+            .is_some_and(|v| session.modified_ts > v.modified_ts)
+    }
 
-    // Output file is the LAST positional arg that's a path (contains / or ends with .json)
-    // Using rfind to get the last match, avoiding --claude-home/--codex-home values
-    let output_file = args.iter()
-        .skip(1)  // skip binary name
-        .filter(|a| !a.starts_with('-') && (a.contains('/') || a.ends_with(".json")))
-        .last()
-        .map(std::path::PathBuf::from);
+    /// Compare with the previous view (if any), then make this view the baseline.
+    /// Also returns whether the new baseline could be saved.
+    fn record(&mut self, session: &Session, content: &str) -> (Option<SessionChanges>, Result<(), String>) {
+        let changes = self
+            .entries
+            .get(&session.session_id)
+            .and_then(|prev| prev.changes_in(content));
+        self.entries
+            .insert(session.session_id.clone(), ViewSnapshot::of(content, session.modified_ts));
+        let saved = self.file.save_entry(&mut self.entries, &session.session_id);
+        (changes, saved)
+    }
+}
 
-    let claude_home = get_arg_value("--claude-home")
-        .or_else(|| std::env::var("CLAUDE_CONFIG_DIR").ok())
-        .or_else(|| {
-            dirs::home_dir().map(|h| h.join(".claude").to_string_lossy().to_string())
-        });
+const UI_STATE_VERSION: u32 = 1;
 
-    let codex_home = get_arg_value("--codex-home")
-        .or_else(|| std::env::var("CODEX_HOME").ok())
-        .or_else(|| {
-            dirs::home_dir().map(|h| h.join(".codex").to_string_lossy().to_string())
-        });
+/// Filter and display choices carried from one TUI session to the next in ~/.cctools/ui-state.json.
+/// Every field has a default, so files written before a field existed still load;
+/// a file from a newer version is ignored rather than half-applied.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+struct UiState {
+    version: u32,
+    include_original: bool,
+    include_sub: bool,
+    include_tool: bool,
+    include_trimmed: bool,
+    include_continued: bool,
+    filter_has_attachments: bool,
+    filter_agent: Option<String>,
+    filter_min_lines: Option<i64>,
+    filter_min_messages: Option<i64>,
+    filter_after_date: Option<String>,
+    filter_before_date: Option<String>,
+    date_format: Option<DateFormat>,
+}
 
-    let global_search = has_flag("--global") || has_flag("-g");
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            version: UI_STATE_VERSION,
+            include_original: true,
+            include_sub: false,
+            include_tool: false,
+            include_trimmed: true,
+            include_continued: true,
+            filter_has_attachments: false,
+            filter_agent: None,
+            filter_min_lines: None,
+            filter_min_messages: None,
+            filter_after_date: None,
+            filter_before_date: None,
+            date_format: None,
+        }
+    }
+}
 
-    // --dir overrides -g: filter to specific directory
-    // Format: --dir path or --dir path:branch
-    let dir_arg = get_arg_value("--dir");
-    let (filter_dir, branch_from_dir) = if let Some(ref dir) = dir_arg {
-        // Parse dir:branch format (use rfind to handle paths with colons)
-        let (dir_part, branch_part) = if let Some(colon_idx) = dir.rfind(':') {
-            // Only treat as branch separator if what follows looks like a branch name
-            // (no slashes) and what precedes is a valid path
-            let before = &dir[..colon_idx];
-            let after = &dir[colon_idx + 1..];
-            if !after.contains('/') && !before.is_empty() {
-                (before.to_string(), Some(after.to_string()))
-            } else {
-                (dir.clone(), None)
-            }
-        } else {
-            (dir.clone(), None)
-        };
+impl UiState {
+    fn from_app(app: &App) -> Self {
+        Self {
+            version: UI_STATE_VERSION,
+            include_original: app.include_original,
+            include_sub: app.include_sub,
+            include_tool: app.include_tool,
+            include_trimmed: app.include_trimmed,
+            include_continued: app.include_continued,
+            filter_has_attachments: app.filter_has_attachments,
+            filter_agent: app.filter_agent.clone(),
+            filter_min_lines: app.filter_min_lines,
+            filter_min_messages: app.filter_min_messages,
+            filter_after_date: app.filter_after_date.clone(),
+            filter_before_date: app.filter_before_date.clone(),
+            date_format: app.date_format,
+        }
+    }
 
-        // Expand ~ to home directory
-        let expanded_dir = if dir_part.starts_with('~') {
-            let home = std::env::var("HOME").unwrap_or_default();
-            format!("{}{}", home, &dir_part[1..])
-        } else if dir_part.starts_with('/') {
-            dir_part
-        } else {
-            // Relative path - make absolute from cwd
-            let cwd = std::env::current_dir()
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_default();
-            format!("{}/{}", cwd, dir_part)
+    /// Missing, corrupt, or newer-version files load as None.
+    fn load(path: &std::path::Path) -> Option<Self> {
+        let raw = std::fs::read_to_string(path).ok()?;
+        let state: Self = serde_json::from_str(&raw).ok()?;
+        (state.version <= UI_STATE_VERSION).then_some(state)
+    }
+
+    /// Best effort: written to a temp file and renamed into place.
+    fn save(&self, path: &std::path::Path) {
+        let Ok(json) = serde_json::to_string_pretty(self) else {
+            return;
         };
-        (Some(expanded_dir), branch_part)
-    } else {
-        (None, None)
-    };
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        if std::fs::write(&tmp, json).is_err() || std::fs::rename(&tmp, path).is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+    }
 
-    let num_results = get_arg_value("--num-results")
-        .or_else(|| get_arg_value("-n"))
-        .and_then(|s| s.parse().ok());
+    /// Fill in the filters the command line left unset; explicit flags always win.
+    /// The on/off filters (--no-original, --no-trimmed, --no-rollover, --sub-agent,
+    /// --include-tool-sessions, --has-attachments) only have a flag for one side, so
+    /// they are restored as a set: passing any of them replaces all the saved ones.
+    fn apply_defaults(self, cli: &mut CliOptions) {
+        let cli_sets_types = cli.no_original
+            || cli.no_trimmed
+            || cli.no_rollover
+            || cli.include_sub
+            || cli.include_tool
+            || cli.has_attachments;
+        if !cli_sets_types {
+            cli.no_original = !self.include_original;
+            cli.include_sub = self.include_sub;
+            cli.include_tool = self.include_tool;
+            cli.no_trimmed = !self.include_trimmed;
+            cli.no_rollover = !self.include_continued;
+            cli.has_attachments = self.filter_has_attachments;
+        }
+        if !cli.all_agents {
+            cli.agent_filter = cli.agent_filter.take().or(self.filter_agent);
+        }
+        cli.min_lines = cli.min_lines.or(self.filter_min_lines);
+        cli.min_messages = cli.min_messages.or(self.filter_min_messages);
+        cli.after_date = cli.after_date.take().or(self.filter_after_date);
+        cli.before_date = cli.before_date.take().or(self.filter_before_date);
+    }
+}
 
-    // Subtractive flags: --no-original, --no-trimmed, --no-rollover
-    // By default all types are shown (except sub-agents); these flags exclude types
-    let no_original = has_flag("--no-original");
-    let no_trimmed = has_flag("--no-trimmed");
-    let no_rollover = has_flag("--no-rollover");
-    // Additive flag: --sub-agent adds sub-agents to defaults
-    let include_sub = has_flag("--sub-agent");
+/// Default permalink format; `{agent}` and `{session_id}` are substituted.
+const PERMALINK_DEFAULT_TEMPLATE: &str = "cctools://{agent}/{session_id}";
+const PERMALINK_SCHEME: &str = "cctools://";
 
-    let min_lines = get_arg_value("--min-lines")
-        .and_then(|s| s.parse().ok());
+fn format_permalink(template: &str, session: &Session) -> String {
+    template
+        .replace("{agent}", &session.agent)
+        .replace("{session_id}", &session.session_id)
+}
 
-    let after_date = get_arg_value("--after");
-    let before_date = get_arg_value("--before");
+/// Parse `cctools://<agent>/<session_id>` (agent optional) into (agent, id). A lone
+/// agent name, as in `cctools://claude/`, has no session ID and is rejected.
+fn parse_permalink(uri: &str) -> Option<(String, String)> {
+    let rest = uri.strip_prefix(PERMALINK_SCHEME)?.trim_end_matches('/');
+    let (agent, id) = rest.rsplit_once('/').unwrap_or(("", rest));
+    if id.is_empty() || (agent.is_empty() && matches!(id, "claude" | "codex")) {
+        return None;
+    }
+    Some((agent.to_string(), id.to_string()))
+}
 
-    let agent_filter = get_arg_value("--agent");
+/// Collapse whitespace and cap at 200 chars, matching `_truncate_text` in export_session.py
+/// so live messages compare equal to what the indexer stored.
+fn truncate_like_indexer(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= 200 {
+        collapsed
+    } else {
+        let head: String = collapsed.chars().take(197).collect();
+        format!("{}...", head)
+    }
+}
 
-    let query = get_arg_value("--query");
+/// (role, content) of a session's first and last messages
+type FirstLast = (Option<(String, String)>, Option<(String, String)>);
+
+/// Re-extract (role, content) of the first and last messages from a session file,
+/// following the indexer's rules (export_session.extract_first_last_messages).
+/// Returns None when the file can't be read.
+fn extract_live_first_last(path: &str, agent: &str) -> Option<FirstLast> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let mut first: Option<(String, String)> = None;
+    let mut last: Option<(String, String)> = None;
+
+    let first_text_block = |blocks: &[serde_json::Value], types: &[&str]| -> Option<String> {
+        blocks.iter().find_map(|b| {
+            if let Some(s) = b.as_str() {
+                return Some(s.trim()).filter(|s| !s.is_empty()).map(str::to_string);
+            }
+            let ty = b.get("type").and_then(|v| v.as_str())?;
+            if !types.contains(&ty) {
+                return None;
+            }
+            let text = b.get("text").and_then(|v| v.as_str())?.trim();
+            Some(text).filter(|s| !s.is_empty()).map(str::to_string)
+        })
+    };
 
-    let json_output = has_flag("--json");
-    let sort_by_time = has_flag("--by-time");
+    for line in content.lines() {
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+            continue;
+        };
+        let entry_type = json.get("type").and_then(|v| v.as_str());
+        let (role, text) = if agent == "codex" {
+            let Some(payload) = json.get("payload").filter(|_| entry_type == Some("response_item")) else {
+                continue;
+            };
+            if payload.get("type").and_then(|v| v.as_str()) != Some("message") {
+                continue;
+            }
+            let role = payload.get("role").and_then(|v| v.as_str());
+            let blocks = payload.get("content").and_then(|v| v.as_array());
+            (role, blocks.and_then(|b| first_text_block(b, &["input_text", "output_text"])))
+        } else {
+            if !matches!(entry_type, Some("user") | Some("assistant")) {
+                continue;
+            }
+            let content = json.get("message").and_then(|m| m.get("content"));
+            let text = match content {
+                Some(serde_json::Value::String(s)) => {
+                    Some(s.trim()).filter(|s| !s.is_empty()).map(str::to_string)
+                }
+                Some(serde_json::Value::Array(blocks)) => first_text_block(blocks, &["text"]),
+                _ => None,
+            };
+            (entry_type, text)
+        };
 
-    // --branch can be specified separately or as part of --dir (dir:branch)
-    let filter_branch = get_arg_value("--branch").or(branch_from_dir);
+        if let (Some(role), Some(text)) = (role, text) {
+            let msg = (role.to_string(), truncate_like_indexer(&text));
+            if first.is_none() {
+                first = Some(msg.clone());
+            }
+            last = Some(msg);
+        }
+    }
 
-    // Scroll/selection state restoration
-    let selected = get_arg_value("--selected")
-        .and_then(|s| s.parse().ok());
-    let list_scroll = get_arg_value("--scroll")
-        .and_then(|s| s.parse().ok());
+    Some((first, last))
+}
 
-    CliOptions {
-        output_file,
-        claude_home,
-        codex_home,
-        global_search,
-        filter_dir,
-        num_results,
-        no_original,
-        no_trimmed,
-        no_rollover,
-        include_sub,
-        min_lines,
-        after_date,
-        before_date,
-        agent_filter,
-        query,
-        json_output,
-        sort_by_time,
-        filter_branch,
-        selected,
-        list_scroll,
+/// Compare a session's indexed first/last messages with its file on disk.
+/// Returns human-readable discrepancies; empty means the index is in sync.
+fn verify_session_against_file(session: &Session) -> Vec<String> {
+    let Some((live_first, live_last)) = extract_live_first_last(&session.export_path, &session.agent) else {
+        return vec![format!("Session file is missing or unreadable: {}", session.export_path)];
+    };
+
+    let mut findings = Vec::new();
+    let checks = [
+        ("First", &session.first_msg_role, &session.first_msg_content, live_first),
+        ("Last", &session.last_msg_role, &session.last_msg_content, live_last),
+    ];
+    for (which, idx_role, idx_content, live) in checks {
+        let (live_role, live_content) = live.unwrap_or_default();
+        if *idx_role != live_role || *idx_content != live_content {
+            findings.push(format!(
+                "{} message differs. Index: [{}] \"{}\"  File: [{}] \"{}\"",
+                which,
+                idx_role,
+                truncate(idx_content, 60),
+                live_role,
+                truncate(&live_content, 60)
+            ));
+        }
     }
+    findings
 }
 
-// Main
-// ============================================================================
-
-fn main() -> Result<()> {
-    let cli = parse_cli_args();
+/// Tags the agents inject into user turns (slash commands, shell escapes, Codex context).
+/// Messages starting with one of these weren't typed by the user.
+const INJECTED_USER_TAGS: &[&str] = &[
+    "<command-name>", "<command-message>", "<command-args>", "<local-command-stdout>",
+    "<bash-input>", "<bash-stdout>", "<bash-stderr>", "<bash-notification>",
+    "<environment_context>", "<user_instructions>", "<user_shell_command>",
+];
+
+/// User-authored prompts of a session in order: tool results, meta messages, injected
+/// context, and Codex's duplicate event_msg copies are dropped.
+fn extract_user_prompts(content: &str) -> Vec<String> {
+    let mut prompts: Vec<String> = Vec::new();
+    for line in content.lines() {
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+            continue;
+        };
+        if json.get("isMeta").and_then(|v| v.as_bool()) == Some(true) {
+            continue;
+        }
+        let (Some(role), Some(text)) = extract_message_from_json(&json, false) else {
+            continue;
+        };
+        let text = text.trim();
+        if role != "user"
+            || text.is_empty()
+            || text.starts_with("[Request interrupted")
+            || INJECTED_USER_TAGS.iter().any(|tag| text.starts_with(tag))
+        {
+            continue;
+        }
+        if prompts.last().map(String::as_str) != Some(text) {
+            prompts.push(text.to_string());
+        }
+    }
+    prompts
+}
 
-    let index_path = dirs::home_dir()
-        .context("Could not find home directory")?
-        .join(".cctools")
-        .join("search-index");
+/// Shell script that feeds `prompts` to the agent one at a time, each continuing the
+/// conversation started by the first. Prompts go through quoted heredocs, so nothing
+/// in them is expanded by the shell.
+fn render_replay_script(session: &Session, prompts: &[String]) -> String {
+    let (first_cmd, next_cmd) = if session.agent == "codex" {
+        ("codex exec -", "codex exec resume --last -")
+    } else {
+        ("claude -p", "claude -p --continue")
+    };
 
-    const SESSION_LIMIT: usize = 100_000;
-    let sessions = load_sessions(index_path.to_str().unwrap(), SESSION_LIMIT)?;
+    let mut out = String::from("#!/bin/sh\n");
+    out.push_str(&format!("# Replay of {} session {}\n", session.agent, session.session_id));
+    out.push_str(&format!("# Project: {}  Branch: {}\n", session.project, session.branch));
+    out.push_str(&format!(
+        "# {} user prompts in order. Run the whole script, or copy one step at a time.\n",
+        prompts.len()
+    ));
+    out.push_str("set -e\n");
 
-    // Warn if we hit the limit - sessions may have been truncated
-    if sessions.len() >= SESSION_LIMIT && !cli.json_output {
-        eprintln!("⚠️  WARNING: Session limit ({}) reached!", SESSION_LIMIT);
-        eprintln!("⚠️  Some sessions may have been dropped.");
-        eprintln!();
+    for (i, prompt) in prompts.iter().enumerate() {
+        // Pick a heredoc delimiter that can't appear as a line of the prompt
+        let mut delim = String::from("CCTOOLS_PROMPT");
+        while prompt.lines().any(|l| l == delim) {
+            delim.push('_');
+        }
+        let cmd = if i == 0 { first_cmd } else { next_cmd };
+        out.push_str(&format!("\n# --- Prompt {} of {} ---\n", i + 1, prompts.len()));
+        out.push_str(&format!("{} <<'{}'\n{}\n{}\n", cmd, delim, prompt, delim));
     }
+    out
+}
 
-    if sessions.is_empty() {
-        if cli.json_output {
-            println!("[]");
-            return Ok(());
-        }
-        eprintln!("No sessions found. Run 'aichat search' to auto-index.");
-        return Ok(());
+/// Write `replay-<session_id>.sh` to the current directory.
+/// Returns the path and the number of prompts written.
+fn write_replay_script(session: &Session) -> std::io::Result<(std::path::PathBuf, usize)> {
+    let content = std::fs::read_to_string(&session.export_path)?;
+    let prompts = extract_user_prompts(&content);
+    let path = std::env::current_dir()?.join(format!("replay-{}.sh", session.session_id));
+    std::fs::write(&path, render_replay_script(session, &prompts))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok((path, prompts.len()))
+}
+
+/// One message of a session transcript, with the JSONL line (0-based) it came from
+struct ConversationMessage {
+    line: usize,
+    role: String,
+    text: String,
+}
+
+impl ConversationMessage {
+    /// A prompt the user typed, as opposed to a tool result or injected context
+    fn is_user_prompt(&self) -> bool {
+        self.role == "user"
+            && !self.text.starts_with(TOOL_RESULT_PREFIX)
+            && !self.text.starts_with("[Request interrupted")
+            && !INJECTED_USER_TAGS.iter().any(|tag| self.text.trim_start().starts_with(tag))
     }
+}
 
-    // Show home filters (only for TUI mode)
-    if !cli.json_output {
-        if let Some(ref home) = cli.claude_home {
-            eprintln!("Claude home filter: {}", home);
-        }
-        if let Some(ref home) = cli.codex_home {
-            eprintln!("Codex home filter: {}", home);
+/// Parse JSONL file content into messages (Claude and Codex formats).
+/// Empty messages and Codex's duplicate event_msg copies are dropped.
+fn parse_jsonl_messages(content: &str) -> Vec<ConversationMessage> {
+    let mut messages: Vec<ConversationMessage> = Vec::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
         }
 
-        let claude_count = sessions.iter().filter(|s| s.agent != "codex").count();
-        let codex_count = sessions.iter().filter(|s| s.agent == "codex").count();
-        eprintln!("Sessions in index: {} Claude, {} Codex", claude_count, codex_count);
-    }
+        // Parse JSON line
+        let json: serde_json::Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
 
-    // Create app with CLI options pre-configured
-    let mut app = App::new_with_options(
-        sessions,
-        index_path.to_string_lossy().to_string(),
-        &cli,
-    );
+        // Try to extract message based on format
+        let (role, text) = extract_message_from_json(&json, true);
 
-    // JSON output mode - output filtered results and exit
-    if cli.json_output {
-        return output_json(&app, cli.num_results);
+        if let (Some(role), Some(text)) = (role, text) {
+            // Skip empty messages
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            // Codex logs the same turn as both response_item and event_msg; keep it once
+            if messages
+                .last()
+                .is_some_and(|m| m.role == role && m.text.trim() == text.trim())
+            {
+                continue;
+            }
+            messages.push(ConversationMessage { line: line_no, role, text });
+        }
     }
 
-    // Interactive TUI mode
-    enable_raw_mode()?;
-    let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    messages
+}
 
-    loop {
-        terminal.draw(|f| render(f, &mut app))?;
+/// How a parsed session transcript is laid out
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TranscriptFormat {
+    Prefixed, // "> " / "⏺ " lines, as the TUI and exported .txt transcripts show them
+    Markdown, // "## User" / "## Assistant" sections, tool calls and results fenced
+}
 
-        if app.should_quit {
-            break;
+impl TranscriptFormat {
+    fn parse(name: &str) -> Option<TranscriptFormat> {
+        match name.trim().to_lowercase().as_str() {
+            "md" | "markdown" => Some(TranscriptFormat::Markdown),
+            "txt" | "text" => Some(TranscriptFormat::Prefixed),
+            _ => None,
         }
+    }
+}
 
-        while event::poll(Duration::from_millis(0))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    // Clear status message on any keypress
-                    app.status_message = None;
+/// Parse JSONL file content into conversational text format.
+/// Handles both Claude and Codex JSONL formats.
+/// Returns text with "> " prefix for user messages and "⏺ " for assistant messages;
+/// with `show_reasoning`, Codex reasoning is kept as "✻ " messages.
+fn parse_jsonl_to_conversation(content: &str, show_reasoning: bool) -> String {
+    parse_jsonl_to_transcript(content, show_reasoning, TranscriptFormat::Prefixed)
+}
 
-                    // Handle exit confirmation dialog
-                    if app.confirming_exit {
-                        match key.code {
-                            KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
-                                app.should_quit = true;
-                            }
-                            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
-                                app.confirming_exit = false;
-                            }
-                            _ => {}
-                        }
-                        continue;
-                    }
+/// Parse JSONL file content into a transcript in the given format
+fn parse_jsonl_to_transcript(content: &str, show_reasoning: bool, format: TranscriptFormat) -> String {
+    let mut output = String::new();
+    let mut last_role: Option<&str> = None;
+    let messages = parse_jsonl_messages(content);
 
-                    // Handle delete confirmation dialog
-                    if app.confirming_delete {
-                        match key.code {
+    for message in &messages {
+        let (role, text) = (message.role.as_str(), message.text.as_str());
+        if role == "reasoning" && !show_reasoning {
+            continue;
+        }
+
+        // Tool results go right under their call, as in exported transcripts
+        if text.starts_with(TOOL_RESULT_PREFIX) {
+            if format == TranscriptFormat::Markdown {
+                output.push('\n');
+                output.push_str(&markdown_fence(&tool_result_body(text)));
+                continue;
+            }
+            output.push_str(text);
+            output.push('\n');
+            continue;
+        }
+
+        if format == TranscriptFormat::Markdown {
+            if last_role != Some(role) {
+                let header = match role {
+                    "user" => "User",
+                    "reasoning" => "Reasoning",
+                    _ => "Assistant",
+                };
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                output.push_str(&format!("## {}\n\n", header));
+            } else if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&markdown_message(text));
+            last_role = Some(role);
+            continue;
+        }
+
+        // Add blank line between different roles
+        if let Some(last) = last_role {
+            if last != role && !output.is_empty() {
+                output.push('\n');
+            }
+        }
+
+        // Format based on role
+        let prefix = match role {
+            "user" => "> ",
+            "reasoning" => REASONING_PREFIX,
+            _ => "⏺ ",
+        };
+
+        // Split text into lines and prefix the first line
+        for (i, line) in text.lines().enumerate() {
+            if i == 0 {
+                output.push_str(prefix);
+                output.push_str(line);
+            } else {
+                // Continuation lines - indent to align with content
+                output.push_str("  ");
+                output.push_str(line);
+            }
+            output.push('\n');
+        }
+
+        last_role = Some(role);
+    }
+
+    output
+}
+
+/// One turn of a session for `--json-full`: its prose, and the tool calls it made
+#[derive(Serialize, Debug, PartialEq)]
+struct ConversationTurn {
+    role: String,
+    text: String,
+    tool_calls: Vec<TurnToolCall>,
+}
+
+/// A `[Tool: Name] summary` call, with the result that came back for it, if any
+#[derive(Serialize, Debug, PartialEq)]
+struct TurnToolCall {
+    name: String,
+    input: String,
+    result: Option<String>,
+}
+
+/// Structured turns of a session transcript. Tool results are attached to the
+/// earliest call of the previous turn still waiting for one; reasoning is left out,
+/// as in exported transcripts.
+fn parse_jsonl_to_turns(content: &str) -> Vec<ConversationTurn> {
+    let mut turns: Vec<ConversationTurn> = Vec::new();
+
+    for message in parse_jsonl_messages(content) {
+        if message.role == "reasoning" {
+            continue;
+        }
+        if message.text.starts_with(TOOL_RESULT_PREFIX) {
+            let pending = turns
+                .last_mut()
+                .and_then(|turn| turn.tool_calls.iter_mut().find(|call| call.result.is_none()));
+            if let Some(call) = pending {
+                call.result = Some(tool_result_body(&message.text));
+            }
+            continue;
+        }
+
+        let mut prose: Vec<&str> = Vec::new();
+        let mut tool_calls: Vec<TurnToolCall> = Vec::new();
+        for line in message.text.lines() {
+            if let Some((name, summary)) = line.strip_prefix("[Tool: ").and_then(|r| r.split_once(']')) {
+                tool_calls.push(TurnToolCall { name: name.to_string(), input: summary.trim().to_string(), result: None });
+            } else if let Some(call) = tool_calls.last_mut() {
+                // Lines under a tool call are the rest of its input
+                if !call.input.is_empty() {
+                    call.input.push('\n');
+                }
+                call.input.push_str(line);
+            } else {
+                prose.push(line);
+            }
+        }
+        turns.push(ConversationTurn {
+            role: message.role,
+            text: prose.join("\n").trim().to_string(),
+            tool_calls,
+        });
+    }
+
+    turns
+}
+
+/// Markdown for one message: prose as-is, and each `[Tool: Name] summary` line,
+/// with the tool input printed under it, as a fenced block headed by the tool name
+fn markdown_message(text: &str) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut prose: Vec<&str> = Vec::new();
+    let mut tool: Option<(&str, Vec<&str>)> = None;
+
+    let mut flush = |prose: &mut Vec<&str>, tool: &mut Option<(&str, Vec<&str>)>| {
+        if !prose.is_empty() {
+            blocks.push(format!("{}\n", prose.join("\n").trim_end()));
+            prose.clear();
+        }
+        if let Some((name, body)) = tool.take() {
+            blocks.push(format!("**Tool: {}**\n\n{}", name, markdown_fence(&body.join("\n"))));
+        }
+    };
+    for line in text.lines() {
+        if let Some((name, summary)) = line.strip_prefix("[Tool: ").and_then(|r| r.split_once(']')) {
+            flush(&mut prose, &mut tool);
+            let summary = summary.trim();
+            tool = Some((name, if summary.is_empty() { Vec::new() } else { vec![summary] }));
+        } else if let Some((_, body)) = tool.as_mut() {
+            body.push(line);
+        } else {
+            prose.push(line);
+        }
+    }
+    flush(&mut prose, &mut tool);
+    blocks.join("\n")
+}
+
+/// The output of a `  ⎿  ` tool-result block, with the prefix and indent taken off
+fn tool_result_body(text: &str) -> String {
+    text.strip_prefix(TOOL_RESULT_PREFIX)
+        .unwrap_or(text)
+        .lines()
+        .map(|line| line.strip_prefix(TOOL_RESULT_INDENT).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A fenced code block, with a fence longer than any backtick run inside
+fn markdown_fence(body: &str) -> String {
+    let longest_run = body
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}\n{}\n{}\n", fence, body.trim_end(), fence)
+}
+
+/// Write the session's transcript as `<session_id>.md` in the current directory
+fn write_markdown_export(session: &Session) -> std::io::Result<std::path::PathBuf> {
+    let content = std::fs::read_to_string(&session.export_path)?;
+    let path = std::env::current_dir()?.join(format!("{}.md", session.session_id));
+    std::fs::write(&path, render_export(session, &content, TranscriptFormat::Markdown))?;
+    Ok(path)
+}
+
+/// An exported transcript; Markdown gets a title line naming the session. Both formats
+/// start with the turn and attachment counts the --json output carries, when known.
+fn render_export(session: &Session, content: &str, format: TranscriptFormat) -> String {
+    let transcript = parse_jsonl_to_transcript(content, false, format);
+    let counts = export_counts(session);
+    match format {
+        TranscriptFormat::Markdown => {
+            let counts = counts.map(|c| format!("{c}\n\n")).unwrap_or_default();
+            format!("# {} ({})\n\n{}{}", session.project, session.session_id, counts, transcript)
+        }
+        TranscriptFormat::Prefixed => match counts {
+            Some(c) => format!("{c}\n\n{transcript}"),
+            None => transcript,
+        },
+    }
+}
+
+/// "12 messages, 1 attachment"; None when the index has neither count
+fn export_counts(session: &Session) -> Option<String> {
+    let plural = |n: i64, what: &str| format!("{n} {what}{}", if n == 1 { "" } else { "s" });
+    let parts: Vec<String> = [
+        session.message_count.map(|n| plural(n, "message")),
+        (session.attachment_count > 0).then(|| plural(session.attachment_count, "attachment")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+/// JSONL line ranges of each turn: a user prompt up to the line before the next one.
+/// Lines before the first prompt (session metadata) belong to no turn.
+fn conversation_turns(content: &str) -> Vec<std::ops::Range<usize>> {
+    let starts: Vec<usize> = parse_jsonl_messages(content)
+        .iter()
+        .filter(|m| m.is_user_prompt())
+        .map(|m| m.line)
+        .collect();
+    let total = content.lines().count();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| start..starts.get(i + 1).copied().unwrap_or(total))
+        .collect()
+}
+
+/// Line numbers to keep for the first and last `keep` turns, plus everything before the
+/// first turn. None when there are no more than 2 * `keep` turns, so nothing would go.
+fn kept_turn_lines(content: &str, keep: usize) -> Option<(Vec<usize>, usize)> {
+    let turns = conversation_turns(content);
+    if turns.len() <= 2 * keep {
+        return None;
+    }
+    let dropped = turns.len() - 2 * keep;
+    let mut lines: Vec<usize> = (0..turns[0].start).collect();
+    for turn in turns[..keep].iter().chain(&turns[turns.len() - keep..]) {
+        lines.extend(turn.clone());
+    }
+    Some((lines, dropped))
+}
+
+/// Random version 4 UUID, from the std hasher's per-process random keys
+fn new_session_uuid() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let random = || {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default(),
+        );
+        hasher.finish()
+    };
+    let mut bits = (u128::from(random()) << 64) | u128::from(random());
+    bits = (bits & !(0xf << 76)) | (0x4 << 76); // version 4
+    bits = (bits & !(0x3 << 62)) | (0x2 << 62); // RFC 4122 variant
+    let hex = format!("{:032x}", bits);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Write a copy of the session with only its first and last `keep` turns, next to the
+/// original under a new session ID. The first line carries `trim_metadata`, as written by
+/// trim-session, so the index lists the copy as trimmed. Returns None when the session is
+/// too short to trim, else the new file and the number of turns dropped.
+fn write_kept_turns(session: &Session, keep: usize) -> std::io::Result<Option<(std::path::PathBuf, usize)>> {
+    let source = std::path::Path::new(&session.export_path);
+    let content = std::fs::read_to_string(source)?;
+    let Some((kept, dropped)) = kept_turn_lines(&content, keep) else {
+        return Ok(None);
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    let new_id = new_session_uuid();
+
+    let mut output = String::new();
+    let mut kept_uuids: HashSet<String> = HashSet::new();
+    let mut last_uuid: Option<String> = None;
+    let mut first_json = true;
+    for &line_no in &kept {
+        let line = lines[line_no];
+        let Ok(mut json) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+        if let Some(obj) = json.as_object_mut() {
+            if first_json {
+                obj.remove("continue_metadata");
+                obj.insert(
+                    "trim_metadata".to_string(),
+                    serde_json::json!({
+                        "parent_file": source.canonicalize().unwrap_or_else(|_| source.to_path_buf()),
+                        "trimmed_at": Utc::now().to_rfc3339(),
+                        "trim_params": { "keep_first_turns": keep, "keep_last_turns": keep },
+                        "stats": { "num_turns_dropped": dropped },
+                    }),
+                );
+                first_json = false;
+            }
+            if obj.contains_key("sessionId") {
+                obj.insert("sessionId".to_string(), serde_json::json!(new_id));
+            }
+            if obj.get("type").and_then(|v| v.as_str()) == Some("session_meta") {
+                if let Some(payload) = obj.get_mut("payload").and_then(|p| p.as_object_mut()) {
+                    payload.insert("id".to_string(), serde_json::json!(new_id));
+                }
+            }
+            // Claude chains messages by parentUuid; bridge the gap left by dropped turns
+            let parent = obj.get("parentUuid").and_then(|v| v.as_str()).map(str::to_string);
+            if parent.is_some_and(|p| !kept_uuids.contains(&p)) {
+                obj.insert("parentUuid".to_string(), serde_json::json!(last_uuid));
+            }
+            if let Some(uuid) = obj.get("uuid").and_then(|v| v.as_str()) {
+                kept_uuids.insert(uuid.to_string());
+                last_uuid = Some(uuid.to_string());
+            }
+        }
+        output.push_str(&json.to_string());
+        output.push('\n');
+    }
+
+    let file_name = if session.agent == "codex" {
+        format!("rollout-{}-{}.jsonl", Local::now().format("%Y-%m-%dT%H-%M-%S"), new_id)
+    } else {
+        format!("{}.jsonl", new_id)
+    };
+    let path = source.with_file_name(file_name);
+    std::fs::write(&path, output)?;
+    Ok(Some((path, dropped)))
+}
+
+/// Extract role and text from a JSON entry (handles Claude and Codex formats).
+/// With `tool_detail`, tool calls carry a summary of their input and tool results
+/// are returned as `  ⎿  ...` blocks (the full view); without it they are dropped.
+fn extract_message_from_json(json: &serde_json::Value, tool_detail: bool) -> (Option<String>, Option<String>) {
+    let entry_type = json.get("type").and_then(|v| v.as_str());
+
+    match entry_type {
+        // Claude format: {"type": "user" | "assistant", "message": {...}}
+        Some("user") | Some("assistant") => {
+            let role = entry_type.map(|s| s.to_string());
+            let text = extract_claude_message_text(json, tool_detail);
+            (role, text)
+        }
+
+        // Codex format: {"type": "response_item", "payload": {"role": "user" | "assistant", ...}}
+        Some("response_item") => {
+            if let Some(payload) = json.get("payload") {
+                if tool_detail {
+                    if let Some(text) = codex_reasoning_text(payload) {
+                        return (Some("reasoning".to_string()), Some(text));
+                    }
+                    if let Some(text) = codex_tool_payload_text(payload) {
+                        return (Some("assistant".to_string()), Some(text));
+                    }
+                }
+                let role = payload
+                    .get("role")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+                let text = extract_codex_message_text(payload, tool_detail);
+                (role, text)
+            } else {
+                (None, None)
+            }
+        }
+
+        // Codex format: {"type": "event_msg", "payload": {"type": "user_message" | "agent_message", "message": "..."}}
+        Some("event_msg") => {
+            if let Some(payload) = json.get("payload") {
+                let msg_type = payload.get("type").and_then(|v| v.as_str());
+                let role = match msg_type {
+                    Some("user_message") => "user",
+                    Some("agent_message") | Some("assistant_message") => "assistant",
+                    // Reasoning, token counts, etc. are not conversation turns
+                    _ => return (None, None),
+                };
+                let text = payload
+                    .get("message")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+                (Some(role.to_string()), text)
+            } else {
+                (None, None)
+            }
+        }
+
+        _ => (None, None),
+    }
+}
+
+/// Extract text from Claude message format.
+/// User: {"message": {"content": "text"}}
+/// Assistant: {"message": {"content": [{"type": "text", "text": "..."}]}}
+fn extract_claude_message_text(json: &serde_json::Value, tool_detail: bool) -> Option<String> {
+    let message = json.get("message")?;
+    let content = message.get("content")?;
+
+    // User messages have string content
+    if let Some(text) = content.as_str() {
+        return Some(text.to_string());
+    }
+
+    // Assistant messages have array of content blocks
+    if let Some(blocks) = content.as_array() {
+        let mut texts = Vec::new();
+        for block in blocks {
+            if let Some(block_type) = block.get("type").and_then(|v| v.as_str()) {
+                match block_type {
+                    "text" => {
+                        if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
+                            texts.push(text.to_string());
+                        }
+                    }
+                    "image" | "document" => {
+                        texts.push(attachment_placeholder(block));
+                    }
+                    "tool_use" => {
+                        // Show tool name
+                        let summary = block.get("input").filter(|_| tool_detail).map(tool_input_summary);
+                        if let Some(name) = block.get("name").and_then(|v| v.as_str()) {
+                            texts.push(tool_call_marker(name, summary.as_deref()));
+                        }
+                        // Index tool input content (code, commands, etc.)
+                        if let Some(input) = block.get("input").and_then(|v| v.as_object()) {
+                            for value in input.values() {
+                                if let Some(s) = value.as_str() {
+                                    if !s.is_empty() && summary.as_deref() != Some(s) {
+                                        texts.push(s.to_string());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    "tool_result" if tool_detail => {
+                        texts.push(tool_result_block(&tool_result_text(block.get("content"))));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if !texts.is_empty() {
+            return Some(texts.join("\n"));
+        }
+    }
+
+    None
+}
+
+/// Placeholder for a non-text content block, e.g. `[image: 1024x768]`.
+/// Claude: {"type": "image" | "document", "source": {"media_type": ..., "data": <base64>}}
+/// Codex: {"type": "input_image", "image_url": "data:image/png;base64,..."}
+fn attachment_placeholder(block: &serde_json::Value) -> String {
+    let block_type = block.get("type").and_then(|v| v.as_str()).unwrap_or("");
+    let kind = if block_type == "document" { "document" } else { "image" };
+
+    let source = block.get("source");
+    let (media_type, data) = match block.get("image_url").and_then(|v| v.as_str()) {
+        Some(url) => {
+            // data:<media_type>;base64,<data>
+            let (header, data) = url.split_once(',').unwrap_or(("", ""));
+            let media_type = header.trim_start_matches("data:").split(';').next().unwrap_or("");
+            (media_type, data)
+        }
+        None => (
+            source
+                .and_then(|s| s.get("media_type"))
+                .and_then(|v| v.as_str())
+                .unwrap_or(""),
+            source.and_then(|s| s.get("data")).and_then(|v| v.as_str()).unwrap_or(""),
+        ),
+    };
+
+    let detail = if kind == "image" {
+        image_dimensions(data).map(|(w, h)| format!("{}x{}", w, h))
+    } else {
+        block.get("title").and_then(|v| v.as_str()).map(|s| s.to_string())
+    };
+    match detail.or_else(|| (!media_type.is_empty()).then(|| media_type.to_string())) {
+        Some(detail) => format!("[{}: {}]", kind, detail),
+        None => format!("[{}]", kind),
+    }
+}
+
+/// Width and height from a base64 PNG or GIF header (other formats: None).
+fn image_dimensions(data: &str) -> Option<(u32, u32)> {
+    let header = decode_base64_prefix(data, 24);
+    if header.len() >= 24 && header.starts_with(b"\x89PNG") {
+        let be = |i: usize| u32::from_be_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+        return Some((be(16), be(20)));
+    }
+    if header.len() >= 10 && header.starts_with(b"GIF8") {
+        let le = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]) as u32;
+        return Some((le(6), le(8)));
+    }
+    None
+}
+
+/// Decode up to `max_bytes` from the start of a base64 string.
+fn decode_base64_prefix(data: &str, max_bytes: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(max_bytes);
+    let (mut buf, mut bits) = (0u32, 0u32);
+    for c in data.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => break,
+        };
+        buf = (buf << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+            buf &= (1 << bits) - 1;
+            if out.len() >= max_bytes {
+                break;
+            }
+        }
+    }
+    out
+}
+
+/// Extract text from Codex message format.
+/// {"content": [{"type": "input_text" | "output_text", "text": "..."}]}
+fn extract_codex_message_text(payload: &serde_json::Value, tool_detail: bool) -> Option<String> {
+    let content = payload.get("content")?.as_array()?;
+
+    let mut texts = Vec::new();
+    for block in content {
+        if let Some(block_type) = block.get("type").and_then(|v| v.as_str()) {
+            match block_type {
+                "input_text" | "output_text" => {
+                    if let Some(text) = block.get("text").and_then(|v| v.as_str()) {
+                        texts.push(text.to_string());
+                    }
+                }
+                "input_image" => {
+                    texts.push(attachment_placeholder(block));
+                }
+                "tool_use" | "function_call" => {
+                    let summary = block.get("input").filter(|_| tool_detail).map(tool_input_summary);
+                    if let Some(name) = block.get("name").and_then(|v| v.as_str()) {
+                        texts.push(tool_call_marker(name, summary.as_deref()));
+                    }
+                    // Index tool input content (code, commands, etc.)
+                    if let Some(input) = block.get("input").and_then(|v| v.as_object()) {
+                        for value in input.values() {
+                            if let Some(s) = value.as_str() {
+                                if !s.is_empty() && summary.as_deref() != Some(s) {
+                                    texts.push(s.to_string());
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if !texts.is_empty() {
+        Some(texts.join("\n"))
+    } else {
+        None
+    }
+}
+
+/// Tool results in transcripts: `  ⎿  first line`, continuation lines indented to match
+const TOOL_RESULT_PREFIX: &str = "  ⎿  ";
+
+/// Full-view line prefix of a Codex reasoning message, and the label it renders as
+const REASONING_PREFIX: &str = "✻ ";
+const REASONING_LABEL: &str = " reasoning  ";
+
+/// Summary text of a Codex reasoning item:
+/// {"type": "reasoning", "summary": [{"type": "summary_text", "text": "..."}], "content": ...}
+/// Falls back to plain `content` text when there is no summary.
+fn codex_reasoning_text(payload: &serde_json::Value) -> Option<String> {
+    if payload.get("type").and_then(|v| v.as_str()) != Some("reasoning") {
+        return None;
+    }
+    let texts = |key: &str| -> Vec<String> {
+        payload
+            .get(key)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|block| block.get("text").and_then(|v| v.as_str()))
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect()
+    };
+    let summary = texts("summary");
+    let parts = if summary.is_empty() { texts("content") } else { summary };
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+const TOOL_RESULT_INDENT: &str = "     ";
+/// Tool input summaries are collapsed to one line and cut at this many characters
+const TOOL_INPUT_SUMMARY_CHARS: usize = 200;
+/// Input keys that say what a tool call did, most telling first
+const TOOL_INPUT_KEYS: &[&str] = &["command", "cmd", "file_path", "path", "pattern", "url", "query", "description"];
+
+/// `[Tool: name]`, followed by the input summary if there is one
+fn tool_call_marker(name: &str, summary: Option<&str>) -> String {
+    match summary.filter(|s| !s.is_empty()) {
+        Some(summary) => format!("[Tool: {}] {}", name, summary),
+        None => format!("[Tool: {}]", name),
+    }
+}
+
+/// The most telling input field (command, file path, pattern, ...) or else the
+/// compact input JSON, on one line
+fn tool_input_summary(input: &serde_json::Value) -> String {
+    let field = TOOL_INPUT_KEYS.iter().find_map(|key| match input.get(key)? {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Array(parts) => {
+            Some(parts.iter().filter_map(|p| p.as_str()).collect::<Vec<_>>().join(" "))
+        }
+        _ => None,
+    });
+    let text = match field {
+        Some(text) => text,
+        None if input.as_object().is_some_and(|o| o.is_empty()) => String::new(),
+        None => input.to_string(),
+    };
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate(&collapsed, TOOL_INPUT_SUMMARY_CHARS)
+}
+
+/// Text of a Claude tool_result `content` (a string or a list of text blocks)
+fn tool_result_text(content: Option<&serde_json::Value>) -> String {
+    match content {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter(|item| item.get("type").and_then(|v| v.as_str()) == Some("text"))
+            .filter_map(|item| item.get("text").and_then(|v| v.as_str()))
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+/// `  ⎿  output` with continuation lines indented, like export_claude_session.py
+fn tool_result_block(text: &str) -> String {
+    let text = text.trim_end();
+    if text.is_empty() {
+        return format!("{}(No content)", TOOL_RESULT_PREFIX);
+    }
+    let indented = text.lines().collect::<Vec<_>>().join(&format!("\n{}", TOOL_RESULT_INDENT));
+    format!("{}{}", TOOL_RESULT_PREFIX, indented)
+}
+
+/// Codex logs tool calls and their output as their own response_items:
+/// {"type": "function_call", "name": ..., "arguments": "<json>"} and
+/// {"type": "function_call_output", "output": "<text or json with an output field>"}
+fn codex_tool_payload_text(payload: &serde_json::Value) -> Option<String> {
+    let as_json = |v: Option<&serde_json::Value>| -> Option<serde_json::Value> {
+        match v? {
+            serde_json::Value::String(s) => serde_json::from_str(s).ok(),
+            other => Some(other.clone()),
+        }
+    };
+    match payload.get("type").and_then(|v| v.as_str())? {
+        "function_call" | "custom_tool_call" => {
+            let name = payload.get("name").and_then(|v| v.as_str()).unwrap_or("tool");
+            let input = as_json(payload.get("arguments").or_else(|| payload.get("input")));
+            Some(tool_call_marker(name, input.as_ref().map(tool_input_summary).as_deref()))
+        }
+        "function_call_output" | "custom_tool_call_output" => {
+            let raw = payload.get("output");
+            let output = as_json(raw)
+                .and_then(|v| v.get("output").and_then(|o| o.as_str()).map(str::to_string))
+                .or_else(|| raw.and_then(|v| v.as_str()).map(str::to_string))
+                .unwrap_or_default();
+            Some(tool_result_block(&output))
+        }
+        _ => None,
+    }
+}
+
+/// One tool invocation in a transcript, with the result printed after it (if any)
+#[derive(Clone, Debug, PartialEq)]
+struct ToolCall {
+    line: usize, // transcript line of the call
+    name: String,
+    input: String,
+    result: Option<String>, // first line of the result
+    result_lines: usize,
+}
+
+/// Tool name and arguments of a transcript line: `⏺ Name(args)` from exports, or
+/// `[Tool: Name] summary` (possibly as a continuation line) from parsed JSONL
+fn parse_tool_call_line(line: &str) -> Option<(String, String)> {
+    let body = line.strip_prefix("⏺ ").or_else(|| line.strip_prefix("  "))?;
+    if let Some(rest) = body.strip_prefix("[Tool: ") {
+        let (name, input) = rest.split_once(']')?;
+        return Some((name.to_string(), input.trim().to_string()));
+    }
+    let (name, args) = line.strip_prefix("⏺ ")?.split_once('(')?;
+    let is_name = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "_-.:".contains(c));
+    let args = args.strip_suffix(')').filter(|_| is_name)?;
+    Some((name.to_string(), args.to_string()))
+}
+
+/// Tool-result blocks of a full-view transcript as (first line, line count): a `⎿`
+/// line and the indented continuation lines under it
+fn tool_result_blocks(conversation: &str) -> Vec<(usize, usize)> {
+    let mut blocks: Vec<(usize, usize)> = Vec::new();
+    let mut in_result = false;
+    for (idx, line) in conversation.lines().enumerate() {
+        if line.starts_with("  ⎿") {
+            blocks.push((idx, 1));
+            in_result = true;
+        } else if in_result && line.starts_with(TOOL_RESULT_INDENT) {
+            if let Some(block) = blocks.last_mut() {
+                block.1 += 1;
+            }
+        } else {
+            in_result = false;
+        }
+    }
+    blocks
+}
+
+/// Tool calls of a full-view transcript in order. A `⎿` result belongs to the last
+/// call if no other message came between them.
+fn tool_timeline(conversation: &str) -> Vec<ToolCall> {
+    let mut calls: Vec<ToolCall> = Vec::new();
+    let mut open_call = false; // the last call can still take a result
+    let mut in_result = false; // counting continuation lines of a result
+    for (idx, line) in conversation.lines().enumerate() {
+        if let Some(first) = line.strip_prefix("  ⎿") {
+            in_result = false;
+            if let Some(call) = calls.last_mut().filter(|c| open_call && c.result.is_none()) {
+                call.result = Some(first.trim().to_string());
+                call.result_lines = 1;
+                in_result = true;
+            }
+            open_call = false;
+            continue;
+        }
+        if in_result && line.starts_with(TOOL_RESULT_INDENT) {
+            if let Some(call) = calls.last_mut() {
+                call.result_lines += 1;
+            }
+            continue;
+        }
+        in_result = false;
+        if let Some((name, input)) = parse_tool_call_line(line) {
+            calls.push(ToolCall { line: idx, name, input, result: None, result_lines: 0 });
+            open_call = true;
+        } else if line.starts_with("> ") || line.starts_with("⏺ ") {
+            open_call = false;
+        }
+    }
+    calls
+}
+
+// ============================================================================
+// JSON Output
+// ============================================================================
+
+/// Query-less --json dumps are capped here unless -n or --all says otherwise
+const JSON_DUMP_WARN_THRESHOLD: usize = 1000;
+
+/// Keys of each --json line unless --fields picks others
+const JSON_FIELDS: &[&str] = &[
+    "session_id", "agent", "project", "branch", "cwd", "lines", "created", "modified",
+    "first_msg", "last_msg", "file_path", "derivation_type", "is_sidechain", "custom_title",
+    "claude_home", "snippet",
+];
+/// Only emitted when asked for by name or with `--fields all`
+const JSON_EXTRA_FIELDS: &[&str] = &[
+    "modified_ts", "message_count", "attachment_count", "code_langs",
+    "first_msg_role", "last_msg_role", "duration",
+];
+/// The whole conversation as structured turns; it reads every session file, so
+/// only --json-full or naming it in --fields adds it, never `--fields all`
+const JSON_TURNS_FIELD: &str = "turns";
+
+/// Parse `--fields a,b,c` (or `all`) into known field names, or say which name is unknown
+fn parse_json_fields(spec: &str) -> Result<Vec<&'static str>, String> {
+    if spec.trim() == "all" {
+        return Ok(JSON_FIELDS.iter().chain(JSON_EXTRA_FIELDS).copied().collect());
+    }
+    let mut fields = Vec::new();
+    for name in spec.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let field = JSON_FIELDS
+            .iter()
+            .chain(JSON_EXTRA_FIELDS)
+            .chain([&JSON_TURNS_FIELD])
+            .find(|f| **f == name)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown --fields name '{}': expected `all` or some of {}, {}, {}",
+                    name,
+                    JSON_FIELDS.join(", "),
+                    JSON_EXTRA_FIELDS.join(", "),
+                    JSON_TURNS_FIELD
+                )
+            })?;
+        if !fields.contains(field) {
+            fields.push(*field);
+        }
+    }
+    if fields.is_empty() {
+        return Err("--fields needs at least one field name".to_string());
+    }
+    Ok(fields)
+}
+
+/// Value of one --json field for a session
+fn json_field(app: &App, s: &Session, field: &str) -> serde_json::Value {
+    use serde_json::json;
+
+    match field {
+        "session_id" => json!(s.session_id),
+        "agent" => json!(s.agent),
+        "project" => json!(s.project),
+        "branch" => json!(s.branch),
+        "cwd" => json!(s.cwd),
+        "lines" => json!(s.lines),
+        "created" => json!(s.created),
+        "modified" => json!(s.modified),
+        "first_msg" => json!(if !s.first_user_msg_content.is_empty() { &s.first_user_msg_content } else { &s.first_msg_content }),
+        "last_msg" => json!(s.last_msg_content),
+        "file_path" => json!(s.export_path),
+        "derivation_type" => json!(s.derivation_type),
+        "is_sidechain" => json!(s.is_sidechain),
+        "custom_title" => json!(s.custom_title),
+        "snippet" => json!(app.search_snippets.get(&s.session_id).map(|s| strip_html_tags(s))),
+        "claude_home" => json!(s.claude_home),
+        "modified_ts" => json!(s.modified_ts),
+        "message_count" => json!(s.message_count),
+        "attachment_count" => json!(s.attachment_count),
+        "code_langs" => json!(s.code_langs),
+        "first_msg_role" => json!(s.first_msg_role),
+        "last_msg_role" => json!(s.last_msg_role),
+        "duration" => json!(s.duration_secs()),
+        // null when the session file can't be read
+        "turns" => json!(std::fs::read_to_string(&s.export_path).ok().map(|content| parse_jsonl_to_turns(&content))),
+        _ => serde_json::Value::Null,
+    }
+}
+
+fn output_json(app: &mut App, dump_all: bool, fields: &[&str]) -> Result<()> {
+    // Guard against accidental massive dumps (e.g. `--json -g` with no query piped into a tool)
+    if app.max_results.is_none()
+        && !dump_all
+        && app.query.trim().is_empty()
+        && app.filtered.len() > JSON_DUMP_WARN_THRESHOLD
+    {
+        eprintln!(
+            "⚠️  {} sessions match with no --query; output capped at {}.",
+            app.filtered.len(),
+            JSON_DUMP_WARN_THRESHOLD
+        );
+        eprintln!("⚠️  Use --query, -n N, or --all to output everything.");
+        app.max_results = Some(JSON_DUMP_WARN_THRESHOLD);
+        app.apply_result_limit();
+    }
+
+    // A closed downstream pipe (`... | head`) just means the reader has enough
+    let stdout = io::stdout();
+    match write_json_lines(io::BufWriter::new(stdout.lock()), app, fields) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.map_err(Into::into),
+    }
+}
+
+/// Write filtered sessions as JSONL (one JSON object per line) for easy piping and
+/// jq processing. Buffered, so large dumps don't lock and flush stdout per line.
+fn write_json_lines<W: io::Write>(mut out: W, app: &App, fields: &[&str]) -> io::Result<()> {
+    for &idx in &app.filtered {
+        let s = &app.sessions[idx];
+        let obj: serde_json::Map<String, serde_json::Value> = fields
+            .iter()
+            .map(|&field| (field.to_string(), json_field(app, s, field)))
+            .collect();
+        serde_json::to_writer(&mut out, &obj)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()
+}
+
+/// Count sessions per key, most sessions first (ties by name)
+fn tally<'a>(keys: impl Iterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for key in keys {
+        *counts.entry(key).or_default() += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+}
+
+/// Aggregate counts over the filtered sessions (--stats): a readable table, or one
+/// JSON object when combined with --json.
+fn write_stats<W: io::Write>(mut out: W, app: &App, json: bool) -> io::Result<()> {
+    use serde_json::json;
+
+    let sessions: Vec<&Session> = app.filtered.iter().map(|&idx| &app.sessions[idx]).collect();
+    let total_lines: i64 = sessions.iter().map(|s| s.lines).sum();
+    let day = |ts: Option<u64>| {
+        ts.and_then(|ts| Local.timestamp_millis_opt(ts as i64).single())
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+    };
+    let dated = || sessions.iter().map(|s| s.modified_ts).filter(|&ts| ts > 0);
+    let (first, last) = (day(dated().min()), day(dated().max()));
+
+    let sections = [
+        ("agent", tally(sessions.iter().map(|s| s.agent.as_str()))),
+        (
+            "derivation",
+            tally(sessions.iter().map(|s| match s.derivation_type.as_str() {
+                "trimmed" => "trimmed",
+                // "continued" internally = "rolled-over" in UI
+                "continued" => "rolled-over",
+                _ => "original",
+            })),
+        ),
+        (
+            "claude_home",
+            tally(
+                sessions
+                    .iter()
+                    .filter(|s| s.agent != "codex")
+                    .map(|s| if s.claude_home.is_empty() { "-" } else { s.claude_home.as_str() }),
+            ),
+        ),
+        ("project", tally(sessions.iter().map(|s| s.project_name()))),
+    ];
+    let sub_agents = sessions.iter().filter(|s| s.is_sidechain).count();
+
+    if json {
+        let mut obj = serde_json::Map::new();
+        obj.insert("sessions".to_string(), json!(sessions.len()));
+        obj.insert("total_lines".to_string(), json!(total_lines));
+        obj.insert("first_modified".to_string(), json!(first));
+        obj.insert("last_modified".to_string(), json!(last));
+        obj.insert("sub_agents".to_string(), json!(sub_agents));
+        for (key, counts) in &sections {
+            // Arrays rather than objects, to keep the most-sessions-first order
+            let rows: Vec<serde_json::Value> =
+                counts.iter().map(|(name, n)| json!({"name": name, "sessions": n})).collect();
+            obj.insert(format!("by_{}", key), json!(rows));
+        }
+        serde_json::to_writer(&mut out, &obj)?;
+        writeln!(out)?;
+        return out.flush();
+    }
+
+    writeln!(out, "Sessions     {}", sessions.len())?;
+    writeln!(out, "Sub-agents   {}", sub_agents)?;
+    writeln!(out, "Total lines  {}", total_lines)?;
+    if let (Some(first), Some(last)) = (&first, &last) {
+        writeln!(out, "Date range   {} .. {}", first, last)?;
+    }
+    for (key, counts) in &sections {
+        let title = match *key {
+            "claude_home" => "Claude home",
+            other => other,
+        };
+        writeln!(out, "\nBy {}", title)?;
+        let width = counts.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+        for (name, n) in counts {
+            writeln!(out, "  {:<width$}  {:>6}", name, n, width = width)?;
+        }
+    }
+    out.flush()
+}
+
+/// Print the index schema as JSON (--dump-schema), plus the fields this TUI depends on.
+/// Intended for indexer authors and bug reports about missing fields.
+fn dump_schema(index_path: &str) -> Result<()> {
+    use serde_json::json;
+    use tantivy::schema::FieldType;
+
+    let index = open_index(index_path)?;
+    let schema = index.schema();
+
+    let fields: Vec<serde_json::Value> = schema
+        .fields()
+        .map(|(_, entry)| {
+            let tokenizer = match entry.field_type() {
+                FieldType::Str(opts) => opts.get_indexing_options().map(|i| i.tokenizer().to_string()),
+                _ => None,
+            };
+            json!({
+                "name": entry.name(),
+                "type": entry.field_type().value_type().name(),
+                "stored": entry.is_stored(),
+                "indexed": entry.is_indexed(),
+                "fast": entry.is_fast(),
+                "tokenizer": tokenizer,
+            })
+        })
+        .collect();
+
+    let missing = |names: &[&str]| -> Vec<String> {
+        names
+            .iter()
+            .filter(|n| schema.get_field(n).is_err())
+            .map(|n| n.to_string())
+            .collect()
+    };
+
+    let output = json!({
+        "index_path": index_path,
+        "fields": fields,
+        "load_sessions": {
+            "required": LOAD_SESSIONS_REQUIRED_FIELDS,
+            "optional": LOAD_SESSIONS_OPTIONAL_FIELDS,
+            "missing_required": missing(LOAD_SESSIONS_REQUIRED_FIELDS),
+        },
+        "search_tantivy": {
+            "required": SEARCH_REQUIRED_FIELDS,
+            "optional": SEARCH_OPTIONAL_FIELDS,
+            "missing_required": missing(SEARCH_REQUIRED_FIELDS),
+        },
+    });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// --index-path must name an existing Tantivy index; unlike the default location, a
+/// missing one is a typo rather than a fresh install waiting for `aichat search`
+fn check_index_dir(path: &std::path::Path) -> Result<()> {
+    if !path.is_dir() {
+        anyhow::bail!("--index-path {}: no such directory", path.display());
+    }
+    if !path.join("meta.json").is_file() {
+        anyhow::bail!("--index-path {}: not a search index (no meta.json)", path.display());
+    }
+    Ok(())
+}
+
+/// The session with this ID, or the only one whose ID starts with it
+fn find_session_by_id<'a>(sessions: &'a [Session], id: &str) -> Result<&'a Session> {
+    if let Some(session) = sessions.iter().find(|s| s.session_id == id) {
+        return Ok(session);
+    }
+    let matches: Vec<&Session> = sessions.iter().filter(|s| s.session_id.starts_with(id)).collect();
+    match matches.as_slice() {
+        [session] => Ok(session),
+        [] => anyhow::bail!("No session with ID '{}' in the index", id),
+        _ => anyhow::bail!("Session ID prefix '{}' matches {} sessions", id, matches.len()),
+    }
+}
+
+/// --export-md: write a session's transcript to `out_path`, or to stdout
+fn export_transcript(
+    sessions: &[Session],
+    id: &str,
+    format: TranscriptFormat,
+    out_path: Option<&std::path::Path>,
+) -> Result<()> {
+    let session = find_session_by_id(sessions, id)?;
+    let content = std::fs::read_to_string(&session.export_path)
+        .with_context(|| format!("Could not read {}", session.export_path))?;
+    let transcript = render_export(session, &content, format);
+    match out_path {
+        Some(path) => std::fs::write(path, transcript)
+            .with_context(|| format!("Could not write {}", path.display()))?,
+        None => io::stdout().lock().write_all(transcript.as_bytes())?,
+    }
+    Ok(())
+}
+
+// CLI Options
+// ============================================================================
+
+#[derive(Default)]
+struct CliOptions {
+    output_file: Option<std::path::PathBuf>,
+    index_path: Option<std::path::PathBuf>, // --index-path: index directory (default: data dir/search-index)
+    claude_home: Option<String>,
+    codex_home: Option<String>,
+    global_search: bool,
+    filter_dir: Option<String>, // --dir: filter to specific directory (overrides -g)
+    launch_cwd: Option<String>, // --cwd: directory the default scope treats as current
+    num_results: Option<usize>, // --limit / --num-results / -n
+    // Subtractive flags: --no-original, --no-trimmed, --no-rollover exclude types from defaults
+    no_original: bool,
+    no_trimmed: bool,
+    no_rollover: bool,
+    // Additive flag: --sub-agent adds sub-agents to defaults
+    include_sub: bool,
+    // Additive flag: --include-tool-sessions adds tool-generated sessions
+    include_tool: bool,
+    has_attachments: bool, // --has-attachments: only sessions with image/document blocks
+    no_view_tracking: bool, // --no-view-tracking: don't snapshot sessions opened in full view
+    preview_context: bool, // --preview-context: load messages around the match into the preview
+    compact: bool, // --compact: no blank line between list items
+    preview_lines: Option<usize>, // --preview-lines: fixed cap for LAST (MATCH gets twice as many)
+    snippet_chars: Option<usize>, // --snippet-chars: match snippet length (default DEFAULT_SNIPPET_CHARS)
+    theme: ThemeChoice, // --theme: light, dark or auto (from COLORFGBG); NO_COLOR overrides it
+    show_messages: bool, // --show: lines (default) or messages for the list's size column
+    no_restore: bool, // --no-restore: ignore and don't update ~/.cctools/ui-state.json
+    min_lines: Option<i64>,
+    min_messages: Option<i64>, // --min-messages: minimum conversation turns
+    after_date: Option<String>,
+    before_date: Option<String>,
+    agent_filter: Option<String>,
+    all_agents: bool, // --agent all/both: no agent filter, even over a restored one
+    query: Option<String>,
+    json_output: bool,
+    json_all: bool, // --all: acknowledge a full --json dump with no query or -n
+    json_fields: Vec<&'static str>, // --fields a,b,c: keys of each --json line (default JSON_FIELDS)
+    count: bool,    // --count: print the number of matching sessions and exit
+    stats: bool,    // --stats: print aggregate counts of matching sessions and exit
+    sort_key: SortKey,    // --sort key[:asc|:desc] (--by-time = modified)
+    sort_descending: bool,
+    group_by: GroupBy,   // --group-by project|agent|home: header rows between groups
+    phrase_mode: bool,   // --phrase: treat the whole query as one exact phrase
+    regex_mode: bool,    // --regex: match the query as a regular expression
+    fuzzy_mode: bool,    // --fuzzy: fall back to fuzzy project/branch matching on no hits
+    dump_schema: bool,   // --dump-schema: print the index schema as JSON and exit
+    export_session: Option<String>, // --export-md <session-id>: print that session's transcript and exit
+    export_format: Option<TranscriptFormat>, // --export-format md|text (default md)
+    export_file: Option<std::path::PathBuf>, // --output-file: where --export-md writes (default stdout)
+    enter_action: EnterAction, // --enter-action: modal (default), view, or select
+    no_confirm_exit: bool, // --no-confirm-exit: Esc quits without asking even with active filters
+    purge: bool, // --purge: add a permanent delete action next to "move to trash"
+    permalink_template: Option<String>, // --permalink-template: format for copied permalinks
+    permalink: Option<(String, String)>, // positional cctools://agent/id: jump to that session
+    filter_branch: Option<String>, // --branch: filter to specific git branch
+    filter_lang: Option<String>,   // --lang: filter to sessions with detected code language
+    filter_project: Option<String>, // --project: project name contains this (case-insensitive)
+    // Scroll/selection state restoration
+    selected: Option<usize>,    // --selected: restore selected row index
+    list_scroll: Option<usize>, // --scroll: restore scroll offset
+}
+
+/// Print the crate version and the index fields this build reads, so it can be
+/// matched against the indexer that wrote ~/.cctools/search-index.
+fn print_version() {
+    println!("aichat-search {}", env!("CARGO_PKG_VERSION"));
+    println!("index schema: {}", INDEX_SCHEMA_VERSION);
+    let index_dir = cctools_data_dir().map(|dir| dir.join("search-index"));
+    if let Some(dir) = index_dir {
+        if let Ok(version) = std::fs::read_to_string(dir.join("VERSION")) {
+            println!("index on disk: {} ({})", version.trim(), dir.display());
+        }
+    }
+    println!("index fields (required): {}", LOAD_SESSIONS_REQUIRED_FIELDS.join(", "));
+    println!("index fields (optional): {}", LOAD_SESSIONS_OPTIONAL_FIELDS.join(", "));
+}
+
+fn parse_cli_args() -> CliOptions {
+    let args: Vec<String> = std::env::args().collect();
+
+    // Helper to get value after a flag
+    let get_arg_value = |flag: &str| -> Option<String> {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+            .map(|s| s.to_string())
+    };
+
+    // Helper to check if flag exists
+    let has_flag = |flag: &str| -> bool {
+        args.iter().any(|a| a == flag)
+    };
+
+    if has_flag("--version") || has_flag("-V") {
+        print_version();
+        std::process::exit(0);
+    }
+
+    // Output file is the LAST positional arg that's a path (contains / or ends with .json)
+    // Using rfind to get the last match, avoiding --claude-home/--codex-home values;
+    // the values of --index-path and --output-file are never it
+    let output_file = args.iter()
+        .enumerate()
+        .skip(1)  // skip binary name
+        .filter(|(i, _)| !matches!(args[i - 1].as_str(), "--index-path" | "--output-file"))
+        .map(|(_, a)| a)
+        .filter(|a| !a.starts_with('-') && !a.contains("://") && (a.contains('/') || a.ends_with(".json")))
+        .last()
+        .map(std::path::PathBuf::from);
+
+    let index_path = get_arg_value("--index-path").map(|p| std::path::PathBuf::from(expand_tilde(&p)));
+
+    // A cctools:// permalink anywhere on the command line opens that session
+    let permalink = args.iter().skip(1).find_map(|a| parse_permalink(a));
+
+    let claude_home = get_arg_value("--claude-home")
+        .or_else(|| std::env::var("CLAUDE_CONFIG_DIR").ok())
+        .or_else(|| {
+            home_dir().map(|h| h.join(".claude").to_string_lossy().to_string())
+        });
+
+    let codex_home = get_arg_value("--codex-home")
+        .or_else(|| std::env::var("CODEX_HOME").ok())
+        .or_else(|| {
+            home_dir().map(|h| h.join(".codex").to_string_lossy().to_string())
+        });
+
+    let global_search = has_flag("--global") || has_flag("-g");
+
+    // --cwd: scope as if launched from PATH (for wrappers and editor integrations)
+    let current_dir = std::env::current_dir()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let launch_cwd = get_arg_value("--cwd").map(|path| {
+        let path = absolute_path(&path, &current_dir);
+        if !std::path::Path::new(&path).is_dir() {
+            eprintln!("--cwd {}: not a directory", path);
+            std::process::exit(2);
+        }
+        path
+    });
+
+    // --dir overrides -g: filter to specific directory
+    // Format: --dir path or --dir path:branch
+    let dir_arg = get_arg_value("--dir");
+    let (filter_dir, branch_from_dir) = if let Some(ref dir) = dir_arg {
+        // Parse dir:branch format (use rfind to handle paths with colons)
+        let (dir_part, branch_part) = if let Some(colon_idx) = dir.rfind(':') {
+            // Only treat as branch separator if what follows looks like a branch name
+            // (no slashes) and what precedes is a valid path
+            let before = &dir[..colon_idx];
+            let after = &dir[colon_idx + 1..];
+            if !after.contains('/') && !before.is_empty() {
+                (before.to_string(), Some(after.to_string()))
+            } else {
+                (dir.clone(), None)
+            }
+        } else {
+            (dir.clone(), None)
+        };
+
+        // Relative paths are from --cwd when given
+        let expanded_dir = absolute_path(&dir_part, launch_cwd.as_deref().unwrap_or(&current_dir));
+        (Some(expanded_dir), branch_part)
+    } else {
+        (None, None)
+    };
+
+    let num_results = get_arg_value("--limit")
+        .or_else(|| get_arg_value("--num-results"))
+        .or_else(|| get_arg_value("-n"))
+        .and_then(|s| s.parse().ok());
+
+    // Subtractive flags: --no-original, --no-trimmed, --no-rollover
+    // By default all types are shown (except sub-agents); these flags exclude types
+    let no_original = has_flag("--no-original");
+    let no_trimmed = has_flag("--no-trimmed");
+    let no_rollover = has_flag("--no-rollover");
+    // Additive flag: --sub-agent adds sub-agents to defaults
+    let include_sub = has_flag("--sub-agent");
+    let include_tool = has_flag("--include-tool-sessions");
+    let has_attachments = has_flag("--has-attachments");
+    let no_view_tracking = has_flag("--no-view-tracking");
+    let preview_context = has_flag("--preview-context");
+    let compact = has_flag("--compact");
+    let preview_lines = get_arg_value("--preview-lines")
+        .and_then(|s| s.parse().ok());
+    let snippet_chars = get_arg_value("--snippet-chars").map(|n| match n.parse::<usize>() {
+        Ok(n) if n >= MIN_SNIPPET_CHARS => n,
+        _ => {
+            eprintln!("Invalid --snippet-chars '{}': expected a number of at least {}", n, MIN_SNIPPET_CHARS);
+            std::process::exit(2);
+        }
+    });
+    let theme = match get_arg_value("--theme") {
+        Some(name) => ThemeChoice::parse(&name).unwrap_or_else(|| {
+            eprintln!("Unknown --theme '{}': expected light, dark or auto", name);
+            std::process::exit(2);
+        }),
+        None => ThemeChoice::Auto,
+    };
+    let show_messages = match get_arg_value("--show").as_deref() {
+        Some("messages") => true,
+        Some("lines") | None => false,
+        Some(other) => {
+            eprintln!("Unknown --show '{}': expected lines or messages", other);
+            std::process::exit(2);
+        }
+    };
+    let no_restore = has_flag("--no-restore");
+
+    let min_lines = get_arg_value("--min-lines")
+        .and_then(|s| s.parse().ok());
+    let min_messages = get_arg_value("--min-messages")
+        .and_then(|s| s.parse().ok());
+
+    // --since: a relative cutoff (7d, 2w, 3mo, 1y), resolved to a date at launch
+    let after_date = match (get_arg_value("--after"), get_arg_value("--since")) {
+        (Some(_), Some(_)) => {
+            eprintln!("--since and --after can't be combined");
+            std::process::exit(2);
+        }
+        (None, Some(spec)) => match parse_relative_date(&spec) {
+            Some((cutoff, _)) => Some(cutoff),
+            None => {
+                eprintln!("Invalid --since value '{}': use Nd, Nw, Nmo or Ny (e.g. 7d, 2w, 3mo)", spec);
+                std::process::exit(2);
+            }
+        },
+        (after, None) => after,
+    };
+    let before_date = get_arg_value("--before");
+
+    let (agent_filter, all_agents) = match get_arg_value("--agent") {
+        Some(value) => match parse_agent(&value) {
+            Ok(agent) => (agent.map(String::from), agent.is_none()),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(2);
+            }
+        },
+        None => (None, false),
+    };
+
+    let query = get_arg_value("--query");
+
+    // --json-full is --json with each session's conversation added as "turns"
+    let json_full = has_flag("--json-full");
+    let json_output = has_flag("--json") || json_full;
+    let json_all = has_flag("--all");
+    let mut json_fields = match get_arg_value("--fields") {
+        Some(spec) => parse_json_fields(&spec).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }),
+        None => JSON_FIELDS.to_vec(),
+    };
+    if json_full && !json_fields.contains(&JSON_TURNS_FIELD) {
+        json_fields.push(JSON_TURNS_FIELD);
+    }
+    let count = has_flag("--count");
+    let stats = has_flag("--stats");
+    let (sort_key, sort_descending) = match get_arg_value("--sort") {
+        Some(spec) => SortKey::parse(&spec).unwrap_or_else(|| {
+            let keys: Vec<&str> = SortKey::NAMES.iter().map(|(n, _)| *n).collect();
+            eprintln!("Unknown --sort '{}': expected one of {} with optional :asc/:desc", spec, keys.join(", "));
+            std::process::exit(2);
+        }),
+        None if has_flag("--by-time") => (SortKey::Modified, true),
+        None => (SortKey::Relevance, true),
+    };
+    let group_by = match get_arg_value("--group-by") {
+        Some(name) => GroupBy::parse(&name).unwrap_or_else(|| {
+            let names: Vec<&str> = GroupBy::NAMES.iter().map(|(n, _)| *n).collect();
+            eprintln!("Unknown --group-by '{}': expected one of {}", name, names.join(", "));
+            std::process::exit(2);
+        }),
+        None => GroupBy::None,
+    };
+    let phrase_mode = has_flag("--phrase");
+    let regex_mode = has_flag("--regex");
+    let fuzzy_mode = has_flag("--fuzzy");
+    let dump_schema = has_flag("--dump-schema");
+    let export_session = get_arg_value("--export-md");
+    let export_format = get_arg_value("--export-format").map(|name| {
+        TranscriptFormat::parse(&name).unwrap_or_else(|| {
+            eprintln!("Unknown --export-format '{}': expected md or text", name);
+            std::process::exit(2);
+        })
+    });
+    let export_file = get_arg_value("--output-file").map(std::path::PathBuf::from);
+    let enter_action = get_arg_value("--enter-action")
+        .map(|s| EnterAction::parse(&s))
+        .unwrap_or(EnterAction::Modal);
+    let no_confirm_exit = has_flag("--no-confirm-exit");
+    let purge = has_flag("--purge");
+    let permalink_template = get_arg_value("--permalink-template");
+
+    // --branch can be specified separately or as part of --dir (dir:branch)
+    let filter_branch = get_arg_value("--branch").or(branch_from_dir);
+
+    // --lang accepts aliases like "rs" or "py"
+    let filter_lang = get_arg_value("--lang").map(|l| {
+        normalize_code_lang(&l)
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| l.trim().to_lowercase())
+    });
+    let filter_project = get_arg_value("--project").filter(|p| !p.is_empty());
+
+    // Scroll/selection state restoration
+    let selected = get_arg_value("--selected")
+        .and_then(|s| s.parse().ok());
+    let list_scroll = get_arg_value("--scroll")
+        .and_then(|s| s.parse().ok());
+
+    CliOptions {
+        output_file,
+        index_path,
+        claude_home,
+        codex_home,
+        global_search,
+        filter_dir,
+        launch_cwd,
+        num_results,
+        no_original,
+        no_trimmed,
+        no_rollover,
+        include_sub,
+        include_tool,
+        has_attachments,
+        no_view_tracking,
+        preview_context,
+        compact,
+        preview_lines,
+        snippet_chars,
+        theme,
+        show_messages,
+        no_restore,
+        min_lines,
+        min_messages,
+        after_date,
+        before_date,
+        agent_filter,
+        all_agents,
+        query,
+        json_output,
+        json_all,
+        json_fields,
+        count,
+        stats,
+        sort_key,
+        sort_descending,
+        group_by,
+        phrase_mode,
+        regex_mode,
+        fuzzy_mode,
+        dump_schema,
+        export_session,
+        export_format,
+        export_file,
+        enter_action,
+        no_confirm_exit,
+        purge,
+        permalink_template,
+        permalink,
+        filter_branch,
+        filter_lang,
+        filter_project,
+        selected,
+        list_scroll,
+    }
+}
+
+/// --agent value: Some(agent) to filter to one agent, None for `all` / `both`
+fn parse_agent(value: &str) -> Result<Option<&'static str>, String> {
+    match value.to_lowercase().as_str() {
+        "claude" => Ok(Some("claude")),
+        "codex" => Ok(Some("codex")),
+        "all" | "both" => Ok(None),
+        _ => Err(format!("Unknown --agent '{}': expected claude, codex, all or both", value)),
+    }
+}
+
+/// First non-blank line of piped input, trimmed, as the search query
+fn read_piped_query(input: impl BufRead) -> Option<String> {
+    input
+        .lines()
+        .map_while(Result::ok)
+        .map(|line| line.trim().to_string())
+        .find(|line| !line.is_empty())
+}
+
+// Main
+// ============================================================================
+
+fn main() -> Result<()> {
+    let mut cli = parse_cli_args();
+
+    // State files stay in the data directory even when --index-path points elsewhere
+    let data_dir = cctools_data_dir();
+    let index_path = match cli.index_path.clone() {
+        Some(path) => {
+            check_index_dir(&path)?;
+            path
+        }
+        None => data_dir
+            .as_ref()
+            .context("Could not find home directory: set HOME or XDG_DATA_HOME, or pass --index-path")?
+            .join("search-index"),
+    };
+    let state_dir = data_dir.unwrap_or_else(|| index_path.parent().map(Into::into).unwrap_or_default());
+
+    if cli.export_format.is_some() && cli.export_session.is_none() {
+        eprintln!("--export-format needs --export-md <session-id>");
+        std::process::exit(2);
+    }
+
+    // --json, --count, --stats and --export-md write only their result to stdout, for scripts
+    let scripted = cli.json_output || cli.count || cli.stats || cli.export_session.is_some();
+
+    // ...and take the query from a pipe with `--query -`: `echo "auth bug" | aichat-search --json --query -`.
+    // Only when asked: agents, CI and `ssh -T` leave stdin an open pipe that never ends
+    if cli.query.as_deref() == Some("-") {
+        if !scripted {
+            eprintln!("--query - reads the query from stdin, which needs --json, --count, --stats or --export-md");
+            std::process::exit(2);
+        }
+        cli.query = read_piped_query(io::stdin().lock());
+    }
+    // The TUI reads keys from the terminal, which crossterm reopens when stdin is a pipe
+    if !scripted && !io::stdin().is_terminal() && std::fs::File::open("/dev/tty").is_err() {
+        anyhow::bail!("No terminal for the interactive UI; use --json, --count or --stats in pipelines");
+    }
+
+    // Interactive launches pick up the filters left by the last one (--no-restore opts out)
+    let ui_state_path = state_dir.join("ui-state.json");
+    let restore_ui_state = !cli.no_restore && !scripted;
+    let mut saved_date_format = None;
+    if restore_ui_state {
+        if let Some(state) = UiState::load(&ui_state_path) {
+            saved_date_format = state.date_format;
+            state.apply_defaults(&mut cli);
+        }
+    }
+
+    // Developer command: inspect the index schema without loading sessions
+    if cli.dump_schema {
+        return dump_schema(&index_path.to_string_lossy());
+    }
+
+    const SESSION_LIMIT: usize = 100_000;
+    // Only --json prints message text for every result. Big indexes take a moment,
+    // so interactive launches get a spinner instead of a blank terminal.
+    let loaded = with_loading_spinner(!scripted && io::stderr().is_terminal(), |progress| {
+        load_sessions(
+            index_path.to_str().unwrap(),
+            SESSION_LIMIT,
+            cli.json_output && !cli.stats,
+            Some(progress),
+        )
+    });
+    // No index yet is an empty result with a hint, so `--json` still prints `[]`;
+    // an index that is there but unreadable is an error
+    let (sessions, index_missing) = match loaded {
+        Ok(sessions) => (sessions, false),
+        Err(e) if matches!(e.downcast_ref::<IndexError>(), Some(IndexError::Missing(_))) => {
+            eprintln!("{}", e);
+            (Vec::new(), true)
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Warn if we hit the limit - sessions may have been truncated
+    let truncated = sessions.len() >= SESSION_LIMIT;
+    if truncated && !scripted {
+        eprintln!("⚠️  WARNING: Session limit ({}) reached!", SESSION_LIMIT);
+        eprintln!("⚠️  Some sessions may have been dropped.");
+        eprintln!();
+    }
+
+    // Export mode - one session's transcript, looked up among all indexed sessions
+    if let Some(ref id) = cli.export_session {
+        let format = cli.export_format.unwrap_or(TranscriptFormat::Markdown);
+        return export_transcript(&sessions, id, format, cli.export_file.as_deref());
+    }
+
+    // --stats reports the zero counts itself
+    if sessions.is_empty() && !cli.stats {
+        if cli.count {
+            println!("0");
+            return Ok(());
+        }
+        if cli.json_output {
+            println!("[]");
+            return Ok(());
+        }
+        if !index_missing {
+            eprintln!("No sessions found. Run 'aichat search' to auto-index.");
+        }
+        return Ok(());
+    }
+
+    // Show home filters (only for TUI mode)
+    if !scripted {
+        if let Some(ref home) = cli.claude_home {
+            eprintln!("Claude home filter: {}", home);
+        }
+        if let Some(ref home) = cli.codex_home {
+            eprintln!("Codex home filter: {}", home);
+        }
+
+        let claude_count = sessions.iter().filter(|s| s.agent != "codex").count();
+        let codex_count = sessions.iter().filter(|s| s.agent == "codex").count();
+        eprintln!("Sessions in index: {} Claude, {} Codex", claude_count, codex_count);
+    }
+
+    // Create app with CLI options pre-configured
+    let mut app = App::new_with_options(
+        sessions,
+        index_path.to_string_lossy().to_string(),
+        &cli,
+    );
+
+    if scripted && app.regex_error.is_some() {
+        anyhow::bail!("Invalid --regex pattern: {}", app.query);
+    }
+    if let Some(err) = app.search_error.as_ref().filter(|_| scripted) {
+        anyhow::bail!("Keyword {}", err);
+    }
+
+    // Stats mode - same filters as --json, aggregated (as one JSON object with --json)
+    if cli.stats {
+        return write_stats(io::BufWriter::new(io::stdout().lock()), &app, cli.json_output).map_err(Into::into);
+    }
+
+    // Count mode - same filters as --json, but only the number of matches
+    if cli.count {
+        // Stdout stays a plain number for scripts; say on stderr that it may be low
+        if truncated {
+            eprintln!(
+                "⚠️  At least {}: the index exceeds the {} session limit, so more may match.",
+                app.filtered.len(),
+                SESSION_LIMIT
+            );
+        }
+        println!("{}", app.filtered.len());
+        return Ok(());
+    }
+
+    // JSON output mode - output filtered results and exit
+    if cli.json_output {
+        return output_json(&mut app, cli.json_all, &cli.json_fields);
+    }
+
+    // stderr is hidden once the alternate screen is up, so repeat the warning in the TUI
+    if truncated {
+        app.truncation_warning = Some(format!(
+            "Showing {} of possibly more sessions: index exceeds the {} session limit",
+            app.sessions.len(),
+            SESSION_LIMIT
+        ));
+    }
+
+    if let Some((ref agent, ref id)) = cli.permalink {
+        app.jump_to_session(agent, id);
+    }
+    app.date_format = saved_date_format;
+
+    // Remapped list-view keys; a line that can't be used keeps that key's default
+    let keys_path = cctools_config_dir().unwrap_or_else(|| state_dir.clone()).join("keys.toml");
+    let (keymap, problems) = KeyMap::load(&keys_path);
+    app.keymap = keymap;
+    if let Some(problem) = problems.first() {
+        let more = if problems.len() > 1 { format!(" (+{} more)", problems.len() - 1) } else { String::new() };
+        app.status_message = Some(format!("keys.toml {}{}", problem, more));
+    }
+
+    // Interactive TUI mode
+    let theme = Theme::resolve(
+        cli.theme,
+        std::env::var("NO_COLOR").ok().as_deref(),
+        std::env::var("COLORFGBG").ok().as_deref(),
+    );
+    enable_raw_mode()?;
+    let mut stdout = stdout();
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    loop {
+        app.poll_summary_job();
+        app.poll_pending_filter();
+        if let Some(path) = app.pending_editor.take() {
+            app.status_message = open_in_editor(&mut terminal, &path)?;
+        }
+        if app.force_redraw {
+            app.force_redraw = false;
+            terminal.clear()?;
+        }
+        terminal.draw(|f| render(f, &mut app, &theme))?;
+
+        if app.should_quit {
+            break;
+        }
+
+        let mut resized = false;
+        while event::poll(Duration::from_millis(0))? {
+            let event = event::read()?;
+            if let Event::Paste(ref text) = event {
+                app.on_paste(text);
+                continue;
+            }
+            if let Event::Resize(_, _) = event {
+                app.on_resize();
+                resized = true;
+                continue;
+            }
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press {
+                    // Clear status message on any keypress
+                    app.status_message = None;
+
+                    // Typing is debounced; every other key acts on up-to-date results
+                    let is_typing = matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace)
+                        && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+                    if !is_typing {
+                        app.flush_pending_filter();
+                    }
+
+                    // C-l repaints a garbled screen, in every mode, as in less and vim
+                    if app.keymap.action(&key) == Some(KeyAction::Redraw) {
+                        app.force_redraw = true;
+                        continue;
+                    }
+
+                    // Handle exit confirmation dialog
+                    if app.confirming_exit {
+                        match key.code {
+                            KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+                                app.should_quit = true;
+                            }
+                            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                                app.confirming_exit = false;
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+
+                    // Handle session details popup - any close key dismisses it
+                    if app.details_modal_open {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                                app.details_modal_open = false;
+                            }
+                            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                app.details_modal_open = false;
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+
+                    // Handle relevance explanation popup - any close key dismisses it
+                    if app.explain_modal_open {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                                app.explain_modal_open = false;
+                            }
+                            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                app.explain_modal_open = false;
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+
+                    // Handle trash / delete confirmation dialog
+                    if let Some(mode) = app.confirming_delete {
+                        match key.code {
                             KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
-                                // Execute delete action
-                                if let Some(session) = app.selected_session() {
+                                if mode == DeleteMode::Trash {
+                                    app.trash_selected();
+                                } else if let Some(session) = app.selected_session() {
+                                    // Permanent delete: Python removes the file and prunes the index
                                     app.should_select = Some(session.clone());
                                     app.selected_action = Some("delete".to_string());
                                     app.should_quit = true;
                                 }
-                                app.confirming_delete = false;
+                                app.confirming_delete = None;
                             }
                             KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
-                                app.confirming_delete = false;
+                                app.confirming_delete = None;
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+
+                    // Handle "changes since last view" popup over the full view
+                    if app.changes_modal_open {
+                        match key.code {
+                            KeyCode::Enter => {
+                                // Jump to the first line added since the last view
+                                if let Some(ref changes) = app.view_changes {
+                                    if changes.appended.is_some() {
+                                        let line = changes.old_lines;
+                                        app.scroll_to_line(line);
+                                    }
+                                }
+                                app.changes_modal_open = false;
+                            }
+                            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('c') => {
+                                app.changes_modal_open = false;
                             }
                             _ => {}
                         }
@@ -4213,23 +10086,55 @@ fn main() -> Result<()> {
                     }
 
                     // Handle full view mode separately
-                    if app.full_view_mode {
-                        if app.view_search_mode {
+                    if app.full_view_open() {
+                        if app.tool_timeline_mode {
+                            // Tool call timeline - pick a call to jump to in the transcript
+                            match key.code {
+                                KeyCode::Up | KeyCode::Char('k') => {
+                                    app.tool_timeline_selected = app.tool_timeline_selected.saturating_sub(1);
+                                }
+                                KeyCode::Down | KeyCode::Char('j') => {
+                                    app.tool_timeline_selected = (app.tool_timeline_selected + 1)
+                                        .min(app.tool_timeline.len().saturating_sub(1));
+                                }
+                                KeyCode::PageUp => {
+                                    app.tool_timeline_selected = app.tool_timeline_selected.saturating_sub(10);
+                                }
+                                KeyCode::PageDown => {
+                                    app.tool_timeline_selected = (app.tool_timeline_selected + 10)
+                                        .min(app.tool_timeline.len().saturating_sub(1));
+                                }
+                                KeyCode::Enter => {
+                                    if let Some(call) = app.tool_timeline.get(app.tool_timeline_selected) {
+                                        let line = call.line;
+                                        app.scroll_to_line(line);
+                                    }
+                                    app.tool_timeline_mode = false;
+                                }
+                                KeyCode::Char('t') | KeyCode::Esc | KeyCode::Char('q') => {
+                                    app.tool_timeline_mode = false;
+                                }
+                                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                    app.should_quit = true;
+                                }
+                                _ => {}
+                            }
+                        } else if app.top_level() == Some(ViewLevel::ViewSearch) {
                             // Search input mode
                             match key.code {
                                 KeyCode::Esc => {
                                     // Cancel search input, keep existing pattern if any
-                                    app.view_search_mode = false;
+                                    app.back();
                                 }
                                 KeyCode::Enter => {
                                     // Confirm search and jump to first match
-                                    app.view_search_mode = false;
+                                    app.back();
                                     if app.view_search_pattern.is_empty() {
                                         // Empty pattern: activate query nav mode (blue/original)
                                         app.query_nav_mode = true;
                                         if !app.query_match_lines.is_empty() {
                                             app.query_match_current = 0;
-                                            app.full_content_scroll = app.query_match_lines[0];
+                                            app.scroll_to_line(app.query_match_lines[0]);
                                         }
                                     } else {
                                         // Non-empty pattern: activate view search mode (yellow)
@@ -4237,13 +10142,19 @@ fn main() -> Result<()> {
                                         app.update_view_search_matches();
                                         if !app.view_search_matches.is_empty() {
                                             app.view_search_current = 0;
-                                            app.full_content_scroll = app.view_search_matches[0];
+                                            app.scroll_to_line(app.view_search_matches[0]);
                                         }
                                     }
                                 }
                                 KeyCode::Backspace => {
                                     app.view_search_pattern.pop();
                                 }
+                                KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                    app.view_search_flags.whole_word = !app.view_search_flags.whole_word;
+                                }
+                                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                    app.view_search_flags.case_sensitive = !app.view_search_flags.case_sensitive;
+                                }
                                 KeyCode::Char(c) => {
                                     app.view_search_pattern.push(c);
                                 }
@@ -4287,15 +10198,18 @@ fn main() -> Result<()> {
                                     // Start new search
                                     app.view_search_pattern.clear();
                                     app.query_nav_mode = false;
-                                    app.view_search_mode = true;
+                                    app.open_level(ViewLevel::ViewSearch);
+                                }
+                                KeyCode::Char('m') => {
+                                    // Toggle search scope: all lines vs message lines only
+                                    app.toggle_view_search_scope();
                                 }
                                 KeyCode::Char(' ') | KeyCode::Char('q') => {
                                     // Exit view mode, clear search
                                     app.view_search_pattern.clear();
                                     app.view_search_matches.clear();
-                                    app.view_search_mode = false;
                                     app.query_nav_mode = false;
-                                    app.full_view_mode = false;
+                                    app.back();
                                 }
                                 KeyCode::Up | KeyCode::Char('k') => {
                                     app.full_content_scroll = app.full_content_scroll.saturating_sub(1);
@@ -4304,17 +10218,16 @@ fn main() -> Result<()> {
                                     app.full_content_scroll = app.full_content_scroll.saturating_add(1);
                                 }
                                 KeyCode::PageUp => {
-                                    app.full_content_scroll = app.full_content_scroll.saturating_sub(20);
+                                    app.full_content_scroll = app.full_content_scroll.saturating_sub(app.full_view_height);
                                 }
                                 KeyCode::PageDown => {
-                                    app.full_content_scroll = app.full_content_scroll.saturating_add(20);
+                                    app.full_content_scroll = app.full_content_scroll.saturating_add(app.full_view_height);
                                 }
                                 KeyCode::Home => {
                                     app.full_content_scroll = 0;
                                 }
                                 KeyCode::End => {
-                                    let lines = app.full_content.lines().count();
-                                    app.full_content_scroll = lines.saturating_sub(20);
+                                    app.full_content_scroll = app.full_view_total_rows().saturating_sub(app.full_view_height);
                                 }
                                 KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                                     app.should_quit = true;
@@ -4333,11 +10246,41 @@ fn main() -> Result<()> {
                                     app.query_match_prev();
                                 }
                                 KeyCode::Char('/') => {
-                                    app.view_search_mode = true;
+                                    app.open_level(ViewLevel::ViewSearch);
                                     app.view_search_pattern.clear();
                                 }
+                                KeyCode::Char('c')
+                                    if !key.modifiers.contains(KeyModifiers::CONTROL) && app.view_changes.is_some() =>
+                                {
+                                    // Show what changed since this session was last viewed
+                                    app.changes_modal_open = true;
+                                }
+                                KeyCode::Char('m') => {
+                                    // Set search scope ahead of the next / search
+                                    app.toggle_view_search_scope();
+                                }
+                                KeyCode::Char('t') => {
+                                    // List the session's tool calls
+                                    app.toggle_tool_timeline();
+                                }
+                                KeyCode::Char('r') => {
+                                    // Show or hide Codex reasoning
+                                    app.toggle_reasoning();
+                                }
+                                KeyCode::Char('e') => {
+                                    // Open the raw session file in $EDITOR
+                                    app.pending_editor = app.selected_session().map(|s| s.export_path.clone());
+                                }
+                                KeyCode::Char('z') => {
+                                    // Collapse or expand every tool result
+                                    app.toggle_all_tool_results();
+                                }
+                                KeyCode::Enter => {
+                                    // Collapse or expand the tool result at the top of the view
+                                    app.toggle_tool_result_at_top();
+                                }
                                 KeyCode::Char(' ') | KeyCode::Esc | KeyCode::Char('q') => {
-                                    app.full_view_mode = false;
+                                    app.back();
                                     app.query_nav_mode = false;
                                 }
                                 KeyCode::Up | KeyCode::Char('k') => {
@@ -4347,17 +10290,16 @@ fn main() -> Result<()> {
                                     app.full_content_scroll = app.full_content_scroll.saturating_add(1);
                                 }
                                 KeyCode::PageUp => {
-                                    app.full_content_scroll = app.full_content_scroll.saturating_sub(20);
+                                    app.full_content_scroll = app.full_content_scroll.saturating_sub(app.full_view_height);
                                 }
                                 KeyCode::PageDown => {
-                                    app.full_content_scroll = app.full_content_scroll.saturating_add(20);
+                                    app.full_content_scroll = app.full_content_scroll.saturating_add(app.full_view_height);
                                 }
                                 KeyCode::Home => {
                                     app.full_content_scroll = 0;
                                 }
                                 KeyCode::End => {
-                                    let lines = app.full_content.lines().count();
-                                    app.full_content_scroll = lines.saturating_sub(20);
+                                    app.full_content_scroll = app.full_view_total_rows().saturating_sub(app.full_view_height);
                                 }
                                 KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                                     app.should_quit = true;
@@ -4445,7 +10387,7 @@ fn main() -> Result<()> {
                         }
                     } else if app.filter_modal_open {
                         // Handle filter modal
-                        let items = FilterMenuItem::all();
+                        let items = FilterMenuItem::matching(app.filter_modal_query.as_deref());
 
                         // Helper to apply filter by item
                         let apply_filter = |app: &mut App, item: &FilterMenuItem| {
@@ -4456,8 +10398,14 @@ fn main() -> Result<()> {
                                     app.include_sub = false;
                                     app.include_trimmed = true;
                                     app.include_continued = true;
+                                    app.include_tool = false;
+                                    app.filter_has_attachments = false;
                                     app.filter_agent = None;
                                     app.filter_min_lines = None;
+                                    app.filter_min_messages = None;
+                                    app.filter_lang = None;
+                                    app.filter_claude_home = None;
+                                    app.filter_project = None;
                                     app.filter();
                                 }
                                 FilterMenuItem::IncludeOriginal => {
@@ -4476,6 +10424,14 @@ fn main() -> Result<()> {
                                     app.include_continued = !app.include_continued;
                                     app.filter();
                                 }
+                                FilterMenuItem::IncludeTool => {
+                                    app.include_tool = !app.include_tool;
+                                    app.filter();
+                                }
+                                FilterMenuItem::HasAttachments => {
+                                    app.filter_has_attachments = !app.filter_has_attachments;
+                                    app.filter();
+                                }
                                 FilterMenuItem::AgentAll => {
                                     app.filter_agent = None;
                                     app.filter();
@@ -4493,11 +10449,53 @@ fn main() -> Result<()> {
                                     app.input_mode = Some(InputMode::MinLines);
                                     app.input_buffer.clear();
                                 }
+                                FilterMenuItem::MinMessages => {
+                                    app.filter_modal_open = false;
+                                    app.input_mode = Some(InputMode::MinMessages);
+                                    app.input_buffer.clear();
+                                }
+                                FilterMenuItem::Language => {
+                                    // Cycle Any -> each discovered language -> Any
+                                    let next = match &app.filter_lang {
+                                        None => 0,
+                                        Some(lang) => app
+                                            .available_langs
+                                            .iter()
+                                            .position(|l| l == lang)
+                                            .map_or(0, |i| i + 1),
+                                    };
+                                    app.filter_lang = app.available_langs.get(next).cloned();
+                                    app.filter();
+                                }
+                                FilterMenuItem::ClaudeHome => {
+                                    // Cycle Any -> each Claude home seen in the index -> Any
+                                    let next = match &app.filter_claude_home {
+                                        None => 0,
+                                        Some(home) => app
+                                            .available_homes
+                                            .iter()
+                                            .position(|h| h == home)
+                                            .map_or(0, |i| i + 1),
+                                    };
+                                    app.filter_claude_home = app.available_homes.get(next).cloned();
+                                    app.filter();
+                                }
+                                FilterMenuItem::Project => {
+                                    app.filter_modal_open = false;
+                                    app.input_mode = Some(InputMode::Project);
+                                    app.input_buffer = app.filter_project.clone().unwrap_or_default();
+                                }
                                 FilterMenuItem::AfterDate => {
                                     app.filter_modal_open = false;
                                     app.input_mode = Some(InputMode::AfterDate);
                                     app.input_buffer.clear();
                                 }
+                                FilterMenuItem::Since => {
+                                    // Pre-filled, so Enter alone means the last week
+                                    app.filter_modal_open = false;
+                                    app.input_mode = Some(InputMode::Since);
+                                    app.input_buffer = "7d".to_string();
+                                }
                                 FilterMenuItem::BeforeDate => {
                                     app.filter_modal_open = false;
                                     app.input_mode = Some(InputMode::BeforeDate);
@@ -4506,26 +10504,47 @@ fn main() -> Result<()> {
                             }
                         };
 
+                        // While `/` filtering, letters go to the filter instead of being shortcuts
+                        let typing = app.filter_modal_query.is_some();
                         match key.code {
+                            // Esc drops the typed filter first, then closes the modal
+                            KeyCode::Esc if typing => {
+                                app.filter_modal_query = None;
+                                app.filter_modal_selected = 0;
+                            }
                             KeyCode::Esc => {
                                 app.filter_modal_open = false;
                             }
-                            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.filter_modal_open = false;
+                            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                app.filter_modal_open = false;
+                            }
+                            KeyCode::Char('/') if !typing => {
+                                app.filter_modal_query = Some(String::new());
+                            }
+                            KeyCode::Backspace if typing => {
+                                if let Some(query) = app.filter_modal_query.as_mut() {
+                                    query.pop();
+                                }
+                                app.filter_modal_selected = 0;
+                            }
+                            KeyCode::Char(c) if typing && !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                if let Some(query) = app.filter_modal_query.as_mut() {
+                                    query.push(c);
+                                }
+                                app.filter_modal_selected = 0;
                             }
                             KeyCode::Up | KeyCode::Char('k') => {
-                                if app.filter_modal_selected > 0 {
-                                    app.filter_modal_selected -= 1;
-                                }
+                                app.filter_modal_selected = app.filter_modal_selected.saturating_sub(1);
                             }
                             KeyCode::Down | KeyCode::Char('j') => {
-                                if app.filter_modal_selected < items.len() - 1 {
+                                if app.filter_modal_selected + 1 < items.len() {
                                     app.filter_modal_selected += 1;
                                 }
                             }
                             KeyCode::Enter | KeyCode::Char(' ') => {
-                                let item = items[app.filter_modal_selected].clone();
-                                apply_filter(&mut app, &item);
+                                if let Some(item) = items.get(app.filter_modal_selected).cloned() {
+                                    apply_filter(&mut app, &item);
+                                }
                             }
                             // Shortcut keys
                             KeyCode::Char(c) => {
@@ -4535,12 +10554,12 @@ fn main() -> Result<()> {
                             }
                             _ => {}
                         }
-                    } else if app.action_mode.is_some() {
+                    } else if app.top_level() == Some(ViewLevel::ActionMenu) {
                         // Handle action menu modal
-                        let items = ActionMenuItem::all();
+                        let items = ActionMenuItem::all(app.purge_enabled);
                         match key.code {
                             KeyCode::Esc => {
-                                app.action_mode = None;
+                                app.back();
                                 app.action_modal_selected = 0;
                             }
                             KeyCode::Up | KeyCode::Char('k') => {
@@ -4582,6 +10601,12 @@ fn main() -> Result<()> {
                                             app.filter();
                                         }
                                     }
+                                    InputMode::MinMessages => {
+                                        if let Ok(num) = app.input_buffer.parse::<i64>() {
+                                            app.filter_min_messages = if num > 0 { Some(num) } else { None };
+                                            app.filter();
+                                        }
+                                    }
                                     InputMode::Agent => {}
                                     InputMode::JumpToLine => {
                                         if let Ok(row) = app.input_buffer.parse::<usize>() {
@@ -4598,6 +10623,16 @@ fn main() -> Result<()> {
                                         }
                                         app.filter();
                                     }
+                                    InputMode::Since => {
+                                        if app.input_buffer.is_empty() {
+                                            app.filter_after_date = None;
+                                            app.filter_after_date_display = None;
+                                        } else if let Some((cmp, disp)) = parse_relative_date(&app.input_buffer) {
+                                            app.filter_after_date = Some(cmp);
+                                            app.filter_after_date_display = Some(disp);
+                                        }
+                                        app.filter();
+                                    }
                                     InputMode::BeforeDate => {
                                         if app.input_buffer.is_empty() {
                                             app.filter_before_date = None;
@@ -4633,8 +10668,7 @@ fn main() -> Result<()> {
                                         } else {
                                             // Expand ~ to home directory
                                             let path = if dir_part.starts_with('~') {
-                                                let home = std::env::var("HOME").unwrap_or_default();
-                                                format!("{}{}", home, &dir_part[1..])
+                                                expand_tilde(&dir_part)
                                             } else if dir_part.starts_with('/') {
                                                 dir_part
                                             } else {
@@ -4668,6 +10702,25 @@ fn main() -> Result<()> {
                                         }
                                         app.filter();
                                     }
+                                    InputMode::Project => {
+                                        let name = app.input_buffer.trim();
+                                        app.filter_project = (!name.is_empty()).then(|| name.to_string());
+                                        app.filter();
+                                    }
+                                    InputMode::KeepTurns => {
+                                        let keep = app.input_buffer.parse::<usize>().unwrap_or(0);
+                                        if let (true, Some(session)) = (keep > 0, app.selected_session()) {
+                                            app.status_message = Some(match write_kept_turns(session, keep) {
+                                                Ok(None) => format!("Session has no more than {} turns; nothing to trim", 2 * keep),
+                                                Ok(Some((path, dropped))) => format!(
+                                                    "Dropped {} middle turns; wrote {} (indexed on next search)",
+                                                    dropped,
+                                                    path.display()
+                                                ),
+                                                Err(e) => format!("Failed to write trimmed session: {}", e),
+                                            });
+                                        }
+                                    }
                                 }
                                 app.input_mode = None;
                                 app.input_buffer.clear();
@@ -4690,14 +10743,11 @@ fn main() -> Result<()> {
                                 app.input_mode = None;
                                 app.input_buffer.clear();
                             }
-                            KeyCode::Char(c) if c.is_ascii_digit() && (mode == InputMode::MinLines || mode == InputMode::JumpToLine) => {
-                                app.input_buffer.push(c);
-                            }
-                            KeyCode::Char(c) if mode == InputMode::AfterDate || mode == InputMode::BeforeDate || mode == InputMode::ScopeDir || mode == InputMode::Branch => {
-                                // Accept any character for flexible input
-                                app.input_buffer.push(c);
+                            KeyCode::Char(c) => {
+                                // Digits for numeric prompts, any printable char for dates/paths
+                                app.push_input_char(c);
                             }
-                            KeyCode::Backspace if mode == InputMode::MinLines || mode == InputMode::JumpToLine || mode == InputMode::AfterDate || mode == InputMode::BeforeDate || mode == InputMode::ScopeDir || mode == InputMode::Branch => {
+                            KeyCode::Backspace if mode == InputMode::MinLines || mode == InputMode::MinMessages || mode == InputMode::JumpToLine || mode == InputMode::AfterDate || mode == InputMode::Since || mode == InputMode::BeforeDate || mode == InputMode::ScopeDir || mode == InputMode::Branch || mode == InputMode::Project || mode == InputMode::KeepTurns => {
                                 app.input_buffer.pop();
                             }
                             _ => {}
@@ -4712,8 +10762,13 @@ fn main() -> Result<()> {
                                 app.include_sub = false;
                                 app.include_trimmed = true;
                                 app.include_continued = true;
+                                app.include_tool = false;
+                                app.filter_has_attachments = false;
                                 app.filter_agent = None;
                                 app.filter_min_lines = None;
+                                app.filter_min_messages = None;
+                                app.filter_lang = None;
+                                app.filter_project = None;
                                 app.filter_after_date = None;
                                 app.filter_after_date_display = None;
                                 app.filter_before_date = None;
@@ -4777,26 +10832,42 @@ fn main() -> Result<()> {
                             _ => {}
                         }
                     } else {
-                        // Normal mode
+                        // Normal mode: bound actions first (keys.toml can remap them), then
+                        // the fixed keys, and anything else types into the query
+                        if let Some(action) = app.keymap.action(&key) {
+                            app.run_key_action(action);
+                            continue;
+                        }
                         match key.code {
-                            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                app.should_quit = true;
-                            }
-                            KeyCode::Char(':') => {
-                                app.command_mode = true;
-                            }
+                            KeyCode::Char(' ') if app.multi_select => app.toggle_mark(),
                             KeyCode::Char(' ') => {
                                 // Space: add to query (for multi-word search)
                                 app.on_char(' ');
                             }
                             KeyCode::Esc => app.on_escape(),
+                            KeyCode::Enter
+                                if key.modifiers.intersects(KeyModifiers::ALT | KeyModifiers::CONTROL)
+                                    && app.selected_session().is_some() =>
+                            {
+                                // Alt/Ctrl-Enter: always open the action menu
+                                app.open_level(ViewLevel::ActionMenu);
+                            }
                             KeyCode::Enter => {
                                 // If there's pending jump input, use it
                                 if !app.jump_input.is_empty() {
                                     app.process_jump_enter();
+                                } else if app.on_enter_marked() {
+                                    // Marked sessions are output as a batch, skipping the action menu
                                 } else if app.selected_session().is_some() {
-                                    // Enter action mode to choose view or actions
-                                    app.action_mode = Some(ActionMode::ActionMenu);
+                                    match app.enter_action {
+                                        EnterAction::Modal => {
+                                            app.open_level(ViewLevel::ActionMenu);
+                                        }
+                                        EnterAction::View => {
+                                            execute_action_item(&mut app, ActionMenuItem::View);
+                                        }
+                                        EnterAction::Select => app.on_enter(),
+                                    }
                                 }
                             }
                             KeyCode::Up => app.on_up(),
@@ -4815,28 +10886,7 @@ fn main() -> Result<()> {
                                     app.preview_scroll = 0;
                                 }
                             }
-                            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => app.page_up(10),
                             KeyCode::Backspace => app.on_backspace(),
-                            KeyCode::Char('/') => {
-                                // Open scope modal
-                                app.scope_modal_open = true;
-                                app.scope_modal_selected = 0;
-                            }
-                            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                // Open filter modal
-                                app.filter_modal_open = true;
-                                app.filter_modal_selected = 0;
-                            }
-                            KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                // Enter jump mode (go to line)
-                                app.input_mode = Some(InputMode::JumpToLine);
-                                app.input_buffer.clear();
-                            }
-                            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                // Toggle sort mode: relevance <-> time
-                                app.sort_by_time = !app.sort_by_time;
-                                app.filter(); // Re-sort results
-                            }
                             KeyCode::Char(c) => app.on_char(c),
                             _ => {}
                         }
@@ -4845,101 +10895,1650 @@ fn main() -> Result<()> {
             }
         }
 
-        std::thread::sleep(Duration::from_millis(16));
+        // Redraw at the new size right away rather than after the frame delay
+        if !resized {
+            std::thread::sleep(Duration::from_millis(16));
+        }
+    }
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen)?;
+
+    if restore_ui_state {
+        UiState::from_app(&app).save(&ui_state_path);
+    }
+
+    for text in &app.manual_copies {
+        eprintln!("Clipboard unavailable; copy manually: {}", text);
+    }
+
+    if let Some(sessions) = app.should_select_many {
+        // Multi-select: a plain array of the marked sessions
+        let json = serde_json::to_string(&sessions)?;
+        if let Some(ref out_path) = cli.output_file {
+            std::fs::write(out_path, &json)?;
+        } else {
+            println!("{}", json);
+        }
+    } else if let Some(session) = app.should_select {
+        // Output session with action and filter state for Python handler
+        let output = serde_json::json!({
+            "session": session,
+            "action": app.selected_action.as_deref().unwrap_or("menu"),
+            "filter_state": {
+                "query": app.query,
+                "scope_global": app.scope_global,
+                "filter_dir": app.filter_dir,
+                "include_original": app.include_original,
+                "include_sub": app.include_sub,
+                "include_tool": app.include_tool,
+                "filter_has_attachments": app.filter_has_attachments,
+                "include_trimmed": app.include_trimmed,
+                "include_continued": app.include_continued,
+                "filter_agent": app.filter_agent,
+                "filter_min_lines": app.filter_min_lines,
+                "filter_min_messages": app.filter_min_messages,
+                "filter_after_date": app.filter_after_date,
+                "filter_before_date": app.filter_before_date,
+                "filter_branch": app.filter_branch,
+                "filter_lang": app.filter_lang,
+                "filter_project": app.filter_project,
+                "sort": format!(
+                    "{}:{}",
+                    app.sort_key.name(),
+                    if app.sort_descending { "desc" } else { "asc" }
+                ),
+                "phrase_mode": app.phrase_mode,
+                "regex_mode": app.regex_mode,
+                "fuzzy_mode": app.fuzzy_mode,
+                "selected": app.selected,
+                "list_scroll": app.list_scroll,
+            }
+        });
+        let json = serde_json::to_string(&output)?;
+        if let Some(ref out_path) = cli.output_file {
+            std::fs::write(out_path, &json)?;
+        } else {
+            println!("{}", json);
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An in-RAM index of (session_id, content) documents with the fields search_tantivy reads
+    fn test_search_index(docs: &[(&str, &str)]) -> SearchIndex {
+        use tantivy::schema::{Schema, STORED, STRING, TEXT};
+        let mut builder = Schema::builder();
+        let session_id = builder.add_text_field("session_id", STRING | STORED);
+        let content = builder.add_text_field("content", TEXT | STORED);
+        let modified = builder.add_text_field("modified", STORED);
+        let index = Index::create_in_ram(builder.build());
+        let mut writer: tantivy::IndexWriter = index.writer(15_000_000).unwrap();
+        for &(id, text) in docs {
+            writer
+                .add_document(tantivy::doc!(session_id => id, content => text, modified => "2025-01-01T00:00:00Z"))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        let reader = index.reader().unwrap();
+        SearchIndex { index, reader }
+    }
+
+    #[test]
+    fn test_truncate_with_max_zero() {
+        // Issue #25: max=0 causes usize underflow in truncate()
+        // When max=0, the expression max-1 wraps to usize::MAX
+        let result = truncate("hello world", 0);
+        assert_eq!(result, "", "truncate with max=0 should return empty string");
+    }
+
+    #[test]
+    fn test_truncate_with_max_one() {
+        // Edge case: max=1 should just show the ellipsis
+        let result = truncate("hello world", 1);
+        assert_eq!(result, "…", "truncate with max=1 should return just ellipsis");
+    }
+
+    #[test]
+    fn test_truncate_normal_case() {
+        // Normal case: string longer than max
+        let result = truncate("hello world", 6);
+        assert_eq!(result, "hello…", "truncate should cut and add ellipsis");
+    }
+
+    #[test]
+    fn test_truncate_short_string() {
+        // String shorter than max should be returned as-is
+        let result = truncate("hi", 10);
+        assert_eq!(result, "hi", "short strings should not be truncated");
+    }
+
+    #[test]
+    fn test_truncate_exact_length() {
+        // String exactly at max length
+        let result = truncate("hello", 5);
+        assert_eq!(result, "hello", "string at exact max length should not be truncated");
+    }
+
+    #[test]
+    fn test_truncate_empty_string() {
+        // Empty string with any max
+        let result = truncate("", 10);
+        assert_eq!(result, "", "empty string should remain empty");
+    }
+
+    #[test]
+    fn test_truncate_empty_string_zero_max() {
+        // Empty string with max=0
+        let result = truncate("", 0);
+        assert_eq!(result, "", "empty string with max=0 should remain empty");
+    }
+
+    #[test]
+    fn test_find_match_windows_distinct_matches() {
+        // Matches far apart get their own window; each window highlights the keyword
+        let content = format!("alpha {} alpha {} beta", "x ".repeat(100), "y ".repeat(100));
+        let windows = find_match_windows(&content, &["alpha"], 20);
+        assert_eq!(windows.len(), 2, "two separated matches should yield two windows");
+        assert!(windows.iter().all(|w| w.contains("<b>alpha</b>")));
+    }
+
+    #[test]
+    fn test_find_match_windows_folds_nearby_matches() {
+        // Matches within the same window are not repeated as separate windows
+        let windows = find_match_windows("foo bar foo", &["foo"], 40);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0], "<b>foo</b> bar <b>foo</b>");
+    }
+
+    #[test]
+    fn test_conversation_cache_invalidates_on_mtime_and_is_bounded() {
+        use std::time::{Duration, SystemTime};
+        let mut cache = ConversationCache::default();
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let t1 = t0 + Duration::from_secs(1);
+
+        cache.insert("s1", t0, "old".to_string());
+        assert_eq!(cache.get("s1", t0).as_deref(), Some("old"));
+        assert_eq!(cache.get("s1", t1), None, "changed mtime must miss");
+
+        cache.insert("s1", t1, "new".to_string());
+        assert_eq!(cache.entries.len(), 1, "re-inserting a session replaces its entry");
+
+        for i in 0..CONVERSATION_CACHE_SIZE + 3 {
+            cache.insert(&format!("x{}", i), t0, String::new());
+        }
+        assert_eq!(cache.entries.len(), CONVERSATION_CACHE_SIZE);
+        assert_eq!(cache.get("s1", t1), None, "oldest entries are evicted");
+    }
+
+    #[test]
+    fn test_parse_codex_event_msg_stream_has_both_roles() {
+        // Codex streams carry turns as event_msg (user_message / agent_message), often
+        // duplicated by response_item messages; later turns may only exist as event_msg
+        let fixture = include_str!("../../tests/fixtures/codex_event_msg_session.jsonl");
+        let conversation = parse_jsonl_to_conversation(fixture, false);
+
+        assert!(conversation.contains("> Why does the parser drop the last line?"));
+        assert!(conversation.contains("⏺ The loop stops at `len() - 1`"));
+        assert!(conversation.contains("> Fix it please"));
+        assert!(conversation.contains("⏺ Changed the bound to `0..lines.len()`."));
+        assert_eq!(
+            conversation.matches("The loop stops at").count(),
+            1,
+            "duplicated response_item/event_msg turns should render once"
+        );
+        assert!(!conversation.contains("Inspecting the loop bounds"), "reasoning is not a turn");
+    }
+
+    #[test]
+    fn test_extract_live_first_last_matches_indexer() {
+        // Expected values are what export_session.extract_first_last_messages produces
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../tests/fixtures");
+        let (first, last) =
+            extract_live_first_last(&format!("{}/claude_session.jsonl", dir), "claude").unwrap();
+        assert_eq!(first, Some(("user".into(), "Hello, please read the file".into())));
+        assert_eq!(last, Some(("assistant".into(), "Short response".into())));
+
+        let (first, last) =
+            extract_live_first_last(&format!("{}/codex_event_msg_session.jsonl", dir), "codex").unwrap();
+        assert_eq!(
+            first.map(|(_, c)| c).as_deref(),
+            Some("<environment_context> <cwd>/test/dir</cwd> <approval_policy>on-request</approval_policy> </environment_context>")
+        );
+        assert_eq!(
+            last,
+            Some(("assistant".into(), "The loop stops at `len() - 1`, so the final line is never visited.".into()))
+        );
+        assert!(extract_live_first_last("/nonexistent/session.jsonl", "claude").is_none());
+    }
+
+    #[test]
+    fn test_parse_permalink() {
+        assert_eq!(
+            parse_permalink("cctools://claude/abc-123"),
+            Some(("claude".to_string(), "abc-123".to_string()))
+        );
+        assert_eq!(parse_permalink("cctools://abc-123/"), Some((String::new(), "abc-123".to_string())));
+        assert_eq!(parse_permalink("cctools://claude/"), None, "agent without an ID");
+        assert_eq!(parse_permalink("cctools://codex"), None);
+        assert_eq!(parse_permalink("cctools://"), None);
+        assert_eq!(parse_permalink("/tmp/out.json"), None);
+    }
+
+    #[test]
+    fn test_summary_store_persists_and_clip_keeps_head_and_tail() {
+        let path = std::env::temp_dir().join(format!("aichat-summaries-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = SummaryStore::open(Some(path.clone()));
+        assert_eq!(store.get("s1"), None);
+        store.insert("s1".to_string(), "Fixed the flaky test.".to_string()).unwrap();
+
+        let reopened = SummaryStore::open(Some(path.clone()));
+        assert_eq!(reopened.get("s1"), Some("Fixed the flaky test."));
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(clip_for_summary("short"), "short");
+        let long = format!("HEAD{}TAIL", "x".repeat(SUMMARY_INPUT_MAX_CHARS * 2));
+        let clipped = clip_for_summary(&long);
+        assert!(clipped.starts_with("HEAD") && clipped.ends_with("TAIL"));
+        assert!(clipped.chars().count() < long.chars().count());
+
+        // The summarizer's own claude -p sessions stay out of the results
+        let first_message: String = format!("{SUMMARY_PROMPT} > fix the flaky test ⏺ Done").chars().take(200).collect();
+        assert!(is_tool_prompt(&first_message, ""));
+    }
+
+    #[test]
+    fn test_is_tool_prompt_checks_the_start_of_lmsh_prompts() {
+        // lmsh with history: the <COMMAND> instruction is past the indexed 200 chars
+        let with_history = "Previous conversation: User: find every log file under /var that changed in the \
+            last week and sort them by size Command: <COMMAND>find /var -name '*.log' -mtime -7 -exec ls -S {} +</COMMAND> User: ...";
+        assert!(is_tool_prompt(&with_history[..200], ""));
+        let codex = "DO NOT READ CLAUDE.MD; You are an expert shell command generator. When the user gives you";
+        assert!(is_tool_prompt("", codex));
+        assert!(is_tool_prompt("Create a shell command for the following request and return ONLY the command wrapped in <COMMAND></COMMAND> tags: list files", ""));
+        assert!(!is_tool_prompt("Why does the previous conversation: User: thing fail?", ""));
+    }
+
+    #[test]
+    fn test_message_line_mask_excludes_tool_results() {
+        let content = "> fix the build\n  please\n\n⏺ Running cargo\n  ⎿ error: build failed\nmore output\n⏺ Fixed the build\n";
+        assert_eq!(
+            message_line_mask(content),
+            vec![true, true, false, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn test_attachment_placeholder_reports_image_size_or_type() {
+        let png = serde_json::json!({
+            "type": "image",
+            "source": {
+                "type": "base64",
+                "media_type": "image/png",
+                "data": "iVBORw0KGgoAAAANSUhEUgAABAAAAAMACAYAAAAAAAAAAAAAAA=="
+            }
+        });
+        assert_eq!(attachment_placeholder(&png), "[image: 1024x768]");
+
+        let jpeg = serde_json::json!({"type": "input_image", "image_url": "data:image/jpeg;base64,/9j/4AAQ"});
+        assert_eq!(attachment_placeholder(&jpeg), "[image: image/jpeg]");
+
+        let doc = serde_json::json!({"type": "document", "source": {"media_type": "application/pdf"}});
+        assert_eq!(attachment_placeholder(&doc), "[document: application/pdf]");
+    }
+
+    #[test]
+    fn test_paste_into_date_input_takes_first_line_and_caps_length() {
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.input_mode = Some(InputMode::AfterDate);
+        app.on_paste("  2025-11-29\n");
+        assert_eq!(app.input_buffer, "2025-11-29", "trailing newline must not submit or be kept");
+
+        app.input_buffer.clear();
+        app.on_paste(&"9".repeat(100));
+        assert_eq!(app.input_buffer.len(), InputMode::AfterDate.max_len());
+
+        app.input_mode = Some(InputMode::MinLines);
+        app.input_buffer.clear();
+        app.on_paste("1a2\t3");
+        assert_eq!(app.input_buffer, "123", "numeric prompts keep digits only");
+    }
+
+    #[test]
+    fn test_view_snapshot_detects_appended_and_rewritten_content() {
+        let v1 = "> fix the bug\n\n⏺ Done\n";
+        let snap = ViewSnapshot::of(v1, 1);
+        assert!(snap.changes_in(v1).is_none(), "unchanged content has no changes");
+
+        let v2 = format!("{}> and add a test\n⏺ Added\n", v1);
+        let grown = snap.changes_in(&v2).expect("appended content is a change");
+        assert_eq!(grown.appended.as_deref(), Some(&["> and add a test".to_string(), "⏺ Added".to_string()][..]));
+        assert_eq!(grown.last, Some(("⏺ Done".to_string(), "⏺ Added".to_string())));
+        assert!(grown.first.is_none());
+
+        let trimmed = snap.changes_in("> fix the bug\n⏺ Done\n").expect("rewrite is a change");
+        assert!(trimmed.appended.is_none(), "a rewritten prefix is not an append");
+    }
+
+    #[test]
+    fn test_json_output_stops_quietly_on_broken_pipe() {
+        struct ClosedPipe;
+        impl io::Write for ClosedPipe {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe))
+            }
+        }
+
+        let app = App::new(Vec::new(), String::new(), None, None);
+        let mut buf = Vec::new();
+        write_json_lines(&mut buf, &app, JSON_FIELDS).unwrap();
+        assert!(buf.is_empty());
+
+        let err = write_json_lines(ClosedPipe, &app, JSON_FIELDS).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe, "caller maps this to a clean exit");
+    }
+
+    #[test]
+    fn test_extract_user_prompts_keeps_only_typed_messages() {
+        let content = [
+            r#"{"type":"user","message":{"content":"<command-name>/clear</command-name>"}}"#,
+            r#"{"type":"user","isMeta":true,"message":{"content":"Caveat: ignore"}}"#,
+            r#"{"type":"user","message":{"content":"fix the login bug"}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"On it"}]}}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","content":"ok"}]}}"#,
+            r#"{"type":"response_item","payload":{"role":"user","content":[{"type":"input_text","text":"<environment_context>x</environment_context>"}]}}"#,
+            r#"{"type":"response_item","payload":{"role":"user","content":[{"type":"input_text","text":"now add a test"}]}}"#,
+            r#"{"type":"event_msg","payload":{"type":"user_message","message":"now add a test"}}"#,
+        ]
+        .join("\n");
+
+        assert_eq!(extract_user_prompts(&content), vec!["fix the login bug", "now add a test"]);
+    }
+
+    #[test]
+    fn test_column_sort_cycles_and_reverses() {
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.cycle_sort_key();
+        assert_eq!((app.sort_key, app.sort_descending), (SortKey::Modified, true));
+        while app.sort_key != SortKey::Project {
+            app.cycle_sort_key();
+        }
+        assert!(!app.sort_descending, "names sort A-Z first");
+        app.reverse_sort();
+        assert!(app.sort_descending);
+        app.cycle_sort_key();
+        assert_eq!(app.sort_key, SortKey::Relevance, "the cycle ends back at the default order");
+
+        assert_eq!(list_header_cell("lines", Some("▼"), 4, true), "  L▼");
+        assert_eq!(list_header_cell("project", Some("▲"), 10, false), "project ▲ ");
+    }
+
+    #[test]
+    fn test_state_file_merges_writes_from_another_instance() {
+        let path = std::env::temp_dir().join(format!("cctools-state-test-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // Two TUIs open the same (missing) file, then each saves a different summary
+        let mut first = SummaryStore::open(Some(path.clone()));
+        let mut second = SummaryStore::open(Some(path.clone()));
+        first.insert("a".to_string(), "from first".to_string()).unwrap();
+        second.insert("b".to_string(), "from second".to_string()).unwrap();
+        assert_eq!(second.get("a"), Some("from first"), "the later writer merges in the earlier entry");
+
+        let on_disk = SummaryStore::open(Some(path.clone()));
+        assert_eq!(on_disk.get("a"), Some("from first"));
+        assert_eq!(on_disk.get("b"), Some("from second"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_preview_context_centers_on_match_inside_the_session() {
+        let conversation = "> start\n\n⏺ one\n\n> two\n  more of two\n\n⏺ three\n\n> four\n\n⏺ end\n";
+        let messages = conversation_messages(conversation);
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[2], (true, "two\nmore of two".to_string()));
+
+        let middle = PreviewContext::around(conversation, &[]);
+        assert!(!middle.matched);
+        assert_eq!((middle.start, middle.messages.len(), middle.total), (2, 3, 6));
+
+        let hit = PreviewContext::around(conversation, &["four".to_string()]);
+        assert!(hit.matched);
+        assert_eq!(hit.start, 2, "window stays clear of the last message");
+        assert_eq!(hit.messages.last().unwrap().1, "four");
+
+        assert!(PreviewContext::around("> only\n", &[]).messages.is_empty());
+    }
+
+    #[test]
+    fn test_tilde_paths_when_home_is_unset() {
+        assert_eq!(expand_tilde_in("~/proj", Some("/home/ann")), "/home/ann/proj");
+        assert_eq!(expand_tilde_in("~", Some("/home/ann")), "/home/ann");
+        assert_eq!(expand_tilde_in("~bob/proj", Some("/home/ann")), "~bob/proj", "~user is not ours to expand");
+        assert_eq!(expand_tilde_in("~/proj", None), "~/proj", "no home: leave the path literal");
+
+        assert_eq!(abbreviate_home_in("/home/ann/proj", Some("/home/ann")), "~/proj");
+        assert_eq!(abbreviate_home_in("/home/anna/proj", Some("/home/ann")), "/home/anna/proj");
+        assert_eq!(abbreviate_home_in("/home/ann/proj", None), "/home/ann/proj");
+        assert_eq!(abbreviate_home_in("/srv/proj", Some("")), "/srv/proj");
+    }
+
+    #[test]
+    fn test_osc52_fallback_detection_and_payload() {
+        assert!(osc52_supported("xterm-kitty", "", false));
+        assert!(osc52_supported("xterm-256color", "iTerm.app", false));
+        assert!(osc52_supported("screen-256color", "", true), "tmux forwards OSC 52");
+        assert!(!osc52_supported("dumb", "", false));
+        assert!(!osc52_supported("xterm-256color", "Apple_Terminal", false));
+
+        assert_eq!(encode_base64(b"abc123"), "YWJjMTIz");
+        assert_eq!(encode_base64(b"ab"), "YWI=");
+        assert_eq!(encode_base64(b"a"), "YQ==");
+        assert_eq!(decode_base64_prefix(&encode_base64(b"session-id"), 64), b"session-id");
+    }
+
+    #[test]
+    fn test_regex_mode_matches_patterns_and_keeps_results_on_bad_pattern() {
+        let re = regex::Regex::new(r"fn\s+\w+_handler").unwrap();
+        let snippet = regex_snippet(&re, "see\nfn  login_handler(req) for details").unwrap();
+        assert_eq!(snippet, "see <b>fn login_handler</b>(req) for details");
+        assert!(regex_snippet(&re, "no handler here").is_none());
+        assert!(regex_snippet(&regex::Regex::new("x*").unwrap(), "abc").is_none(), "empty matches don't count");
+
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.regex_mode = true;
+        app.filtered = vec![0];
+        app.query = "fn(".to_string();
+        app.filter();
+        assert_eq!(app.regex_error.as_deref(), Some("invalid pattern"));
+        assert_eq!(app.filtered, vec![0], "results are kept while the pattern is invalid");
+
+        // A repeated pattern reuses the last scan, which here is all there is (no index)
+        app.sessions = vec![Session { session_id: "abc".into(), ..Default::default() }];
+        app.query = "Login_Handler".to_string();
+        app.regex_matches = Some(("Login_Handler".to_string(), HashMap::from([("abc".to_string(), "x".to_string())])));
+        app.filter();
+        assert_eq!(app.filtered, vec![0]);
+        app.query = "login_handler".to_string();
+        app.filter();
+        assert!(app.filtered.is_empty(), "a new pattern scans again");
+    }
+
+    #[test]
+    fn test_fuzzy_name_score_ranks_closest_project_first() {
+        let matcher = SkimMatcherV2::default();
+        let close = fuzzy_name_score(&matcher, &["search-ui", "main"], "serch-ui").expect("typo still matches");
+        let far = fuzzy_name_score(&matcher, &["secret-rust-ui-helpers", "main"], "serch-ui");
+        assert!(far.is_none_or(|far| close > far));
+        assert!(fuzzy_name_score(&matcher, &["api", "main"], "serch-ui").is_none());
+        assert!(fuzzy_name_score(&matcher, &["search-ui"], "").is_none(), "empty query never fuzzy-matches");
+    }
+
+    #[test]
+    fn test_sort_key_parsing() {
+        assert_eq!(SortKey::parse("lines"), Some((SortKey::Lines, true)));
+        assert_eq!(SortKey::parse("project"), Some((SortKey::Project, false)));
+        assert_eq!(SortKey::parse("Modified:asc"), Some((SortKey::Modified, false)));
+        assert_eq!(SortKey::parse("project:desc"), Some((SortKey::Project, true)));
+        assert_eq!(SortKey::parse("lines:sideways"), None);
+        assert_eq!(SortKey::parse("size"), None);
+        for &(name, key) in SortKey::NAMES {
+            assert_eq!(key.name(), name);
+        }
+    }
+
+    #[test]
+    fn test_ui_state_restores_only_unset_filters() {
+        // A file from before most fields existed still loads, with defaults for the rest
+        let old: UiState = serde_json::from_str(r#"{"version": 1, "include_sub": true, "filter_min_lines": 50}"#).unwrap();
+        assert!(old.include_sub && old.include_original);
+        assert_eq!(old.filter_min_lines, Some(50));
+
+        let mut cli = CliOptions { min_lines: Some(10), ..Default::default() };
+        UiState { filter_agent: Some("codex".into()), include_trimmed: false, ..old }.apply_defaults(&mut cli);
+        assert_eq!(cli.min_lines, Some(10), "explicit flags win");
+        assert_eq!(cli.agent_filter.as_deref(), Some("codex"));
+        assert!(cli.include_sub && cli.no_trimmed && !cli.no_original);
+
+        // A type flag on the command line replaces the saved types rather than adding to them
+        let saved = UiState { include_sub: true, include_trimmed: false, filter_has_attachments: true, ..Default::default() };
+        let mut cli = CliOptions { include_tool: true, ..Default::default() };
+        saved.apply_defaults(&mut cli);
+        assert!(cli.include_tool);
+        assert!(!cli.include_sub && !cli.no_trimmed && !cli.has_attachments, "the CLI flag wins");
+
+        let dir = std::env::temp_dir().join(format!("aichat-ui-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ui-state.json");
+        std::fs::write(&path, r#"{"version": 99, "include_sub": true}"#).unwrap();
+        assert_eq!(UiState::load(&path), None, "newer versions are ignored");
+        UiState::default().save(&path);
+        assert_eq!(UiState::load(&path), Some(UiState::default()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_preview_line_caps_follow_height() {
+        assert_eq!(preview_line_caps(36, None), (6, 12), "a typical pane keeps the old caps");
+        assert_eq!(preview_line_caps(90, None), (15, 30));
+        assert_eq!(preview_line_caps(5, None), (2, 4), "short panes still show something");
+        assert_eq!(preview_line_caps(90, Some(3)), (3, 6), "--preview-lines ignores the height");
+    }
+
+    #[test]
+    fn test_tool_timeline_pairs_calls_with_results() {
+        // Parsed JSONL: calls carry an input summary and results follow their call
+        let fixture = include_str!("../../tests/fixtures/claude_session.jsonl");
+        let conversation = parse_jsonl_to_conversation(fixture, false);
+        let calls = tool_timeline(&conversation);
+        assert_eq!(calls.len(), 2);
+        assert_eq!((calls[0].name.as_str(), calls[0].input.as_str()), ("Read", "/test/file.txt"));
+        assert!(calls[0].result.as_deref().unwrap().starts_with("This is a very long file"));
+        assert_eq!((calls[1].name.as_str(), calls[1].input.as_str()), ("Bash", "ls -la"));
+        assert_eq!(calls[1].result.as_deref(), Some("total 48"));
+        assert_eq!(calls[1].result_lines, 4);
+        assert!(conversation.lines().nth(calls[1].line).unwrap().contains("[Tool: Bash]"));
+
+        // Exported transcripts: `⏺ Name(args)` with the result after a blank line
+        let export = "> run it\n\n⏺ Bash(command=\"make\")\n\n  ⎿  ok\n\n⏺ Done (all green)\n⏺ Read(x.rs)\n> next\n  ⎿  stray\n";
+        let calls = tool_timeline(export);
+        assert_eq!(calls.len(), 2, "prose with parentheses is not a call");
+        assert_eq!((calls[0].name.as_str(), calls[0].result.as_deref()), ("Bash", Some("ok")));
+        assert_eq!(calls[1].result, None, "a result after a new prompt is not the call's");
+
+        // Codex logs calls and outputs as separate items, with JSON-encoded arguments
+        let codex = concat!(
+            r#"{"type":"response_item","payload":{"type":"function_call","name":"shell","arguments":"{\"command\":[\"bash\",\"-lc\",\"cargo test\"]}"}}"#,
+            "\n",
+            r#"{"type":"response_item","payload":{"type":"function_call_output","output":"{\"output\":\"test result: ok\"}"}}"#,
+        );
+        let calls = tool_timeline(&parse_jsonl_to_conversation(codex, false));
+        assert_eq!((calls[0].name.as_str(), calls[0].input.as_str()), ("shell", "bash -lc cargo test"));
+        assert_eq!(calls[0].result.as_deref(), Some("test result: ok"));
+
+        // The replay prompts never include tool results
+        assert_eq!(extract_user_prompts(fixture), vec!["Hello, please read the file", "Now run a command"]);
+    }
+
+    #[test]
+    fn test_json_fields_selects_and_validates_keys() {
+        assert_eq!(parse_json_fields("session_id, cwd,modified,cwd"), Ok(vec!["session_id", "cwd", "modified"]));
+        assert_eq!(parse_json_fields("all").unwrap().len(), JSON_FIELDS.len() + JSON_EXTRA_FIELDS.len());
+        let err = parse_json_fields("session_id,first_message").unwrap_err();
+        assert!(err.contains("'first_message'") && err.contains("first_msg"), "{}", err);
+        assert!(parse_json_fields(" , ").is_err());
+
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.sessions = vec![Session { session_id: "abc".into(), cwd: "/tmp".into(), ..Default::default() }];
+        app.filtered = vec![0];
+        let mut buf = Vec::new();
+        write_json_lines(&mut buf, &app, &["session_id", "cwd"]).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "{\"cwd\":\"/tmp\",\"session_id\":\"abc\"}\n");
+    }
+
+    #[test]
+    fn test_multi_select_marks_survive_query_changes() {
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.sessions = ["a", "b", "c"]
+            .iter()
+            .map(|id| Session { session_id: id.to_string(), ..Default::default() })
+            .collect();
+        app.filtered = vec![0, 1, 2];
+
+        // Without multi-select, Enter isn't a batch
+        assert!(!app.on_enter_marked());
+
+        app.toggle_multi_select();
+        assert!(!app.on_enter_marked(), "nothing marked yet");
+        app.selected = 2;
+        app.toggle_mark();
+        app.selected = 0;
+        app.toggle_mark();
+        assert_eq!(app.selected, 1, "marking moves to the next row");
+
+        // Narrowing the results and clearing the query keep the marks
+        app.filtered = vec![1];
+        app.selected = 0;
+        app.query = "b".to_string();
+        app.on_escape();
+        assert!(app.query.is_empty());
+        assert!(app.multi_select);
+
+        assert!(app.on_enter_marked());
+        let ids: Vec<String> = app
+            .should_select_many
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|s| s.session_id.clone())
+            .collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert!(app.should_select.is_none());
+
+        // Leaving the mode drops the marks
+        app.on_escape();
+        assert!(!app.multi_select);
+        assert!(app.selected_set.is_empty());
+    }
+
+    #[test]
+    fn test_theme_resolution() {
+        let is_dark = |t: &Theme| t.search_bg == Theme::dark().search_bg;
+        let is_light = |t: &Theme| t.search_bg == Theme::light().search_bg;
+
+        assert!(is_dark(&Theme::resolve(ThemeChoice::Auto, None, None)));
+        assert!(is_light(&Theme::resolve(ThemeChoice::Auto, None, Some("0;15"))));
+        assert!(is_light(&Theme::resolve(ThemeChoice::Auto, None, Some("0;default;7"))));
+        assert!(is_dark(&Theme::resolve(ThemeChoice::Auto, None, Some("15;0"))));
+        assert!(is_dark(&Theme::resolve(ThemeChoice::Auto, None, Some("garbage"))));
+        assert!(is_dark(&Theme::resolve(ThemeChoice::Dark, None, Some("0;15"))));
+        assert!(is_light(&Theme::resolve(ThemeChoice::Light, None, None)));
+
+        // NO_COLOR wins over an explicit theme, but only when non-empty
+        let mono = Theme::resolve(ThemeChoice::Light, Some("1"), None);
+        assert_eq!(mono.accent, Color::Reset);
+        assert_eq!(mono.selection().add_modifier, Modifier::REVERSED);
+        assert!(is_light(&Theme::resolve(ThemeChoice::Light, Some(""), None)));
+
+        assert_eq!(ThemeChoice::parse("light"), Some(ThemeChoice::Light));
+        assert_eq!(ThemeChoice::parse("solarized"), None);
+    }
+
+    #[test]
+    fn test_typing_defers_filter_until_pause() {
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.on_char('a');
+        app.on_char('b');
+        app.on_backspace();
+        assert!(app.filter_pending_since.is_some(), "edits wait for a pause");
+
+        app.poll_pending_filter();
+        assert!(app.filter_pending_since.is_some(), "too soon to search");
+
+        app.filter_pending_since = Some(std::time::Instant::now() - FILTER_DEBOUNCE);
+        app.poll_pending_filter();
+        assert!(app.filter_pending_since.is_none());
+
+        app.on_char('c');
+        app.flush_pending_filter();
+        assert!(app.filter_pending_since.is_none(), "flushing runs it right away");
+        assert_eq!(app.query, "ac");
+    }
+
+    #[test]
+    fn test_current_view_match_line_follows_navigation() {
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.full_content = "> find foo\n⏺ no match\n⏺ foo again\n> more foo".to_string();
+        assert_eq!(app.current_view_match_line(), None);
+
+        app.view_search_pattern = "foo".to_string();
+        app.update_view_search_matches();
+        assert_eq!(app.current_view_match_line(), Some(0));
+        app.view_search_next();
+        assert_eq!(app.current_view_match_line(), Some(2));
+        app.view_search_prev();
+        app.view_search_prev();
+        assert_eq!(app.current_view_match_line(), Some(3), "wraps to the last match");
+
+        app.view_search_pattern.clear();
+        assert_eq!(app.current_view_match_line(), None);
+    }
+
+    #[test]
+    fn test_view_search_whole_word_and_case() {
+        let text: Vec<char> = "Foo foobar foo_x (foo) FOO".chars().collect();
+        let flags = |whole_word, case_sensitive| ViewSearchFlags { whole_word, case_sensitive };
+
+        assert_eq!(find_view_matches(&text, "foo", flags(false, false)).len(), 5);
+        assert_eq!(
+            find_view_matches(&text, "foo", flags(true, false)),
+            vec![(0, 3), (18, 21), (23, 26)],
+            "foobar and foo_x are not whole words"
+        );
+        assert_eq!(find_view_matches(&text, "foo", flags(false, true)).len(), 3);
+        assert_eq!(find_view_matches(&text, "foo", flags(true, true)), vec![(18, 21)]);
+        assert!(find_view_matches(&text, "", flags(false, false)).is_empty());
+
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.full_content = "> Foo\n⏺ foobar\n⏺ foo".to_string();
+        app.view_search_pattern = "foo".to_string();
+        app.view_search_flags = flags(true, true);
+        app.update_view_search_matches();
+        assert_eq!(app.view_search_matches, vec![2]);
+    }
+
+    #[test]
+    fn test_load_sessions_defers_message_text() {
+        use tantivy::schema::{Schema, STORED, STRING, TEXT};
+
+        let dir = std::env::temp_dir().join(format!("aichat-lazy-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut builder = Schema::builder();
+        for name in LOAD_SESSIONS_REQUIRED_FIELDS.iter().chain(["first_user_msg_content", "content"].iter()) {
+            match *name {
+                "modified_ts" => builder.add_u64_field(name, STORED),
+                "lines" => builder.add_i64_field(name, STORED),
+                "export_path" | "session_id" => builder.add_text_field(name, STRING | STORED),
+                _ => builder.add_text_field(name, TEXT | STORED),
+            };
+        }
+        let schema = builder.build();
+        let index = Index::create_in_dir(&dir, schema.clone()).unwrap();
+        let mut writer: tantivy::IndexWriter = index.writer(15_000_000).unwrap();
+        let field = |name: &str| schema.get_field(name).unwrap();
+        let mut doc = tantivy::TantivyDocument::default();
+        doc.add_text(field("session_id"), "abc");
+        doc.add_text(field("export_path"), "/tmp/abc.jsonl");
+        doc.add_text(field("first_msg_content"), "Read the session transcript at /x");
+        doc.add_text(field("last_msg_content"), "all done");
+        doc.add_u64(field("modified_ts"), 1);
+        doc.add_i64(field("lines"), 3);
+        writer.add_document(doc).unwrap();
+        writer.commit().unwrap();
+
+        let path = dir.to_str().unwrap();
+        let eager = load_sessions(path, 10, true, None).unwrap();
+        assert_eq!(eager[0].last_msg_content, "all done");
+
+        let progress = LoadProgress::default();
+        let lazy = load_sessions(path, 10, false, Some(&progress)).unwrap();
+        assert_eq!(progress.done.load(Ordering::Relaxed), lazy.len());
+        assert_eq!(progress.total.load(Ordering::Relaxed), lazy.len());
+        assert!(lazy[0].last_msg_content.is_empty());
+        assert!(lazy[0].is_tool_generated(), "decided before the text is dropped");
+
+        let mut app = App::new(lazy, path.to_string(), None, None);
+        app.ensure_messages(0);
+        assert_eq!(app.sessions[0].last_msg_content, "all done");
+        assert!(app.sessions[0].messages_loaded);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_index_schema_version_matches_the_indexer() {
+        let indexer = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../claude_code_tools/search_index.py");
+        let Ok(source) = std::fs::read_to_string(indexer) else {
+            return; // Built outside the repo
+        };
+        assert!(source.contains(&format!("\nINDEX_SCHEMA_VERSION = {}\n", INDEX_SCHEMA_VERSION)));
     }
 
-    disable_raw_mode()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
+    #[test]
+    fn test_load_sessions_reads_the_list_meta_column() {
+        use tantivy::schema::{Schema, FAST, STORED, STRING, TEXT};
+
+        let dir = std::env::temp_dir().join(format!("aichat-meta-index-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut builder = Schema::builder();
+        for name in LOAD_SESSIONS_REQUIRED_FIELDS.iter().chain(["content"].iter()) {
+            match *name {
+                "modified_ts" => builder.add_u64_field(name, STORED | FAST),
+                "lines" => builder.add_i64_field(name, STORED),
+                _ => builder.add_text_field(name, TEXT | STORED),
+            };
+        }
+        builder.add_text_field("list_meta", STRING | FAST);
+        let schema = builder.build();
+        let index = Index::create_in_dir(&dir, schema.clone()).unwrap();
+        let mut writer: tantivy::IndexWriter = index.writer(15_000_000).unwrap();
+        let field = |name: &str| schema.get_field(name).unwrap();
+        for (id, ts, first) in [("old", 1u64, "fix the build"), ("new", 2, "Read the session transcript at /x")] {
+            let mut doc = tantivy::TantivyDocument::default();
+            // Stored fields that disagree with list_meta show which one was read
+            doc.add_text(field("session_id"), "stored");
+            doc.add_text(field("content"), "the whole conversation");
+            let meta = serde_json::json!({
+                "session_id": id, "agent": "claude", "modified_ts": ts, "lines": 7,
+                "message_count": 3, "first_msg_content": first, "last_msg_content": "done",
+                "is_sidechain": "false", "code_langs": "rust,python",
+            });
+            doc.add_text(field("list_meta"), meta.to_string());
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
 
-    if let Some(session) = app.should_select {
-        // Output session with action and filter state for Python handler
-        let output = serde_json::json!({
-            "session": session,
-            "action": app.selected_action.as_deref().unwrap_or("menu"),
-            "filter_state": {
-                "query": app.query,
-                "scope_global": app.scope_global,
-                "filter_dir": app.filter_dir,
-                "include_original": app.include_original,
-                "include_sub": app.include_sub,
-                "include_trimmed": app.include_trimmed,
-                "include_continued": app.include_continued,
-                "filter_agent": app.filter_agent,
-                "filter_min_lines": app.filter_min_lines,
-                "filter_after_date": app.filter_after_date,
-                "filter_before_date": app.filter_before_date,
-                "filter_branch": app.filter_branch,
-                "sort_by_time": app.sort_by_time,
-                "selected": app.selected,
-                "list_scroll": app.list_scroll,
-            }
-        });
-        let json = serde_json::to_string(&output)?;
-        if let Some(ref out_path) = cli.output_file {
-            std::fs::write(out_path, &json)?;
-        } else {
-            println!("{}", json);
+        let path = dir.to_str().unwrap();
+        let progress = LoadProgress::default();
+        let sessions = load_sessions(path, 10, false, Some(&progress)).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(sessions.iter().map(|s| s.session_id.as_str()).collect::<Vec<_>>(), vec!["new", "old"]);
+        assert_eq!(progress.done.load(Ordering::Relaxed), 2);
+        let newest = &sessions[0];
+        assert_eq!((newest.lines, newest.message_count), (7, Some(3)));
+        assert_eq!(newest.code_langs, vec!["rust", "python"]);
+        assert!(newest.is_tool_generated() && !sessions[1].is_tool_generated());
+        assert!(newest.first_msg_content.is_empty() && !newest.messages_loaded);
+    }
+
+    #[test]
+    fn test_project_filter_is_case_insensitive_substring() {
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.scope_global = true;
+        app.sessions = ["rust-search-ui", "Search-Tools", "langroid"]
+            .iter()
+            .map(|p| Session { project: p.to_string(), ..Default::default() })
+            .collect();
+
+        app.filter_project = Some("SEARCH".to_string());
+        app.filter();
+        assert_eq!(app.filtered, vec![0, 1]);
+        assert!(app.has_active_filters());
+
+        app.filter_project = None;
+        app.filter();
+        assert_eq!(app.filtered.len(), 3);
+    }
+
+    #[test]
+    fn test_date_format_cycle_and_width() {
+        assert_eq!(DateFormat::for_width(30), DateFormat::Full);
+        assert_eq!(DateFormat::for_width(15), DateFormat::Medium);
+        assert_eq!(DateFormat::for_width(5), DateFormat::Compact);
+
+        let mut pinned = None;
+        let mut seen = Vec::new();
+        for _ in 0..5 {
+            pinned = DateFormat::next(pinned);
+            seen.push(pinned);
         }
+        assert_eq!(
+            seen,
+            vec![
+                Some(DateFormat::Full),
+                Some(DateFormat::Medium),
+                Some(DateFormat::Compact),
+                Some(DateFormat::Relative),
+                None
+            ]
+        );
+
+        let session = Session { modified: "not a date".to_string(), ..Default::default() };
+        assert_eq!(DateFormat::Relative.format(&session), "not a date");
+
+        let state = UiState { date_format: Some(DateFormat::Relative), ..Default::default() };
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.contains(r#""date_format":"relative""#));
+        assert_eq!(serde_json::from_str::<UiState>(&json).unwrap(), state);
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_size_column_and_duration() {
+        let s = Session {
+            lines: 295,
+            message_count: Some(42),
+            created: "2025-11-27T10:00:00Z".to_string(),
+            modified: "2025-11-27T11:30:00Z".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(s.size_display(false), "295L");
+        assert_eq!(s.size_display(true), "42M");
+        assert_eq!(Session { message_count: None, ..Default::default() }.size_display(true), "?M");
+        assert_eq!(s.duration_secs(), Some(90 * 60));
+        assert_eq!(Session::default().duration_secs(), None);
 
-// ============================================================================
-// Tests
-// ============================================================================
+        let widths = ListColumnWidths::compute(std::slice::from_ref(&s), &[0], true);
+        assert_eq!(widths.lines, 4, "minimum width still applies");
+        assert!(parse_json_fields("duration,message_count").is_ok());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_write_kept_turns() {
+        let line = |kind: &str, uuid: &str, parent: &str, text: &str| {
+            serde_json::json!({
+                "type": kind, "uuid": uuid, "parentUuid": parent, "sessionId": "old",
+                "message": { "role": kind, "content": text },
+            })
+            .to_string()
+        };
+        let mut jsonl = vec![serde_json::json!({"type": "summary", "summary": "s"}).to_string()];
+        let mut parent = String::new();
+        for turn in 1..=5 {
+            jsonl.push(line("user", &format!("u{turn}"), &parent, &format!("prompt {turn}")));
+            jsonl.push(line("assistant", &format!("a{turn}"), &format!("u{turn}"), &format!("reply {turn}")));
+            parent = format!("a{turn}");
+        }
+        let content = jsonl.join("\n");
+        assert_eq!(conversation_turns(&content).len(), 5);
+        assert_eq!(conversation_turns(&content)[0], 1..3);
+        assert!(kept_turn_lines(&content, 3).is_none(), "nothing to drop");
+        assert_eq!(kept_turn_lines(&content, 1), Some((vec![0, 1, 2, 9, 10], 3)));
+
+        let dir = std::env::temp_dir().join(format!("aichat-keep-turns-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("old.jsonl");
+        std::fs::write(&source, &content).unwrap();
+        let session = Session {
+            agent: "claude".to_string(),
+            export_path: source.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let (path, dropped) = write_kept_turns(&session, 1).unwrap().unwrap();
+        assert_eq!(dropped, 3);
+        let written = std::fs::read_to_string(&path).unwrap();
+        let rows: Vec<serde_json::Value> = written.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0]["trim_metadata"]["stats"]["num_turns_dropped"], 3);
+        let new_id = path.file_stem().unwrap().to_string_lossy().to_string();
+        assert_eq!(new_id.len(), 36);
+        assert_eq!(&new_id[14..15], "4");
+        assert!(rows[1..].iter().all(|r| r["sessionId"] == new_id.as_str()));
+        assert_eq!(rows[3]["uuid"], "u5");
+        assert_eq!(rows[3]["parentUuid"], "a1", "chain bridges the dropped turns");
+        assert!(parse_jsonl_to_conversation(&written, false).contains("> prompt 5"));
+    }
 
     #[test]
-    fn test_truncate_with_max_zero() {
-        // Issue #25: max=0 causes usize underflow in truncate()
-        // When max=0, the expression max-1 wraps to usize::MAX
-        let result = truncate("hello world", 0);
-        assert_eq!(result, "", "truncate with max=0 should return empty string");
+    fn test_codex_reasoning_shown_on_request() {
+        let fixture = include_str!("../../tests/fixtures/codex_event_msg_session.jsonl");
+        let conversation = parse_jsonl_to_conversation(fixture, true);
+
+        // The response_item summary is kept once; the event_msg copy is still skipped
+        assert_eq!(conversation.matches("Inspecting the loop bounds").count(), 1);
+        assert!(conversation.contains("\n✻ **Inspecting the loop bounds**\n"));
+        assert_eq!(conversation.matches("The loop stops at").count(), 1);
+        assert!(message_line_mask(&conversation)[conversation.lines().position(|l| l.starts_with("✻ ")).unwrap()]);
+
+        // Reasoning never becomes a turn boundary
+        assert_eq!(conversation_turns(fixture).len(), 2);
+        let no_summary = serde_json::json!({"type": "reasoning", "summary": [], "content": [{"type": "reasoning_text", "text": " raw "}]});
+        assert_eq!(codex_reasoning_text(&no_summary).as_deref(), Some("raw"));
+        assert_eq!(codex_reasoning_text(&serde_json::json!({"type": "reasoning", "summary": []})), None);
     }
 
     #[test]
-    fn test_truncate_with_max_one() {
-        // Edge case: max=1 should just show the ellipsis
-        let result = truncate("hello world", 1);
-        assert_eq!(result, "…", "truncate with max=1 should return just ellipsis");
+    fn test_full_view_scrolls_by_wrapped_rows() {
+        let text = |rows: &[Line]| rows.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let line = Line::from(vec![Span::styled("User ", bold), Span::raw("one two three fourfivesix")]);
+        let rows = wrap_styled_line(line, 10);
+        assert_eq!(text(&rows), vec!["User one ", "two three ", "fourfivesi", "x"]);
+        assert_eq!(rows[0].spans[0].style, bold, "styles survive the split");
+        assert_eq!(text(&wrap_styled_line(Line::from("short"), 10)), vec!["short"]);
+
+        // Line 1 wraps to three rows, so line 2 starts at row 4
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.full_content = "> a\n⏺ long\n⏺ foo".to_string();
+        app.full_view_rows = vec![0, 1, 4, 5];
+        app.view_search_pattern = "foo".to_string();
+        app.update_view_search_matches();
+        app.view_search_next();
+        assert_eq!(app.full_content_scroll, 4);
+        assert_eq!(app.top_line(), 2);
+        app.full_content_scroll = 3;
+        assert_eq!(app.top_line(), 1, "inside a wrapped line");
+        assert_eq!(app.full_view_total_rows(), 5);
     }
 
     #[test]
-    fn test_truncate_normal_case() {
-        // Normal case: string longer than max
-        let result = truncate("hello world", 6);
-        assert_eq!(result, "hello…", "truncate should cut and add ellipsis");
+    fn test_wrap_styled_line_counts_wide_chars_as_two_columns() {
+        let text = |rows: &[Line]| rows.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        let rows = wrap_styled_line(Line::from("日本語のテキスト ok 🎉🎉"), 6);
+        assert_eq!(text(&rows), vec!["日本語", "のテキ", "スト ", "ok ", "🎉🎉"]);
+        assert!(rows.iter().all(|r| r.width() <= 6), "no row is wider than the pane");
+        assert_eq!(text(&wrap_styled_line(Line::from("日本語"), 6)), vec!["日本語"]);
+        // A wide char doesn't fit in one column but still gets a row of its own
+        assert_eq!(text(&wrap_styled_line(Line::from("日本"), 1)), vec!["日", "本"]);
     }
 
     #[test]
-    fn test_truncate_short_string() {
-        // String shorter than max should be returned as-is
-        let result = truncate("hi", 10);
-        assert_eq!(result, "hi", "short strings should not be truncated");
+    fn test_search_reports_matched_terms() {
+        let search = test_search_index(&[("s1", "fix the auth flow"), ("s2", "refresh the Token and auth"), ("s3", "unrelated")]);
+
+        let (snippets, _, _, matched) = search.search_tantivy("auth token -x", None, None, false, DEFAULT_SNIPPET_CHARS).unwrap();
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(matched.get("s1"), Some(&vec!["auth".to_string()]));
+        assert_eq!(matched.get("s2"), Some(&vec!["auth".to_string(), "token".to_string()]));
+        // A single term or a phrase says nothing the snippet doesn't
+        assert!(search.search_tantivy("auth", None, None, false, DEFAULT_SNIPPET_CHARS).unwrap().3.is_empty());
+        assert!(search.search_tantivy("auth flow", None, None, true, DEFAULT_SNIPPET_CHARS).unwrap().3.is_empty());
     }
 
     #[test]
-    fn test_truncate_exact_length() {
-        // String exactly at max length
-        let result = truncate("hello", 5);
-        assert_eq!(result, "hello", "string at exact max length should not be truncated");
+    fn test_parse_relative_date() {
+        let today = Local::now().date_naive();
+        let ymd = |d: chrono::NaiveDate| d.format("%Y%m%d").to_string();
+        let cutoff = |spec: &str| parse_relative_date(spec).map(|(cmp, _)| cmp);
+
+        assert_eq!(cutoff("7d"), Some(ymd(today - chrono::Days::new(7))));
+        assert_eq!(cutoff(" 2W "), Some(ymd(today - chrono::Days::new(14))));
+        assert_eq!(cutoff("3mo"), Some(ymd(today.checked_sub_months(chrono::Months::new(3)).unwrap())));
+        assert_eq!(cutoff("1y"), Some(ymd(today.checked_sub_months(chrono::Months::new(12)).unwrap())));
+        assert_eq!(cutoff("0d"), Some(ymd(today)));
+        for bad in ["", "7", "d", "7m", "2 weeks", "-3d"] {
+            assert_eq!(cutoff(bad), None, "{:?}", bad);
+        }
+        // The display form parses back to the same cutoff
+        let (cmp, display) = parse_relative_date("2w").unwrap();
+        assert_eq!(parse_flexible_date(&display).map(|(c, _)| c), Some(cmp));
     }
 
     #[test]
-    fn test_truncate_empty_string() {
-        // Empty string with any max
-        let result = truncate("", 10);
-        assert_eq!(result, "", "empty string should remain empty");
+    fn test_stats_aggregates_filtered_sessions() {
+        let session = |agent: &str, project: &str, derivation: &str, lines: i64, day: u64| Session {
+            session_id: format!("{}-{}-{}", agent, project, lines),
+            agent: agent.to_string(),
+            project: project.to_string(),
+            derivation_type: derivation.to_string(),
+            claude_home: if agent == "codex" { String::new() } else { "/h/.claude".to_string() },
+            lines,
+            // Noon UTC on 2025-11-<day>
+            modified_ts: (1_761_998_400 + day * 86_400) * 1000,
+            ..Default::default()
+        };
+        let sessions = vec![
+            session("claude", "web", "", 100, 3),
+            session("claude", "api", "trimmed", 20, 1),
+            session("claude", "api", "continued", 30, 5),
+            session("codex", "api", "", 7, 2),
+        ];
+        let app = App::new(sessions, String::new(), None, None);
+        assert_eq!(app.filtered.len(), 4);
+
+        let mut buf = Vec::new();
+        write_stats(&mut buf, &app, false).unwrap();
+        let table = String::from_utf8(buf).unwrap();
+        assert!(table.starts_with("Sessions     4\nSub-agents   0\nTotal lines  157\n"), "{}", table);
+        assert!(table.contains("Date range   2025-11-02 .. 2025-11-06\n"), "{}", table);
+        assert!(table.contains("\nBy project\n  api       3\n  web       1\n"), "{}", table);
+        assert!(table.contains("\nBy Claude home\n  /h/.claude       3\n"), "{}", table);
+
+        let mut buf = Vec::new();
+        write_stats(&mut buf, &app, true).unwrap();
+        let obj: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(obj["sessions"], 4);
+        assert_eq!(obj["by_agent"][0], serde_json::json!({"name": "claude", "sessions": 3}));
+        let derivations: Vec<&str> =
+            obj["by_derivation"].as_array().unwrap().iter().map(|r| r["name"].as_str().unwrap()).collect();
+        assert_eq!(derivations, vec!["original", "rolled-over", "trimmed"]);
     }
 
     #[test]
-    fn test_truncate_empty_string_zero_max() {
-        // Empty string with max=0
-        let result = truncate("", 0);
-        assert_eq!(result, "", "empty string with max=0 should remain empty");
+    fn test_preview_bubble_highlights_query_keeps_width() {
+        let t = Theme::dark();
+        let bg = t.user_bubble_bg;
+        let line = preview_bubble_line("Fix the Login bug", 20, bg, "login", &t);
+        let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text.chars().count(), 22, "margin + bubble width + margin");
+        let hit = line.spans.iter().find(|s| s.content == "Login").expect("query word split out");
+        assert_eq!(hit.style.bg, t.highlight().bg);
+        assert!(line.spans.iter().filter(|s| s.content != "Login").all(|s| s.style.bg == Some(bg)));
+
+        let plain = preview_bubble_line("Fix the Login bug", 20, bg, "", &t);
+        assert_eq!(plain.spans.len(), 3);
+    }
+
+    #[test]
+    fn test_editor_command_prefers_visual_then_editor_then_pager() {
+        let some = |s: &str| Some(s.to_string());
+        let none_installed = |_: &str| false;
+        assert_eq!(editor_command(some("code --wait"), some("vim"), none_installed), some("code --wait"));
+        assert_eq!(editor_command(some(" "), some("vim"), none_installed), some("vim"));
+        assert_eq!(editor_command(None, None, |p| p == "more"), some("more"));
+        assert_eq!(editor_command(None, some(""), none_installed), None);
+    }
+
+    #[test]
+    fn test_cwd_override_sets_default_scope() {
+        let session = |cwd: &str| Session {
+            session_id: cwd.to_string(),
+            agent: "claude".to_string(),
+            cwd: cwd.to_string(),
+            ..Default::default()
+        };
+        let sessions = || vec![session("/work/api"), session("/work/web")];
+
+        let cli = CliOptions { launch_cwd: Some("/work/api".to_string()), ..Default::default() };
+        let app = App::new_with_options(sessions(), String::new(), &cli);
+        assert_eq!(app.filtered.len(), 1);
+        assert_eq!(app.sessions[app.filtered[0]].cwd, "/work/api");
+        assert!(app.scope_display().ends_with("api"), "{}", app.scope_display());
+
+        // Explicit --dir still wins, and -g still searches everywhere
+        let cli = CliOptions {
+            launch_cwd: Some("/work/api".to_string()),
+            filter_dir: Some("/work/web".to_string()),
+            ..Default::default()
+        };
+        let app = App::new_with_options(sessions(), String::new(), &cli);
+        assert_eq!(app.sessions[app.filtered[0]].cwd, "/work/web");
+        let cli = CliOptions { launch_cwd: Some("/work/api".to_string()), global_search: true, ..Default::default() };
+        assert_eq!(App::new_with_options(sessions(), String::new(), &cli).filtered.len(), 2);
+
+        assert_eq!(absolute_path("web/", "/work"), "/work/web");
+        assert_eq!(absolute_path("/srv/x", "/work"), "/srv/x");
+        assert_eq!(absolute_path("/", "/work"), "/");
+    }
+
+    #[test]
+    fn test_parse_boolean_query_operators() {
+        let word = |w: &str| QueryClause::Word(w.to_string());
+        assert_eq!(parse_boolean_query("auth token"), None, "plain words keep the default ranking");
+        assert_eq!(
+            parse_boolean_query("auth AND token"),
+            Some(vec![(Occur::Must, word("auth")), (Occur::Must, word("token"))])
+        );
+        assert_eq!(
+            parse_boolean_query("auth OR token"),
+            Some(vec![(Occur::Should, word("auth")), (Occur::Should, word("token"))])
+        );
+        assert_eq!(
+            parse_boolean_query("auth -legacy NOT oauth"),
+            Some(vec![(Occur::Should, word("auth")), (Occur::MustNot, word("legacy")), (Occur::MustNot, word("oauth"))])
+        );
+        assert_eq!(
+            parse_boolean_query("auth AND token OR jwt"),
+            Some(vec![(Occur::Must, word("auth")), (Occur::Must, word("token")), (Occur::Should, word("jwt"))])
+        );
+        assert_eq!(
+            parse_boolean_query("\"refresh the token\" AND auth -\"old flow\""),
+            Some(vec![
+                (Occur::Must, QueryClause::Phrase("refresh the token".to_string())),
+                (Occur::Must, word("auth")),
+                (Occur::MustNot, QueryClause::Phrase("old flow".to_string())),
+            ])
+        );
+        // Lowercase operators are ordinary words; a hyphen inside a word isn't negation
+        assert_eq!(
+            parse_boolean_query("rock and roll -x"),
+            Some(vec![
+                (Occur::Should, word("rock")),
+                (Occur::Should, word("and")),
+                (Occur::Should, word("roll")),
+                (Occur::MustNot, word("x")),
+            ])
+        );
+        assert_eq!(parse_boolean_query("foo-bar AND"), Some(vec![(Occur::Must, word("foo-bar"))]));
+    }
+
+    #[test]
+    fn test_search_boolean_operators() {
+        let search = test_search_index(&[
+            ("s1", "fix the auth flow"),
+            ("s2", "refresh the token and auth"),
+            ("s3", "token rotation only"),
+        ]);
+        let ids = |query: &str| {
+            let mut ids = search.search_tantivy(query, None, None, false, DEFAULT_SNIPPET_CHARS).unwrap().1;
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids("auth AND token"), vec!["s2"]);
+        assert_eq!(ids("auth OR rotation"), vec!["s1", "s2", "s3"]);
+        assert_eq!(ids("token -auth"), vec!["s3"]);
+        assert_eq!(ids("NOT token"), vec!["s1"]);
+        assert_eq!(ids("\"auth flow\" OR \"token rotation\""), vec!["s1", "s3"]);
+    }
+
+    #[test]
+    fn test_session_count_label_shows_position() {
+        let sessions = (0..3)
+            .map(|i| Session { session_id: format!("s{}", i), agent: "claude".to_string(), ..Default::default() })
+            .collect();
+        let mut app = App::new(sessions, String::new(), None, None);
+        app.scope_global = true;
+        app.filter();
+        assert_eq!(session_count_label(&app), "1/3 sessions");
+        app.selected = 2;
+        assert_eq!(session_count_label(&app), "3/3 sessions");
+        app.filtered.clear();
+        assert_eq!(session_count_label(&app), "0 sessions");
+    }
+
+    #[test]
+    fn test_snippet_chars_sets_snippet_length() {
+        let text = format!("{} needle {}", "lorem ipsum ".repeat(40), "dolor sit ".repeat(40));
+        let search = test_search_index(&[("s1", &text)]);
+
+        let snippet_len = |chars: usize| {
+            let snippets = search.search_tantivy("needle", None, None, false, chars).unwrap().0;
+            strip_html_tags(&snippets["s1"]).chars().count()
+        };
+        let (short, long) = (snippet_len(40), snippet_len(400));
+        assert!(short <= 40, "{}", short);
+        assert!(long > 200 && long <= 400, "{}", long);
+    }
+
+    #[test]
+    fn test_trash_moves_file_and_drops_session() {
+        let dir = std::env::temp_dir().join(format!("aichat-trash-{}", std::process::id()));
+        let trash = dir.join("trash");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("abc.jsonl");
+        std::fs::write(&file, "one").unwrap();
+        let first = move_to_trash(&file, &trash).unwrap();
+        std::fs::write(&file, "two").unwrap();
+        let second = move_to_trash(&file, &trash).unwrap();
+        let contents = (std::fs::read_to_string(&first), std::fs::read_to_string(&second), file.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(first, trash.join("abc.jsonl"));
+        assert_eq!(second, trash.join("abc.jsonl.1"), "an earlier trashed copy is kept");
+        assert_eq!((contents.0.unwrap(), contents.1.unwrap(), contents.2), ("one".to_string(), "two".to_string(), false));
+
+        let sessions = (0..4)
+            .map(|i| Session { session_id: format!("s{}", i), agent: "claude".to_string(), ..Default::default() })
+            .collect();
+        let mut app = App::new(sessions, String::new(), None, None);
+        app.scope_global = true;
+        app.filter();
+        app.filtered = vec![3, 1, 0];
+        app.selected_set = [3, 0].into_iter().collect();
+        app.selected = 1;
+        app.remove_session(1);
+        assert_eq!(app.filtered, vec![2, 0]);
+        assert_eq!(app.sessions[app.filtered[app.selected]].session_id, "s0", "selection moves to the next row");
+        assert_eq!(app.selected_set, [2, 0].into_iter().collect());
+        app.remove_session(0);
+        assert_eq!((app.filtered.clone(), app.selected), (vec![1], 0));
+        assert_eq!(app.total_sessions, 2);
+    }
+
+    #[test]
+    fn test_read_piped_query() {
+        assert_eq!(read_piped_query("  auth bug \nignored\n".as_bytes()), Some("auth bug".to_string()));
+        assert_eq!(read_piped_query("\n\n  token\n".as_bytes()), Some("token".to_string()));
+        assert_eq!(read_piped_query("".as_bytes()), None);
+    }
+
+    #[test]
+    fn test_parse_agent_validates_values() {
+        assert_eq!(parse_agent("claude"), Ok(Some("claude")));
+        assert_eq!(parse_agent("Codex"), Ok(Some("codex")));
+        assert_eq!(parse_agent("all"), Ok(None));
+        assert_eq!(parse_agent("both"), Ok(None));
+        assert!(parse_agent("claud").unwrap_err().contains("'claud'"));
+
+        // An explicit --agent all beats a restored agent filter
+        let saved = || UiState { filter_agent: Some("codex".into()), ..Default::default() };
+        let mut cli = CliOptions { all_agents: true, ..Default::default() };
+        saved().apply_defaults(&mut cli);
+        assert_eq!(cli.agent_filter, None);
+        let mut cli = CliOptions::default();
+        saved().apply_defaults(&mut cli);
+        assert_eq!(cli.agent_filter.as_deref(), Some("codex"));
+    }
+
+    #[test]
+    fn test_tool_results_collapse() {
+        let conversation = "⏺ Bash(ls)\n  ⎿  a\n     b\n     c\n⏺ done\n  ⎿  ok";
+        assert_eq!(tool_result_blocks(conversation), vec![(1, 3), (5, 1)]);
+
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.full_content = conversation.to_string();
+        assert!(app.collapsed_tool_blocks.is_empty(), "expanded by default");
+        app.toggle_all_tool_results();
+        assert_eq!(app.collapsed_tool_blocks, HashSet::from([1, 5]));
+        app.toggle_all_tool_results();
+        assert!(app.collapsed_tool_blocks.is_empty());
+
+        // Enter works on the block at the top, or the next one below it
+        app.full_content_scroll = 2;
+        app.toggle_tool_result_at_top();
+        assert_eq!(app.collapsed_tool_blocks, HashSet::from([1]));
+        app.full_content_scroll = 4;
+        app.toggle_tool_result_at_top();
+        assert_eq!(app.collapsed_tool_blocks, HashSet::from([1, 5]));
+        app.toggle_all_tool_results();
+        assert!(app.collapsed_tool_blocks.is_empty(), "all collapsed, so z expands them");
+    }
+
+    #[test]
+    fn test_limit_keeps_top_results_by_score() {
+        let docs = [
+            ("weak", "auth once among many other unrelated words here"),
+            ("strong", "auth auth auth"),
+            ("middle", "auth and auth again"),
+        ];
+
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.scope_global = true;
+        app.sessions = docs
+            .iter()
+            .map(|(id, _)| Session { session_id: id.to_string(), ..Default::default() })
+            .collect();
+        app.search_index = Some(test_search_index(&docs));
+        app.query = "auth".to_string();
+        app.max_results = Some(2);
+        app.filter();
+
+        // The weakest match is dropped, not whichever session came first
+        let ids: Vec<&str> = app.filtered.iter().map(|&i| app.sessions[i].session_id.as_str()).collect();
+        assert_eq!(ids, vec!["strong", "middle"]);
+        assert!(app.search_scores["strong"].final_score > app.search_scores["middle"].final_score);
+        assert!(app.search_scores["middle"].final_score > app.search_scores["weak"].final_score);
+    }
+
+    #[test]
+    fn test_visible_rank_counts_only_filtered_hits() {
+        let docs = [
+            ("weak", "auth once among many other unrelated words here"),
+            ("strong", "auth auth auth"),
+            ("middle", "auth and auth again"),
+        ];
+
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.scope_global = true;
+        app.sessions = docs
+            .iter()
+            .map(|(id, _)| Session {
+                session_id: id.to_string(),
+                agent: if *id == "strong" { "claude" } else { "codex" }.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        app.search_index = Some(test_search_index(&docs));
+        app.query = "auth".to_string();
+        app.filter_agent = Some("codex".to_string());
+        app.filter();
+
+        // "strong" ranks first overall, but the agent filter hides it
+        assert_eq!(app.search_scores["middle"].rank, 2);
+        assert_eq!(app.visible_rank(&app.search_scores["middle"]), (1, 2));
+        assert_eq!(app.visible_rank(&app.search_scores["weak"]), (2, 2));
+    }
+
+    #[test]
+    fn test_group_by_project_keeps_groups_together() {
+        let session = |project: &str, modified_ts: u64| Session {
+            session_id: format!("{}-{}", project, modified_ts),
+            project: project.to_string(),
+            modified_ts,
+            ..Default::default()
+        };
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.scope_global = true;
+        app.sessions = vec![session("api", 1), session("web", 3), session("api", 4), session("web", 2)];
+        app.group_by = GroupBy::Project;
+        app.filter();
+
+        // Newest first within each group; the group with the newest session leads
+        let ids: Vec<&str> = app.filtered.iter().map(|&i| app.sessions[i].session_id.as_str()).collect();
+        assert_eq!(ids, vec!["api-4", "api-1", "web-3", "web-2"]);
+        assert_eq!((0..4).map(|i| app.starts_group(i)).collect::<Vec<_>>(), vec![true, false, true, false]);
+        // Two headers among four sessions of two rows each, and one on a mid-group window
+        assert_eq!(app.list_rows(0, 4, 2), 10);
+        assert_eq!(app.list_rows(1, 2, 2), 3);
+
+        assert_eq!(GroupBy::parse("Home"), Some(GroupBy::ClaudeHome));
+        assert_eq!(GroupBy::parse("branch"), None);
+        app.group_by = GroupBy::None;
+        assert_eq!(app.list_rows(0, 4, 2), 8);
+    }
+
+    #[test]
+    fn test_claude_homes_for_filter_and_json() {
+        let session = |agent: &str, home: &str| Session {
+            session_id: format!("{}{}", agent, home),
+            agent: agent.to_string(),
+            claude_home: home.to_string(),
+            ..Default::default()
+        };
+        let sessions = vec![
+            session("claude", "/h/.claude-work"),
+            session("claude", "/h/.claude"),
+            session("claude", "/h/.claude-work"),
+            session("codex", "/h/.codex"),
+            session("claude", ""),
+        ];
+        assert_eq!(discover_claude_homes(&sessions), vec!["/h/.claude", "/h/.claude-work"]);
+
+        let app = App::new(sessions, String::new(), None, None);
+        assert_eq!(app.available_homes.len(), 2);
+        assert!(JSON_FIELDS.contains(&"claude_home"), "in --json output by default");
+        assert_eq!(json_field(&app, &app.sessions[0], "claude_home"), serde_json::json!("/h/.claude-work"));
+    }
+
+    #[test]
+    fn test_resize_keeps_full_view_line() {
+        let mut app = App::new(Vec::new(), String::new(), None, None);
+        app.full_content = "> a\n⏺ long\n⏺ foo".to_string();
+        app.full_view_rows = vec![0, 1, 4, 5];
+        app.full_content_scroll = 4;
+        app.on_resize();
+        assert_eq!(app.full_view_anchor, None, "only the full view keeps a line");
+
+        app.open_level(ViewLevel::FullView);
+        app.on_resize();
+        assert_eq!(app.full_view_anchor, Some(2));
+        // Narrower: line 1 now wraps to five rows, and the next draw scrolls there
+        app.full_view_rows = vec![0, 1, 6, 7];
+        let line = app.full_view_anchor.take().unwrap();
+        app.scroll_to_line(line);
+        assert_eq!(app.full_content_scroll, 6);
+    }
+
+    #[test]
+    fn test_keymap_file_overrides_defaults() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        let ctrl = |c| key(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let defaults = KeyMap::default();
+        assert_eq!(defaults.bindings.len(), KEY_ACTIONS.len(), "every default chord parses");
+        assert_eq!(defaults.action(&ctrl('f')), Some(KeyAction::OpenFilter));
+        assert_eq!(defaults.action(&ctrl('F')), Some(KeyAction::OpenFilter), "case of Ctrl letters");
+        assert_eq!(defaults.action(&key(KeyCode::Char('q'), KeyModifiers::NONE)), None, "plain keys type");
+
+        let (keymap, problems) = KeyMap::parse(
+            "# vim-ish\n\
+             [keys]\n\
+             quit = \"ctrl-q\"\n\
+             open_filter = 'alt-f'  # was fuzzy_toggle's\n\
+             sort_toggle = \"none\"\n\
+             goto = \"hyper-g\"\n\
+             jump = \"ctrl-j\"\n\
+             details\n",
+        );
+        assert_eq!(keymap.action(&ctrl('q')), Some(KeyAction::Quit));
+        assert_eq!(keymap.action(&ctrl('c')), None, "the old chord is freed");
+        assert_eq!(keymap.action(&key(KeyCode::Char('f'), KeyModifiers::ALT)), Some(KeyAction::OpenFilter));
+        assert_eq!(keymap.keycap(KeyAction::FuzzyToggle), None);
+        assert_eq!(keymap.keycap(KeyAction::SortToggle), None);
+        assert_eq!(keymap.keycap(KeyAction::Goto).as_deref(), Some(" C-g "), "bad line keeps the default");
+        assert_eq!(
+            problems,
+            vec![
+                "line 6: can't read key 'hyper-g'".to_string(),
+                "line 7: unknown action 'jump'".to_string(),
+                "line 8: expected action = \"key\"".to_string(),
+            ]
+        );
+
+        assert_eq!(KeyChord::parse("-").map(KeyChord::label).as_deref(), Some("-"));
+        assert_eq!(KeyChord::parse("m-Tab").map(KeyChord::label).as_deref(), Some("M-Tab"));
+        assert_eq!(KeyChord::parse("ctrl-space").map(KeyChord::label).as_deref(), Some("C-Space"));
+    }
+
+    #[test]
+    fn test_exports_start_with_the_session_counts() {
+        let content = r#"{"type":"user","message":{"role":"user","content":"hi"}}"#;
+        let mut session = Session {
+            project: "proj".into(),
+            session_id: "abc".into(),
+            message_count: Some(1),
+            attachment_count: 2,
+            ..Default::default()
+        };
+        let md = render_export(&session, content, TranscriptFormat::Markdown);
+        assert!(md.starts_with("# proj (abc)\n\n1 message, 2 attachments\n\n"), "{md}");
+        let txt = render_export(&session, content, TranscriptFormat::Prefixed);
+        assert!(txt.starts_with("1 message, 2 attachments\n\n"), "{txt}");
+
+        session.message_count = None;
+        session.attachment_count = 0;
+        let bare = render_export(&session, content, TranscriptFormat::Prefixed);
+        assert_eq!(bare, parse_jsonl_to_transcript(content, false, TranscriptFormat::Prefixed));
+    }
+
+    #[test]
+    fn test_markdown_transcript_sections_and_fences() {
+        let fixture = include_str!("../../tests/fixtures/claude_session.jsonl");
+        let markdown = parse_jsonl_to_transcript(fixture, false, TranscriptFormat::Markdown);
+        assert!(markdown.starts_with("## User\n\n"));
+        assert!(markdown.contains("\n## Assistant\n\n"));
+        assert!(markdown.contains("**Tool: Bash**\n\n```\nls -la\n```\n"));
+        assert!(markdown.contains("```\ntotal 48\n"), "results lose the ⎿ prefix");
+        assert!(!markdown.contains("⏺ ") && !markdown.contains(TOOL_RESULT_PREFIX));
+
+        // A fence outgrows the backtick runs it wraps
+        assert_eq!(markdown_fence("a ```b``` c"), "````\na ```b``` c\n````\n");
+        assert_eq!(TranscriptFormat::parse("Markdown"), Some(TranscriptFormat::Markdown));
+        assert_eq!(TranscriptFormat::parse("txt"), Some(TranscriptFormat::Prefixed));
+
+        let session = |id: &str| Session { session_id: id.to_string(), ..Default::default() };
+        let sessions = vec![session("abc-1"), session("abc-2"), session("abd-3")];
+        assert_eq!(find_session_by_id(&sessions, "abd").unwrap().session_id, "abd-3");
+        assert!(find_session_by_id(&sessions, "abc").is_err(), "ambiguous prefix");
+        assert!(find_session_by_id(&sessions, "zzz").is_err());
+    }
+
+    #[test]
+    fn test_cctools_dir_honors_absolute_xdg() {
+        let home = Some(std::path::PathBuf::from("/home/u"));
+        let dir = |xdg: Option<&str>| cctools_dir_in(xdg.map(Into::into), home.clone());
+        assert_eq!(dir(Some("/data")), Some("/data/cctools".into()));
+        assert_eq!(dir(None), Some("/home/u/.cctools".into()));
+        assert_eq!(dir(Some("")), Some("/home/u/.cctools".into()), "empty is unset");
+        assert_eq!(dir(Some("rel/dir")), Some("/home/u/.cctools".into()), "relative values are ignored");
+        assert_eq!(cctools_dir_in(None, None), None);
+    }
+
+    #[test]
+    fn test_cctools_dir_keeps_an_existing_legacy_dir() {
+        let root = std::env::temp_dir().join(format!("aichat-xdg-{}", std::process::id()));
+        let home = root.join("home");
+        let xdg = root.join("data");
+        std::fs::create_dir_all(home.join(".cctools")).unwrap();
+        let dir = || cctools_dir_in(Some(xdg.clone().into()), Some(home.clone()));
+        let before = dir();
+        std::fs::create_dir_all(xdg.join("cctools")).unwrap();
+        let after = dir();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(before, Some(home.join(".cctools")), "legacy dir wins until the XDG one exists");
+        assert_eq!(after, Some(xdg.join("cctools")));
+    }
+
+    #[test]
+    fn test_check_index_dir_wants_a_tantivy_index() {
+        let dir = std::env::temp_dir().join(format!("aichat-index-path-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let missing = check_index_dir(&dir.join("nope")).unwrap_err().to_string();
+        let empty = check_index_dir(&dir).unwrap_err().to_string();
+        std::fs::write(dir.join("meta.json"), "{}").unwrap();
+        let ok = check_index_dir(&dir).is_ok();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(missing.ends_with("no such directory"), "{}", missing);
+        assert!(empty.ends_with("not a search index (no meta.json)"), "{}", empty);
+        assert!(ok);
+    }
+
+    #[test]
+    fn test_index_errors_tell_missing_from_corrupt() {
+        let dir = std::env::temp_dir().join(format!("aichat-index-errors-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_string_lossy().to_string();
+        let missing = load_sessions(&path, 10, false, None).unwrap_err();
+        std::fs::write(dir.join("meta.json"), "not json").unwrap();
+        let corrupt = open_index(&path).map(|_| ()).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(missing.downcast_ref::<IndexError>(), Some(IndexError::Missing(p)) if *p == path));
+        assert!(missing.to_string().contains("aichat build-index"));
+        assert!(matches!(corrupt, IndexError::Corrupt(ref p, _) if *p == path));
+        assert!(corrupt.to_string().contains("aichat clear-index"));
+    }
+
+    #[test]
+    fn test_esc_closes_one_screen_at_a_time() {
+        let session = Session { session_id: "s1".to_string(), export_path: "/nonexistent.jsonl".to_string(), ..Default::default() };
+        let mut app = App::new(vec![session], String::new(), None, None);
+        app.open_level(ViewLevel::ActionMenu);
+        execute_action_item(&mut app, ActionMenuItem::View);
+        app.open_level(ViewLevel::ViewSearch);
+        assert_eq!(app.view_stack, vec![ViewLevel::ActionMenu, ViewLevel::FullView, ViewLevel::ViewSearch]);
+
+        app.back();
+        assert!(app.full_view_open() && app.top_level() == Some(ViewLevel::FullView), "search returns to the view");
+        app.back();
+        assert_eq!(app.top_level(), Some(ViewLevel::ActionMenu), "the view returns to the menu it came from");
+
+        // Any other action closes the menu outright
+        execute_action_item(&mut app, ActionMenuItem::KeepEnds);
+        assert_eq!(app.top_level(), None);
+        app.back();
+        assert!(app.view_stack.is_empty());
+    }
+
+    #[test]
+    fn test_json_full_turns_attach_tool_results() {
+        let fixture = include_str!("../../tests/fixtures/claude_session.jsonl");
+        let turns = parse_jsonl_to_turns(fixture);
+        let roles: Vec<&str> = turns.iter().map(|t| t.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant", "assistant", "user", "assistant", "assistant"]);
+        assert_eq!(turns[0].text, "Hello, please read the file");
+        assert!(turns[0].tool_calls.is_empty());
+
+        let bash = &turns[4].tool_calls[0];
+        assert_eq!((bash.name.as_str(), bash.input.as_str()), ("Bash", "ls -la"));
+        assert!(bash.result.as_deref().unwrap().starts_with("total 48\ndrwxr-xr-x"), "{:?}", bash.result);
+        assert!(turns[1].tool_calls[0].result.is_some(), "the Read result goes with its call");
+        assert_eq!(turns[4].text, "");
+
+        let json = serde_json::to_value(&turns[4]).unwrap();
+        assert_eq!(json["tool_calls"][0]["name"], "Bash");
+        assert!(parse_json_fields("session_id,turns").is_ok());
+        assert!(!parse_json_fields("all").unwrap().contains(&JSON_TURNS_FIELD), "all skips the expensive field");
+    }
+
+    #[test]
+    fn test_filter_modal_items_narrow_by_name() {
+        assert_eq!(FilterMenuItem::matching(None).len(), FilterMenuItem::all().len());
+        assert_eq!(FilterMenuItem::IncludeSub.name(), "Include sub-agent sessions");
+
+        let names = |query: &str| -> Vec<String> {
+            FilterMenuItem::matching(Some(query)).iter().map(|i| i.name().to_string()).collect()
+        };
+        assert_eq!(names("CLAUDE"), ["Claude only", "Claude home"]);
+        assert_eq!(names("date"), ["After date", "Before date"]);
+        assert!(names("(o)").is_empty(), "shortcut labels aren't part of the name");
+        assert!(names("zzz").is_empty());
+    }
+
+    #[test]
+    fn test_summary_store_creates_its_directory() {
+        let dir = std::env::temp_dir().join(format!("cctools-missing-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("cctools").join("summaries.json");
+
+        let mut store = SummaryStore::open(Some(path.clone()));
+        let saved = store.insert("a".to_string(), "done".to_string());
+        let reopened = SummaryStore::open(Some(path.clone()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(saved, Ok(()));
+        assert_eq!(reopened.get("a"), Some("done"));
     }
 }
@@ -427,3 +427,131 @@ class TestIndexFromJsonl:
         # The is_sidechain field should be "true" for session 2
         rust_result = next(r for r in results if "002" in r["session_id"])
         # Note: is_sidechain is stored as string "true"/"false"
+
+    def test_parse_jsonl_session_indexes_tool_inputs_and_results(
+        self, tmp_path: Path, index_path: Path
+    ):
+        """Shell commands and tool output should be searchable, not just prose."""
+        from claude_code_tools.search_index import SessionIndex
+
+        session = tmp_path / "session-tools-001.jsonl"
+        lines = [
+            {"type": "user", "message": {"role": "user", "content": "Clean up the build"}},
+            {
+                "type": "assistant",
+                "message": {
+                    "role": "assistant",
+                    "content": [
+                        {
+                            "type": "tool_use",
+                            "name": "Bash",
+                            "input": {"command": "rm -rf target/wasm-cache"},
+                        },
+                        {
+                            "type": "tool_use",
+                            "name": "MultiEdit",
+                            "input": {"edits": [{"old_string": "fn legacy_loader()"}]},
+                        },
+                    ],
+                },
+            },
+            {
+                "type": "user",
+                "message": {
+                    "role": "user",
+                    "content": [
+                        {
+                            "type": "tool_result",
+                            "content": [{"type": "text", "text": "removed 42 files"}],
+                        }
+                    ],
+                },
+            },
+        ]
+        session.write_text("".join(json.dumps(line) + "\n" for line in lines))
+
+        index = SessionIndex(index_path)
+        content, user_count, _, _, _ = index._extract_session_content(session, "claude")
+
+        assert "rm -rf target/wasm-cache" in content
+        assert "fn legacy_loader()" in content
+        assert "removed 42 files" in content
+        assert user_count == 1  # tool results are not user messages
+
+    def test_parse_codex_session_indexes_tool_calls(
+        self, tmp_path: Path, index_path: Path
+    ):
+        """Codex function calls and their output should be searchable."""
+        from claude_code_tools.search_index import SessionIndex
+
+        session = tmp_path / "rollout-tools.jsonl"
+        items = [
+            {"type": "message", "role": "user",
+             "content": [{"type": "input_text", "text": "Run the tests"}]},
+            {"type": "function_call", "name": "shell",
+             "arguments": json.dumps({"command": ["bash", "-lc", "pytest -k flaky_io"]})},
+            {"type": "function_call_output",
+             "output": json.dumps({"output": "3 passed in 0.4s"})},
+        ]
+        session.write_text("".join(
+            json.dumps({"type": "response_item", "payload": item}) + "\n"
+            for item in items
+        ))
+
+        index = SessionIndex(index_path)
+        content, user_count, turn_count, _, _ = index._extract_session_content(
+            session, "codex"
+        )
+
+        assert "[Tool: shell]" in content
+        assert "bash -lc pytest -k flaky_io" in content
+        assert "3 passed in 0.4s" in content
+        assert (user_count, turn_count) == (1, 1)  # tool items are not turns
+
+
+def test_detect_code_languages_from_fences_and_markers():
+    """Tagged fences win (aliases normalized); without them, keyword markers count."""
+    from claude_code_tools.search_index import detect_code_languages
+
+    fenced = "```rs\nfn a() {}\n```\n```rust,ignore\n```\n```py\nx = 1\n```\n```\nplain\n```"
+    assert detect_code_languages(fenced) == ["rust", "python"]
+
+    unfenced = "def main():\n    import os\n    print(os.getcwd())  # run.py"
+    assert detect_code_languages(unfenced) == ["python"]
+    assert detect_code_languages("just some prose") == []
+
+
+def test_list_meta_copies_every_field_but_content():
+    """list_meta carries the fields aichat-search lists sessions from."""
+    import tantivy
+
+    from claude_code_tools.search_index import _add_list_meta
+
+    doc = tantivy.Document()
+    doc.add_text("session_id", "session-abc123")
+    doc.add_unsigned("modified_ts", 1764324000000)
+    doc.add_integer("lines", 50)
+    doc.add_text("content", "the whole conversation")
+    _add_list_meta(doc)
+
+    meta = json.loads(doc.to_dict()["list_meta"][0])
+    assert meta == {
+        "session_id": "session-abc123",
+        "modified_ts": 1764324000000,
+        "lines": 50,
+    }
+
+
+def test_default_index_path_keeps_an_existing_legacy_dir(tmp_path: Path, monkeypatch):
+    """XDG_DATA_HOME takes over only once ~/.cctools is gone or the XDG dir exists."""
+    from claude_code_tools.search_index import default_index_path
+
+    monkeypatch.setenv("HOME", str(tmp_path / "home"))
+    monkeypatch.setenv("XDG_DATA_HOME", str(tmp_path / "data"))
+    assert default_index_path() == tmp_path / "data" / "cctools" / "search-index"
+
+    (tmp_path / "home" / ".cctools").mkdir(parents=True)
+    assert default_index_path() == tmp_path / "home" / ".cctools" / "search-index"
+
+    (tmp_path / "data" / "cctools").mkdir(parents=True)
+    assert default_index_path() == tmp_path / "data" / "cctools" / "search-index"